@@ -0,0 +1,19 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate aoc_2019;
+
+use aoc_2019::aoc_problems::intcode_fuzz;
+
+// Every two bytes make a cell. Cells that small are mostly opcodes with their modes, or
+// addresses in and around the program, which is what gets a run past its first few
+// instructions.
+fuzz_target!(|data: &[u8]| {
+    let program: Vec<i64> = data.chunks_exact(2).map(|cell| i16::from_le_bytes([cell[0], cell[1]]) as i64).collect();
+    if program.is_empty() {
+        return;
+    }
+
+    let crashes = intcode_fuzz::check(&program, 2019);
+    assert!(crashes.is_empty(), "{}", crashes[0]);
+});
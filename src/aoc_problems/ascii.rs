@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::result;
+
+use aoc_problems::intcode::{Program, RunState};
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum LineKind {
+    // The program asking for input, e.g. "Input instructions:"
+    Prompt,
+    // Anything that isn't matched otherwise
+    Narrative,
+    // The program reporting a failure, e.g. "Didn't make it across:"
+    Error
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    Line(LineKind, String),
+    // A value too large to be a character, which ASCII programs use for their answer
+    Value(i64)
+}
+
+// An Intcode program which talks in lines of ASCII text, turning what it prints into lines
+// and lines into its input. A value too large to be a character ends the line it's in and
+// is kept for `take_value`.
+#[derive(Clone, Debug)]
+pub struct AsciiMachine {
+    program: Program,
+    // The start of a line the machine was printing when it stopped
+    partial: String,
+    value: Option<i64>
+}
+
+impl AsciiMachine {
+    pub fn new(program: Program) -> AsciiMachine {
+        AsciiMachine {
+            program,
+            partial: String::new(),
+            value: None
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        self.program.add_line(line);
+    }
+
+    // The next line printed, without its newline, or None once the machine halts or waits
+    // for input, or prints a value. A line without a newline, such as a prompt, is still
+    // returned when the machine stops.
+    pub fn read_line(&mut self) -> Result<Option<String>> {
+        while self.value.is_none() {
+            match self.program.run_program()? {
+                RunState::Output(10) => return Ok(Some(self.partial.split_off(0))),
+                RunState::Output(output @ 0..=127) => self.partial.push(output as u8 as char),
+                RunState::Output(output) => self.value = Some(output),
+                _ => break
+            }
+        }
+
+        if self.partial.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(self.partial.split_off(0)))
+        }
+    }
+
+    // Lines up to a blank one, as a camera prints each frame, or until the machine stops
+    pub fn read_frame(&mut self) -> Result<Vec<String>> {
+        let mut frame = vec![];
+        while let Some(line) = self.read_line()? {
+            if line.is_empty() {
+                break;
+            }
+            frame.push(line);
+        }
+
+        Ok(frame)
+    }
+
+    // The value which stopped `read_line`, letting it carry on reading
+    pub fn take_value(&mut self) -> Option<i64> {
+        self.value.take()
+    }
+}
+
+// An Intcode program which talks in lines of ASCII text. Lines it prints are sorted into
+// prompts, narrative and errors by the first matcher they contain, so solvers can react
+// to what happened without searching the text themselves.
+#[derive(Clone, Debug)]
+pub struct Console {
+    machine: AsciiMachine,
+    matchers: Vec<(String, LineKind)>
+}
+
+impl Console {
+    pub fn new(program: Program) -> Console {
+        Console {
+            machine: AsciiMachine::new(program),
+            matchers: vec![]
+        }
+    }
+
+    // Classifies lines containing `pattern` as `kind`, unless an earlier matcher applies
+    pub fn matching(mut self, pattern: &str, kind: LineKind) -> Console {
+        self.matchers.push((pattern.to_string(), kind));
+        self
+    }
+
+    pub fn program(&self) -> &Program {
+        self.machine.program()
+    }
+
+    pub fn classify(&self, line: &str) -> LineKind {
+        self.matchers.iter()
+            .find(|(pattern, _)| line.contains(pattern.as_str()))
+            .map_or(LineKind::Narrative, |&(_, kind)| kind)
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        self.machine.send_line(line);
+    }
+
+    // Runs until the program halts or waits for input, returning what it printed
+    pub fn read_events(&mut self) -> Result<Vec<Event>> {
+        let mut events = vec![];
+        loop {
+            match self.machine.read_line()? {
+                Some(line) => events.push(Event::Line(self.classify(&line), line)),
+                None => match self.machine.take_value() {
+                    Some(value) => events.push(Event::Value(value)),
+                    None => return Ok(events)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_problems::intcode_asm::assemble;
+
+    fn printer(text: &str, value: Option<i64>) -> Program {
+        let mut memory: Vec<i64> = text.bytes().flat_map(|b| vec![104, i64::from(b)]).collect();
+        if let Some(value) = value {
+            memory.extend(vec![104, value]);
+        }
+        memory.push(99);
+
+        Program::new(memory)
+    }
+
+    #[test]
+    fn ascii_classifies_lines() {
+        let mut console = Console::new(printer("Input instructions:\nWalking...\nDidn't make it across:\n\n", None))
+            .matching("Input instructions", LineKind::Prompt)
+            .matching("Didn't make it", LineKind::Error);
+
+        assert_eq!(console.read_events().unwrap(), vec![
+            Event::Line(LineKind::Prompt, "Input instructions:".to_string()),
+            Event::Line(LineKind::Narrative, "Walking...".to_string()),
+            Event::Line(LineKind::Error, "Didn't make it across:".to_string()),
+            Event::Line(LineKind::Narrative, String::new()),
+        ]);
+        assert!(console.program().is_halted());
+
+        let mut console = Console::new(printer("ok\nCommand?", Some(19354083)));
+        assert_eq!(console.read_events().unwrap(), vec![
+            Event::Line(LineKind::Narrative, "ok".to_string()),
+            Event::Line(LineKind::Narrative, "Command?".to_string()),
+            Event::Value(19354083),
+        ]);
+    }
+
+    #[test]
+    fn ascii_reads_lines_and_frames() {
+        let mut machine = AsciiMachine::new(printer("#.#\n.#.\n\n##\nScore", Some(500)));
+        assert_eq!(machine.read_frame().unwrap(), vec!["#.#", ".#."]);
+        assert_eq!(machine.read_line().unwrap(), Some("##".to_string()));
+        assert_eq!(machine.read_line().unwrap(), Some("Score".to_string()));
+        assert_eq!(machine.read_line().unwrap(), None);
+        assert_eq!(machine.take_value(), Some(500));
+        assert_eq!(machine.read_line().unwrap(), None);
+        assert!(machine.program().is_halted());
+
+        // Echoes back each line it reads, until it reads an empty one
+        let echo = assemble("
+            loop:   in [c]
+                    out [c]
+                    eq [c], 10, [nl]
+                    jz [nl], loop
+                    in [c]
+                    out [c]
+                    eq [c], 10, [nl]
+                    jz [nl], loop
+                    halt
+            c:      .data 0
+            nl:     .data 0
+        ").unwrap();
+        let mut machine = AsciiMachine::new(Program::new(echo));
+        machine.send_line("hi");
+        assert_eq!(machine.read_line().unwrap(), Some("hi".to_string()));
+        assert_eq!(machine.read_line().unwrap(), None);
+        assert!(machine.program().awaiting_input());
+        machine.send_line("");
+        assert_eq!(machine.read_frame().unwrap(), Vec::<String>::new());
+        assert_eq!(machine.read_line().unwrap(), None);
+        assert!(machine.program().is_halted());
+    }
+}
@@ -1,5 +1,4 @@
-use std::fs::File;
-use std::io::prelude::*;
+use util::input;
 
 fn fuel_needed_for(mass: usize) -> usize {
     if mass <= 8 {
@@ -23,10 +22,7 @@ fn total_fuel_requirement_for(mass: usize) -> usize {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let masses: Vec<usize> = f_contents.trim()
         .lines()
         .map(|l| l.trim().parse().unwrap())
@@ -36,10 +32,7 @@ pub fn q1(fname: String) -> usize {
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let masses: Vec<usize> = f_contents.trim()
         .lines()
         .map(|l| l.trim().parse().unwrap())
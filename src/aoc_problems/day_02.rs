@@ -1,9 +1,10 @@
 use std::error::Error;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::result;
 
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 fn pause() {
@@ -50,10 +51,7 @@ fn output_value(mut numbers: Vec<usize>, noun: usize, verb: usize) -> usize {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let numbers: Vec<usize> = f_contents.trim().split(',').map(|x: &str| {
         x.parse().unwrap()
     }).collect();
@@ -62,10 +60,7 @@ pub fn q1(fname: String) -> usize {
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let numbers: Vec<usize> = f_contents.trim().split(',').map(|x: &str| {
         x.parse().unwrap()
     }).collect();
@@ -1,13 +1,11 @@
-use std::cmp;
 use std::error::Error;
-use std::fmt;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
-use std::ops::{Add, Sub, AddAssign};
 use std::result;
 
-use std::collections::{BTreeMap, HashMap};
+use util::geom::Segment;
+use util::grid::Point2;
+use util::input;
 
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
@@ -27,80 +25,6 @@ fn pause() {
     let _ = stdin.read(&mut [0u8]).unwrap();
 }
 
-#[derive(Clone, Copy, Default, Eq, PartialEq, Hash)]
-struct Coordinate {
-    x: i32,
-    y: i32
-}
-
-impl Coordinate {
-    fn new(x: i32, y: i32) -> Coordinate {
-        Coordinate { x, y }
-    }
-
-    fn distance_from(&self, other: Self) -> usize {
-        (
-            (self.x - other.x).abs()
-                + (self.y - other.y).abs()
-        ) as usize
-    }
-}
-
-impl Ord for Coordinate {
-    fn cmp(&self, other: &Coordinate) -> cmp::Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
-
-impl PartialOrd for Coordinate {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        Some((self.y, self.x).cmp(&(other.y, other.x)))
-    }
-}
-
-impl Add for Coordinate {
-    type Output = Coordinate;
-
-    fn add(self, other: Coordinate) -> Coordinate {
-        Coordinate {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
-
-impl Sub for Coordinate {
-    type Output = Coordinate;
-
-    fn sub(self, other: Coordinate) -> Coordinate {
-        Coordinate {
-            x: self.x - other.x,
-            y: self.y - other.y,
-        }
-    }
-}
-
-impl AddAssign for Coordinate {
-    fn add_assign(&mut self, other: Self) {
-        *self = Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
-
-impl fmt::Debug for Coordinate {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
-
-impl fmt::Display for Coordinate {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
-
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum Direction {
     UP,
@@ -110,13 +34,13 @@ enum Direction {
 }
 
 impl Direction {
-    fn to_coord(&self) -> Coordinate {
+    fn to_coord(self) -> Point2 {
         use self::Direction::*;
-        match &self {
-            UP => Coordinate::new(0, 1),
-            DOWN => Coordinate::new(0, -1),
-            LEFT => Coordinate::new(-1, 0),
-            RIGHT => Coordinate::new(1, 0),
+        match self {
+            UP => Point2::new(0, 1),
+            DOWN => Point2::new(0, -1),
+            LEFT => Point2::new(-1, 0),
+            RIGHT => Point2::new(1, 0),
         }
     }
 }
@@ -145,44 +69,58 @@ impl Displacement {
     }
 }
 
-struct WireGrid {
-    grid: BTreeMap<Coordinate, HashMap<usize, usize>>
+struct Wire {
+    segments: Vec<Segment>
 }
 
-impl WireGrid {
-    fn new() -> WireGrid {
-        WireGrid { grid: BTreeMap::new() }
-    }
-
-    fn add_wire(&mut self, wire_str: String, marker: usize) -> Result<()> {
+impl Wire {
+    fn new(wire_str: String) -> Result<Wire> {
         let wire_displacements: Result<Vec<Displacement>> = wire_str.split(',').map(|x: &str| {
             Displacement::new(x.to_string())
         }).collect();
-        let wire_displacements = wire_displacements?;
 
-        let mut current_position = Coordinate::new(0, 0);
-        let mut steps: usize = 0;
-        for displacement in wire_displacements {
+        let mut current_position = Point2::new(0, 0);
+        let mut segments = vec![];
+        for displacement in wire_displacements? {
             let unit_displacement = displacement.dir.to_coord();
-            for _ in 0..displacement.dist {
-                steps += 1;
-                current_position += unit_displacement;
-                let wire_count = self.grid.entry(current_position).or_insert(HashMap::new());
-                if !wire_count.contains_key(&marker) {
-                    wire_count.insert(marker, steps);
-                }
+            let end = Point2::new(
+                current_position.x + unit_displacement.x * displacement.dist as i64,
+                current_position.y + unit_displacement.y * displacement.dist as i64
+            );
+            segments.push(Segment::new(current_position, end));
+            current_position = end;
+        }
+
+        Ok(Wire { segments })
+    }
+
+    // Points where this wire meets the other one, apart from where they both start
+    fn crossings(&self, other: &Wire) -> Vec<Point2> {
+        let origin = Point2::new(0, 0);
+
+        self.segments.iter()
+            .flat_map(|a| other.segments.iter().filter_map(move |b| a.overlap(b)))
+            .flat_map(|overlap| overlap.points())
+            .filter(|&pt| pt != origin)
+            .collect()
+    }
+
+    // Steps along the wire to the first time it reaches `pt`
+    fn steps_to(&self, pt: Point2) -> Option<usize> {
+        let mut steps = 0;
+        for segment in &self.segments {
+            if let Some(steps_along) = segment.steps_to(pt) {
+                return Some(steps + steps_along);
             }
+            steps += segment.length();
         }
 
-        Ok(())
+        None
     }
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let wires: Vec<String> = f_contents.trim().lines().map(|x: &str| {
         x.to_string()
     }).collect();
@@ -194,25 +132,19 @@ pub fn q1(fname: String) -> usize {
 }
 
 fn _q1(wire_str_1: String, wire_str_2: String) -> Result<usize> {
-    let mut wire_grid = WireGrid::new();
-
-    wire_grid.add_wire(wire_str_1, 1)?;
-    wire_grid.add_wire(wire_str_2, 2)?;
+    let wire_1 = Wire::new(wire_str_1)?;
+    let wire_2 = Wire::new(wire_str_2)?;
 
-    let min_dist = wire_grid.grid.iter()
-        .filter(|(_, n)| n.len() > 1)
-        .map(|(coord, _)| coord.distance_from(Coordinate::new(0, 0)))
+    let min_dist = wire_1.crossings(&wire_2).into_iter()
+        .map(|pt| pt.manhattan_distance(Point2::new(0, 0)) as usize)
         .min()
-        .ok_or("No elements in wire grid!")?;
+        .ok_or("The wires don't cross!")?;
 
     Ok(min_dist)
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let wires: Vec<String> = f_contents.trim().lines().map(|x: &str| {
         x.to_string()
     }).collect();
@@ -224,16 +156,13 @@ pub fn q2(fname: String) -> usize {
 }
 
 fn _q2(wire_str_1: String, wire_str_2: String) -> Result<usize> {
-    let mut wire_grid = WireGrid::new();
-
-    wire_grid.add_wire(wire_str_1, 1)?;
-    wire_grid.add_wire(wire_str_2, 2)?;
+    let wire_1 = Wire::new(wire_str_1)?;
+    let wire_2 = Wire::new(wire_str_2)?;
 
-    let min_dist = wire_grid.grid.iter()
-        .filter(|(_, n)| n.len() > 1)
-        .map(|(_, step_count)| step_count.values().sum())
+    let min_dist = wire_1.crossings(&wire_2).into_iter()
+        .filter_map(|pt| Some(wire_1.steps_to(pt)? + wire_2.steps_to(pt)?))
         .min()
-        .ok_or("No elements in wire grid!")?;
+        .ok_or("The wires don't cross!")?;
 
     Ok(min_dist)
 }
@@ -5,6 +5,8 @@ use std::result;
 
 use std::collections::HashMap;
 
+use util::digits;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 fn pause() {
@@ -19,7 +21,7 @@ fn pause() {
     let _ = stdin.read(&mut [0u8]).unwrap();
 }
 
-fn matching_adjacent_digits(digit_list: &Vec<u32>) -> bool {
+fn matching_adjacent_digits(digit_list: &[u8]) -> bool {
     for t in digit_list.windows(2) {
         if t[0] == t[1] {
             return true;
@@ -28,8 +30,8 @@ fn matching_adjacent_digits(digit_list: &Vec<u32>) -> bool {
     false
 }
 
-fn matching_only_2_digits(digit_list: &Vec<u32>) -> bool {
-    let mut digit_count: HashMap<u32, u32> = HashMap::new();
+fn matching_only_2_digits(digit_list: &[u8]) -> bool {
+    let mut digit_count: HashMap<u8, u32> = HashMap::new();
 
     for &digit in digit_list {
         let count = digit_count.entry(digit).or_insert(0);
@@ -39,7 +41,7 @@ fn matching_only_2_digits(digit_list: &Vec<u32>) -> bool {
     digit_count.values().any(|&x| x == 2)
 }
 
-fn ordered_digits(digit_list: &Vec<u32>) -> bool {
+fn ordered_digits(digit_list: &[u8]) -> bool {
     let mut ordered = true;
     for t in digit_list.windows(2) {
         if t[0] > t[1] {
@@ -51,15 +53,13 @@ fn ordered_digits(digit_list: &Vec<u32>) -> bool {
 }
 
 fn fits_password_criteria(n: u32) -> bool {
-    let digit_list = n.to_string();
-    let digit_list: Vec<_> = digit_list.chars().map(|d| d.to_digit(10).unwrap()).collect();
+    let digit_list = digits::digits(u64::from(n));
 
     matching_adjacent_digits(&digit_list) && ordered_digits(&digit_list)
 }
 
 fn fits_full_password_criteria(n: u32) -> bool {
-    let digit_list = n.to_string();
-    let digit_list: Vec<_> = digit_list.chars().map(|d| d.to_digit(10).unwrap()).collect();
+    let digit_list = digits::digits(u64::from(n));
 
     matching_only_2_digits(&digit_list) && ordered_digits(&digit_list)
 }
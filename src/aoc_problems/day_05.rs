@@ -1,9 +1,11 @@
 use std::error::Error;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::result;
 
+use aoc_problems::intcode::Program;
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 macro_rules! err {
@@ -22,200 +24,31 @@ fn pause() {
     let _ = stdin.read(&mut [0u8]).unwrap();
 }
 
-#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Parameter {
-    Position,
-    Immediate
-}
-
-#[derive(Clone, Eq, Default, Debug, PartialEq, Hash)]
-struct Instruction {
-    opcode: usize,
-    parameters: Vec<Parameter>
-}
-
-impl Instruction {
-    fn new(number: usize) -> Result<Instruction> {
-        let opcode = number % 100;
-        let mut digit_list: Vec<_> = (number / 100).to_string().chars().map(|d| d.to_digit(10).unwrap()).collect();
-        digit_list.reverse();
-
-        let params_length = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            99 => 0,
-            x => return err!("{}", format!("Cannot read opcode: {}", x))
-        };
-
-        digit_list.resize(params_length, 0);
-        let parameters: Result<Vec<Parameter>> = digit_list.into_iter().map(|d| match d {
-            0 => Ok(Parameter::Position),
-            1 => Ok(Parameter::Immediate),
-            x => err!("{}", format!("Cannot read parameter digit: {}", x))
-        }).collect();
-        let parameters = parameters?;
-
-        Ok(
-            Instruction {
-                opcode,
-                parameters
-            }
-        )
-
-    }
-}
-
-fn get_param(parameter_form: Parameter, val: i32, program: &Vec<i32>) -> i32 {
-    use self::Parameter::*;
-
-    match parameter_form {
-        Position => program[val as usize],
-        Immediate => val
-    }
-}
-
-fn run_program(mut program: Vec<i32>, input_value: i32) -> Result<()> {
-
-    let mut pointer_idx = 0;
-    loop {
-        let current_instruction = Instruction::new(program[pointer_idx] as usize)?;
-        match current_instruction.opcode {
-            1 => {
-                let input_1 = get_param(
-                    current_instruction.parameters[0],
-                    program[pointer_idx+1],
-                    &program
-                );
-                let input_2 = get_param(
-                    current_instruction.parameters[1],
-                    program[pointer_idx+2],
-                    &program
-                );
-                let output_idx = program[pointer_idx+3] as usize;
-                program[output_idx] = input_1 + input_2;
-
-                pointer_idx += 4;
-            },
-            2 => {
-                let input_1 = get_param(
-                    current_instruction.parameters[0],
-                    program[pointer_idx+1],
-                    &program
-                );
-                let input_2 = get_param(
-                    current_instruction.parameters[1],
-                    program[pointer_idx+2],
-                    &program
-                );
-                let output_idx = program[pointer_idx+3] as usize;
-                program[output_idx] = input_1 * input_2;
-
-                pointer_idx += 4;
-            },
-            3 => {
-                let output_idx = program[pointer_idx+1] as usize;
-                program[output_idx] = input_value;
-
-                pointer_idx += 2;
-            },
-            4 => {
-                let input_idx = program[pointer_idx+1] as usize;
-                println!("Output value from instruction {:?}: {}", current_instruction, program[input_idx]);
-
-                pointer_idx += 2;
-            },
-            5 => {
-                let input_1 = get_param(
-                    current_instruction.parameters[0],
-                    program[pointer_idx+1],
-                    &program
-                );
-                let input_2 = get_param(
-                    current_instruction.parameters[1],
-                    program[pointer_idx+2],
-                    &program
-                );
-                if input_1 != 0 {
-                    pointer_idx = input_2 as usize;
-                } else {
-                    pointer_idx += 3;
-                }
-            },
-            6 => {
-                let input_1 = get_param(
-                    current_instruction.parameters[0],
-                    program[pointer_idx+1],
-                    &program
-                );
-                let input_2 = get_param(
-                    current_instruction.parameters[1],
-                    program[pointer_idx+2],
-                    &program
-                );
-                if input_1 == 0 {
-                    pointer_idx = input_2 as usize;
-                } else {
-                    pointer_idx += 3;
-                }
-            },
-            7 => {
-                let input_1 = get_param(
-                    current_instruction.parameters[0],
-                    program[pointer_idx+1],
-                    &program
-                );
-                let input_2 = get_param(
-                    current_instruction.parameters[1],
-                    program[pointer_idx+2],
-                    &program
-                );
-                let output_idx = program[pointer_idx+3] as usize;
-                program[output_idx] = if input_1 < input_2 {
-                    1
-                } else {
-                    0
-                };
-
-                pointer_idx += 4;
-            },
-            8 => {
-                let input_1 = get_param(
-                    current_instruction.parameters[0],
-                    program[pointer_idx+1],
-                    &program
-                );
-                let input_2 = get_param(
-                    current_instruction.parameters[1],
-                    program[pointer_idx+2],
-                    &program
-                );
-                let output_idx = program[pointer_idx+3] as usize;
-                program[output_idx] = if input_1 == input_2 {
-                    1
-                } else {
-                    0
-                };
+// Runs each test program with the ID of the system to test as its only input. Every
+// check but the last outputs 0 if it passed, and the last output is the diagnostic code.
+fn run_diagnostics(programs: Vec<Vec<i64>>, system_id: i64) -> Result<usize> {
+    let mut diagnostic_code = None;
+    for memory in programs {
+        println!("New program");
+        let mut program = Program::new(memory);
+        program.add_input(system_id);
+        let outputs = program.run_to_halt()?;
+        for output in &outputs {
+            println!("Output value: {}", output);
+        }
 
-                pointer_idx += 4;
-            },
-            99 => break,
-            x => return err!("{}", format!("Incorrect opcode: {}", x))
+        let (&code, checks) = outputs.split_last().ok_or("The test program output nothing")?;
+        if let Some(failed) = checks.iter().position(|&check| check != 0) {
+            return err!("Check {} failed, outputting {}", failed + 1, checks[failed]);
         }
+        diagnostic_code = Some(code as usize);
     }
-    Ok(())
+
+    diagnostic_code.ok_or_else(|| "There are no test programs".into())
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let test_programs: Vec<String> = f_contents.trim().lines().map(|x: &str| {
         x.to_string()
     }).collect();
@@ -224,23 +57,15 @@ pub fn q1(fname: String) -> usize {
 }
 
 fn _q1(test_programs: Vec<String>) -> Result<usize> {
-    let programs: Vec<Vec<i32>> = test_programs.into_iter().map(|s| {
-        s.split(',').filter_map(|ss| ss.parse::<i32>().ok()).collect()
+    let programs: Vec<Vec<i64>> = test_programs.into_iter().map(|s| {
+        s.split(',').filter_map(|ss| ss.trim().parse::<i64>().ok()).collect()
     }).collect();
 
-    for program in programs {
-        println!("New program");
-        run_program(program, 1)?;
-    }
-
-    unimplemented!();
+    run_diagnostics(programs, 1)
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let test_programs: Vec<String> = f_contents.trim().lines().map(|x: &str| {
         x.to_string()
 
@@ -250,13 +75,9 @@ pub fn q2(fname: String) -> usize {
 }
 
 fn _q2(test_programs: Vec<String>) -> Result<usize> {
-    let programs: Vec<Vec<i32>> = test_programs.into_iter().map(|s| {
-        s.split(',').filter_map(|ss| ss.parse::<i32>().ok()).collect()
+    let programs: Vec<Vec<i64>> = test_programs.into_iter().map(|s| {
+        s.split(',').filter_map(|ss| ss.trim().parse::<i64>().ok()).collect()
     }).collect();
 
-    for program in programs {
-        println!("New program");
-        run_program(program, 5)?;
-    }
-    unimplemented!();
+    run_diagnostics(programs, 5)
 }
@@ -1,5 +1,4 @@
 use std::error::Error;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::result;
@@ -8,6 +7,8 @@ use std::collections::{HashMap, HashSet};
 
 use regex::Regex;
 
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 
@@ -152,10 +153,7 @@ impl Orbits {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let orbits: Vec<String> = f_contents.trim().lines().map(|x: &str| {
         x.trim().to_string()
     }).collect();
@@ -171,10 +169,7 @@ fn _q1(orbits: Vec<String>) -> Result<usize> {
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let orbits: Vec<String> = f_contents.trim().lines().map(|x: &str| {
         x.trim().to_string()
     }).collect();
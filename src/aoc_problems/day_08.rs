@@ -1,7 +1,6 @@
 use std::cmp;
 use std::error::Error;
 use std::fmt;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::ops::{Add, Sub, AddAssign};
@@ -9,6 +8,10 @@ use std::result;
 
 use std::collections::BTreeMap;
 
+use util::digits;
+use util::input;
+use util::iter;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 fn pause() {
@@ -128,20 +131,16 @@ struct Picture {
 }
 
 impl Picture {
-    fn new(mut pixels: Vec<u32>, width: u32, height: u32) -> Result<Picture> {
-        let layer_count = pixels.len() / (width * height) as usize;
+    fn new(pixels: &[u32], width: u32, height: u32) -> Result<Picture> {
+        let layer_size = (width * height) as usize;
+        let layers: Vec<BTreeMap<Coordinate, PixelType>> = iter::chunks_exact_mapped(pixels, layer_size, |layer| {
+            layer.iter().enumerate()
+                .map(|(idx, &pixel)| (Coordinate::new(idx as u32 % width, idx as u32 / width), PixelType::new(pixel)))
+                .collect()
+        }).collect();
+        let layer_count = layers.len();
         println!("Layer count is {}", layer_count);
 
-        let mut layers: Vec<_> = (0..layer_count).map(|_| BTreeMap::new()).collect();
-
-        for layer_num in 0..layer_count {
-            for y in 0..height {
-                for x in 0..width {
-                    layers[layer_num].insert(Coordinate::new(x, y), PixelType::new(pixels.pop().unwrap()));
-                }
-            }
-        }
-
         Ok(
             Picture {
                 layers, width, height, layer_count
@@ -202,21 +201,17 @@ impl fmt::Display for Picture {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
-    let pixel_data: Vec<u32> = f_contents.trim().chars().map(|x| {
-        x.to_digit(10).unwrap()
-    }).collect();
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+    let pixel_data: Vec<u32> = digits::parse_digits(&f_contents).expect("Couldn't read image data")
+        .into_iter()
+        .map(u32::from)
+        .collect();
 
     _q1(pixel_data).unwrap()
 }
 
-fn _q1(mut pixels: Vec<u32>) -> Result<usize> {
-    // reversing so pop pulls off the right order in Picture constructor
-    pixels.reverse();
-    let picture = Picture::new(pixels, 25, 6)?;
+fn _q1(pixels: Vec<u32>) -> Result<usize> {
+    let picture = Picture::new(&pixels, 25, 6)?;
 
     let zero_layer_min = (0..picture.layer_count).map(|idx| {
             (idx, picture.layers[idx].values().filter(|&&pixel| pixel == PixelType::Black).count())
@@ -234,20 +229,17 @@ fn _q1(mut pixels: Vec<u32>) -> Result<usize> {
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
-    let pixel_data: Vec<u32> = f_contents.trim().chars().map(|x| {
-        x.to_digit(10).unwrap()
-    }).collect();
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+    let pixel_data: Vec<u32> = digits::parse_digits(&f_contents).expect("Couldn't read image data")
+        .into_iter()
+        .map(u32::from)
+        .collect();
 
     _q2(pixel_data).unwrap()
 }
 
-fn _q2(mut pixels: Vec<u32>) -> Result<usize> {
-    pixels.reverse();
-    let picture = Picture::new(pixels, 25, 6)?;
+fn _q2(pixels: Vec<u32>) -> Result<usize> {
+    let picture = Picture::new(&pixels, 25, 6)?;
 
     print!("{}", picture);
 
@@ -260,9 +252,8 @@ mod tests {
 
     #[test]
     fn day08_q2_tests() {
-        let mut pixels = vec![0,2,2,2,1,1,2,2,2,2,1,2,0,0,0,0];
-        pixels.reverse();
-        let picture = Picture::new(pixels, 2, 2).unwrap();
+        let pixels = vec![0,2,2,2,1,1,2,2,2,2,1,2,0,0,0,0];
+        let picture = Picture::new(&pixels, 2, 2).unwrap();
         print!("{:?}", picture);
         print!("{}", picture);
     }
@@ -1,15 +1,34 @@
 use std::error::Error;
-use std::fs::File;
+use std::fmt;
 use std::io;
 use std::io::prelude::*;
 use std::result;
 
+use aoc_problems::intcode::{describe_instruction, Program};
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
-macro_rules! err {
-    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+// BOOST's test mode prints the code of every instruction it finds to be broken, modes
+// included, instead of the keycode
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FaultyOpcodes(pub Vec<i64>);
+
+impl fmt::Display for FaultyOpcodes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let faults: Vec<String> = self.0.iter()
+            .map(|&code| match describe_instruction(code) {
+                Some(description) => format!("{}: {}", code, description),
+                None => code.to_string()
+            })
+            .collect();
+
+        write!(f, "BOOST reported faulty opcodes: {}", faults.join("; "))
+    }
 }
 
+impl Error for FaultyOpcodes {}
+
 fn pause() {
     let mut stdin = io::stdin();
     let mut stdout = io::stdout();
@@ -22,327 +41,79 @@ fn pause() {
     let _ = stdin.read(&mut [0u8]).unwrap();
 }
 
-#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Parameter {
-    Position,
-    Immediate,
-    Relative
-}
-
-#[derive(Clone, Eq, Default, Debug, PartialEq, Hash)]
-struct Instruction {
-    opcode: usize,
-    parameters: Vec<Parameter>
-}
-
-impl Instruction {
-    fn new(number: usize) -> Result<Instruction> {
-        let opcode = number % 100;
-        let mut digit_list: Vec<_> = (number / 100).to_string().chars().map(|d| d.to_digit(10).unwrap()).collect();
-        digit_list.reverse();
-
-        let params_length = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            9 => 1,
-            99 => 0,
-            x => return err!("{}", format!("Cannot read opcode: {}", x))
-        };
-
-        digit_list.resize(params_length, 0);
-        let parameters: Result<Vec<Parameter>> = digit_list.into_iter().map(|d| match d {
-            0 => Ok(Parameter::Position),
-            1 => Ok(Parameter::Immediate),
-            2 => Ok(Parameter::Relative),
-            x => err!("{}", format!("Cannot read parameter digit: {}", x))
-        }).collect();
-        let parameters = parameters?;
-
-        Ok(
-            Instruction {
-                opcode,
-                parameters,
-            }
-        )
+pub fn q1(fname: String) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
-    }
-}
+    let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
-struct Program {
-    memory: Vec<i64>,
-    first_input: i64,
-    second_input: i64,
-    current_input: usize,
-    pointer_idx: usize,
-    relative_base: i64
+    _q1(memory).unwrap()
 }
 
-impl Program {
-    fn new(memory: Vec<i64>, first_input: i64, second_input: i64)  -> Program {
-        Program {
-            memory,
-            first_input,
-            second_input,
-            current_input: 1,
-            pointer_idx: 0,
-            relative_base: 0
-        }
-    }
-
-    fn get_input(&mut self) -> Result<i64> {
-        Ok(self.first_input)
-    }
-
-    fn set_input(&mut self, input: i64) {
-        self.second_input = input;
-    }
-
-    fn get_parameter(&mut self, parameter_form: Parameter, val: i64) -> i64 {
-        use self::Parameter::*;
-
-        match parameter_form {
-            Position => {
-                let idx = val as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            },
-            Immediate => val,
-            Relative => {
-                let idx = (self.relative_base + val) as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            }
-        }
-    }
-
-    fn set_parameter(&mut self, idx: usize, val: i64) -> Result<()> {
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-
-        self.memory[idx] = val;
-
-        Ok(())
-    }
+fn _q1(memory: Vec<i64>) -> Result<usize> {
+    let mut boost = Program::new(memory).scripted(&[1]);
+    let outputs: Vec<i64> = boost.outputs().inspect(|result| println!("Result outputted = {}", result)).collect();
+    boost.finish()?;
 
-    fn get_output_idx(&mut self, idx: usize, parameter_type: Parameter) -> usize {
-        use self::Parameter::*;
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-        match parameter_type {
-            Position => {
-                self.memory[idx] as usize
-            },
-            Relative => {
-                (self.memory[idx] + self.relative_base) as usize
-            },
-            _ => panic!("Should never be here")
-        }
-    }
+    keycode(outputs)
+}
 
-    fn run_program(&mut self) -> Result<Option<i64>> {
-        loop {
-            let current_instruction = Instruction::new(self.memory[self.pointer_idx] as usize)?;
-
-            match current_instruction.opcode {
-                1 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 + input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                2 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 * input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                3 => {
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 1,
-                        current_instruction.parameters[0]
-                    );
-                    let input = self.get_input()?;
-                    self.set_parameter(output_idx, input)?;
-
-                    self.pointer_idx += 2;
-                },
-                4 => {
-                    let output_val = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1]
-                    );
-
-                    // let output_idx = self.memory[self.pointer_idx+1];
-                    self.pointer_idx += 2;
-
-                    return Ok(Some(output_val));
-                },
-                5 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 != 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                6 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 == 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                7 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 < input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                8 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 == input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                9 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    self.relative_base += input_1;
-
-                    self.pointer_idx += 2;
-                },
-                99 => break,
-                x => return err!("{}", format!("Incorrect opcode: {}", x))
-            }
-        }
-        Ok(None)
+// A working BOOST program prints only the keycode
+fn keycode(outputs: Vec<i64>) -> Result<usize> {
+    match outputs.as_slice() {
+        [] => Err("BOOST halted without printing a keycode".into()),
+        &[keycode] => Ok(keycode as usize),
+        _ => Err(Box::new(FaultyOpcodes(outputs)))
     }
 }
 
-pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+pub fn q2(fname: String) -> String {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
-    _q1(memory).unwrap()
+    _q2(memory).unwrap()
 }
 
-fn _q1(memory: Vec<i64>) -> Result<usize> {
-    let mut program = Program::new(memory, 1, 1);
-    let mut last_output = 0;
-    while let Some(result) = program.run_program()? {
-        last_output = result;
-        println!("Result outputted = {}", result);
-    }
+fn _q2(memory: Vec<i64>) -> Result<String> {
+    let mut boost = Program::new(memory).scripted(&[2]);
+    let output: Vec<i64> = boost.outputs().collect();
+    boost.finish()?;
 
-    Ok(last_output as usize)
-}
+    println!("Output = {:?}", output);
 
-pub fn q2(fname: String) -> String {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+    Ok(output.iter().map(|&n| n.to_string()).collect::<Vec<_>>().join(","))
+}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+// Runs BOOST in test mode and then, if it reported no faulty opcodes, in sensor boost
+// mode, parsing the program only once
+pub fn both(fname: String) -> (usize, String) {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
-    _q2(memory).unwrap()
+    _both(memory).unwrap()
 }
 
-fn _q2(memory: Vec<i64>) -> Result<String> {
-    let mut program = Program::new(memory, 2, 2);
-    let mut output = vec![];
-    while let Some(result) = program.run_program()? {
-        output.push(result);
-    }
+fn _both(memory: Vec<i64>) -> Result<(usize, String)> {
+    let program = Program::new(memory);
 
-    println!("Output = {:?}", output);
+    let mut test_mode = program.clone();
+    test_mode.add_input(1);
+    let keycode = keycode(test_mode.run_to_halt()?)?;
 
-    Ok(output.iter().map(|&n| n.to_string()).collect::<Vec<_>>().join(","))
+    let mut boost_mode = program;
+    boost_mode.add_input(2);
+    let coordinates: Vec<String> = boost_mode.run_to_halt()?.iter().map(|n| n.to_string()).collect();
+
+    Ok((keycode, coordinates.join(",")))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use aoc_problems::intcode;
+
     #[test]
     fn day09_q1_test1() {
         let new_program: Vec<i64> = "104,1125899906842624,99".to_string().split(',').map(|s| s.parse().unwrap()).collect();
@@ -357,11 +128,8 @@ mod tests {
     fn day09_q1_test2() {
         let new_program: Vec<i64> = "1102,34915192,34915192,7,4,7,99,0".to_string().split(',').map(|s| s.parse().unwrap()).collect();
 
-        let mut program = Program::new(new_program, 1, 1);
-        let mut output = vec![];
-        while let Some(result) = program.run_program().unwrap() {
-            output.push(result);
-        }
+        let mut program = Program::new(new_program);
+        let output: Vec<i64> = program.outputs().map(|n| n.unwrap()).collect();
 
         if !output.iter().any(|n: &i64| (*n).to_string().chars().count() == 16) {
             println!("Failure: no 16-digit number in result {:?}", output);
@@ -373,15 +141,43 @@ mod tests {
     fn day09_q1_test3() {
         let new_program: Vec<i64> = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99".to_string().split(',').map(|s| s.parse().unwrap()).collect();
 
-        let mut program = Program::new(new_program.clone(), 1, 1);
-        let mut output = vec![];
-        while let Some(result) = program.run_program().unwrap() {
-            output.push(result);
-        }
+        let mut program = Program::new(new_program.clone());
+        let output: Vec<i64> = program.outputs().map(|n| n.unwrap()).collect();
 
         assert_eq!(
             output,
             new_program
         )
     }
+
+    #[test]
+    fn day09_both_parts() {
+        let echo: Vec<i64> = "3,5,4,5,99,0".split(',').map(|s| s.parse().unwrap()).collect();
+        assert_eq!(_both(echo).unwrap(), (1, "2".to_string()));
+
+        let faulty: Vec<i64> = "104,203,104,1,99".split(',').map(|s| s.parse().unwrap()).collect();
+        assert!(_both(faulty).is_err());
+    }
+
+    #[test]
+    fn day09_counts_instructions() {
+        // Counting instructions rather than time catches a solver doing more work than it
+        // used to, however busy the machine running the tests is
+        let echo: Vec<i64> = "3,5,4,5,99,0".split(',').map(|s| s.parse().unwrap()).collect();
+        let (answers, executed) = intcode::count_instructions(|| _both(echo).unwrap());
+        assert_eq!(answers, (1, "2".to_string()));
+        assert_eq!(executed, 6);
+    }
+
+    #[test]
+    fn day09_q1_reports_faulty_opcodes() {
+        let faulty: Vec<i64> = "104,203,104,1002,99".split(',').map(|s| s.parse().unwrap()).collect();
+        let error = _q1(faulty).err().unwrap();
+
+        assert_eq!(error.downcast_ref::<FaultyOpcodes>(), Some(&FaultyOpcodes(vec![203, 1002])));
+        assert_eq!(
+            error.to_string(),
+            "BOOST reported faulty opcodes: 203: input (relative); 1002: multiply (position, immediate, position)"
+        );
+    }
 }
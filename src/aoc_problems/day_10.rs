@@ -1,7 +1,6 @@
 use std::cmp;
 use std::error::Error;
 use std::fmt;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::ops::{Add, Sub, AddAssign};
@@ -9,8 +8,18 @@ use std::result;
 
 use std::collections::{BTreeMap, HashMap, HashSet};
 
+use aoc_problems::facts;
+use util::geom;
+use util::grid::{Grid2D, Point2};
+use util::input;
+use viz;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
 fn pause() {
     let mut stdin = io::stdin();
     let mut stdout = io::stdout();
@@ -167,15 +176,50 @@ impl AsteroidField {
         )
     }
 
-    fn most_visible_asteroid(&self) -> (Coordinate, usize) {
-        let asteroid_coords: Vec<Coordinate> = self.map
+    fn asteroids(&self) -> Vec<Coordinate> {
+        self.map
             .iter()
             .filter_map(|(&c, square)| {
                     if square.is_asteroid() {
                         Some(c)
                     } else { None }
                 }
-            ).collect();
+            ).collect()
+    }
+
+    // Asteroids a station at `point` would see, whether or not there's an asteroid there
+    fn visible_count_from(&self, point: Coordinate) -> usize {
+        number_of_visible_coords(point, self.asteroids())
+    }
+
+    // How many asteroids could be seen from every position in the field
+    fn visibility_grid(&self) -> Grid2D<Option<i64>> {
+        let asteroid_coords = self.asteroids();
+        let (width, height) = match self.map.keys().next_back() {
+            Some(corner) => (corner.x as usize + 1, corner.y as usize + 1),
+            None => (0, 0)
+        };
+
+        Grid2D::from_fn(width, height, |pt| {
+            let point = Coordinate::new(pt.x as u32, pt.y as u32);
+            Some(number_of_visible_coords(point, asteroid_coords.clone()) as i64)
+        })
+    }
+
+    // Puts an asteroid at `coord`, or takes one away, to see how the best station moves
+    fn change(&mut self, coord: Coordinate, space: SpaceType) -> Result<()> {
+        match self.map.get_mut(&coord) {
+            Some(square) if *square == space => err!("There is already {} at {}", if space.is_asteroid() { "an asteroid" } else { "no asteroid" }, coord),
+            Some(square) => {
+                *square = space;
+                Ok(())
+            },
+            None => err!("{} is outside the asteroid field", coord)
+        }
+    }
+
+    fn most_visible_asteroid(&self) -> (Coordinate, usize) {
+        let asteroid_coords = self.asteroids();
 
         let visible_asteroids: Vec<(Coordinate, usize)> = asteroid_coords
             .iter()
@@ -246,12 +290,8 @@ fn gcd(m: i32, n: i32) -> i32 {
     }
 }
 
-fn gradient_of(line_of_sight: LineOfSight) -> f32 {
-    match (line_of_sight.x, line_of_sight.y) {
-        (x, 0) if x > 0 => std::f32::INFINITY,
-        (x, 0) if x < 0 => std::f32::NEG_INFINITY,
-        (x, y) => (x as f32) / (y as f32)
-    }
+fn point_of(coord: Coordinate) -> Point2 {
+    Point2::new(coord.x as i64, coord.y as i64)
 }
 
 impl fmt::Display for AsteroidField {
@@ -270,10 +310,7 @@ impl fmt::Display for AsteroidField {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let asteroid_data: Vec<Vec<char>> = f_contents.trim().lines().map(|x| {
         x.chars().collect()
     }).collect();
@@ -284,20 +321,63 @@ pub fn q1(fname: String) -> usize {
 fn _q1(asteroid_data: Vec<Vec<char>>) -> Result<usize> {
     // Tactic for this is find mow many of the lines of sight for the asteroids dy/dx
     // are distinct
-    let asteroid_field = AsteroidField::new(asteroid_data)?;
+    _q1_with(asteroid_data, &[], false)
+}
+
+// Positions written as x,y, separated by semicolons
+fn parse_coordinates(s: &str) -> Result<Vec<Coordinate>> {
+    s.split(';')
+        .filter(|coord| !coord.trim().is_empty())
+        .map(|coord| match coord.trim().split_once(',') {
+            Some((x, y)) => match (x.trim().parse(), y.trim().parse()) {
+                (Ok(x), Ok(y)) => Ok(Coordinate::new(x, y)),
+                _ => err!("Cannot read position {:?}", coord)
+            },
+            None => err!("Cannot read position {:?}, expected x,y", coord)
+        })
+        .collect()
+}
+
+// Solves part 1 after adding asteroids at the positions in `add` and taking away those in
+// `remove`, drawing how many asteroids every position can see as a heatmap first if
+// `heatmap` is set
+pub fn q1_with(fname: String, add: Option<&str>, remove: Option<&str>, heatmap: bool) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+    let asteroid_data: Vec<Vec<char>> = f_contents.trim().lines().map(|x| {
+        x.chars().collect()
+    }).collect();
+
+    let mut changes = vec![];
+    for (positions, space) in [(add, SpaceType::Asteroid), (remove, SpaceType::Empty)] {
+        for coord in parse_coordinates(positions.unwrap_or("")).unwrap() {
+            changes.push((coord, space));
+        }
+    }
+
+    _q1_with(asteroid_data, &changes, heatmap).unwrap()
+}
+
+fn _q1_with(asteroid_data: Vec<Vec<char>>, changes: &[(Coordinate, SpaceType)], heatmap: bool) -> Result<usize> {
+    let mut asteroid_field = AsteroidField::new(asteroid_data)?;
+    for &(coord, space) in changes {
+        asteroid_field.change(coord, space)?;
+    }
+
+    if heatmap {
+        println!("Asteroids visible from each position");
+        println!("{}", viz::heatmap(&asteroid_field.visibility_grid())?);
+    }
 
     let most_visible_asteroid = asteroid_field.most_visible_asteroid();
 
     println!("Most visible asteroid = {:?}", most_visible_asteroid);
+    facts::record("station", most_visible_asteroid.0);
 
     Ok(most_visible_asteroid.1)
 }
 
 pub fn q2(fname: String) -> u32 {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
     let asteroid_data: Vec<Vec<char>> = f_contents.trim().lines().map(|x| {
         x.chars().collect()
     }).collect();
@@ -321,35 +401,18 @@ fn _q2(asteroid_data: Vec<Vec<char>>) -> Result<u32> {
     // and from part 1, there are > 300 in sight
     let line_of_sight_info = line_of_sight_info(station_coord, asteroid_coords);
 
-    // Going clockwise: split into 2 parts
-    // Right of station: blow up in decreasing gradient
-    // Left of station: blow up in increasing gradient
-    let mut first_half: Vec<_> = line_of_sight_info.iter().filter_map(|(&line_of_sight, &coord)| {
-        if (coord.x > station_coord.x)
-            || (coord.x == station_coord.x) && (coord.y > station_coord.y)
-        {
-            Some((coord, gradient_of(line_of_sight)))
-        } else { None }
-    }).collect();
-
-    // reverse sorting
-    first_half.sort_by(|t1, t2| t2.1.partial_cmp(&t1.1).unwrap());
-
-    let mut second_half: Vec<_> = line_of_sight_info.iter().filter_map(|(&line_of_sight, &coord)| {
-        if (coord.x < station_coord.x)
-            || (coord.x == station_coord.x) && (coord.y < station_coord.y)
-        {
-            Some((coord, gradient_of(line_of_sight)))
-        } else { None }
-    }).collect();
-    second_half.sort_by(|t1, t2| t1.1.partial_cmp(&t2.1).unwrap());
-
-    first_half.extend(second_half);
+    // The laser sweeps clockwise from straight up, hitting the nearest asteroid along
+    // each line of sight in turn
+    let station = point_of(station_coord);
+    let mut targets: Vec<Coordinate> = line_of_sight_info.values().cloned().collect();
+    targets.sort_by(|&a, &b| geom::clockwise_cmp(station, point_of(a), point_of(b)));
 
     // Looking for 200th
-    let relevant_coord: Coordinate = first_half[199].0;
+    let relevant_coord: Coordinate = targets[199];
 
     println!("200th coordinate = {}", relevant_coord);
+    facts::record("station", station_coord);
+    facts::record("200th asteroid", relevant_coord);
 
     Ok((relevant_coord.x * 100) + relevant_coord.y)
 }
@@ -498,4 +561,36 @@ mod tests {
            802
         )
     }
+
+    #[test]
+    fn day10_what_if() {
+        let asteroid_data : Vec<Vec<char>> = "
+            .#..#
+            .....
+            #####
+            ....#
+            ...##
+        ".trim().lines().map(|l| l.trim().chars().collect()).collect();
+        let mut field = AsteroidField::new(asteroid_data.clone()).unwrap();
+
+        // A station could go where there's no asteroid
+        assert_eq!(field.visible_count_from(Coordinate::new(0, 0)), 8);
+        let grid = field.visibility_grid();
+        assert_eq!((grid.width(), grid.height()), (5, 5));
+        assert_eq!(grid[Point2::new(3, 4)], Some(8));
+        assert_eq!(grid[Point2::new(4, 2)], Some(5));
+
+        // Taking away the asteroid at 1,0 hides 4,0 from it no longer
+        field.change(Coordinate::new(1, 0), SpaceType::Empty).unwrap();
+        assert_eq!(field.visible_count_from(Coordinate::new(0, 0)), 8);
+        assert_eq!(field.visible_count_from(Coordinate::new(4, 0)), 6);
+        assert!(field.change(Coordinate::new(1, 0), SpaceType::Empty).is_err());
+        assert!(field.change(Coordinate::new(5, 0), SpaceType::Asteroid).is_err());
+
+        let changes: Vec<(Coordinate, SpaceType)> = parse_coordinates("3,4; 4,4").unwrap().into_iter()
+            .map(|coord| (coord, SpaceType::Empty))
+            .collect();
+        assert_eq!(_q1_with(asteroid_data, &changes, false).unwrap(), 6);
+        assert!(parse_coordinates("3;4").is_err());
+    }
 }
@@ -1,13 +1,13 @@
-use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
-use std::ops::{Add, Sub, AddAssign};
 use std::result;
+use std::thread;
 
-use std::collections::BTreeMap;
+use aoc_problems::intcode::Program;
+use util::grid::{Point2, SparseGrid};
+use util::input;
 
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
@@ -27,345 +27,8 @@ fn pause() {
     let _ = stdin.read(&mut [0u8]).unwrap();
 }
 
-#[derive(Clone, Copy, Default, Eq, PartialEq, Hash)]
-struct Coordinate {
-    x: i32,
-    y: i32
-}
-
-impl Coordinate {
-    fn new(x: i32, y: i32) -> Coordinate {
-        Coordinate { x, y }
-    }
-}
-
-impl Ord for Coordinate {
-    fn cmp(&self, other: &Coordinate) -> cmp::Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
-
-impl PartialOrd for Coordinate {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        Some((self.y, self.x).cmp(&(other.y, other.x)))
-    }
-}
-
-impl Add for Coordinate {
-    type Output = Coordinate;
-
-    fn add(self, other: Coordinate) -> Coordinate {
-        Coordinate {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
-
-impl Sub for Coordinate {
-    type Output = Coordinate;
-
-    fn sub(self, other: Coordinate) -> Coordinate {
-        Coordinate {
-            x: self.x - other.x,
-            y: self.y - other.y,
-        }
-    }
-}
-
-impl AddAssign for Coordinate {
-    fn add_assign(&mut self, other: Self) {
-        *self = Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
-    }
-}
-
-impl fmt::Debug for Coordinate {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
-
-impl fmt::Display for Coordinate {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
-
-#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Parameter {
-    Position,
-    Immediate,
-    Relative
-}
-
-#[derive(Clone, Eq, Default, Debug, PartialEq, Hash)]
-struct Instruction {
-    opcode: usize,
-    parameters: Vec<Parameter>
-}
-
-impl Instruction {
-    fn new(number: usize) -> Result<Instruction> {
-        let opcode = number % 100;
-        let mut digit_list: Vec<_> = (number / 100).to_string().chars().map(|d| d.to_digit(10).unwrap()).collect();
-        digit_list.reverse();
-
-        let params_length = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            9 => 1,
-            99 => 0,
-            x => return err!("{}", format!("Cannot read opcode: {}", x))
-        };
-
-        digit_list.resize(params_length, 0);
-        let parameters: Result<Vec<Parameter>> = digit_list.into_iter().map(|d| match d {
-            0 => Ok(Parameter::Position),
-            1 => Ok(Parameter::Immediate),
-            2 => Ok(Parameter::Relative),
-            x => err!("{}", format!("Cannot read parameter digit: {}", x))
-        }).collect();
-        let parameters = parameters?;
-
-        Ok(
-            Instruction {
-                opcode,
-                parameters,
-            }
-        )
-
-    }
-}
-
-struct Program {
-    memory: Vec<i64>,
-    input: i64,
-    current_input: usize,
-    pointer_idx: usize,
-    relative_base: i64
-}
-
-impl Program {
-    fn new(memory: Vec<i64>)  -> Program {
-        Program {
-            memory,
-            input: 0,
-            current_input: 1,
-            pointer_idx: 0,
-            relative_base: 0
-        }
-    }
-
-    fn get_input(&mut self) -> Result<i64> {
-        Ok(self.input)
-    }
-
-    fn set_input(&mut self, input: i64) {
-        self.input = input;
-    }
-
-    fn get_parameter(&mut self, parameter_form: Parameter, val: i64) -> i64 {
-        use self::Parameter::*;
-
-        match parameter_form {
-            Position => {
-                let idx = val as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            },
-            Immediate => val,
-            Relative => {
-                let idx = (self.relative_base + val) as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            }
-        }
-    }
-
-    fn set_parameter(&mut self, idx: usize, val: i64) -> Result<()> {
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-
-        self.memory[idx] = val;
-
-        Ok(())
-    }
-
-    fn get_output_idx(&mut self, idx: usize, parameter_type: Parameter) -> usize {
-        use self::Parameter::*;
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-        match parameter_type {
-            Position => {
-                self.memory[idx] as usize
-            },
-            Relative => {
-                (self.memory[idx] + self.relative_base) as usize
-            },
-            _ => panic!("Should never be here")
-        }
-    }
-
-    fn run_program(&mut self) -> Result<Option<i64>> {
-        loop {
-            let current_instruction = Instruction::new(self.memory[self.pointer_idx] as usize)?;
-
-            match current_instruction.opcode {
-                1 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 + input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                2 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 * input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                3 => {
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 1,
-                        current_instruction.parameters[0]
-                    );
-                    let input = self.get_input()?;
-                    self.set_parameter(output_idx, input)?;
-
-                    self.pointer_idx += 2;
-                },
-                4 => {
-                    let output_val = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1]
-                    );
-
-                    // let output_idx = self.memory[self.pointer_idx+1];
-                    self.pointer_idx += 2;
-
-                    return Ok(Some(output_val));
-                },
-                5 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 != 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                6 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 == 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                7 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 < input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                8 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 == input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                9 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    self.relative_base += input_1;
-
-                    self.pointer_idx += 2;
-                },
-                99 => break,
-                x => return err!("{}", format!("Incorrect opcode: {}", x))
-            }
-        }
-        Ok(None)
-    }
-}
-
 #[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Colour {
+pub enum Colour {
     Black,
     White
 }
@@ -399,83 +62,64 @@ impl fmt::Display for Colour {
     }
 }
 
-#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right
+fn print_paint_grid(paint_grid: &SparseGrid<Colour>) {
+    print!("{}", paint_grid.to_dense(Colour::Black).0);
 }
 
-impl Direction {
-    fn turn_left(self) -> Direction {
-        use self::Direction::*;
-        match self {
-            Up => Left,
-            Left => Down,
-            Down => Right,
-            Right => Up
-        }
-    }
-
-    fn turn_right(self) -> Direction {
-        use self::Direction::*;
-        match self {
-            Up => Right,
-            Right => Down,
-            Down => Left,
-            Left => Up
-        }
-    }
-
-    fn unit_step(&self) -> Coordinate {
-        use self::Direction::*;
-        match self {
-            Up => Coordinate::new(0, 1),
-            Right => Coordinate::new(1, 0),
-            Down => Coordinate::new(0, -1),
-            Left => Coordinate::new(-1, 0),
-        }
-    }
-}
-
-fn print_paint_grid(mut paint_grid: BTreeMap<Coordinate, Colour>) {
-    // fill in blanks
-    let mut coords: Vec<Coordinate> = paint_grid.keys().map(|&c| c.clone()).collect();
-    coords.sort();
-    let first_coord = coords.first().unwrap();
-    let last_coord = coords.last().unwrap();
-
-    println!("First coord is {}", first_coord);
-    println!("Last coord is {}", last_coord);
-
-    for y in first_coord.y .. last_coord.y+1 {
-        for x in first_coord.x .. last_coord.x+1 {
-            let coord = Coordinate::new(x, y);
-            if !paint_grid.contains_key(&coord) {
-                paint_grid.insert(coord, Colour::Black);
-            }
+// Directions the robot can start out facing, where y increases downwards
+const FACINGS: [(&str, Point2); 4] = [
+    ("up", Point2 { x: 0, y: -1 }),
+    ("right", Point2 { x: 1, y: 0 }),
+    ("down", Point2 { x: 0, y: 1 }),
+    ("left", Point2 { x: -1, y: 0 }),
+];
+
+// Runs the painting robot until it halts, starting at the origin on a panel of
+// `start_colour` facing `facing`, and returns the panels it painted
+fn paint(program: &mut Program, start_colour: Colour, facing: Point2) -> Result<SparseGrid<Colour>> {
+    let origin = Point2::new(0, 0);
+    let mut paint_grid: SparseGrid<Colour> = SparseGrid::new();
+    let mut current_coord = origin;
+    let mut current_orientation = facing;
+    loop {
+        // Every panel but the starting one is black until it's painted
+        let colour = match paint_grid.get(current_coord) {
+            Some(&colour) => colour,
+            None if current_coord == origin => start_colour,
+            None => Colour::Black
+        };
+        program.add_input(colour.to_digit());
+
+        // First output: what colour to paint current square, second: which direction to move
+        let (colour, turn) = match program.run_until_output_len(2)?.as_slice() {
+            &[colour, turn] => (colour, turn),
+            &[colour] if program.is_halted() => {
+                // it painted its last panel and stopped without turning
+                paint_grid.insert(current_coord, Colour::new(colour)?);
+                break;
+            },
+            [] if program.is_halted() => break,
+            _ => return err!("The robot asked for another colour before it had painted and turned")
+        };
+        paint_grid.insert(current_coord, Colour::new(colour)?);
+        match turn {
+            0 => {
+                current_orientation = current_orientation.rotate_about(origin, -1);
+            },
+            1 => {
+                current_orientation = current_orientation.rotate_about(origin, 1);
+            },
+            x => return err!("Invalid direction output from program: {}", x)
         }
-    }
 
-    for y in first_coord.y .. last_coord.y+1 {
-        for x in first_coord.x .. last_coord.x+1 {
-            let coord = Coordinate::new(x, y);
-            print!("{}", paint_grid.get(&coord).unwrap_or(&Colour::Black));
-            // if !paint_grid.contains_key(&coord) {
-            //     paint_grid.insert(coord, Colour::Black);
-            // }
-        }
-        print!("{}", '\n');
+        current_coord += current_orientation;
     }
 
+    Ok(paint_grid)
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
@@ -483,46 +127,13 @@ pub fn q1(fname: String) -> usize {
 }
 
 fn _q1(memory: Vec<i64>) -> Result<usize> {
-    let mut program = Program::new(memory);
-    let mut paint_grid: BTreeMap<Coordinate, Colour> = BTreeMap::new();
-    let mut current_coord: Coordinate = Coordinate::new(0, 0);
-    let mut current_orientation: Direction = Direction::Up;
-    loop {
-        program.set_input(
-            paint_grid.get(&current_coord)
-                .map(|colour| colour.to_digit())
-                .unwrap_or(0)
-        );
-
-        // First output: what colour to paint current square
-        if let Some(output) = program.run_program()? {
-            paint_grid.insert(current_coord, Colour::new(output)?);
-        } else { break; }
-
-        // Second output: which direction to move
-        if let Some(output) = program.run_program()? {
-            match output {
-                0 => {
-                    current_orientation = current_orientation.turn_left();
-                },
-                1 => {
-                    current_orientation = current_orientation.turn_right();
-                },
-                x => return err!("Invalid direction output from program: {}", x)
-            }
-
-            current_coord += current_orientation.unit_step();
-        } else { break; }
-    }
+    let paint_grid = paint(&mut Program::new(memory), Colour::Black, FACINGS[0].1)?;
 
     Ok(paint_grid.len())
 }
 
 pub fn q2(fname: String) -> String {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
@@ -530,44 +141,122 @@ pub fn q2(fname: String) -> String {
 }
 
 fn _q2(memory: Vec<i64>) -> Result<String> {
-    let mut program = Program::new(memory);
-    let mut paint_grid: BTreeMap<Coordinate, Colour> = BTreeMap::new();
-    let mut current_coord: Coordinate = Coordinate::new(0, 0);
-    let mut current_orientation: Direction = Direction::Up;
-
     // Starts on a white square instead
-    paint_grid.insert(current_coord, Colour::White);
+    let paint_grid = paint(&mut Program::new(memory), Colour::White, FACINGS[0].1)?;
 
-    loop {
-        program.set_input(
-            paint_grid.get(&current_coord)
-                .map(|colour| colour.to_digit())
-                .unwrap_or(0)
-        );
+    // fill in blanks with black squares
+    print_paint_grid(&paint_grid);
+
+    Ok("".to_string())
+}
 
-        // First output: what colour to paint current square
-        if let Some(output) = program.run_program()? {
-            paint_grid.insert(current_coord, Colour::new(output)?);
-        } else { break; }
-
-        // Second output: which direction to move
-        if let Some(output) = program.run_program()? {
-            match output {
-                0 => {
-                    current_orientation = current_orientation.turn_left();
-                },
-                1 => {
-                    current_orientation = current_orientation.turn_right();
-                },
-                x => return err!("Invalid direction output from program: {}", x)
-            }
-
-            current_coord += current_orientation.unit_step();
-        } else { break; }
+// What the robot did from one of the starts tried by `batch`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaintStats {
+    pub start_colour: Colour,
+    pub facing: &'static str,
+    // Panels painted at least once, and how many of those ended up white
+    pub painted: usize,
+    pub white: usize,
+    pub width: usize,
+    pub height: usize,
+    pub instructions: u64
+}
+
+// Runs the robot from every combination of starting panel colour and direction, one
+// thread each, and prints what each painted. Returns the most panels any of them painted.
+pub fn batch(fname: String) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+
+    let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
+
+    let runs = _batch(memory).unwrap();
+
+    println!("{:<5}  {:<6}  {:>7}  {:>5}  {:>5}  {:>6}  {:>12}", "Start", "Facing", "Painted", "White", "Width", "Height", "Instructions");
+    for run in &runs {
+        println!(
+            "{:<5}  {:<6}  {:>7}  {:>5}  {:>5}  {:>6}  {:>12}",
+            format!("{:?}", run.start_colour).to_lowercase(),
+            run.facing,
+            run.painted,
+            run.white,
+            run.width,
+            run.height,
+            run.instructions
+        );
     }
 
-    // fill in blanks with black squares
-    print_paint_grid(paint_grid);
+    runs.iter().map(|run| run.painted).max().unwrap_or(0)
+}
 
-    Ok("".to_string())
-}
\ No newline at end of file
+fn _batch(memory: Vec<i64>) -> Result<Vec<PaintStats>> {
+    let program = Program::new(memory);
+    let starts: Vec<(Colour, &'static str, Point2)> = [Colour::Black, Colour::White].iter()
+        .flat_map(|&colour| FACINGS.iter().map(move |&(name, facing)| (colour, name, facing)))
+        .collect();
+
+    // Each robot forks the parsed program, sharing its memory until it writes to it.
+    // Results come back in the order of `starts`, whichever thread finishes first.
+    let runs: Vec<result::Result<PaintStats, String>> = thread::scope(|scope| {
+        let handles: Vec<_> = starts.iter()
+            .map(|&(start_colour, facing_name, facing)| {
+                let mut robot = program.clone();
+                scope.spawn(move || {
+                    let paint_grid = paint(&mut robot, start_colour, facing).map_err(|e| e.to_string())?;
+                    let (width, height) = match paint_grid.bounds() {
+                        Some((min, max)) => ((max.x - min.x + 1) as usize, (max.y - min.y + 1) as usize),
+                        None => (0, 0)
+                    };
+
+                    Ok(PaintStats {
+                        start_colour,
+                        facing: facing_name,
+                        painted: paint_grid.len(),
+                        white: paint_grid.iter().filter(|&(_, &colour)| colour == Colour::White).count(),
+                        width,
+                        height,
+                        instructions: robot.instructions()
+                    })
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|handle| handle.join().expect("A painting robot panicked")).collect()
+    });
+
+    runs.into_iter().map(|run| run.map_err(|e| e.into())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day11_batch_runs_every_start() {
+        // Paints white and turns left, black and turns left, then white and turns right,
+        // whatever it sees
+        let robot: Vec<i64> = "3,100,104,1,104,0,3,100,104,0,104,0,3,100,104,1,104,1,99"
+            .split(',').map(|s| s.parse().unwrap()).collect();
+
+        let runs = _batch(robot).unwrap();
+        assert_eq!(runs.len(), 8);
+        assert_eq!((runs[0].start_colour, runs[0].facing), (Colour::Black, "up"));
+        assert_eq!((runs[7].start_colour, runs[7].facing), (Colour::White, "left"));
+        assert!(runs.iter().all(|run| run.painted == 3 && run.white == 2 && run.instructions == 10));
+        assert_eq!((runs[0].width, runs[0].height), (2, 2));
+        assert_eq!((runs[1].width, runs[1].height), (2, 2));
+    }
+
+    #[test]
+    fn day11_paints_before_halting_without_a_turn() {
+        // Paints white and turns right, then paints white and halts
+        let robot = vec![3, 100, 104, 1, 104, 1, 3, 100, 104, 1, 99];
+        let paint_grid = paint(&mut Program::new(robot), Colour::Black, FACINGS[0].1).unwrap();
+        assert_eq!(paint_grid.len(), 2);
+        assert!(paint_grid.iter().all(|(_, &colour)| colour == Colour::White));
+
+        // Asks for a second colour having only painted
+        let robot = vec![3, 100, 104, 1, 3, 100, 99];
+        assert!(paint(&mut Program::new(robot), Colour::Black, FACINGS[0].1).is_err());
+    }
+}
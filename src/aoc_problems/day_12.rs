@@ -1,6 +1,5 @@
 use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
@@ -10,6 +9,8 @@ use std::str::FromStr;
 
 use regex::Regex;
 
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 macro_rules! err {
@@ -131,56 +132,33 @@ impl fmt::Display for Coordinate {
     }
 }
 
-#[derive(Clone, Copy, Default, Eq, PartialEq, Hash)]
-struct Moon1D {
-    position: i32,
-    velocity: i32
-}
-
-impl Moon1D {
-    fn new(position: i32) -> Moon1D {
-        Moon1D {
-            position,
-            velocity: 0
-        }
-    }
-
-    fn gravity_from(&self, other: &Moon1D) -> i32 {
-        (other.position - self.position).signum()
-    }
-}
-
-impl fmt::Display for Moon1D {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Pos: {}, velocity: {}", self.position, self.velocity)
-    }
-}
-
+// A single axis of the simulation, stored as separate position and velocity arrays so the
+// pairwise gravity loop runs over plain contiguous i32 slices
+#[derive(Clone, Default, Eq, PartialEq, Hash)]
 struct Jupiter1D {
-    moons: Vec<Moon1D>
+    positions: Vec<i32>,
+    velocities: Vec<i32>
 }
 
 impl Jupiter1D {
     fn new(coords: Vec<i32>) -> Jupiter1D {
+        let velocities = vec![0; coords.len()];
         Jupiter1D {
-            moons: coords.into_iter().map(|coord| Moon1D::new(coord)).collect()
+            positions: coords,
+            velocities
         }
     }
 
     fn add_gravity_effect(&mut self) -> Result<()> {
-        let other_moons = self.moons.clone();
-
-        for moon in &mut self.moons {
-            for other_moon in &other_moons {
-                moon.velocity += moon.gravity_from(&other_moon);
-            }
+        for (velocity, &position) in self.velocities.iter_mut().zip(self.positions.iter()) {
+            *velocity += self.positions.iter().map(|&other| (other - position).signum()).sum::<i32>();
         }
         Ok(())
     }
 
     fn move_moons(&mut self) -> Result<()> {
-        for moon in &mut self.moons {
-            moon.position += moon.velocity;
+        for (position, &velocity) in self.positions.iter_mut().zip(self.velocities.iter()) {
+            *position += velocity;
         }
 
         Ok(())
@@ -194,15 +172,14 @@ impl Jupiter1D {
     }
 
     fn period(&mut self) -> Result<usize> {
-        let initial_positions: Vec<i32> = self.moons.iter().map(|&moon| moon.position).collect();
+        let initial_positions = self.positions.clone();
 
         let mut t = 0;
         loop {
             t += 1;
             self.increment_time()?;
 
-            let current_positions: Vec<i32> = self.moons.iter().map(|&moon| moon.position).collect();
-            if current_positions == initial_positions {
+            if self.positions == initial_positions {
                 break;
             }
         }
@@ -246,14 +223,6 @@ impl Moon {
     fn total_energy(&self) -> usize {
         (self.potential_energy() * self.kinetic_energy()) as usize
     }
-
-    fn gravity_from(&self, other: &Moon) -> Coordinate {
-        Coordinate::new(
-            (other.position.x - self.position.x).signum(),
-            (other.position.y - self.position.y).signum(),
-            (other.position.z - self.position.z).signum(),
-        )
-    }
 }
 
 impl fmt::Display for Moon {
@@ -263,50 +232,45 @@ impl fmt::Display for Moon {
 }
 
 struct Jupiter {
-    moons: Vec<Moon>
+    x: Jupiter1D,
+    y: Jupiter1D,
+    z: Jupiter1D
 }
 
 impl Jupiter {
     fn new(coords: Vec<Coordinate>) -> Jupiter {
         Jupiter {
-            moons: coords.into_iter().map(|coord| Moon::new(coord)).collect()
+            x: Jupiter1D::new(coords.iter().map(|coord| coord.x).collect()),
+            y: Jupiter1D::new(coords.iter().map(|coord| coord.y).collect()),
+            z: Jupiter1D::new(coords.iter().map(|coord| coord.z).collect()),
         }
     }
 
-    fn add_gravity_effect(&mut self) -> Result<()> {
-        let other_moons = self.moons.clone();
-
-        for moon in &mut self.moons {
-            for other_moon in &other_moons {
-                moon.velocity += moon.gravity_from(&other_moon);
+    fn moons(&self) -> Vec<Moon> {
+        (0..self.x.positions.len()).map(|idx| {
+            Moon {
+                position: Coordinate::new(self.x.positions[idx], self.y.positions[idx], self.z.positions[idx]),
+                velocity: Coordinate::new(self.x.velocities[idx], self.y.velocities[idx], self.z.velocities[idx]),
             }
-        }
-        Ok(())
-    }
-
-    fn move_moons(&mut self) -> Result<()> {
-        for moon in &mut self.moons {
-            moon.position += moon.velocity;
-        }
-
-        Ok(())
+        }).collect()
     }
 
     fn increment_time(&mut self) -> Result<()> {
-        self.add_gravity_effect()?;
-        self.move_moons()?;
+        self.x.increment_time()?;
+        self.y.increment_time()?;
+        self.z.increment_time()?;
 
         Ok(())
     }
 
     fn total_energy(&self) -> usize {
-        self.moons.iter().map(|moon| moon.total_energy()).sum()
+        self.moons().iter().map(|moon| moon.total_energy()).sum()
     }
 }
 
 impl fmt::Display for Jupiter {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for (idx, moon) in self.moons.iter().enumerate() {
+        for (idx, moon) in self.moons().iter().enumerate() {
             writeln!(f, "Moon {} -  {}", idx, moon)?;
         }
         Ok(())
@@ -326,14 +290,15 @@ fn lcm(m: usize, n: usize) -> usize {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+    q1_for_steps(fname, 1000)
+}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+pub fn q1_for_steps(fname: String, t: usize) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let coords: Vec<String> = f_contents.trim().lines().map(|line| line.trim().to_string()).collect();
 
-    match _q1(coords, 1000) {
+    match _q1(coords, t) {
         Ok(result) => result,
         Err(e) => {
             println!("Error: {}", e);
@@ -362,10 +327,7 @@ fn _q1(coords: Vec<String>, t: usize) -> Result<usize> {
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let coords: Vec<String> = f_contents.trim().lines().map(|line| line.trim().to_string()).collect();
 
@@ -403,6 +365,30 @@ fn _q2(coords: Vec<String>) -> Result<usize> {
 mod tests {
     use super::*;
 
+    use std::time::Instant;
+
+    use generate;
+
+    // The step day 12 used before each axis got its own position and velocity arrays: one
+    // Vec<Moon>, cloned every step so that each moon can be pulled by the others
+    fn array_of_structs_step(moons: &mut Vec<Moon>) {
+        let other_moons = moons.clone();
+
+        for moon in moons.iter_mut() {
+            for other_moon in &other_moons {
+                moon.velocity += Coordinate::new(
+                    (other_moon.position.x - moon.position.x).signum(),
+                    (other_moon.position.y - moon.position.y).signum(),
+                    (other_moon.position.z - moon.position.z).signum(),
+                );
+            }
+        }
+
+        for moon in moons.iter_mut() {
+            moon.position += moon.velocity;
+        }
+    }
+
     #[test]
     fn day12_q1_test1() {
         let moon_coords: Vec<String> = "
@@ -462,4 +448,32 @@ mod tests {
             4686774924
         )
     }
-}
\ No newline at end of file
+
+    // A benchmark rather than a test: run it with
+    // `cargo test --release -- --ignored --nocapture day12_bench`
+    #[test]
+    #[ignore]
+    fn day12_bench_per_axis_arrays() {
+        let coords: Result<Vec<Coordinate>> = generate::generate(12, Some(64), 2019).unwrap()
+            .trim().lines().map(|line| line.trim().parse()).collect();
+        let coords = coords.unwrap();
+        let steps = 10_000;
+        println!("{} moons, {} steps", coords.len(), steps);
+
+        let now = Instant::now();
+        let mut moons: Vec<Moon> = coords.iter().map(|&coord| Moon::new(coord)).collect();
+        for _ in 0..steps {
+            array_of_structs_step(&mut moons);
+        }
+        println!("    Vec<Moon>:       {:?}", now.elapsed());
+
+        let now = Instant::now();
+        let mut jupiter = Jupiter::new(coords);
+        for _ in 0..steps {
+            jupiter.increment_time().unwrap();
+        }
+        println!("    Jupiter1D x 3:   {:?}", now.elapsed());
+
+        assert!(jupiter.moons() == moons);
+    }
+}
@@ -1,6 +1,5 @@
 use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
@@ -9,6 +8,15 @@ use std::result;
 
 use std::collections::BTreeMap;
 
+use itertools::Itertools;
+
+use aoc_problems::facts;
+use aoc_problems::intcode::{Execution, Program, VmBuilder};
+use util::grid::{Point2, SparseGrid};
+use util::input;
+use util::iter;
+use viz::Recorder;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 macro_rules! err {
@@ -94,300 +102,46 @@ impl fmt::Display for Coordinate {
     }
 }
 
-#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Parameter {
-    Position,
-    Immediate,
-    Relative
-}
-
-#[derive(Clone, Eq, Default, Debug, PartialEq, Hash)]
-struct Instruction {
-    opcode: usize,
-    parameters: Vec<Parameter>
-}
-
-impl Instruction {
-    fn new(number: usize) -> Result<Instruction> {
-        let opcode = number % 100;
-        let mut digit_list: Vec<_> = (number / 100).to_string().chars().map(|d| d.to_digit(10).unwrap()).collect();
-        digit_list.reverse();
-
-        let params_length = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            9 => 1,
-            99 => 0,
-            x => return err!("{}", format!("Cannot read opcode: {}", x))
-        };
-
-        digit_list.resize(params_length, 0);
-        let parameters: Result<Vec<Parameter>> = digit_list.into_iter().map(|d| match d {
-            0 => Ok(Parameter::Position),
-            1 => Ok(Parameter::Immediate),
-            2 => Ok(Parameter::Relative),
-            x => err!("{}", format!("Cannot read parameter digit: {}", x))
-        }).collect();
-        let parameters = parameters?;
-
-        Ok(
-            Instruction {
-                opcode,
-                parameters,
-            }
-        )
-
-    }
+// Which way the paddle chases the ball in part 2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Controller {
+    // Move towards wherever the ball is now
+    Follow,
+    // Move towards where the ball will next come down to the paddle, found by running
+    // a copy of the game ahead with the joystick left alone
+    Predict
 }
 
-struct Program {
-    memory: Vec<i64>,
-    input: i64,
-    current_input: usize,
-    pointer_idx: usize,
-    relative_base: i64,
+#[derive(Clone)]
+struct Game {
+    program: Program,
     display: BTreeMap<Coordinate, TileType>,
     score: usize,
     ball_coord: Coordinate,
     paddle_coord: Coordinate
 }
 
-impl Program {
-    fn new(memory: Vec<i64>)  -> Program {
-        Program {
-            memory,
-            input: 0,
-            current_input: 1,
-            pointer_idx: 0,
-            relative_base: 0,
-            display: BTreeMap::new(),
-            score: 0,
-            ball_coord: Coordinate::new(0, 0),
-            paddle_coord: Coordinate::new(0, 0),
-        }
-    }
-
-    fn get_input(&mut self) -> Result<i64> {
-        Ok((self.ball_coord.x as i64 - self.paddle_coord.x as i64).signum())
-    }
-
-    fn set_input(&mut self, input: i64) {
-        self.input = input;
-    }
-
-    fn get_parameter(&mut self, parameter_form: Parameter, val: i64) -> i64 {
-        use self::Parameter::*;
-
-        match parameter_form {
-            Position => {
-                let idx = val as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            },
-            Immediate => val,
-            Relative => {
-                let idx = (self.relative_base + val) as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            }
-        }
-    }
-
-    fn set_parameter(&mut self, idx: usize, val: i64) -> Result<()> {
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-
-        self.memory[idx] = val;
-
-        Ok(())
-    }
-
-    fn get_output_idx(&mut self, idx: usize, parameter_type: Parameter) -> usize {
-        use self::Parameter::*;
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-        match parameter_type {
-            Position => {
-                self.memory[idx] as usize
-            },
-            Relative => {
-                (self.memory[idx] + self.relative_base) as usize
-            },
-            _ => panic!("Should never be here")
-        }
-    }
-
-    fn run_program(&mut self) -> Result<Option<i64>> {
-        loop {
-            let current_instruction = Instruction::new(self.memory[self.pointer_idx] as usize)?;
-
-            match current_instruction.opcode {
-                1 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 + input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                2 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 * input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                3 => {
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 1,
-                        current_instruction.parameters[0]
-                    );
-                    let input = self.get_input()?;
-                    self.set_parameter(output_idx, input)?;
-
-                    self.pointer_idx += 2;
-                },
-                4 => {
-                    let output_val = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1]
-                    );
-
-                    // let output_idx = self.memory[self.pointer_idx+1];
-                    self.pointer_idx += 2;
-
-                    return Ok(Some(output_val));
-                },
-                5 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 != 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                6 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 == 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                7 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 < input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                8 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 == input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                9 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    self.relative_base += input_1;
-
-                    self.pointer_idx += 2;
-                },
-                99 => break,
-                x => return err!("{}", format!("Incorrect opcode: {}", x))
+impl Game {
+    fn new(memory: Vec<i64>) -> Result<Game> {
+        Ok(
+            Game {
+                // The game loop runs millions of instructions
+                program: VmBuilder::new(memory).execution(Execution::Compiled).build()?,
+                display: BTreeMap::new(),
+                score: 0,
+                ball_coord: Coordinate::new(0, 0),
+                paddle_coord: Coordinate::new(0, 0),
             }
-        }
-        Ok(None)
+        )
     }
 
-    fn run_game(&mut self) -> Result<()> {
-        loop {
-            // First output: x coord
-            let x = if let Some(output) = self.run_program()? {
-                output
-            } else { break; };
-
-            // Second output: y coord
-            let y = if let Some(output) = self.run_program()? {
-                output
-            } else { break; };
-
-            // Third output: tile type
-            let third_output = if let Some(output) = self.run_program()? {
-                output
-            } else { break; };
+    // Draws everything the game outputs until it next reads the joystick, returning
+    // false once the game is over
+    fn update(&mut self) -> Result<bool> {
+        let outputs = self.program.run_to_end()?;
 
+        // Each tile is drawn as three outputs: x, y and the tile type
+        for (x, y, third_output) in iter::chunks_exact_mapped(&outputs, 3, |t| (t[0], t[1], t[2])) {
             match (x, y) {
                 (-1, 0) => {
                     self.score = third_output as usize;
@@ -404,32 +158,96 @@ impl Program {
                         self.paddle_coord = coord;
                     }
                     self.display.insert(coord, tile);
-
-                    if tile == TileType::Block {
-                        let block_count = self.display.values().filter(|&&tile| tile == TileType::Block).count();
-                        if block_count == 0 {
-                            return Ok(());
-                        }
-                    }
                 }
             }
         }
-        Ok(())
+
+        Ok(self.program.awaiting_input())
     }
-}
 
-impl fmt::Display for Program {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let current_y: usize = 0;
-        for (coord, tile) in self.display.iter() {
-            if coord.y != current_y {
-                write!(f, "{}", '\n')?;
+    // The column where the ball will next reach the row above the paddle, if it does
+    // before the game ends
+    fn predict_landing(&self) -> Result<Option<usize>> {
+        let mut future = Game {
+            display: BTreeMap::new(),
+            ..self.clone()
+        };
+        let landing_row = self.paddle_coord.y - 1;
+
+        for _ in 0..10_000 {
+            future.program.add_input(0);
+            if !future.update()? {
+                break;
             }
+            if future.ball_coord.y == landing_row {
+                return Ok(Some(future.ball_coord.x));
+            }
+        }
 
-            write!(f, "{}", tile)?;
+        Ok(None)
+    }
+
+    // Plays until the game ends, drawing every `frame_skip`th frame if given one
+    // Returns how many frames were played. Every frame is saved to `recorder` if given.
+    fn run_game(&mut self, controller: Controller, frame_skip: Option<usize>, mut recorder: Option<&mut Recorder>) -> Result<usize> {
+        let mut target: Option<usize> = None;
+        let mut frame = 0;
+
+        while self.update()? {
+            if let Some(frame_skip) = frame_skip {
+                if frame % frame_skip.max(1) == 0 {
+                    self.draw();
+                }
+            }
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&self.frame())?;
+            }
+            frame += 1;
+
+            // A prediction holds until the ball comes down, whatever the paddle does in the
+            // meantime. When it does, the paddle has to keep still, as moving it changes
+            // which way the ball bounces.
+            if controller == Controller::Predict {
+                if self.ball_coord.y + 1 == self.paddle_coord.y {
+                    target = None;
+                } else if target.is_none() {
+                    target = self.predict_landing()?;
+                }
+            }
+
+            let goal = match controller {
+                Controller::Predict => target.unwrap_or(self.ball_coord.x),
+                Controller::Follow => self.ball_coord.x
+            };
+            self.program.add_input((goal as i64 - self.paddle_coord.x as i64).signum());
         }
 
-        Ok(())
+        if frame_skip.is_some() {
+            self.draw();
+        }
+        if let Some(recorder) = recorder {
+            recorder.record(&self.frame())?;
+        }
+
+        Ok(frame)
+    }
+
+    fn frame(&self) -> String {
+        format!("{}\nScore: {}", self, self.score)
+    }
+
+    fn draw(&self) {
+        println!("\x1b[H\x1b[2J{}", self.frame());
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let screen: SparseGrid<TileType> = self.display.iter()
+            .map(|(coord, &tile)| (Point2::new(coord.x as i64, coord.y as i64), tile))
+            .collect();
+
+        write!(f, "{}", screen.to_dense(TileType::Empty).0)
     }
 }
 
@@ -482,58 +300,55 @@ impl fmt::Display for TileType {
 
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
-
-    let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
+    let memory = input::read_intcode(&fname).expect("Couldn't read input file");
 
     _q1(memory).unwrap()
 }
 
 fn _q1(memory: Vec<i64>) -> Result<usize> {
-    let mut program = Program::new(memory);
+    let mut game = VmBuilder::new(memory).execution(Execution::Compiled).build()?.scripted(&[]);
     let mut tiles: BTreeMap<Coordinate, TileType> = BTreeMap::new();
-    loop {
-        // First output: x coord
-        let x: usize = if let Some(output) = program.run_program()? {
-            output as usize
-        } else { break; };
-
-        // Second output: y coord
-        let y: usize = if let Some(output) = program.run_program()? {
-            output as usize
-        } else { break; };
-
-        // Third output: tile type
-        let tile_type: usize = if let Some(output) = program.run_program()? {
-            output as usize
-        } else { break; };
-
-        tiles.insert(Coordinate::new(x, y), TileType::new(tile_type)?);
+
+    // Outputs come in threes: x coord, y coord, tile type
+    for (x, y, tile_type) in game.outputs().tuples() {
+        tiles.insert(Coordinate::new(x as usize, y as usize), TileType::new(tile_type as usize)?);
     }
+    game.finish()?;
 
     Ok(tiles.values().filter(|&&tile| tile == TileType::Block).count())
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    q2_with(fname, Controller::Predict, None, None)
+}
 
-    let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
+// Plays the game with the given controller, animating it in the terminal if given how
+// many frames to skip between each one drawn, and saving every frame to `record` for
+// playing back later if given a file
+pub fn q2_with(fname: String, controller: Controller, frame_skip: Option<usize>, record: Option<&str>) -> usize {
+    let memory = input::read_intcode(&fname).expect("Couldn't read input file");
 
-    _q2(memory).unwrap()
+    _q2(memory, controller, frame_skip, record).unwrap()
 }
 
-fn _q2(mut memory: Vec<i64>) -> Result<usize> {
+fn _q2(mut memory: Vec<i64>, controller: Controller, frame_skip: Option<usize>, record: Option<&str>) -> Result<usize> {
     // Change first number so game can play
     memory[0] = 2;
 
-    let mut program = Program::new(memory);
-    program.run_game()?;
+    let mut recorder = match record {
+        Some(path) => Some(Recorder::create(path)?),
+        None => None
+    };
+
+    let mut game = Game::new(memory)?;
+    let frames = game.run_game(controller, frame_skip, recorder.as_mut())?;
+
+    if let (Some(recorder), Some(path)) = (recorder, record) {
+        println!("Recorded {} frames to {}", recorder.finish()?, path);
+    }
+
+    facts::record("frames played", frames);
+    facts::record("blocks remaining", game.display.values().filter(|&&tile| tile == TileType::Block).count());
 
-    Ok(program.score)
-}
\ No newline at end of file
+    Ok(game.score)
+}
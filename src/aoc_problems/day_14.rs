@@ -1,20 +1,81 @@
 use std::error::Error;
-use std::fs::File;
+use std::fmt;
 use std::io;
 use std::io::prelude::*;
+use std::ops::Sub;
 use std::result;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use std::collections::HashMap;
 
 use regex::Regex;
 
+use util::cache::{self, Memo};
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 macro_rules! err {
     ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
 }
 
+// A quantity of some chemical got too big to count in the integers used
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QuantityOverflow {
+    pub chemical: String,
+    pub bits: u32
+}
+
+impl fmt::Display for QuantityOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The amount of {} needed is too big for {} bits", self.chemical, self.bits)
+    }
+}
+
+impl Error for QuantityOverflow {}
+
+// The integers a nanofactory counts in. Every sum is checked, so a deep chain of reactions
+// gives a QuantityOverflow rather than wrapping around to a wrong answer.
+trait Quantity: Copy + Default + Ord + fmt::Display + Sub<Output = Self> {
+    const BITS: u32;
+
+    fn from_usize(n: usize) -> Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    // Rounded up
+    fn checked_div_ceil(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! quantity {
+    ($t:ty) => {
+        impl Quantity for $t {
+            const BITS: u32 = <$t>::BITS;
+
+            fn from_usize(n: usize) -> $t {
+                n as $t
+            }
+
+            fn checked_add(self, other: $t) -> Option<$t> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_mul(self, other: $t) -> Option<$t> {
+                <$t>::checked_mul(self, other)
+            }
+
+            fn checked_div_ceil(self, other: $t) -> Option<$t> {
+                match other {
+                    0 => None,
+                    _ => Some(self / other + (self % other != 0) as $t)
+                }
+            }
+        }
+    };
+}
+
+quantity!(u64);
+quantity!(u128);
+
 fn pause() {
     let mut stdin = io::stdin();
     let mut stdout = io::stdout();
@@ -78,78 +139,93 @@ struct RecipeRequirements {
     inputs: Vec<Material>
 }
 
-#[derive(Clone, Default, Debug, Eq, PartialEq)]
-struct Nanofactory {
-    recipes: HashMap<String, RecipeRequirements>,
-    material_counts: HashMap<String, usize>,
-    ore_usage: usize,
-    fuel_count: usize
+type Recipes = HashMap<String, RecipeRequirements>;
+
+fn recipe_lines(s: &str) -> Vec<String> {
+    s.trim().lines().map(|line| line.trim().to_string()).collect()
 }
 
-impl Nanofactory {
-    fn new(recipes: Vec<String>) -> Result<Nanofactory> {
-        lazy_static! {
-            static ref RECIPE_RE: Regex = Regex::new(
-                r"(?P<inputs>[a-zA-Z0-9, ]+) => (?P<output_material>[a-zA-Z0-9 ]+)"
-            ).unwrap();
-        }
+fn parse_recipes(recipes: Vec<String>) -> Result<Recipes> {
+    lazy_static! {
+        static ref RECIPE_RE: Regex = Regex::new(
+            r"(?P<inputs>[a-zA-Z0-9, ]+) => (?P<output_material>[a-zA-Z0-9 ]+)"
+        ).unwrap();
+    }
 
-        let mut recipe_map: HashMap<String, RecipeRequirements> = HashMap::new();
-        for recipe in recipes {
-            if let Some(caps) = RECIPE_RE.captures(&recipe) {
-                let output_material: Material = caps["output_material"].parse().expect("Invalid output material");
-                let input_materials: Vec<Material> = caps["inputs"]
-                    .split(", ")
-                    .map(|s| s.parse().expect("Invalid material input"))
-                    .collect();
-
-                recipe_map.insert(
-                    output_material.chemical.clone(),
-                    RecipeRequirements {
-                        output: output_material,
-                        inputs: input_materials
-                    }
-                );
-            } else {
-                return err!("Cannot parse recipe {}", recipe);
-            }
+    let mut recipe_map: HashMap<String, RecipeRequirements> = HashMap::new();
+    for recipe in recipes {
+        if let Some(caps) = RECIPE_RE.captures(&recipe) {
+            let output_material: Material = caps["output_material"].parse().expect("Invalid output material");
+            let input_materials: Vec<Material> = caps["inputs"]
+                .split(", ")
+                .map(|s| s.parse().expect("Invalid material input"))
+                .collect();
+
+            recipe_map.insert(
+                output_material.chemical.clone(),
+                RecipeRequirements {
+                    output: output_material,
+                    inputs: input_materials
+                }
+            );
+        } else {
+            return err!("Cannot parse recipe {}", recipe);
         }
+    }
+
+    if let Some(recipe) = recipe_map.values().find(|recipe| recipe.output.amount == 0) {
+        return err!("The recipe for {} makes none of it", recipe.output.chemical);
+    }
+
+    Ok(recipe_map)
+}
+
+#[derive(Clone, Default, Debug, Eq, PartialEq)]
+struct Nanofactory<Q: Quantity> {
+    recipes: Recipes,
+    material_counts: HashMap<String, Q>,
+    ore_usage: Q,
+    fuel_count: usize
+}
 
-        let material_counts: HashMap<String, usize> = recipe_map
+impl<Q: Quantity> Nanofactory<Q> {
+    fn new(recipes: Recipes) -> Nanofactory<Q> {
+        let material_counts: HashMap<String, Q> = recipes
             .keys()
-            .map(|chemical| (chemical.clone(), 0))
+            .map(|chemical| (chemical.clone(), Q::default()))
             .collect();
 
-        Ok(
-            Nanofactory {
-                recipes: recipe_map,
-                material_counts,
-                ore_usage: 0,
-                fuel_count: 0
-            }
-        )
+        Nanofactory {
+            recipes,
+            material_counts,
+            ore_usage: Q::default(),
+            fuel_count: 0
+        }
     }
 
-    fn _create(&mut self, chemical_name: String, minimum_amount: usize) -> Result<()> {
+    fn _create(&mut self, chemical_name: String, minimum_amount: Q) -> Result<()> {
         let chemical_rqmts = self.recipes.get(&chemical_name)
             .ok_or(format!("Cannot find recipe for chemical {}", chemical_name))?
             .clone();
+        let overflow = |chemical: &str| QuantityOverflow { chemical: chemical.to_string(), bits: Q::BITS };
 
-        let complete_sets_needed: usize = (minimum_amount as f64 / chemical_rqmts.output.amount as f64).ceil() as usize;
+        let complete_sets_needed = minimum_amount.checked_div_ceil(Q::from_usize(chemical_rqmts.output.amount))
+            .ok_or_else(|| overflow(&chemical_name))?;
 
         for input_material in &chemical_rqmts.inputs {
+            let amount_needed = complete_sets_needed.checked_mul(Q::from_usize(input_material.amount))
+                .ok_or_else(|| overflow(&input_material.chemical))?;
+
             if &input_material.chemical == "ORE" {
-                self.ore_usage += complete_sets_needed*input_material.amount;
-                if self.ore_usage > 1_000_000_000_000 {
-                    return err!("Run out of ore");
-                }
+                self.ore_usage = self.ore_usage.checked_add(amount_needed).ok_or_else(|| overflow("ORE"))?;
                 continue;
             }
 
-            let current_amount: usize = self.material_counts[&input_material.chemical];
+            let current_amount: Q = *self.material_counts.get(&input_material.chemical)
+                .ok_or(format!("Cannot find recipe for chemical {}", input_material.chemical))?;
 
-            if current_amount < input_material.amount * complete_sets_needed {
-                self._create(input_material.chemical.clone(), input_material.amount*complete_sets_needed - current_amount)?;
+            if current_amount < amount_needed {
+                self._create(input_material.chemical.clone(), amount_needed - current_amount)?;
             }
 
             // while self.material_counts[&input_material.chemical] < bulk_amount * input_material.amount {
@@ -158,13 +234,15 @@ impl Nanofactory {
 
             let input_count = self.material_counts.get_mut(&input_material.chemical)
                 .ok_or(format!("Cannot find recipe for chemical {}", input_material.chemical))?;
-            *input_count -= complete_sets_needed * input_material.amount;
+            *input_count = *input_count - amount_needed;
         }
 
         // increase chemical amount
+        let amount_made = complete_sets_needed.checked_mul(Q::from_usize(chemical_rqmts.output.amount))
+            .ok_or_else(|| overflow(&chemical_name))?;
         let chemical_count = self.material_counts.get_mut(&chemical_name)
             .ok_or(format!("Cannot find recipe for chemical {}", chemical_name))?;
-        *chemical_count += complete_sets_needed * chemical_rqmts.output.amount;
+        *chemical_count = chemical_count.checked_add(amount_made).ok_or_else(|| overflow(&chemical_name))?;
 
         Ok(())
     }
@@ -211,117 +289,149 @@ impl Nanofactory {
     // }
 
     fn produce_one_fuel(&mut self) -> Result<()> {
-        self._create("FUEL".to_string(), 1)?;
+        self._create("FUEL".to_string(), Q::from_usize(1))?;
 
         Ok(())
     }
 
-    fn find_cyclic_usage(&mut self) -> Result<(usize, usize)> {
+    fn find_cyclic_usage(&mut self) -> Result<(usize, Q)> {
         let mut fuel_produced = 0;
 
         loop {
-            self._create("FUEL".to_string(), 1)?;
+            self._create("FUEL".to_string(), Q::from_usize(1))?;
             fuel_produced += 1;
 
-            if self.material_counts.iter().filter(|(k, _)| *k != "FUEL").all(|(_, &v)| v == 0) {
+            if self.material_counts.iter().filter(|(k, _)| *k != "FUEL").all(|(_, &v)| v == Q::default()) {
                 break;
             }
         }
 
         let total_ore_used = self.ore_usage;
 
-        self.ore_usage = 0;
+        self.ore_usage = Q::default();
 
         Ok((fuel_produced, total_ore_used))
     }
 
     fn wipe_everything(&mut self) {
-        self.material_counts = self.material_counts.keys().map(|k| (k.clone(), 0)).collect();
-        self.ore_usage = 0;
+        self.material_counts = self.material_counts.keys().map(|k| (k.clone(), Q::default())).collect();
+        self.ore_usage = Q::default();
     }
 
-    fn max_fuel_output(&mut self, lower_limit: usize) -> Result<usize> {
-        // let mut total_fuel_produced = 0;
-        // answer in [955_000, 955_000 + 1_048_576]
-        // binary search
+    // Ore needed to make `fuel` from nothing
+    fn ore_for_fuel(&mut self, fuel: Q) -> Result<Q> {
+        self.wipe_everything();
+        self._create("FUEL".to_string(), fuel)?;
 
-        // let upper_limit = lower_limit + 1_048_576;
+        Ok(self.ore_usage)
+    }
+}
 
-        let mut current_guess = lower_limit;
-        let mut jump = 1048576/2;
+// Ore needed to make `fuel` from nothing, counted in 64 bits, or in 128 for chains of
+// reactions too deep for that
+fn ore_needed(recipes: &Recipes, fuel: u64) -> Result<u128> {
+    match Nanofactory::<u64>::new(recipes.clone()).ore_for_fuel(fuel) {
+        Ok(ore) => Ok(u128::from(ore)),
+        Err(e) if e.is::<QuantityOverflow>() => Nanofactory::<u128>::new(recipes.clone()).ore_for_fuel(u128::from(fuel)),
+        Err(e) => Err(e)
+    }
+}
 
-        let mut result = 0;
+// The recipes in a form which can key a memo, sorted by what each one makes
+type RecipeKey = Vec<(String, RecipeRequirements)>;
 
-        loop {
-            self.wipe_everything();
-
-            if self._create("FUEL".to_string(), current_guess).is_ok() {
-                println!("{} fuel needed {} ore", current_guess, self.ore_usage);
-                result = current_guess;
-                current_guess += jump;
-            } else {
-                println!("Cannot make {} fuel", current_guess);
-                current_guess -= jump;
-            }
+lazy_static! {
+    static ref ORE_PER_FUEL: Mutex<Memo<RecipeKey, u128>> = Mutex::new(Memo::new());
+}
 
-            if jump == 0 {
-                return Ok(result);
-            }
+// Ore needed to make one fuel, which both parts start from, remembered in `memo` for each
+// set of recipes
+fn ore_per_fuel_in(memo: &mut Memo<RecipeKey, u128>, recipes: &Recipes) -> Result<u128> {
+    let mut key: RecipeKey = recipes.iter().map(|(name, recipe)| (name.clone(), recipe.clone())).collect();
+    key.sort_by(|a, b| a.0.cmp(&b.0));
 
-            jump /= 2;
-        }
-    }
+    memo.try_get_or_compute(key, |_| ore_needed(recipes, 1))
 }
 
-pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+// As `ore_per_fuel_in`, sharing one memo between the parts, so running both only works
+// it out once
+fn ore_per_fuel(recipes: &Recipes) -> Result<u128> {
+    ore_per_fuel_in(&mut ORE_PER_FUEL.lock().unwrap(), recipes)
+}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+const ORE_COLLECTED: u128 = 1_000_000_000_000;
 
-    let recipes: Vec<String> = f_contents.trim().lines().map(|s| s.trim().to_string()).collect();
+fn max_fuel_output(recipes: &Recipes, lower_limit: u64) -> Result<u64> {
+    // let mut total_fuel_produced = 0;
+    // answer in [955_000, 955_000 + 1_048_576]
+    // binary search
 
-    _q1(recipes).unwrap()
-}
+    // let upper_limit = lower_limit + 1_048_576;
+
+    let mut current_guess = lower_limit;
+    let mut jump = 1048576/2;
+
+    let mut result = 0;
+
+    loop {
+        let ore = ore_needed(recipes, current_guess)?;
+        if ore <= ORE_COLLECTED {
+            println!("{} fuel needed {} ore", current_guess, ore);
+            result = current_guess;
+            current_guess += jump;
+        } else {
+            println!("Cannot make {} fuel", current_guess);
+            current_guess -= jump;
+        }
 
+        if jump == 0 {
+            return Ok(result);
+        }
 
-fn _q1(recipes: Vec<String>) -> Result<usize> {
-    let mut nanofactory = Nanofactory::new(recipes)?;
+        jump /= 2;
+    }
+}
 
-    nanofactory.produce_one_fuel()?;
+pub fn q1(fname: String) -> u128 {
+    let recipes = cache::warm(&fname, |s| parse_recipes(recipe_lines(s))).expect("Couldn't read input file");
 
-    Ok(nanofactory.ore_usage)
+    _q1_from(recipes).unwrap()
 }
 
 
-pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+fn _q1(recipes: Vec<String>) -> Result<u128> {
+    _q1_from(parse_recipes(recipes)?)
+}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+fn _q1_from(recipes: Recipes) -> Result<u128> {
+    ore_per_fuel(&recipes)
+}
 
-    let recipes: Vec<String> = f_contents.trim().lines().map(|s| s.trim().to_string()).collect();
 
-    _q2(recipes).unwrap()
+pub fn q2(fname: String) -> u64 {
+    let recipes = cache::warm(&fname, |s| parse_recipes(recipe_lines(s))).expect("Couldn't read input file");
+
+    _q2_from(recipes).unwrap()
 }
 
 
-fn _q2(recipes: Vec<String>) -> Result<usize> {
-    let mut nanofactory = Nanofactory::new(recipes)?;
+fn _q2(recipes: Vec<String>) -> Result<u64> {
+    _q2_from(parse_recipes(recipes)?)
+}
 
+fn _q2_from(recipes: Recipes) -> Result<u64> {
     // first, get the general lower bound for what to guess
     // 1 trillion / amount to make 1 fuel
     // using this, x - (x % 10000)
-    nanofactory.produce_one_fuel()?;
-    println!("Ore usage for one fuel is {}", nanofactory.ore_usage);
-    let lower_bound = 1_000_000_000_000 / nanofactory.ore_usage;
+    let ore_usage = ore_per_fuel(&recipes)?;
+    println!("Ore usage for one fuel is {}", ore_usage);
+    let lower_bound = (ORE_COLLECTED / ore_usage) as u64;
     println!("Initial lower bound is {}", lower_bound);
-    nanofactory.wipe_everything();
     let lower_bound = lower_bound - (lower_bound % 10_000);
 
     println!("Initial lower bound is now {}", lower_bound);
 
-    let max_fuel = nanofactory.max_fuel_output(lower_bound)?;
+    let max_fuel = max_fuel_output(&recipes, lower_bound)?;
 
     Ok(max_fuel)
 }
@@ -331,6 +441,8 @@ fn _q2(recipes: Vec<String>) -> Result<usize> {
 mod tests {
     use super::*;
 
+    use generate::generate;
+
     #[test]
     fn day14_q1_test1() {
         let recipe_list : Vec<String> = "
@@ -507,4 +619,50 @@ mod tests {
             460664
         )
     }
+
+    #[test]
+    fn day14_remembers_the_ore_per_fuel() {
+        let recipes = parse_recipes("
+            10 ORE => 10 A
+            1 ORE => 1 B
+            7 A, 1 B => 1 C
+            7 A, 1 C => 1 D
+            7 A, 1 D => 1 E
+            7 A, 1 E => 1 FUEL
+        ".trim().lines().map(|l| l.trim().to_string()).collect()).unwrap();
+
+        let mut memo = Memo::new();
+        assert_eq!(ore_per_fuel_in(&mut memo, &recipes).unwrap(), 31);
+        assert_eq!(ore_per_fuel_in(&mut memo, &recipes.clone()).unwrap(), 31);
+        assert_eq!((memo.hits(), memo.misses()), (1, 1));
+
+        // Part 2 starts from what part 1 worked out. Other tests share the memo, so only
+        // its growth is known.
+        assert_eq!(_q1_from(recipes.clone()).unwrap(), 31);
+        let hits = ORE_PER_FUEL.lock().unwrap().hits();
+        assert_eq!(ore_per_fuel(&recipes).unwrap(), 31);
+        assert!(ORE_PER_FUEL.lock().unwrap().hits() > hits);
+    }
+
+    #[test]
+    fn day14_counts_deep_chains_in_128_bits() {
+        let chain = |size| -> Recipes {
+            let input = generate(14, Some(size), 14).unwrap();
+            parse_recipes(input.lines().map(|l| l.to_string()).collect()).unwrap()
+        };
+
+        // Both widths agree while the numbers are small
+        let shallow = chain(40);
+        let ore = Nanofactory::<u64>::new(shallow.clone()).ore_for_fuel(1).unwrap();
+        assert_eq!(ore_needed(&shallow, 1).unwrap(), u128::from(ore));
+
+        let deep = chain(100);
+        let error = Nanofactory::<u64>::new(deep.clone()).ore_for_fuel(1).unwrap_err();
+        assert_eq!(error.downcast_ref::<QuantityOverflow>().unwrap().bits, 64);
+        assert!(ore_needed(&deep, 1).unwrap() > u128::from(u64::MAX));
+
+        // Too deep for either gives the typed error rather than a wrapped answer
+        let error = ore_needed(&chain(150), 1).unwrap_err();
+        assert_eq!(error.downcast_ref::<QuantityOverflow>().unwrap().bits, 128);
+    }
 }
@@ -1,13 +1,21 @@
 use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
 use std::ops::{Add, Sub, AddAssign};
 use std::result;
 
-use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use aoc_problems::facts;
+use aoc_problems::intcode::{RunState, VmBuilder};
+use util::grid::{astar, bounds, flood_fill_from, manhattan, Grid2D, Point2, SparseGrid};
+use util::input;
+use util::iter;
+use util::maze;
+use viz::{self, Recorder};
 
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
@@ -46,6 +54,10 @@ impl Coordinate {
             Coordinate::new(self.x, self.y - 1)
         ]
     }
+
+    fn to_point(self) -> Point2 {
+        Point2::new(i64::from(self.x), i64::from(self.y))
+    }
 }
 
 impl Ord for Coordinate {
@@ -103,276 +115,6 @@ impl fmt::Display for Coordinate {
     }
 }
 
-#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Parameter {
-    Position,
-    Immediate,
-    Relative
-}
-
-#[derive(Clone, Eq, Default, Debug, PartialEq, Hash)]
-struct Instruction {
-    opcode: usize,
-    parameters: Vec<Parameter>
-}
-
-impl Instruction {
-    fn new(number: usize) -> Result<Instruction> {
-        let opcode = number % 100;
-        let mut digit_list: Vec<_> = (number / 100).to_string().chars().map(|d| d.to_digit(10).unwrap()).collect();
-        digit_list.reverse();
-
-        let params_length = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            9 => 1,
-            99 => 0,
-            x => return err!("{}", format!("Cannot read opcode: {}", x))
-        };
-
-        digit_list.resize(params_length, 0);
-        let parameters: Result<Vec<Parameter>> = digit_list.into_iter().map(|d| match d {
-            0 => Ok(Parameter::Position),
-            1 => Ok(Parameter::Immediate),
-            2 => Ok(Parameter::Relative),
-            x => err!("{}", format!("Cannot read parameter digit: {}", x))
-        }).collect();
-        let parameters = parameters?;
-
-        Ok(
-            Instruction {
-                opcode,
-                parameters,
-            }
-        )
-
-    }
-}
-
-struct Program {
-    memory: Vec<i64>,
-    input: i64,
-    current_input: usize,
-    pointer_idx: usize,
-    relative_base: i64,
-}
-
-impl Program {
-    fn new(memory: Vec<i64>)  -> Program {
-        Program {
-            memory,
-            input: 0,
-            current_input: 1,
-            pointer_idx: 0,
-            relative_base: 0,
-        }
-    }
-
-    fn get_input(&mut self) -> Result<i64> {
-        Ok(self.input as i64)
-    }
-
-    fn set_input(&mut self, input: i64) {
-        self.input = input;
-    }
-
-    fn get_parameter(&mut self, parameter_form: Parameter, val: i64) -> i64 {
-        use self::Parameter::*;
-
-        match parameter_form {
-            Position => {
-                let idx = val as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            },
-            Immediate => val,
-            Relative => {
-                let idx = (self.relative_base + val) as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            }
-        }
-    }
-
-    fn set_parameter(&mut self, idx: usize, val: i64) -> Result<()> {
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-
-        self.memory[idx] = val;
-
-        Ok(())
-    }
-
-    fn get_output_idx(&mut self, idx: usize, parameter_type: Parameter) -> usize {
-        use self::Parameter::*;
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-        match parameter_type {
-            Position => {
-                self.memory[idx] as usize
-            },
-            Relative => {
-                (self.memory[idx] + self.relative_base) as usize
-            },
-            _ => panic!("Should never be here")
-        }
-    }
-
-    fn run_program(&mut self) -> Result<Option<i64>> {
-        loop {
-            let current_instruction = Instruction::new(self.memory[self.pointer_idx] as usize)?;
-
-            match current_instruction.opcode {
-                1 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 + input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                2 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 * input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                3 => {
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 1,
-                        current_instruction.parameters[0]
-                    );
-                    let input = self.get_input()?;
-                    self.set_parameter(output_idx, input)?;
-
-                    self.pointer_idx += 2;
-                },
-                4 => {
-                    let output_val = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1]
-                    );
-
-                    // let output_idx = self.memory[self.pointer_idx+1];
-                    self.pointer_idx += 2;
-
-                    return Ok(Some(output_val));
-                },
-                5 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 != 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                6 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 == 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                7 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 < input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                8 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 == input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                9 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    self.relative_base += input_1;
-
-                    self.pointer_idx += 2;
-                },
-                99 => break,
-                x => return err!("{}", format!("Incorrect opcode: {}", x))
-            }
-        }
-        Ok(None)
-    }
-}
-
 #[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
 enum SquareType {
     Wall,
@@ -433,186 +175,179 @@ impl Direction {
 }
 
 struct Droid {
-    program: Program,
     floor_map: BTreeMap<Coordinate, SquareType>,
     leak_location: Coordinate,
     current_coord: Coordinate,
+    // The square being explored, and the moves left to get next to it
+    current_target: Coordinate,
+    route: VecDeque<Direction>,
+    // The move the program was last given, whose outcome it outputs next
+    moving: Option<Direction>,
+    // Saves the map after every move, if set
+    recorder: Option<Recorder>
 }
 
 impl Droid {
-    fn new(memory: Vec<i64>) -> Droid {
+    fn new() -> Droid {
+        let mut floor_map = BTreeMap::new();
+        floor_map.insert(Coordinate::new(0, 0), SquareType::Open);
+
         Droid {
-            program: Program::new(memory),
-            floor_map: BTreeMap::new(),
+            floor_map,
             leak_location: Coordinate::new(0, 0),
-            current_coord: Coordinate::new(0, 0)
+            current_coord: Coordinate::new(0, 0),
+            current_target: Coordinate::new(0, 0),
+            route: VecDeque::new(),
+            moving: None,
+            recorder: None
         }
     }
 
-    fn shortest_path_from_to(&self, from: Coordinate, to: Coordinate) -> Result<Vec<Coordinate>> {
-        let mut d = BTreeMap::new();
-        d.insert(from, 0);
-
-        let mut queue: VecDeque<Coordinate> = VecDeque::new();
-        queue.push_front(from);
-        let mut todo_set: BTreeSet<Coordinate> = BTreeSet::new();
-        let mut visited: BTreeSet<Coordinate> = BTreeSet::new();
-        while let Some(c) = queue.pop_front() {
-            todo_set.remove(&c);
-            visited.insert(c);
-
-            if c == to {
-                break;
-            }
+    fn record_frame(&mut self) -> Result<()> {
+        if let Some(mut recorder) = self.recorder.take() {
+            recorder.record(&self.to_string())?;
+            self.recorder = Some(recorder);
+        }
 
-            for neighbour in c.neighbours().into_iter().filter(|coord| self.floor_map.get(&coord) == Some(&SquareType::Open)) {
-                if visited.contains(&neighbour) {
-                    continue;
-                }
-                if !todo_set.contains(&neighbour) {
-                    queue.push_back(neighbour);
-                    todo_set.insert(neighbour);
-                }
+        Ok(())
+    }
 
-                let new_dist = 1 + *d.get(&c).unwrap_or(&0);
-                if !d.contains_key(&neighbour) || new_dist < d[&neighbour] {
-                    d.insert(neighbour, new_dist);
-                }
-            }
-        }
+    fn shortest_path_from_to(&self, from: Coordinate, to: Coordinate) -> Result<Vec<Coordinate>> {
+        let goal = Point2::new(to.x as i64, to.y as i64);
+        let passable = |pt: Point2| {
+            pt == goal || self.floor_map.get(&Coordinate::new(pt.x as i32, pt.y as i32)) == Some(&SquareType::Open)
+        };
 
-        let mut path_to_take: Vec<Coordinate> = vec![to];
-        let mut current_position = to;
-        loop {
-            let next_position = current_position.neighbours().into_iter().filter(|&c| d.contains_key(&c)).min_by_key(|&c| d[&c]);
-            if next_position.is_none() {
+        match astar(Point2::new(from.x as i64, from.y as i64), goal, passable, manhattan) {
+            Some(path) => Ok(path.points.iter().map(|pt| Coordinate::new(pt.x as i32, pt.y as i32)).collect()),
+            None => {
                 println!("{}", self);
-                return err!("Cannot find any neighbours for {}", current_position);
-            }
-
-            let next_position = next_position.unwrap();
-            path_to_take.push(next_position);
-            if next_position == from {
-                break;
+                err!("Cannot find a path from {} to {}", from, to)
             }
-            current_position = next_position;
         }
+    }
 
-        path_to_take.reverse();
-        Ok(path_to_take)
+    // Steps from the start to the oxygen system, over the map with its corridors collapsed.
+    // Only the start and the system are marked, so the dead ends the droid explored are
+    // dropped from the graph.
+    fn dist_to_leak(&self) -> Result<usize> {
+        let start = Coordinate::new(0, 0);
+        let floor: SparseGrid<char> = self.floor_map.iter()
+            .map(|(&coord, &square)| {
+                let c = match square {
+                    SquareType::Wall => '#',
+                    SquareType::Open if coord == start => '@',
+                    SquareType::Open => '.',
+                    SquareType::System => 'x'
+                };
+                (coord.to_point(), c)
+            })
+            .collect();
+        let (grid, origin) = floor.to_dense('#');
+
+        maze::compress(&grid)
+            .shortest_path(start.to_point() - origin, self.leak_location.to_point() - origin)
+            .ok_or_else(|| format!("Cannot find a path from {} to {}", start, self.leak_location).into())
     }
 
-    fn steps_to_get_to(&self, coord: Coordinate) -> Result<usize> {
-        let path = self.shortest_path_from_to(Coordinate::new(0, 0), coord)?;
+    // Plans the way to the next square worth exploring once it has got where it was going,
+    // returning false when every square next to open floor has been mapped
+    fn plan(&mut self) -> Result<bool> {
+        if !self.route.is_empty() {
+            return Ok(true);
+        }
+
+        if self.floor_map.contains_key(&self.current_target) {
+            let next_potential_target = self.floor_map.iter()
+                .filter(|(_, &square)| square == SquareType::Open)
+                .flat_map(|(&coord, _)| coord.neighbours())
+                .find(|coord| !self.floor_map.contains_key(coord));
+            match next_potential_target {
+                Some(c) => self.current_target = c,
+                None => return Ok(false)
+            }
+        }
+
+        let path_to_next_target = self.shortest_path_from_to(self.current_coord, self.current_target)?;
+        self.route = convert_path_to_directions(path_to_next_target)?.into_iter().collect();
 
-        Ok(path.len() - 1)
+        Ok(true)
     }
 
-    fn dist_to_leak(&self) -> Result<usize> {
-        self.steps_to_get_to(self.leak_location)
+    // The program's next input, which is the next move along the planned route
+    fn next_move(&mut self) -> i64 {
+        let direction = self.route.pop_front().expect("The droid moves only once it has planned a route");
+        self.moving = Some(direction);
+        direction.to_digit()
     }
 
-    fn find_leak(&mut self, stop_on_leak: bool) -> Result<()> {
-        self.floor_map.insert(self.current_coord, SquareType::Open);
-
-        let mut current_target = Coordinate::new(0, 0);
-
-        // set input to chosen direction
-        // run program
-        // figure out what to do based on output values
-        'main: loop {
-            if self.floor_map.contains_key(&current_target) {
-                let next_potential_target = self.floor_map.iter()
-                    .filter(|(_, &square)| square == SquareType::Open)
-                    .flat_map(|(&coord, _)| coord.neighbours())
-                    .filter(|&coord| !self.floor_map.contains_key(&coord))
-                    .next();
-                if let Some(c) = next_potential_target {
-                    current_target = c;
-                } else {
-                    return Ok(());
-                }
-            }
+    // Takes in the program's report on the last move, returning what the droid found
+    fn moved(&mut self, status: i64) -> Result<SquareType> {
+        let direction = self.moving.take().ok_or("The program reported a move it wasn't given")?;
+        let next = self.current_coord + direction.to_coordinate();
+        let square = match status {
+            0 => {
+                // hit a wall, so the rest of the route goes nowhere
+                self.route.clear();
+                SquareType::Wall
+            },
+            1 => SquareType::Open,
+            2 => {
+                println!("Found leak at {}!", next);
+                self.leak_location = next;
+                SquareType::System
+            },
+            x => return err!("Unexpected output from program: {}", x)
+        };
 
-            // println!("Current target = {:?}", current_target);
-
-            let path_to_next_target = self.shortest_path_from_to(self.current_coord, current_target)?;
-
-            // println!("Path from {} to {} = {:?}", self.current_coord, current_target, path_to_next_target);
-
-            let directions = convert_path_to_directions(path_to_next_target)?;
-
-            for direction in directions {
-                // println!("Inputting {:?} ({}) into program", direction, direction.to_digit());
-                self.program.set_input(direction.to_digit());
-                if let Some(result) = self.program.run_program()? {
-                    match result {
-                        0 => {
-                            // hit a wall
-                            // println!("{} is a wall, droid doesn't move", self.current_coord + direction.to_coordinate());
-                            self.floor_map.insert(self.current_coord + direction.to_coordinate(), SquareType::Wall);
-                            continue 'main;
-                        },
-                        1 => {
-                            // all is well
-                            // println!("{} is clear, droid moves", self.current_coord + direction.to_coordinate());
-                            self.floor_map.insert(self.current_coord + direction.to_coordinate(), SquareType::Open);
-                            self.current_coord += direction.to_coordinate();
-                        },
-                        2 => {
-                            // moved and found leak!
-                            println!("Found leak at {}!", self.current_coord + direction.to_coordinate());
-                            // println!("{}", self);
-                            self.floor_map.insert(self.current_coord + direction.to_coordinate(), SquareType::System);
-                            self.leak_location = self.current_coord + direction.to_coordinate();
-                            self.current_coord += direction.to_coordinate();
-                            if stop_on_leak {
-                                break 'main;
-                            }
-                        },
-                        x => panic!("Unexpected output from program: {}", x)
-                    }
-                }
-            }
+        self.floor_map.insert(next, square);
+        if square != SquareType::Wall {
+            self.current_coord = next;
+            self.record_frame()?;
         }
 
-        Ok(())
+        Ok(square)
     }
 
-    fn time_for_oxygen_spread(&mut self) -> Result<usize> {
-        let mut oxygen_squares: BTreeSet<Coordinate> = BTreeSet::new();
-        oxygen_squares.insert(self.leak_location);
-
-        let mut t = 0;
+    // Steps from every square of the mapped area to the nearest oxygen system, searching
+    // out from all of them at once. The grid's top left corner is at the returned point;
+    // walls and unexplored squares have no distance.
+    fn oxygen_distances(&self) -> (Grid2D<Option<usize>>, Point2) {
+        let floor: SparseGrid<SquareType> = self.floor_map.iter()
+            .map(|(&coord, &square)| (coord.to_point(), square))
+            .collect();
+        let (grid, origin) = floor.to_dense(SquareType::Wall);
 
-        loop {
-            let near_oxygen_squares: Vec<_> = oxygen_squares.iter()
-                .flat_map(|&coord| coord.neighbours())
-                .filter(|&coord| self.floor_map[&coord] == SquareType::Open && !oxygen_squares.contains(&coord)).collect();
+        let systems = grid.points().filter(|&pt| grid[pt] == SquareType::System).collect::<Vec<_>>();
+        let oxygen = flood_fill_from(&grid, systems, |&square| square != SquareType::Wall);
 
-            if near_oxygen_squares.is_empty() {
-                break;
-            }
+        (oxygen.distance_grid(grid.width(), grid.height()), origin)
+    }
 
-            oxygen_squares.extend(near_oxygen_squares.iter());
+    // How far the droid would have been from oxygen had it started at `start` instead,
+    // or None if `start` isn't an open square of the map
+    fn steps_to_oxygen_from(&self, start: Coordinate) -> Option<usize> {
+        let (distances, origin) = self.oxygen_distances();
+        distances.get(start.to_point() - origin).cloned().flatten()
+    }
 
-            t += 1;
-        }
+    fn time_for_oxygen_spread(&mut self) -> Result<usize> {
+        let (distances, _) = self.oxygen_distances();
 
-        Ok(t)
+        Ok(distances.iter().filter_map(|(_, &dist)| dist).max().unwrap_or(0))
     }
 }
 
 impl fmt::Display for Droid {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let first_x = self.floor_map.keys().map(|&c| c.x).min().unwrap();
-        let last_x = self.floor_map.keys().map(|&c| c.x).max().unwrap();
-        let first_y = self.floor_map.keys().map(|&c| c.y).min().unwrap();
-        let last_y = self.floor_map.keys().map(|&c| c.y).max().unwrap();
-
-        for y in (first_y..=last_y).rev() {
-            for x in first_x..=last_x {
-                let coord = Coordinate::new(x, y);
+        let (first, last) = match bounds(self.floor_map.keys().map(|c| c.to_point())) {
+            Some(bounds) => bounds,
+            None => return Ok(())
+        };
+
+        for y in (first.y..=last.y).rev() {
+            for x in first.x..=last.x {
+                let coord = Coordinate::new(x as i32, y as i32);
                 if coord == self.current_coord {
                     write!(f, "D")?;
                     continue;
@@ -639,11 +374,42 @@ impl fmt::Display for Droid {
     }
 }
 
+// Maps the area by running the repair droid's program, which asks where to move next
+// through its input. It stops once the oxygen system is found if `stop_on_leak` is set,
+// and otherwise once there is nothing left to explore.
+fn explore(memory: Vec<i64>, stop_on_leak: bool, recorder: Option<Recorder>) -> Result<Droid> {
+    let mut droid = Droid::new();
+    droid.recorder = recorder;
+    let droid = Arc::new(Mutex::new(droid));
+
+    let policy = Arc::clone(&droid);
+    let mut program = VmBuilder::new(memory)
+        .input_fn(move || policy.lock().unwrap().next_move())
+        .build()?;
+
+    while droid.lock().unwrap().plan()? {
+        let found = match program.run_program()? {
+            RunState::Output(status) => droid.lock().unwrap().moved(status)?,
+            state => return err!("The droid's program stopped exploring: {:?}", state)
+        };
+        if found == SquareType::System && stop_on_leak {
+            break;
+        }
+    }
+
+    // The program holds the other handle on the droid
+    drop(program);
+    match Arc::try_unwrap(droid) {
+        Ok(droid) => Ok(droid.into_inner().unwrap()),
+        Err(_) => err!("The droid is still in use")
+    }
+}
+
 fn convert_path_to_directions(path: Vec<Coordinate>) -> Result<Vec<Direction>> {
-    path.windows(2).map(|t| {
+    iter::windows2(&path).map(|(&from, &to)| {
         use self::Direction::*;
 
-        let coord_difference = t[1] - t[0];
+        let coord_difference = to - from;
 
         match (coord_difference.x, coord_difference.y) {
             (1, 0) => Ok(Right),
@@ -657,10 +423,7 @@ fn convert_path_to_directions(path: Vec<Coordinate>) -> Result<Vec<Direction>> {
 
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
@@ -668,29 +431,87 @@ pub fn q1(fname: String) -> usize {
 }
 
 fn _q1(memory: Vec<i64>) -> Result<usize> {
-    let mut droid = Droid::new(memory);
-    droid.find_leak(true)?;
+    let droid = explore(memory, true, None)?;
+    facts::record("oxygen system", droid.leak_location);
     droid.dist_to_leak()
 }
 
+// Maps the whole area, then answers part 1 as if the droid had started at `start`, given
+// as `x,y` with y increasing northwards
+pub fn q1_from(fname: String, start: &str) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+
+    let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
+
+    _q1_from(memory, start).unwrap()
+}
+
+fn _q1_from(memory: Vec<i64>, start: &str) -> Result<usize> {
+    let start = match start.split(',').map(|n| n.trim().parse()).collect::<result::Result<Vec<i32>, _>>() {
+        Ok(ref xy) if xy.len() == 2 => Coordinate::new(xy[0], xy[1]),
+        _ => return err!("Cannot read starting position {:?}, expected x,y", start)
+    };
+
+    let droid = explore(memory, false, None)?;
+    facts::record("oxygen system", droid.leak_location);
+
+    match droid.steps_to_oxygen_from(start) {
+        Some(steps) => Ok(steps),
+        None => err!("{} is not an open square", start)
+    }
+}
+
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+    q2_with(fname, None, false)
+}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+// As part 2, saving the map after each of the droid's moves to `record` for playing
+// back later if given a file, and drawing each square's distance from the oxygen system
+// as a heatmap if `heatmap` is set
+pub fn q2_with(fname: String, record: Option<&str>, heatmap: bool) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
-    _q2(memory).unwrap()
+    _q2(memory, record, heatmap).unwrap()
 }
 
-fn _q2(memory: Vec<i64>) -> Result<usize> {
-    let mut droid = Droid::new(memory);
+fn _q2(memory: Vec<i64>, record: Option<&str>, heatmap: bool) -> Result<usize> {
+    let recorder = match record {
+        Some(path) => Some(Recorder::create(path)?),
+        None => None
+    };
+    let mut droid = explore(memory, false, recorder)?;
+    facts::record("oxygen system", droid.leak_location);
 
-    droid.find_leak(false)?;
+    if let (Some(recorder), Some(path)) = (droid.recorder.take(), record) {
+        println!("Recorded {} frames to {}", recorder.finish()?, path);
+    }
 
     // Map has been completely filled in
     println!("{}", droid);
+    if heatmap {
+        let (distances, _) = droid.oxygen_distances();
+        let distances = Grid2D::from_fn(distances.width(), distances.height(), |pt| distances[pt].map(|d| d as i64));
+        println!("{}", viz::heatmap(&distances)?);
+    }
 
     droid.time_for_oxygen_spread()
 }
+
+// Both parts from a single exploration of the area: the shortest route to the oxygen
+// system through the complete map is the same one part 1 finds
+pub fn both(fname: String) -> (usize, usize) {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+
+    let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
+
+    _both(memory).unwrap()
+}
+
+fn _both(memory: Vec<i64>) -> Result<(usize, usize)> {
+    let mut droid = explore(memory, false, None)?;
+    facts::record("oxygen system", droid.leak_location);
+
+    Ok((droid.dist_to_leak()?, droid.time_for_oxygen_spread()?))
+}
@@ -1,11 +1,15 @@
+use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
+use std::mem;
 use std::result;
 
 use itertools::Itertools;
 
+use util::digits::{self, parse_digits};
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 fn pause() {
@@ -20,20 +24,60 @@ fn pause() {
     let _ = stdin.read(&mut [0u8]).unwrap();
 }
 
+// The repeating pattern each output digit multiplies the input by. For output digit n
+// every value of `base` is repeated (n + 1) * `repeat` times, and the very first value
+// of the whole sequence is skipped.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pattern {
+    pub base: Vec<i32>,
+    pub repeat: usize
+}
+
+impl Default for Pattern {
+    fn default() -> Pattern {
+        Pattern {
+            base: vec![0, 1, 0, -1],
+            repeat: 1
+        }
+    }
+}
+
+impl Pattern {
+    // Reads a base pattern such as `0,1,0,-1`
+    pub fn parse(s: &str, repeat: usize) -> Result<Pattern> {
+        let base: result::Result<Vec<i32>, _> = s.split(',').map(|n| n.trim().parse()).collect();
+        let base = base.map_err(|_| format!("Cannot read pattern {:?}, expected numbers separated by commas", s))?;
+        if base.is_empty() || repeat == 0 {
+            return Err("A pattern needs at least one value, repeated at least once".into());
+        }
+
+        Ok(Pattern { base, repeat })
+    }
+}
+
 struct Signal {
-    numbers: Vec<i32>
+    numbers: Vec<u8>,
+    buffer: Vec<u8>,
+    pattern: Pattern
 }
 
 impl Signal {
-    fn new(numbers: Vec<i32>) -> Signal {
+    fn new(numbers: Vec<u8>) -> Signal {
+        Signal::with_pattern(numbers, Pattern::default())
+    }
+
+    fn with_pattern(numbers: Vec<u8>, pattern: Pattern) -> Signal {
+        let buffer = vec![0; numbers.len()];
         Signal {
-            numbers
+            numbers,
+            buffer,
+            pattern
         }
     }
 
     fn fft_iterate(&mut self) -> Result<()> {
-        let result = (1..=self.numbers.len()).map(|n| fft_step_for(&self.numbers, n)).collect();
-        self.numbers = result;
+        fft_phase_with(&self.numbers, &mut self.buffer, &self.pattern);
+        mem::swap(&mut self.numbers, &mut self.buffer);
 
         Ok(())
     }
@@ -55,43 +99,75 @@ impl Signal {
     }
 }
 
-fn fft_pattern(step: usize, size: usize) -> Vec<i32> {
-    let mut result: Vec<i32> = vec![];
+// Runs a single FFT phase of `input` with the puzzle's pattern, writing the new digits
+// into `output`
+pub(crate) fn fft_phase(input: &[u8], output: &mut [u8]) {
+    fft_phase_with(input, output, &Pattern::default());
+}
+
+// Runs a single FFT phase with any pattern. The pattern for output digit n is constant
+// over blocks of (n + 1) * repeat input digits, so each digit is a sum over blocks,
+// each found from running totals of the input, rather than over every input digit.
+pub fn fft_phase_with(input: &[u8], output: &mut [u8], pattern: &Pattern) {
+    let mut totals: Vec<i32> = Vec::with_capacity(input.len() + 1);
+    totals.push(0);
+    for &d in input {
+        let total = totals[totals.len() - 1] + i32::from(d);
+        totals.push(total);
+    }
 
-    'main: loop {
-        for digit in [0, 1, 0, -1].iter() {
-            for _ in 0..step {
-                result.push(*digit);
-                if result.len() >= size + 1 {
-                    break 'main;
-                }
+    for (idx, digit) in output.iter_mut().enumerate() {
+        let step = (idx + 1) * pattern.repeat;
+
+        // Block k covers the input digits whose pattern position, counting the skipped
+        // first value, is in [k * step, (k + 1) * step)
+        let mut sum: i32 = 0;
+        let mut block = 0;
+        while block * step < input.len() + 1 {
+            let coefficient = pattern.base[block % pattern.base.len()];
+            if coefficient != 0 {
+                let start = cmp::max(block * step, 1) - 1;
+                let end = cmp::min((block + 1) * step - 1, input.len());
+                sum += coefficient * (totals[end] - totals[start]);
             }
+            block += 1;
         }
-    }
 
-    result.remove(0);
-    result
+        *digit = (sum.abs() % 10) as u8;
+    }
 }
 
-fn fft_step_for(numbers: &Vec<i32>, n: usize) -> i32 {
-    let pattern = fft_pattern(n, numbers.len());
+// Runs `phases` phases of the FFT with any pattern
+pub fn fft_with(numbers: Vec<u8>, pattern: Pattern, phases: usize) -> Vec<u8> {
+    let mut signal = Signal::with_pattern(numbers, pattern);
+    for _ in 0..phases {
+        fft_phase_with(&signal.numbers, &mut signal.buffer, &signal.pattern);
+        mem::swap(&mut signal.numbers, &mut signal.buffer);
+    }
 
-    let sum_number: i32 = numbers.iter().zip(pattern.iter()).map(|(x, y)| x * y).sum();
-    sum_number.abs() % 10
+    signal.numbers
 }
 
 pub fn q1(fname: String) -> String {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
-    let number_list: Vec<i32> = f_contents.trim().chars().map(|s| s.to_digit(10).unwrap() as i32).collect();
+    let number_list = parse_digits(&f_contents).expect("Couldn't read signal");
 
     _q1(number_list).unwrap()
 }
 
-fn _q1(numbers: Vec<i32>) -> Result<String> {
+// Part 1 with a different base pattern, given as e.g. `0,1,0,-1`, each value repeated
+// `repeat` times as often as the puzzle's
+pub fn q1_with(fname: String, pattern: &str, repeat: usize) -> String {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+
+    let number_list = parse_digits(&f_contents).expect("Couldn't read signal");
+    let pattern = Pattern::parse(pattern, repeat).expect("Couldn't read pattern");
+
+    fft_with(number_list, pattern, 100)[..8].iter().join("")
+}
+
+fn _q1(numbers: Vec<u8>) -> Result<String> {
     let mut signal = Signal::new(numbers);
 
     for _ in 0..100 {
@@ -104,21 +180,18 @@ fn _q1(numbers: Vec<i32>) -> Result<String> {
 }
 
 pub fn q2(fname: String) -> String {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
-
-    let number_list: Vec<i32> = f_contents.trim().chars().map(|s| s.to_digit(10).unwrap() as i32).collect();
+    let number_list = parse_digits(&f_contents).expect("Couldn't read signal");
 
     _q2(number_list).unwrap()
 }
 
 
-fn _q2(number_list: Vec<i32>) -> Result<String> {
-    let offset: usize = number_list[..7].iter().join("").parse()?;
+fn _q2(number_list: Vec<u8>) -> Result<String> {
+    let offset = digits::from_digits(&number_list[..7]) as usize;
 
-    let mut extended_list: Vec<i32> = vec![];
+    let mut extended_list: Vec<u8> = Vec::with_capacity(number_list.len() * 10_000);
     for _ in 0..10_000 {
         extended_list.extend(&number_list);
     }
@@ -142,9 +215,44 @@ fn _q2(number_list: Vec<i32>) -> Result<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn day16_fft_phase() {
+        let mut signal = Signal::new(parse_digits("12345678").unwrap());
+
+        for expected in ["48226158", "34040438", "03415518", "01029498"].iter() {
+            signal.fft_iterate().unwrap();
+            assert_eq!(
+                signal.numbers,
+                parse_digits(expected).unwrap()
+            )
+        }
+    }
+
+    #[test]
+    fn day16_fft_with_other_patterns() {
+        let input = parse_digits("80871224585914546619083218645595").unwrap();
+        let pattern = Pattern::parse("1, 2, -3", 2).unwrap();
+
+        // Straight from the definition, one input digit at a time
+        let expected: Vec<u8> = (0..input.len())
+            .map(|idx| {
+                let step = (idx + 1) * 2;
+                let sum: i32 = input.iter().enumerate()
+                    .map(|(i, &d)| i32::from(d) * pattern.base[((i + 1) / step) % 3])
+                    .sum();
+                (sum.abs() % 10) as u8
+            })
+            .collect();
+
+        assert_eq!(fft_with(input.clone(), pattern, 1), expected);
+        assert_eq!(fft_with(input.clone(), Pattern::default(), 100)[..8].to_vec(), parse_digits("24176176").unwrap());
+        assert!(Pattern::parse("1,x", 1).is_err());
+        assert!(Pattern::parse("1", 0).is_err());
+    }
+
     #[test]
     fn day16_q1_test1() {
-        let input: Vec<_> = "80871224585914546619083218645595".trim().chars().map(|c| c.to_digit(10).unwrap() as i32).collect();
+        let input = parse_digits("80871224585914546619083218645595").unwrap();
 
         assert_eq!(
             _q1(input).unwrap(),
@@ -154,7 +262,7 @@ mod tests {
 
     #[test]
     fn day16_q1_test2() {
-        let input: Vec<_> = "19617804207202209144916044189917".trim().chars().map(|c| c.to_digit(10).unwrap() as i32).collect();
+        let input = parse_digits("19617804207202209144916044189917").unwrap();
 
         assert_eq!(
             _q1(input).unwrap(),
@@ -164,7 +272,7 @@ mod tests {
 
     #[test]
     fn day16_q1_test3() {
-        let input: Vec<_> = "69317163492948606335995924319873".trim().chars().map(|c| c.to_digit(10).unwrap() as i32).collect();
+        let input = parse_digits("69317163492948606335995924319873").unwrap();
 
         assert_eq!(
             _q1(input).unwrap(),
@@ -174,7 +282,7 @@ mod tests {
 
     #[test]
     fn day16_q2_test1() {
-        let input: Vec<_> = "03036732577212944063491565474664".trim().chars().map(|c| c.to_digit(10).unwrap() as i32).collect();
+        let input = parse_digits("03036732577212944063491565474664").unwrap();
 
         assert_eq!(
             _q2(input).unwrap(),
@@ -184,7 +292,7 @@ mod tests {
 
     #[test]
     fn day16_q2_test2() {
-        let input: Vec<_> = "02935109699940807407585447034323".trim().chars().map(|c| c.to_digit(10).unwrap() as i32).collect();
+        let input = parse_digits("02935109699940807407585447034323").unwrap();
 
         assert_eq!(
             _q2(input).unwrap(),
@@ -193,7 +301,7 @@ mod tests {
     }
     #[test]
     fn day16_q2_test3() {
-        let input: Vec<_> = "03081770884921959731165446850517".trim().chars().map(|c| c.to_digit(10).unwrap() as i32).collect();
+        let input = parse_digits("03081770884921959731165446850517").unwrap();
 
         assert_eq!(
             _q2(input).unwrap(),
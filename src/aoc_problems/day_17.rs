@@ -1,12 +1,14 @@
 use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
 use std::ops::{Add, Sub, AddAssign};
 use std::result;
 
+use aoc_problems::intcode::Program;
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 fn pause() {
@@ -104,12 +106,106 @@ impl fmt::Display for Coordinate {
     }
 }
 
+// Longest each routine can be, not counting the newline after it
+const ROUTINE_LIMIT: usize = 20;
 
-pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+// The routines the vacuum robot is given to walk the scaffold in part 2
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Routine {
+    Main,
+    A,
+    B,
+    C
+}
+
+impl fmt::Display for Routine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Routine::Main => write!(f, "main routine"),
+            Routine::A => write!(f, "function A"),
+            Routine::B => write!(f, "function B"),
+            Routine::C => write!(f, "function C"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RoutineError {
+    TooLong { routine: Routine, length: usize },
+    IllegalToken { routine: Routine, token: String }
+}
+
+impl fmt::Display for RoutineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoutineError::TooLong { routine, length } => {
+                write!(f, "The {} is {} characters long, but the robot only takes {}", routine, length, ROUTINE_LIMIT)
+            },
+            RoutineError::IllegalToken { routine, token } => {
+                write!(f, "The {} contains {:?}, which the robot can't follow", routine, token)
+            }
+        }
+    }
+}
+
+impl Error for RoutineError {}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+// A main routine calling the movement functions A, B and C, e.g. "A,B,A" calling
+// functions like "R,8,L,10"
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MovementRoutines {
+    pub main: String,
+    pub a: String,
+    pub b: String,
+    pub c: String
+}
+
+impl MovementRoutines {
+    fn routines(&self) -> [(Routine, &str); 4] {
+        [
+            (Routine::Main, &self.main),
+            (Routine::A, &self.a),
+            (Routine::B, &self.b),
+            (Routine::C, &self.c),
+        ]
+    }
+
+    // Checks every routine fits in the robot's memory and only uses moves it knows,
+    // reporting the first one which doesn't
+    pub fn validate(&self) -> result::Result<(), RoutineError> {
+        for &(routine, text) in self.routines().iter() {
+            if text.len() > ROUTINE_LIMIT {
+                return Err(RoutineError::TooLong { routine, length: text.len() });
+            }
+
+            let legal = |token: &str| match routine {
+                Routine::Main => token == "A" || token == "B" || token == "C",
+                _ => token == "L" || token == "R" || token.parse::<u32>().is_ok_and(|n| n > 0)
+            };
+            if let Some(token) = text.split(',').find(|token| !legal(token)) {
+                return Err(RoutineError::IllegalToken { routine, token: token.to_string() });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Validates the routines, then queues them for the robot's program, followed by
+    // whether it should show the continuous video feed
+    pub fn upload(&self, program: &mut Program, video_feed: bool) -> result::Result<(), RoutineError> {
+        self.validate()?;
+
+        for &(_, text) in self.routines().iter() {
+            program.add_line(text);
+        }
+        program.add_line(if video_feed { "y" } else { "n" });
+
+        Ok(())
+    }
+}
+
+pub fn q1(fname: String) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let map_char_list: Vec<Vec<char>> = f_contents.trim().split('\n').map(|s| s.trim().chars().collect()).collect();
 
@@ -121,10 +217,7 @@ fn _q1(_chars: Vec<Vec<char>>) -> Result<usize> {
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let map_char_list: Vec<Vec<char>> = f_contents.trim().split('\n').map(|s| s.trim().chars().collect()).collect();
 
@@ -135,3 +228,50 @@ fn _q2(_chars: Vec<Vec<char>>) -> Result<usize> {
     unimplemented!();
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routines(main: &str, a: &str, b: &str, c: &str) -> MovementRoutines {
+        MovementRoutines {
+            main: main.to_string(),
+            a: a.to_string(),
+            b: b.to_string(),
+            c: c.to_string()
+        }
+    }
+
+    #[test]
+    fn day17_validate_routines() {
+        assert!(routines("A,B,C,B,A,C", "R,8,R,8", "R,4,R,4,R,8", "L,6,L,2").validate().is_ok());
+
+        assert_eq!(
+            routines("A,B,C,B,A,C,A,B,C,A,B", "R,8", "R,4", "L,6").validate(),
+            Err(RoutineError::TooLong { routine: Routine::Main, length: 21 })
+        );
+        assert_eq!(
+            routines("A,B,C", "R,8", "R,4,U,4", "L,6").validate(),
+            Err(RoutineError::IllegalToken { routine: Routine::B, token: "U".to_string() })
+        );
+        assert_eq!(
+            routines("A,D", "R,8", "R,4", "L,6").validate(),
+            Err(RoutineError::IllegalToken { routine: Routine::Main, token: "D".to_string() })
+        );
+        assert_eq!(
+            routines("A,B,C", "R,8", "R,4", "").validate().unwrap_err().to_string(),
+            "The function C contains \"\", which the robot can't follow"
+        );
+    }
+
+    #[test]
+    fn day17_upload_routines() {
+        // Echoes back the first character it reads
+        let mut program = Program::parse("3,0,4,0,99").unwrap();
+        routines("A,A", "R,8", "L,4", "L,6").upload(&mut program, false).unwrap();
+        assert_eq!(program.run_to_end().unwrap(), vec!['A' as i64]);
+
+        let mut program = Program::parse("3,0,4,0,99").unwrap();
+        assert!(routines("A,A", "R,8", "L,4", "L,6,F").upload(&mut program, false).is_err());
+        assert!(program.awaiting_input());
+    }
+}
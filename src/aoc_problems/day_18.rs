@@ -1,6 +1,5 @@
 use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::fmt;
 use std::io;
 use std::io::prelude::*;
@@ -9,6 +8,12 @@ use std::result;
 
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
+use aoc_problems::facts;
+use util::cache::Memo;
+use util::grid::{astar, manhattan, Grid2D, Path, Point2};
+use util::input;
+use viz;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 type GraphEdge = (usize, HashSet<TileType>);
@@ -46,6 +51,10 @@ impl Coordinate {
         Coordinate { x, y }
     }
 
+    fn to_point(self) -> Point2 {
+        Point2::new(self.x as i64, self.y as i64)
+    }
+
     fn adjacent_squares(&self) -> Vec<Coordinate> {
         let mut result = vec![
             Coordinate::new(self.x, self.y + 1),
@@ -118,7 +127,7 @@ impl fmt::Display for Coordinate {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 enum TileType {
     Open,
     Wall,
@@ -415,62 +424,109 @@ impl Vault {
         Ok(())
     }
 
+    // Steps along each edge of the key graph, with a row and column for the start followed
+    // by one for each key in order. Keys with no edge between them have no value.
+    fn key_distances(&self) -> (Grid2D<Option<i64>>, String) {
+        let mut keys: Vec<TileType> = self.key_locations.keys().cloned().collect();
+        keys.sort();
+        let nodes: Vec<GraphNode> = Some(GraphNode::Start(self.current_location)).into_iter()
+            .chain(keys.iter().map(|&key| GraphNode::Key(key)))
+            .collect();
+        let names: String = Some('@').into_iter()
+            .chain(keys.iter().filter_map(|key| match key {
+                TileType::Key(c) => Some(*c),
+                _ => None
+            }))
+            .collect();
+
+        let grid = Grid2D::from_fn(nodes.len(), nodes.len(), |pt| {
+            self.dists.get(&(nodes[pt.y as usize], nodes[pt.x as usize])).map(|&(steps, _)| steps as i64)
+        });
+        (grid, names)
+    }
+
     fn path_from_to(&self, from: Coordinate, to: Coordinate) -> (usize, HashSet<TileType>) {
-        let mut d = BTreeMap::new();
-        d.insert(from, 0);
+        let path = self.shortest_path(from, to, true).expect("Cannot find a path");
 
-        let mut queue: VecDeque<Coordinate> = VecDeque::new();
-        queue.push_front(from);
+        (path.len(), self.keys_for_doors_on(&path))
+    }
 
-        let mut todo_set: BTreeSet<Coordinate> = BTreeSet::new();
-        let mut visited: BTreeSet<Coordinate> = BTreeSet::new();
+    // Ignores doors, so that the keys needed to get through them can be worked out from
+    // the path. Other keys block the way unless `through_keys` is set.
+    fn shortest_path(&self, from: Coordinate, to: Coordinate, through_keys: bool) -> Option<Path> {
+        let goal = to.to_point();
+        let passable = |pt: Point2| {
+            if pt.x < 0 || pt.y < 0 {
+                return false;
+            }
+            match self.floor_map.get(&Coordinate::new(pt.x as usize, pt.y as usize)) {
+                None | Some(TileType::Wall) => false,
+                Some(TileType::Key(_)) => through_keys || pt == goal,
+                _ => true
+            }
+        };
 
-        while let Some(c) = queue.pop_front() {
-            todo_set.remove(&c);
-            visited.insert(c);
+        astar(from.to_point(), goal, passable, manhattan)
+    }
 
-            if c == to {
-                break;
-            }
+    fn keys_for_doors_on(&self, path: &Path) -> HashSet<TileType> {
+        path.points.iter()
+            .filter_map(|pt| match self.floor_map.get(&Coordinate::new(pt.x as usize, pt.y as usize)) {
+                Some(TileType::Door(c)) => Some(TileType::Key(*c)),
+                _ => None
+            })
+            .collect()
+    }
 
-            for neighbour in c.adjacent_squares().into_iter().filter(|coord| self.floor_map.get(&coord) != Some(&TileType::Wall)) {
-                // Don't add to squares to do
-                if visited.contains(&neighbour) {
-                    continue;
-                }
+    // The cells walked through to collect the keys in `order`, starting with the entrance
+    fn walk_for_keys(&self, order: &[TileType]) -> Result<Vec<Coordinate>> {
+        let mut walk = vec![self.current_location];
+        for key in order {
+            let from = *walk.last().unwrap();
+            let to = *self.key_locations.get(key).ok_or(format!("There is no key {}", key))?;
+            let path = self.shortest_path(from, to, false).ok_or(format!("Cannot walk from {} to key {}", from, key))?;
+            walk.extend(path.points.iter().skip(1).map(|pt| Coordinate::new(pt.x as usize, pt.y as usize)));
+        }
 
-                if !todo_set.contains(&neighbour) {
-                    queue.push_back(neighbour);
-                    todo_set.insert(neighbour);
-                }
+        Ok(walk)
+    }
 
-                let new_dist = 1 + *d.get(&c).unwrap_or(&0);
-                if !d.contains_key(&neighbour) || new_dist < d[&neighbour] {
-                    d.insert(neighbour, new_dist);
-                }
-            }
+    // Replays a walk through the vault, checking that each step is to a neighbouring open
+    // cell and that doors are only passed once their key has been picked up, and that it
+    // collects every key. Returns the number of steps taken.
+    fn verify_walk(&self, walk: &[Coordinate]) -> Result<usize> {
+        if walk.first() != Some(&self.current_location) {
+            return err!("Walk doesn't start at the entrance {}", self.current_location);
         }
 
-        // now distance has been found, backtrace to find out what doors we pass through
-        let mut current_square = to;
-        let mut doors: HashSet<TileType> = HashSet::new();
-        loop {
-            let mut min_dist = std::usize::MAX;
-            for neighbour in current_square.adjacent_squares().into_iter().filter(|coord| self.floor_map.get(&coord) != Some(&TileType::Wall)) {
-                if let Some(TileType::Door(c)) = self.floor_map.get(&neighbour) {
-                    doors.insert(TileType::Key(*c));
-                }
-                if d.contains_key(&neighbour) && d[&neighbour] < min_dist {
-                    min_dist = d[&neighbour];
-                    current_square = neighbour;
-                }
+        let mut held: HashSet<TileType> = HashSet::new();
+        for (step, (&from, &to)) in walk.iter().zip(walk.iter().skip(1)).enumerate() {
+            if !from.adjacent_squares().contains(&to) {
+                return err!("Step {} goes from {} to {}, which aren't neighbours", step + 1, from, to);
             }
-            if current_square == from {
-                break;
+
+            match self.floor_map.get(&to) {
+                None | Some(TileType::Wall) => return err!("Step {} walks into a wall at {}", step + 1, to),
+                Some(TileType::Door(c)) if !held.contains(&TileType::Key(*c)) => {
+                    return err!("Step {} passes door {} at {} without its key", step + 1, TileType::Door(*c), to);
+                },
+                Some(&key @ TileType::Key(_)) => {
+                    held.insert(key);
+                },
+                _ => {}
             }
         }
 
-        (d[&to], doors)
+        let mut missing: Vec<String> = self.key_locations.keys()
+            .filter(|key| !held.contains(key))
+            .map(|key| key.to_string())
+            .collect();
+        if !missing.is_empty() {
+            missing.sort();
+            return err!("Walk finishes without keys {}", missing.join(", "));
+        }
+
+        Ok(walk.len() - 1)
     }
 
     fn total_steps_for_keys(&self, path: &Vec<TileType>) -> usize {
@@ -496,27 +552,50 @@ impl fmt::Display for Vault {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let map_lines: Vec<Vec<char>> = f_contents.trim().lines().map(|s| s.trim().chars().collect()).collect();
 
     _q1(map_lines).unwrap()
 }
 
+// Solves part 1, drawing the steps between every pair of keys as a heatmap first if
+// `heatmap` is set. With `verify`, then walks the vault in the key order found to check
+// that the number of steps really can be achieved without passing a locked door.
+pub fn q1_with(fname: String, verify: bool, heatmap: bool) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
+
+    let map_lines: Vec<Vec<char>> = f_contents.trim().lines().map(|s| s.trim().chars().collect()).collect();
+
+    _q1_with(map_lines, verify, heatmap).unwrap()
+}
+
 fn _q1(chars: Vec<Vec<char>>) -> Result<usize> {
+    _q1_with(chars, false, false)
+}
+
+fn _q1_with(chars: Vec<Vec<char>>, verify: bool, heatmap: bool) -> Result<usize> {
     let mut vault = Vault::new(chars)?;
 
     vault.generate_key_graph()?;
+    if heatmap {
+        let (distances, names) = vault.key_distances();
+        println!("Steps from each of {} (rows) to each of them (columns)", names);
+        println!("{}", viz::heatmap(&distances)?);
+    }
+
+    // The keys reachable only depend on which keys are held, not the order they were
+    // picked up in, so many orderings share the same search
+    let mut reachable: Memo<Vec<TileType>, Vec<TileType>> = Memo::new();
 
     let mut potential_key_orderings: Vec<Vec<TileType>> = vec![vec![]];
     loop {
         let mut new_keys = false;
         let mut new_key_orderings: Vec<Vec<TileType>> = vec![];
         for key_list in &potential_key_orderings {
-            let available_keys = vault.all_reachable_keys(key_list)?;
+            let mut held = key_list.clone();
+            held.sort();
+            let available_keys = reachable.try_get_or_compute(held, |held| vault.all_reachable_keys(held))?;
             println!("All available keys with {:?} is {:?}", key_list, available_keys);
             if !available_keys.is_empty() {
                 new_keys = true;
@@ -537,18 +616,33 @@ fn _q1(chars: Vec<Vec<char>>) -> Result<usize> {
 
     println!("Number of potential orderings = {}", potential_key_orderings.len());
 
-    let timings: Vec<usize> = potential_key_orderings.iter()
-        .map(|path| vault.total_steps_for_keys(path))
+    let (steps, best_order) = potential_key_orderings.iter()
+        .map(|path| (vault.total_steps_for_keys(path), path))
+        .min_by_key(|&(steps, _)| steps)
+        .ok_or("No key orderings to try")?;
+
+    let key_order: String = best_order.iter()
+        .filter_map(|key| match key {
+            TileType::Key(c) => Some(*c),
+            _ => None
+        })
         .collect();
+    facts::record("key order", key_order);
 
-    Ok(*timings.iter().min().unwrap())
+    if verify {
+        let walk = vault.walk_for_keys(best_order)?;
+        let walked = vault.verify_walk(&walk)?;
+        if walked != steps {
+            return err!("Solver found {} steps, but walking its key order takes {}", steps, walked);
+        }
+        facts::record("walk verified", format!("{} cells", walk.len()));
+    }
+
+    Ok(steps)
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let map_lines: Vec<Vec<char>> = f_contents.trim().lines().map(|s| s.trim().chars().collect()).collect();
 
@@ -629,6 +723,25 @@ mod tests {
         )
     }
 
+    #[test]
+    fn day18_verifies_walks() {
+        let map: Vec<Vec<char>> = "
+        #########
+        #b.A.@.a#
+        #########
+        ".trim().lines().map(|line| line.trim().chars().collect()).collect();
+        let vault = Vault::new(map).unwrap();
+
+        let walk = vault.walk_for_keys(&[TileType::Key('a'), TileType::Key('b')]).unwrap();
+        assert_eq!(walk.len(), 9);
+        assert_eq!(vault.verify_walk(&walk).unwrap(), 8);
+        assert!(vault.verify_walk(&walk[..3]).unwrap_err().to_string().contains("without keys b"));
+        assert!(vault.verify_walk(&[walk[0], walk[2]]).is_err());
+
+        let walk = vault.walk_for_keys(&[TileType::Key('b'), TileType::Key('a')]).unwrap();
+        assert!(vault.verify_walk(&walk).unwrap_err().to_string().contains("without its key"));
+    }
+
     #[test]
     fn day18_q1_test5() {
         let map: Vec<Vec<char>> = "
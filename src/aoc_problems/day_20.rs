@@ -1,7 +1,7 @@
 use std::cmp;
 use std::error::Error;
-use std::fs::File;
 use std::fmt;
+use std::fs;
 use std::io;
 use std::io::prelude::*;
 use std::ops::{Add, Sub, AddAssign};
@@ -9,7 +9,12 @@ use std::result;
 
 use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 
-use itertools::Itertools;
+use aoc_problems::facts;
+use util::grid::{Grid2D, Point2};
+use util::input;
+use util::maze;
+use util::search::BucketQueue;
+use viz;
 
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
@@ -139,8 +144,7 @@ struct PlutoMaze {
     inside_portals: BTreeSet<Coordinate>,
     outside_portals: BTreeSet<Coordinate>,
     starting_position: Coordinate,
-    end_position: Coordinate,
-    end_distance: usize
+    end_position: Coordinate
 }
 
 impl PlutoMaze {
@@ -229,126 +233,202 @@ impl PlutoMaze {
                 outside_portals,
                 inside_portals,
                 starting_position,
-                end_position,
-                end_distance: 0
+                end_position
             }
         )
     }
+    // Collapses the maze to the portals and the walking distance between each pair which
+    // can reach each other without going through a portal
+    // Steps from AA to every tile of the outermost level of the recursive maze. Outer
+    // portals are closed there and inner ones lead down a level, so no portal is taken.
+    fn level0_distances(&self) -> Grid2D<Option<i64>> {
+        let mut d: HashMap<Coordinate, i64> = HashMap::new();
+        d.insert(self.starting_position, 0);
+        let mut queue: VecDeque<Coordinate> = VecDeque::new();
+        queue.push_back(self.starting_position);
 
-    fn adjacent_tiles(&self, position: (Coordinate, usize), recursive: bool) -> Result<Vec<(Coordinate, usize)>> {
-        let (coord, recursion_level) = position;
-        let neighbours: Vec<(Coordinate, usize)> = match self.tile_map.get(&coord).ok_or(format!("Cannot find {} in tile map", coord))? {
-            TileType::Open => {
-                coord.neighbours()
-                    .into_iter()
-                    .filter_map(|coord| {
-                        if self.tile_map.get(&coord) != Some(&TileType::Wall) && self.tile_map.get(&coord) != Some(&TileType::Blank) {
-                            Some((coord, recursion_level))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect()
-            },
-            TileType::Portal(char_tuple) => {
-                let mut neighbours = coord.neighbours()
-                    .into_iter()
-                    .filter_map(|coord| {
-                        if self.tile_map.get(&coord) != Some(&TileType::Wall) && self.tile_map.get(&coord) != Some(&TileType::Blank) {
-                            Some((coord, recursion_level))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect_vec();
-                if recursive {
-                    // if level = 0, only outside portals = AA, ZZ
-                    // otherwise,
-                    //     - inside goes to outside with recursion level + 1
-                    //     - outside goes to inside with recursion level - 1
-                    if self.inside_portals.contains(&coord) {
-                        let other_portal_coord: Coordinate = self.portal_map.get(&TileType::Portal(*char_tuple))
-                            .ok_or(format!("Cannot find portal for {:?}", char_tuple))?
-                            .iter()
-                            .filter(|&&cc| cc != coord)
-                            .next()
-                            .ok_or(format!("Cannot find other portal coordinate for inside portal {}", coord))?
-                            .clone();
-                        neighbours.push((other_portal_coord, recursion_level+1));
-                    } else if self.outside_portals.contains(&coord) {
-                        if recursion_level != 0 && *char_tuple != ('A', 'A') && *char_tuple != ('Z', 'Z') {
-                            let other_portal_coord: Coordinate = self.portal_map.get(&TileType::Portal(*char_tuple))
-                                .ok_or(format!("Cannot find portal for {:?}", char_tuple))?
-                                .iter()
-                                .filter(|&&cc| cc != coord)
-                                .next()
-                                .ok_or(format!("Cannot find other portal coordinate for outside portal {}", coord))?
-                                .clone();
-                            neighbours.push((other_portal_coord, recursion_level-1));
-                        }
-                    } else {
-                        return err!("{} is neither inside or outside portal", coord);
-                    }
-                } else {
-                    if *char_tuple != ('A', 'A') && *char_tuple != ('Z', 'Z') {
-                        let other_portal_coord: Coordinate = self.portal_map.get(&TileType::Portal(*char_tuple))
-                            .ok_or(format!("Cannot find portal for {:?}", char_tuple))?
-                            .iter()
-                            .filter(|&&cc| cc != coord)
-                            .next()
-                            .ok_or(format!("Cannot find other portal coordinate for {}", coord))?
-                            .clone();
-                        neighbours.push((other_portal_coord, recursion_level));
-                    }
+        while let Some(c) = queue.pop_front() {
+            for neighbour in c.neighbours() {
+                match self.tile_map.get(&neighbour) {
+                    Some(TileType::Open) | Some(TileType::Portal(_)) if !d.contains_key(&neighbour) => {
+                        d.insert(neighbour, d[&c] + 1);
+                        queue.push_back(neighbour);
+                    },
+                    _ => {}
                 }
+            }
+        }
 
-                neighbours
-            },
-            tile_type => return err!("Cannot find neighbours of type {:?}", tile_type),
-        };
+        let width = self.tile_map.keys().map(|c| c.x + 1).max().unwrap_or(0);
+        let height = self.tile_map.keys().map(|c| c.y + 1).max().unwrap_or(0);
+        Grid2D::from_fn(width, height, |pt: Point2| d.get(&Coordinate::new(pt.x as usize, pt.y as usize)).cloned())
+    }
 
-        Ok(neighbours)
+    fn portal_graph(&self) -> PortalGraph {
+        let portals: Vec<Coordinate> = self.outside_portals.iter().chain(self.inside_portals.iter()).cloned().collect();
+        let index: HashMap<Coordinate, usize> = portals.iter().enumerate().map(|(idx, &coord)| (coord, idx)).collect();
+
+        let nodes = portals.iter()
+            .map(|coord| {
+                let name = match self.tile_map.get(coord) {
+                    Some(TileType::Portal((a, b))) => format!("{}{}", a, b),
+                    _ => String::new()
+                };
+                let side = if self.inside_portals.contains(coord) { Side::Inner } else { Side::Outer };
+
+                PortalNode {
+                    name,
+                    side,
+                    position: (coord.x, coord.y)
+                }
+            })
+            .collect();
+
+        // Walking distances come from the maze with its corridors collapsed, keeping the
+        // portals and the junctions between them
+        let width = self.tile_map.keys().map(|c| c.x + 1).max().unwrap_or(0);
+        let height = self.tile_map.keys().map(|c| c.y + 1).max().unwrap_or(0);
+        let grid = Grid2D::from_fn(width, height, |pt: Point2| {
+            self.tile_map.get(&Coordinate::new(pt.x as usize, pt.y as usize)).cloned().unwrap_or(TileType::Blank)
+        });
+        let maze = maze::compress_by(
+            &grid,
+            |tile| matches!(tile, TileType::Open | TileType::Portal(_)),
+            |tile| matches!(tile, TileType::Portal(_))
+        );
+
+        let edges = portals.iter()
+            .map(|&from| {
+                let distances = maze.distances(Point2::new(from.x as i64, from.y as i64));
+                let mut reachable: Vec<(usize, usize)> = distances.iter()
+                    .filter_map(|(pt, &dist)| index.get(&Coordinate::new(pt.x as usize, pt.y as usize)).map(|&idx| (idx, dist)))
+                    .filter(|&(idx, _)| portals[idx] != from)
+                    .collect();
+                reachable.sort_by_key(|&(idx, dist)| (dist, idx));
+
+                reachable
+            })
+            .collect();
+
+        PortalGraph {
+            nodes,
+            edges
+        }
     }
+}
 
-    fn find_path_through_maze(&mut self, recursive: bool) -> Result<()> {
-        let mut d = BTreeMap::new();
-        d.insert((self.starting_position, 0), 0);
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Side {
+    Inner,
+    Outer
+}
 
-        let mut queue: VecDeque<(Coordinate, usize)> = VecDeque::new();
-        queue.push_front((self.starting_position, 0));
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PortalNode {
+    // The two letters labelling the portal, e.g. "AA" for the start and "ZZ" for the end
+    pub name: String,
+    pub side: Side,
+    pub position: (usize, usize)
+}
 
-        let mut todo_set: BTreeSet<(Coordinate, usize)> = BTreeSet::new();
-        let mut visited: BTreeSet<(Coordinate, usize)> = BTreeSet::new();
+// The maze reduced to its portals. `edges[n]` holds each node reachable by walking from
+// node n and how many steps it takes; stepping through a portal to the node with the same
+// name always takes one more.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PortalGraph {
+    pub nodes: Vec<PortalNode>,
+    pub edges: Vec<Vec<(usize, usize)>>
+}
 
-        while let Some(c) = queue.pop_front() {
-            todo_set.remove(&c);
-            visited.insert(c);
+impl PortalGraph {
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
 
-            if c == (self.end_position, 0) {
-                break;
+    // The node at the other end of a portal
+    pub fn partner(&self, idx: usize) -> Option<usize> {
+        let name = &self.nodes[idx].name;
+        (0..self.nodes.len()).find(|&other| other != idx && self.nodes[other].name == *name)
+    }
+
+    // Dijkstra from AA to ZZ over (node, recursion level). In the recursive maze inner
+    // portals lead a level down and outer ones a level up, and the search gives up on
+    // going deeper than there are portals.
+    pub fn shortest_path(&self, recursive: bool) -> Result<usize> {
+        let start = (self.find("AA").ok_or("Cannot find starting point in maze")?, 0);
+        let end = (self.find("ZZ").ok_or("Cannot find finishing point in maze")?, 0);
+        let max_level = self.nodes.len();
+
+        let max_step = self.edges.iter().flatten().map(|&(_, steps)| steps).max().unwrap_or(0).max(1);
+        let mut queue = BucketQueue::new(max_step);
+        queue.push(0, start);
+
+        let mut d: HashMap<(usize, usize), usize> = HashMap::new();
+        d.insert(start, 0);
+
+        while let Some((dist, (idx, level))) = queue.pop() {
+            if (idx, level) == end {
+                facts::record("states searched", d.len());
+                return Ok(dist);
             }
 
-            for neighbour in self.adjacent_tiles(c, recursive)? {
-                // Don't add to squares to do
-                if visited.contains(&neighbour) {
-                    continue;
-                }
+            if dist > d[&(idx, level)] {
+                continue;
+            }
 
-                if !todo_set.contains(&neighbour) {
-                    queue.push_back(neighbour);
-                    todo_set.insert(neighbour);
+            let mut next: Vec<((usize, usize), usize)> = self.edges[idx].iter()
+                .map(|&(other, steps)| ((other, level), dist + steps))
+                .collect();
+
+            if let Some(other) = self.partner(idx) {
+                let other_level = match (recursive, self.nodes[idx].side) {
+                    (false, _) => Some(level),
+                    (true, Side::Inner) if level < max_level => Some(level + 1),
+                    (true, Side::Outer) if level > 0 => Some(level - 1),
+                    _ => None
+                };
+                if let Some(other_level) = other_level {
+                    next.push(((other, other_level), dist + 1));
                 }
+            }
 
-                let new_dist = 1 + *d.get(&c).unwrap_or(&0);
+            for (neighbour, new_dist) in next {
                 if !d.contains_key(&neighbour) || new_dist < d[&neighbour] {
                     d.insert(neighbour, new_dist);
+                    queue.push(new_dist, neighbour);
                 }
             }
         }
 
-        self.end_distance = *d.get(&(self.end_position, 0)).ok_or("End position not in distance map")?;
+        err!("Cannot reach the end of the maze")
+    }
 
-        Ok(())
+    // The graph in Graphviz DOT format, with outer portals drawn as boxes, walking
+    // distances as edge labels and portals as dashed edges
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph portals {\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let shape = match node.side {
+                Side::Inner => "ellipse",
+                Side::Outer => "box"
+            };
+            dot.push_str(&format!(
+                "    n{} [label=\"{}\\n({}, {})\", shape={}];\n",
+                idx, node.name, node.position.0, node.position.1, shape
+            ));
+        }
+
+        for (idx, edges) in self.edges.iter().enumerate() {
+            for &(other, steps) in edges.iter().filter(|&&(other, _)| other > idx) {
+                dot.push_str(&format!("    n{} -- n{} [label=\"{}\"];\n", idx, other, steps));
+            }
+            if let Some(other) = self.partner(idx).filter(|&other| other > idx) {
+                dot.push_str(&format!("    n{} -- n{} [style=dashed];\n", idx, other));
+            }
+        }
+        dot.push_str("}\n");
+
+        dot
     }
 }
 
@@ -368,41 +448,54 @@ impl fmt::Display for PlutoMaze {
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+    q1_with(fname, None)
+}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+// Also writes the portal graph to `dot` in Graphviz DOT format, if given
+pub fn q1_with(fname: String, dot: Option<&str>) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let chars: Vec<Vec<char>> = f_contents.split('\n').filter(|s| s.len() > 0).map(|s| s.chars().collect()).collect();
 
+    if let Some(out) = dot {
+        let graph = PlutoMaze::new(chars.clone()).expect("Couldn't read maze").portal_graph();
+        fs::write(out, graph.to_dot()).expect("Couldn't write portal graph");
+    }
+
     _q1(chars).unwrap()
 }
 
 fn _q1(chars: Vec<Vec<char>>) -> Result<usize> {
     println!("Started Q1 calculation");
-    let mut maze = PlutoMaze::new(chars)?;
+    let maze = PlutoMaze::new(chars)?;
     println!("{}", maze);
     println!("Created maze object");
-    maze.find_path_through_maze(false)?;
-    Ok(maze.end_distance)
+    maze.portal_graph().shortest_path(false)
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
+    q2_with(fname, false)
+}
 
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+// Also draws the steps from AA to every tile of the outermost level as a heatmap, if
+// `heatmap` is set
+pub fn q2_with(fname: String, heatmap: bool) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let chars: Vec<Vec<char>> = f_contents.split('\n').filter(|s| s.len() > 0).map(|s| s.chars().collect()).collect();
 
+    if heatmap {
+        let maze = PlutoMaze::new(chars.clone()).expect("Couldn't read maze");
+        println!("{}", viz::heatmap(&maze.level0_distances()).expect("Couldn't draw the heatmap"));
+    }
+
     _q2(chars).unwrap()
 }
 
 fn _q2(chars: Vec<Vec<char>>) -> Result<usize> {
-    let mut maze = PlutoMaze::new(chars)?;
+    let maze = PlutoMaze::new(chars)?;
     println!("Created maze object");
-    maze.find_path_through_maze(true)?;
-    Ok(maze.end_distance)
+    maze.portal_graph().shortest_path(true)
 }
 
 #[cfg(test)]
@@ -439,6 +532,48 @@ FG..#########.....#
         )
     }
 
+    #[test]
+    fn day20_portal_graph() {
+        let map: Vec<Vec<char>> = "
+         A           
+         A           
+  #######.#########  
+  #######.........#  
+  #######.#######.#  
+  #######.#######.#  
+  #######.#######.#  
+  #####  B    ###.#  
+BC...##  C    ###.#  
+  ##.##       ###.#  
+  ##...DE  F  ###.#  
+  #####    G  ###.#  
+  #########.#####.#  
+DE..#######...###.#  
+  #.#########.###.#  
+FG..#########.....#  
+  ###########.#####  
+             Z       
+             Z       
+        ".split('\n').filter(|line| !line.is_empty()).map(|line| line.chars().collect()).collect();
+
+        let graph = PlutoMaze::new(map).unwrap().portal_graph();
+        assert_eq!(graph.nodes.len(), 8);
+
+        let start = graph.find("AA").unwrap();
+        let end = graph.find("ZZ").unwrap();
+        let outer_bc = graph.find("BC").unwrap();
+        let inner_bc = graph.partner(outer_bc).unwrap();
+        assert_eq!((graph.nodes[outer_bc].side, graph.nodes[inner_bc].side), (Side::Outer, Side::Inner));
+        assert!(graph.edges[start].contains(&(end, 26)));
+        assert!(graph.edges[start].contains(&(inner_bc, 4)));
+        assert_eq!(graph.partner(start), None);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("graph portals {\n"));
+        assert!(dot.contains(&format!("    n{} -- n{} [label=\"26\"];\n", start.min(end), start.max(end))));
+        assert_eq!(dot.matches("style=dashed").count(), 3);
+    }
+
     #[test]
     fn day20_q1_test2() {
         let map: Vec<Vec<char>> = "
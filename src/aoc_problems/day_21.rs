@@ -1,13 +1,16 @@
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 use std::result;
 
-use std::collections::VecDeque;
-
 use itertools::Itertools;
 
+use aoc_problems::ascii::{Console, Event, LineKind};
+use aoc_problems::intcode::Program;
+use util::input;
+
 type Result<T> = result::Result<T, Box<dyn Error>>;
 
 macro_rules! err {
@@ -26,283 +29,159 @@ fn pause() {
     let _ = stdin.read(&mut [0u8]).unwrap();
 }
 
-#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
-enum Parameter {
-    Position,
-    Immediate,
-    Relative
-}
-
-#[derive(Clone, Eq, Default, Debug, PartialEq, Hash)]
-struct Instruction {
-    opcode: usize,
-    parameters: Vec<Parameter>
+// One way the springdroid fell into space: the stretch of hull it was crossing, where
+// it last stood, and what its sensors A to I read from there, true for ground
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Death {
+    pub hull: String,
+    pub position: usize,
+    pub sensors: [bool; 9]
 }
 
-impl Instruction {
-    fn new(number: usize) -> Result<Instruction> {
-        let opcode = number % 100;
-        let mut digit_list: Vec<_> = (number / 100).to_string().chars().map(|d| d.to_digit(10).unwrap()).collect();
-        digit_list.reverse();
-
-        let params_length = match opcode {
-            1 => 3,
-            2 => 3,
-            3 => 1,
-            4 => 1,
-            5 => 2,
-            6 => 2,
-            7 => 3,
-            8 => 3,
-            9 => 1,
-            99 => 0,
-            x => return err!("{}", format!("Cannot read opcode: {}", x))
-        };
-
-        digit_list.resize(params_length, 0);
-        let parameters: Result<Vec<Parameter>> = digit_list.into_iter().map(|d| match d {
-            0 => Ok(Parameter::Position),
-            1 => Ok(Parameter::Immediate),
-            2 => Ok(Parameter::Relative),
-            x => err!("{}", format!("Cannot read parameter digit: {}", x))
-        }).collect();
-        let parameters = parameters?;
-
-        Ok(
-            Instruction {
-                opcode,
-                parameters,
+impl Death {
+    // Reads the frames the droid's program draws after it falls, which show the droid
+    // as @ above a line of hull until it drops into a hole in the last frame
+    fn from_output(output: &str) -> Option<Death> {
+        let frames_start = output.find("Didn't make it across:")?;
+        let frames: Vec<Vec<&str>> = output[frames_start..].lines().skip(1)
+            .collect::<Vec<_>>()
+            .split(|line| line.trim().is_empty())
+            .filter(|frame| frame.len() >= 2)
+            .map(|frame| frame.to_vec())
+            .collect();
+
+        // The last place it stood on the hull, rather than being over a hole on its way
+        // down, is where it made the fatal choice
+        let hull = frames.first()?.last()?.to_string();
+        let position = frames.iter().rev()
+            .filter_map(|frame| frame[frame.len() - 2].find('@'))
+            .find(|&position| hull.chars().nth(position) == Some('#'))?;
+
+        let mut sensors = [true; 9];
+        for (idx, sensor) in sensors.iter_mut().enumerate() {
+            if let Some(c) = hull.chars().nth(position + idx + 1) {
+                *sensor = c == '#';
             }
-        )
+        }
 
+        Some(Death { hull, position, sensors })
     }
 }
 
-struct Program {
-    memory: Vec<i64>,
-    inputs: VecDeque<i64>,
-    current_input: usize,
-    pointer_idx: usize,
-    relative_base: i64,
-}
-
-impl Program {
-    fn new(memory: Vec<i64>)  -> Program {
-        Program {
-            memory,
-            inputs: VecDeque::new(),
-            current_input: 1,
-            pointer_idx: 0,
-            relative_base: 0,
-        }
+impl fmt::Display for Death {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sensors: String = self.sensors.iter().map(|&ground| if ground { '#' } else { '.' }).collect();
+        write!(f, "{}\t{}\t{}", self.hull, self.position, sensors)
     }
+}
 
-    fn get_input(&mut self) -> Result<i64> {
-        let input = self.inputs.pop_front().ok_or("No inputs left!")?;
+enum Outcome {
+    Survived(usize),
+    Fell(String)
+}
 
-        Ok(input)
-    }
+// Far more instructions than the droid takes to run any script, so that one it gets stuck
+// on fails instead of hanging
+const MAX_STEPS: u64 = 10_000_000;
+
+// The springdroid's console, which prompts for a script and then either reports the
+// hull damage or shows how the droid fell
+fn console(droid: &Program) -> Console {
+    let mut droid = droid.clone();
+    droid.limit_steps(MAX_STEPS);
+    Console::new(droid)
+        .matching("Input instructions:", LineKind::Prompt)
+        .matching("Didn't make it across:", LineKind::Error)
+}
 
-    fn add_input(&mut self, input: i64) {
-        self.inputs.push_back(input);
+fn run_script(droid: &Program, script: &[String]) -> Result<Outcome> {
+    let mut console = console(droid);
+    for instruction in script {
+        console.send_line(instruction);
     }
 
-    fn get_parameter(&mut self, parameter_form: Parameter, val: i64) -> i64 {
-        use self::Parameter::*;
-
-        match parameter_form {
-            Position => {
-                let idx = val as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
-            },
-            Immediate => val,
-            Relative => {
-                let idx = (self.relative_base + val) as usize;
-                if self.memory.len() < idx+1 {
-                    self.memory.resize(idx+1, 0);
-                }
-
-                self.memory[idx]
+    let mut output = String::new();
+    let mut fell = false;
+    for event in console.read_events()? {
+        match event {
+            Event::Value(damage) => return Ok(Outcome::Survived(damage as usize)),
+            Event::Line(kind, line) => {
+                fell |= kind == LineKind::Error;
+                output.push_str(&line);
+                output.push('\n');
             }
         }
     }
 
-    fn set_parameter(&mut self, idx: usize, val: i64) -> Result<()> {
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-
-        self.memory[idx] = val;
-
-        Ok(())
+    if fell {
+        Ok(Outcome::Fell(output))
+    } else {
+        err!("The springdroid didn't report any hull damage or fall:\n{}", output)
     }
+}
 
-    fn get_output_idx(&mut self, idx: usize, parameter_type: Parameter) -> usize {
-        use self::Parameter::*;
-        if self.memory.len() < idx+1 {
-            self.memory.resize(idx+1, 0);
-        }
-        match parameter_type {
-            Position => {
-                self.memory[idx] as usize
-            },
-            Relative => {
-                (self.memory[idx] + self.relative_base) as usize
-            },
-            _ => panic!("Should never be here")
-        }
-    }
+// Every springscript of one or two instructions reading the given sensors, ending in
+// `command`
+fn candidate_scripts(sensors: &str, command: &str) -> Vec<Vec<String>> {
+    let instructions: Vec<String> = ["AND", "OR", "NOT"].iter()
+        .flat_map(|op| {
+            sensors.chars().chain("TJ".chars())
+                .flat_map(move |x| "TJ".chars().map(move |y| format!("{} {} {}", op, x, y)))
+        })
+        .collect();
+
+    let singles = instructions.iter().map(|first| vec![first.clone()]);
+    let pairs = instructions.iter().cartesian_product(instructions.iter())
+        .map(|(first, second)| vec![first.clone(), second.clone()]);
+
+    singles.chain(pairs)
+        .map(|mut script| {
+            script.push(command.to_string());
+            script
+        })
+        .collect()
+}
 
-    fn run_program(&mut self) -> Result<Option<i64>> {
-        loop {
-            let current_instruction = Instruction::new(self.memory[self.pointer_idx] as usize)?;
-
-            match current_instruction.opcode {
-                1 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 + input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                2 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, input_1 * input_2)?;
-
-                    self.pointer_idx += 4;
-                },
-                3 => {
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 1,
-                        current_instruction.parameters[0]
-                    );
-                    let input = self.get_input()?;
-                    self.set_parameter(output_idx, input)?;
-
-                    self.pointer_idx += 2;
-                },
-                4 => {
-                    let output_val = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1]
-                    );
+// Runs every short springscript for a part and writes the sensor readings from each
+// distinct way they fell to `out`, one per line, as counterexamples for searching for
+// a working script. Returns how many were written.
+pub fn traces(fname: String, part: usize, out: &str) -> usize {
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
-                    // let output_idx = self.memory[self.pointer_idx+1];
-                    self.pointer_idx += 2;
+    let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
-                    return Ok(Some(output_val));
-                },
-                5 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 != 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
-                    }
-                },
-                6 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    if input_1 == 0 {
-                        self.pointer_idx = input_2 as usize;
-                    } else {
-                        self.pointer_idx += 3;
+    _traces(memory, part, out).unwrap()
+}
+
+fn _traces(memory: Vec<i64>, part: usize, out: &str) -> Result<usize> {
+    let (sensors, command) = if part == 1 { ("ABCD", "WALK") } else { ("ABCDEFGHI", "RUN") };
+    let droid = Program::new(memory);
+
+    let mut deaths: Vec<Death> = vec![];
+    for script in candidate_scripts(sensors, command) {
+        if let Outcome::Fell(output) = run_script(&droid, &script)? {
+            match Death::from_output(&output) {
+                Some(death) => {
+                    if !deaths.contains(&death) {
+                        deaths.push(death);
                     }
                 },
-                7 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 < input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                8 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    let input_2 = self.get_parameter(
-                        current_instruction.parameters[1],
-                        self.memory[self.pointer_idx+2],
-                    );
-                    let output_idx = self.get_output_idx(
-                        self.pointer_idx + 3,
-                        current_instruction.parameters[2]
-                    );
-                    self.set_parameter(output_idx, if input_1 == input_2 {1} else {0})?;
-
-                    self.pointer_idx += 4;
-                },
-                9 => {
-                    let input_1 = self.get_parameter(
-                        current_instruction.parameters[0],
-                        self.memory[self.pointer_idx+1],
-                    );
-                    self.relative_base += input_1;
-
-                    self.pointer_idx += 2;
-                },
-                99 => break,
-                x => return err!("{}", format!("Incorrect opcode: {}", x))
+                None => return err!("Cannot read how the droid fell from:\n{}", output)
             }
         }
-        Ok(None)
     }
+
+    let mut file = File::create(out)?;
+    writeln!(file, "# hull\tposition\tsensors A-I")?;
+    for death in &deaths {
+        writeln!(file, "{}", death)?;
+    }
+
+    Ok(deaths.len())
 }
 
 pub fn q1(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
@@ -310,8 +189,6 @@ pub fn q1(fname: String) -> usize {
 }
 
 fn _q1(memory: Vec<i64>) -> Result<usize> {
-    let mut program = Program::new(memory);
-
     // ground = true
     // hole = false
     let instructions: Vec<String> = vec![
@@ -322,29 +199,21 @@ fn _q1(memory: Vec<i64>) -> Result<usize> {
         "WALK"
     ].into_iter().map(|s| s.to_string()).collect_vec();
 
-    for instruction in instructions {
-        for char in instruction.chars() {
-            program.add_input(char as u8 as i64);
-        }
-        program.add_input('\n' as u8 as i64);
-    }
+    hull_damage(&Program::new(memory), &instructions)
+}
 
-    while let Some(result) = program.run_program()? {
-        if result <= 255 {
-            print!("{}", result as u8 as char);
-        } else {
-            return Ok(result as usize);
+fn hull_damage(droid: &Program, script: &[String]) -> Result<usize> {
+    match run_script(droid, script)? {
+        Outcome::Survived(damage) => Ok(damage),
+        Outcome::Fell(output) => {
+            print!("{}", output);
+            err!("The springdroid fell into space")
         }
     }
-
-    err!("Program ended without correct value")
 }
 
 pub fn q2(fname: String) -> usize {
-    let mut f = File::open(fname).expect("File not found");
-    let mut f_contents = String::new();
-
-    f.read_to_string(&mut f_contents).expect("Couldn't find file");
+    let f_contents = input::read_file(fname).expect("Couldn't read input file");
 
     let memory: Vec<i64> = f_contents.trim().split(',').map(|s| s.parse().unwrap()).collect();
 
@@ -352,8 +221,6 @@ pub fn q2(fname: String) -> usize {
 }
 
 fn _q2(memory: Vec<i64>) -> Result<usize> {
-    let mut program = Program::new(memory);
-
     // ground = true
     // hole = false
     let instructions: Vec<String> = vec![
@@ -364,21 +231,33 @@ fn _q2(memory: Vec<i64>) -> Result<usize> {
         "RUN"
     ].into_iter().map(|s| s.to_string()).collect_vec();
 
-    for instruction in instructions {
-        for char in instruction.chars() {
-            program.add_input(char as u8 as i64);
-        }
-        program.add_input('\n' as u8 as i64);
-    }
+    hull_damage(&Program::new(memory), &instructions)
+}
 
-    while let Some(result) = program.run_program()? {
-        if result <= 255 {
-            print!("{}", result as u8 as char);
-        } else {
-            return Ok(result as usize);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day21_reads_deaths() {
+        let output = "Input instructions:\n\nWalking...\n\n\nDidn't make it across:\n\n\
+.................\n.................\n@................\n#####.#..########\n\n\
+.................\n.................\n.@...............\n#####.#..########\n\n\
+.................\n.................\n.................\n#####@#..########\n\n";
+
+        let death = Death::from_output(output).unwrap();
+        assert_eq!(death.hull, "#####.#..########");
+        assert_eq!(death.position, 1);
+        assert_eq!(death.to_string(), "#####.#..########\t1\t###.#..##");
+        assert!(Death::from_output("no frames").is_none());
     }
 
-    err!("Program ended without correct value")
-}
+    #[test]
+    fn day21_candidate_scripts() {
+        let scripts = candidate_scripts("ABCD", "WALK");
 
+        assert_eq!(scripts.len(), 36 + 36 * 36);
+        assert_eq!(scripts[0], vec!["AND A T", "WALK"]);
+        assert!(scripts.iter().all(|script| script.len() <= 3 && script.last().unwrap() == "WALK"));
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+use aoc_problems::intcode::Device;
+
+// Host devices for Intcode programs to talk to through memory mapped addresses, see
+// `VmBuilder::device`
+
+// A single address: writing it prints a character, or a number if the value isn't ASCII,
+// and reading it takes the next character typed in, or 0 if there isn't one
+#[derive(Clone, Debug, Default)]
+pub struct Console {
+    text: String,
+    typed: VecDeque<i64>
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console::default()
+    }
+
+    pub fn type_line(&mut self, line: &str) {
+        self.typed.extend(line.bytes().map(i64::from));
+        self.typed.push_back(i64::from(b'\n'));
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Device for Console {
+    fn size(&self) -> usize {
+        1
+    }
+
+    fn read(&mut self, _offset: usize) -> i64 {
+        self.typed.pop_front().unwrap_or(0)
+    }
+
+    fn write(&mut self, _offset: usize, value: i64) {
+        match value {
+            0..=127 => self.text.push(value as u8 as char),
+            _ => self.text.push_str(&value.to_string())
+        }
+    }
+}
+
+// A grid of cells, one address each in rows, followed by one more address which saves
+// the grid as a frame when written. Cells show 0 as a space, 1 as `#` and other ASCII
+// values as themselves.
+#[derive(Clone, Debug)]
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    cells: Vec<i64>,
+    frames: Vec<String>
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> FrameBuffer {
+        FrameBuffer {
+            width,
+            height,
+            cells: vec![0; width * height],
+            frames: vec![]
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let rows: Vec<String> = self.cells.chunks(self.width.max(1))
+            .map(|row| row.iter()
+                .map(|&cell| match cell {
+                    0 => ' ',
+                    1 => '#',
+                    32..=126 => cell as u8 as char,
+                    _ => '?'
+                })
+                .collect())
+            .collect();
+
+        rows.join("\n")
+    }
+
+    // Every frame the program has presented, for showing with a `viz::Visualizer`
+    pub fn frames(&self) -> &[String] {
+        &self.frames
+    }
+}
+
+impl Device for FrameBuffer {
+    fn size(&self) -> usize {
+        self.width * self.height + 1
+    }
+
+    fn read(&mut self, offset: usize) -> i64 {
+        self.cells.get(offset).cloned().unwrap_or(0)
+    }
+
+    fn write(&mut self, offset: usize, value: i64) {
+        if offset < self.cells.len() {
+            self.cells[offset] = value;
+        } else {
+            let frame = self.render();
+            self.frames.push(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use aoc_problems::intcode::{Program, VmBuilder};
+    use aoc_problems::intcode_asm::assemble;
+
+    #[test]
+    fn devices_are_mapped_into_memory() {
+        let console = Arc::new(Mutex::new(Console::new()));
+        let screen = Arc::new(Mutex::new(FrameBuffer::new(2, 2)));
+        console.lock().unwrap().type_line("A");
+
+        // Echoes a typed line to the console, fills the screen's top left and bottom right
+        // cells and presents a frame, then outputs the first cell back
+        let memory = assemble("
+            add [1000], 0, [1000]
+            add [1000], 0, [1000]
+            add 1, 0, [2000]
+            add 35, 0, [2003]
+            add 0, 0, [2004]
+            out [2000]
+            halt
+        ").unwrap();
+        let mut program = VmBuilder::new(memory.clone())
+            .device(1000, console.clone())
+            .device(2000, screen.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(program.run_to_end().unwrap(), vec![1]);
+        assert_eq!(console.lock().unwrap().text(), "A\n");
+        assert_eq!(screen.lock().unwrap().frames(), &["# \n #".to_string()]);
+
+        // With nothing mapped the same addresses are plain memory
+        assert_eq!(Program::new(memory).run_to_end().unwrap(), vec![1]);
+
+        let overlapping = VmBuilder::new(vec![99])
+            .device(10, Arc::new(Mutex::new(FrameBuffer::new(3, 3))))
+            .device(15, Arc::new(Mutex::new(Console::new())))
+            .build();
+        assert!(overlapping.is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+
+// Named facts a solver works out on the way to its answer, such as where it put the
+// monitoring station, which the runner shows with --verbose. They're kept per thread,
+// so tests solving different days at once don't see each other's facts.
+thread_local! {
+    static FACTS: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn record<T: Display>(name: &str, value: T) {
+    FACTS.with(|facts| facts.borrow_mut().push((name.to_string(), value.to_string())));
+}
+
+// Every fact recorded on this thread since the last call, in the order they were recorded
+pub fn take() -> Vec<(String, String)> {
+    FACTS.with(|facts| facts.borrow_mut().drain(..).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facts_are_taken_in_order() {
+        record("station", "11,13");
+        record("visible", 210);
+
+        assert_eq!(take(), vec![
+            ("station".to_string(), "11,13".to_string()),
+            ("visible".to_string(), "210".to_string()),
+        ]);
+        assert!(take().is_empty());
+    }
+}
@@ -0,0 +1,2679 @@
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::result;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use util::digits;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+const PAGE_SIZE: usize = 512;
+
+// Instructions executed by every machine in this process, which unlike the wall time
+// doesn't depend on the machine the solvers run on
+static EXECUTED: AtomicU64 = AtomicU64::new(0);
+
+pub fn instructions_executed() -> u64 {
+    EXECUTED.load(Ordering::Relaxed)
+}
+
+thread_local! {
+    // The same, for the machines run on each thread, which tests running side by side
+    // can count without seeing each other's
+    static THREAD_EXECUTED: Cell<u64> = const { Cell::new(0) };
+}
+
+fn count_executed(n: u64) {
+    EXECUTED.fetch_add(n, Ordering::Relaxed);
+    THREAD_EXECUTED.with(|executed| executed.set(executed.get() + n));
+}
+
+// Runs `f`, returning what it returned and how many instructions machines on this thread
+// executed meanwhile. Machines `f` runs on other threads, as in a pipeline, aren't counted.
+pub fn count_instructions<T, F: FnOnce() -> T>(f: F) -> (T, u64) {
+    let start = THREAD_EXECUTED.with(|executed| executed.get());
+    let result = f();
+
+    (result, THREAD_EXECUTED.with(|executed| executed.get()) - start)
+}
+
+// Whether machines can be debugged, traced or profiled at all. Building with the `lean`
+// feature makes this false, and the checks below fold away with it.
+pub const INSTRUMENTED: bool = cfg!(not(feature = "lean"));
+
+// The debugger every machine in this process stops in, if one is attached. Machines only
+// look at it when DEBUGGING is set, so runs without one don't pay for the lock.
+static DEBUGGING: AtomicBool = AtomicBool::new(false);
+static DEBUGGER: Mutex<Option<Debugger>> = Mutex::new(None);
+
+// Stops every machine at the debugger's prompt, on standard error, before the first
+// instruction any of them runs and then at each breakpoint
+pub fn attach_debugger(debugger: Debugger) {
+    *DEBUGGER.lock().unwrap() = Some(debugger);
+    DEBUGGING.store(true, Ordering::Relaxed);
+}
+
+pub fn detach_debugger() {
+    DEBUGGING.store(false, Ordering::Relaxed);
+    *DEBUGGER.lock().unwrap() = None;
+}
+
+// Where every machine in this process logs each instruction it runs, if tracing. As with
+// the debugger, machines only take the lock when TRACING is set.
+static TRACING: AtomicBool = AtomicBool::new(false);
+static TRACER: Mutex<Option<Tracer>> = Mutex::new(None);
+
+// Where traced instructions go, one line each, such as `12: add 3, 4, [20] -> 7`
+pub enum Tracer {
+    Writer(Box<dyn Write + Send>),
+    // `log` records at trace level with the target "intcode", for whatever logger the
+    // program has installed
+    Log
+}
+
+impl Tracer {
+    fn trace(&mut self, line: &str) -> Result<()> {
+        match self {
+            Tracer::Writer(writer) => writeln!(writer, "{}", line)?,
+            Tracer::Log => ::log::trace!(target: "intcode", "{}", line)
+        }
+
+        Ok(())
+    }
+}
+
+pub fn attach_tracer(tracer: Tracer) {
+    *TRACER.lock().unwrap() = Some(tracer);
+    TRACING.store(true, Ordering::Relaxed);
+}
+
+// Stops tracing, flushing whatever the tracer has buffered
+pub fn detach_tracer() -> Result<()> {
+    TRACING.store(false, Ordering::Relaxed);
+    if let Some(Tracer::Writer(mut writer)) = TRACER.lock().unwrap().take() {
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+// Counts of what every machine in this process runs, gathered while PROFILING is set
+static PROFILING: AtomicBool = AtomicBool::new(false);
+static PROFILE: Mutex<Option<VmProfile>> = Mutex::new(None);
+
+// Where the VM spends its time: how often each opcode ran, and the addresses of the
+// instructions run most and of the memory most used as parameters. Addresses from
+// different machines are counted together, which suits days running one program.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VmProfile {
+    pub opcodes: BTreeMap<usize, u64>,
+    pub instructions: HashMap<usize, u64>,
+    pub accesses: HashMap<usize, u64>
+}
+
+impl VmProfile {
+    // The opcodes by how often they ran, then the `top` busiest instruction and memory
+    // addresses
+    pub fn report(&self, top: usize) -> String {
+        let total: u64 = self.opcodes.values().sum();
+        let mut lines = vec![format!("Opcodes ({} instructions):", total)];
+
+        let mut opcodes: Vec<(&usize, &u64)> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (&opcode, &count) in opcodes {
+            let name = MNEMONICS.iter()
+                .find(|&&(_, code, _)| code as usize == opcode)
+                .map_or(format!("op{}", opcode), |&(name, _, _)| name.to_string());
+            lines.push(format!("  {:<6} {:>12}  {:>5.1}%", name, count, 100.0 * count as f64 / total.max(1) as f64));
+        }
+
+        for (title, counts) in &[("Hottest instructions:", &self.instructions), ("Hottest memory:", &self.accesses)] {
+            lines.push(title.to_string());
+            let mut busiest: Vec<(&usize, &u64)> = counts.iter().collect();
+            busiest.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            for (address, count) in busiest.into_iter().take(top) {
+                lines.push(format!("  {:>8} {:>12}", address, count));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+pub fn start_profiling() {
+    *PROFILE.lock().unwrap() = Some(VmProfile::default());
+    PROFILING.store(true, Ordering::Relaxed);
+}
+
+// Stops profiling, returning what was counted
+pub fn stop_profiling() -> VmProfile {
+    PROFILING.store(false, Ordering::Relaxed);
+    PROFILE.lock().unwrap().take().unwrap_or_default()
+}
+
+const DEBUGGER_HELP: &str = "Commands:
+    c                 continue to the next breakpoint
+    s                 run one instruction
+    b <addr>          break before the instruction at an address
+    b op <n>          break before any instruction with opcode n
+    d <addr>          delete a breakpoint, or `d op <n>` for an opcode
+    x <addr> [count]  show memory, 1 cell by default
+    set <addr> <n>    write n to memory
+    rb [n]            show the relative base, or set it to n
+    i                 show where the machine is
+    q                 detach the debugger and run on
+    h                 show this help";
+
+// What the debugger does after a command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DebugAction {
+    Show(String),
+    Resume,
+    Detach
+}
+
+// Breakpoints on addresses and opcodes, with commands to look at and change a stopped
+// machine
+#[derive(Clone, Debug)]
+pub struct Debugger {
+    addresses: BTreeSet<usize>,
+    opcodes: BTreeSet<usize>,
+    stepping: bool
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger {
+            addresses: BTreeSet::new(),
+            opcodes: BTreeSet::new(),
+            stepping: true
+        }
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger::default()
+    }
+
+    fn should_break(&mut self, address: usize, opcode: usize) -> bool {
+        let stop = self.stepping || self.addresses.contains(&address) || self.opcodes.contains(&opcode);
+        self.stepping = false;
+        stop
+    }
+
+    pub fn execute(&mut self, program: &mut Program, command: &str) -> Result<DebugAction> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let number = |idx: usize| -> Result<i64> {
+            let word = words.get(idx).ok_or(format!("{} needs more arguments, see h", words[0]))?;
+            word.parse().map_err(|_| format!("Cannot read {:?} as a number", word).into())
+        };
+        let address = |idx: usize| -> Result<usize> {
+            let n = number(idx)?;
+            program.in_range(n).ok_or_else(|| format!("{} is not an address", n).into())
+        };
+
+        let shown = match words.as_slice() {
+            [] => return Ok(DebugAction::Show(String::new())),
+            ["c"] => return Ok(DebugAction::Resume),
+            ["s"] => {
+                self.stepping = true;
+                return Ok(DebugAction::Resume);
+            },
+            ["q"] => return Ok(DebugAction::Detach),
+            ["h"] => DEBUGGER_HELP.to_string(),
+            ["i"] => program.debug_state(),
+            ["b", "op", _] => {
+                self.opcodes.insert(number(2)? as usize);
+                format!("Breaking on opcode {}", words[2])
+            },
+            ["b", _] => {
+                self.addresses.insert(address(1)?);
+                format!("Breaking at {}", words[1])
+            },
+            ["d", "op", _] => {
+                self.opcodes.remove(&(number(2)? as usize));
+                format!("No longer breaking on opcode {}", words[2])
+            },
+            ["d", _] => {
+                self.addresses.remove(&address(1)?);
+                format!("No longer breaking at {}", words[1])
+            },
+            ["x", _] | ["x", _, _] => {
+                let start = address(1)?;
+                let count = if words.len() == 3 { number(2)?.clamp(1, 1000) as usize } else { 1 };
+                let cells: Vec<String> = (start..start + count).map(|idx| program.memory.get(idx).to_string()).collect();
+                format!("{}: {}", start, cells.join(","))
+            },
+            ["set", _, _] => {
+                let (idx, value) = (address(1)?, number(2)?);
+                program.memory.set(idx, value);
+                if let Some(compiled) = program.compiled.as_mut() {
+                    compiled.written(idx);
+                }
+                format!("{} = {}", idx, value)
+            },
+            ["rb"] => format!("Relative base {}", program.relative_base),
+            ["rb", _] => {
+                program.relative_base = number(1)?;
+                format!("Relative base {}", program.relative_base)
+            },
+            _ => return err!("Unknown command {:?}, see h", command.trim())
+        };
+
+        Ok(DebugAction::Show(shown))
+    }
+}
+
+// Where a machine keeps its memory. Paged memory is fastest, but holds a page table entry
+// for every page up to the highest address written, so a program writing far out, such as
+// through a large relative address, is better off with sparse memory, which only holds
+// the cells written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum MemoryBackend {
+    Paged,
+    Sparse
+}
+
+impl MemoryBackend {
+    // The highest address a program can use. Nothing real comes near it, so an address
+    // past it is from a corrupted program, and paged memory would run out trying to reach
+    // it. Sparse memory can go much further, as it doesn't fill in the gap.
+    pub fn max_address(self) -> i64 {
+        match self {
+            MemoryBackend::Paged => 1 << 32,
+            MemoryBackend::Sparse => 1 << 48
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<MemoryBackend> {
+        match s {
+            "paged" => Ok(MemoryBackend::Paged),
+            "sparse" => Ok(MemoryBackend::Sparse),
+            _ => err!("Unknown memory backend {:?}, expected paged or sparse", s)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Storage {
+    Paged(Vec<Option<Arc<Vec<i64>>>>),
+    Sparse(Arc<HashMap<usize, i64>>)
+}
+
+// Intcode memory, shared between copies of a machine until one of them writes to it.
+// Cloning a machine is then cheap, so searches can fork thousands of machines from one
+// state. Cells which have never been written read as zeroes, however far out they are.
+//
+// Paged memory is split into pages which are only copied when written, so a fork only
+// copies the pages it changes. Sparse memory is copied whole on a fork's first write.
+#[derive(Clone, Debug)]
+pub struct Memory {
+    storage: Storage
+}
+
+impl Default for Memory {
+    fn default() -> Memory {
+        Memory::new(&[])
+    }
+}
+
+impl Memory {
+    pub fn new(values: &[i64]) -> Memory {
+        Memory::with_backend(values, MemoryBackend::Paged)
+    }
+
+    pub fn with_backend(values: &[i64], backend: MemoryBackend) -> Memory {
+        let storage = match backend {
+            MemoryBackend::Paged => Storage::Paged(
+                values.chunks(PAGE_SIZE)
+                    .map(|chunk| {
+                        let mut page = chunk.to_vec();
+                        page.resize(PAGE_SIZE, 0);
+                        Some(Arc::new(page))
+                    })
+                    .collect()
+            ),
+            MemoryBackend::Sparse => Storage::Sparse(Arc::new(
+                values.iter().enumerate().filter(|&(_, &val)| val != 0).map(|(idx, &val)| (idx, val)).collect()
+            ))
+        };
+
+        Memory { storage }
+    }
+
+    pub fn backend(&self) -> MemoryBackend {
+        match self.storage {
+            Storage::Paged(_) => MemoryBackend::Paged,
+            Storage::Sparse(_) => MemoryBackend::Sparse
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> i64 {
+        match &self.storage {
+            Storage::Paged(pages) => match pages.get(idx / PAGE_SIZE) {
+                Some(Some(page)) => page[idx % PAGE_SIZE],
+                _ => 0
+            },
+            Storage::Sparse(cells) => cells.get(&idx).cloned().unwrap_or(0)
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, val: i64) {
+        match &mut self.storage {
+            Storage::Paged(pages) => {
+                let page_idx = idx / PAGE_SIZE;
+                if pages.len() <= page_idx {
+                    pages.resize(page_idx + 1, None);
+                }
+
+                let page = pages[page_idx].get_or_insert_with(|| Arc::new(vec![0; PAGE_SIZE]));
+                Arc::make_mut(page)[idx % PAGE_SIZE] = val;
+            },
+            Storage::Sparse(cells) => {
+                Arc::make_mut(cells).insert(idx, val);
+            }
+        }
+    }
+
+    // Every cell which isn't zero, in address order
+    pub fn cells(&self) -> Vec<(usize, i64)> {
+        let mut cells: Vec<(usize, i64)> = match &self.storage {
+            Storage::Paged(pages) => pages.iter()
+                .enumerate()
+                .filter_map(|(page_idx, page)| page.as_ref().map(|page| (page_idx, page)))
+                .flat_map(|(page_idx, page)| page.iter()
+                    .enumerate()
+                    .map(move |(offset, &val)| (page_idx * PAGE_SIZE + offset, val)))
+                .filter(|&(_, val)| val != 0)
+                .collect(),
+            Storage::Sparse(cells) => cells.iter().filter(|&(_, &val)| val != 0).map(|(&idx, &val)| (idx, val)).collect()
+        };
+        cells.sort_unstable();
+
+        cells
+    }
+
+    // Every cell which isn't zero as a line of `address: value`, for saving to a file and
+    // comparing with another dump later
+    pub fn dump(&self) -> String {
+        self.cells().iter().map(|(idx, val)| format!("{}: {}\n", idx, val)).collect()
+    }
+
+    // Reads back a dump, into sparse memory as it may be full of gaps
+    pub fn parse_dump(s: &str) -> Result<Memory> {
+        let mut memory = Memory::with_backend(&[], MemoryBackend::Sparse);
+        for line in s.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            let cell = line.split_once(':').and_then(|(idx, val)| Some((idx.trim().parse().ok()?, val.trim().parse().ok()?)));
+            match cell {
+                Some((idx, val)) => memory.set(idx, val),
+                None => return err!("Cannot read {:?} as a dumped cell, expected address: value", line)
+            }
+        }
+
+        Ok(memory)
+    }
+
+    // The cells which differ between this memory and `other`, as the address with the
+    // value here and the value there
+    pub fn changes(&self, other: &Memory) -> Vec<(usize, i64, i64)> {
+        let mut cells: BTreeMap<usize, (i64, i64)> = self.cells().into_iter().map(|(idx, val)| (idx, (val, 0))).collect();
+        for (idx, val) in other.cells() {
+            cells.entry(idx).or_insert((0, 0)).1 = val;
+        }
+
+        cells.into_iter().filter(|&(_, (a, b))| a != b).map(|(idx, (a, b))| (idx, a, b)).collect()
+    }
+
+    // How many pages this memory still shares with `other`, for checking that forks
+    // aren't copying more than they write. Sparse memory counts as a single page.
+    pub fn shared_pages(&self, other: &Memory) -> usize {
+        match (&self.storage, &other.storage) {
+            (Storage::Paged(pages), Storage::Paged(other_pages)) => pages.iter().zip(other_pages.iter())
+                .filter(|(a, b)| match (a, b) {
+                    (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                    _ => false
+                })
+                .count(),
+            (Storage::Sparse(cells), Storage::Sparse(other_cells)) => Arc::ptr_eq(cells, other_cells) as usize,
+            _ => 0
+        }
+    }
+}
+
+#[derive(Clone, Copy, Eq, Debug, PartialEq, Hash)]
+pub enum Parameter {
+    Position,
+    Immediate,
+    Relative
+}
+
+// An instruction code split into its opcode and the mode of each parameter
+#[derive(Clone, Eq, Default, Debug, PartialEq, Hash)]
+pub struct Instruction {
+    pub opcode: usize,
+    pub parameters: Vec<Parameter>
+}
+
+impl Instruction {
+    fn new(number: i64) -> Result<Instruction> {
+        if number < 0 {
+            return err!("Cannot read instruction: {}", number);
+        }
+
+        let params_length = match number % 100 {
+            1 | 2 | 7 | 8 => 3,
+            5 | 6 => 2,
+            3 | 4 | 9 => 1,
+            99 => 0,
+            x => return err!("Cannot read opcode: {}", x)
+        };
+
+        Instruction::with_params(number, params_length)
+    }
+
+    fn with_params(number: i64, params_length: usize) -> Result<Instruction> {
+        let opcode = (number % 100) as usize;
+        let parameters: Result<Vec<Parameter>> = digits::low_digits((number / 100) as u64, params_length)
+            .into_iter()
+            .map(|d| match d {
+                0 => Ok(Parameter::Position),
+                1 => Ok(Parameter::Immediate),
+                2 => Ok(Parameter::Relative),
+                x => err!("Cannot read parameter digit: {}", x)
+            })
+            .collect();
+
+        Ok(
+            Instruction {
+                opcode,
+                parameters: parameters?,
+            }
+        )
+    }
+}
+
+// Describes an instruction code such as 203 by its operation and parameter modes, e.g.
+// "input (relative)", or None if it isn't a valid instruction
+pub fn describe_instruction(code: i64) -> Option<String> {
+    let instruction = Instruction::new(code).ok()?;
+    let name = match instruction.opcode {
+        1 => "add",
+        2 => "multiply",
+        3 => "input",
+        4 => "output",
+        5 => "jump-if-true",
+        6 => "jump-if-false",
+        7 => "less than",
+        8 => "equals",
+        9 => "adjust relative base",
+        _ => "halt"
+    };
+
+    let modes: Vec<&str> = instruction.parameters.iter()
+        .map(|parameter| match parameter {
+            Parameter::Position => "position",
+            Parameter::Immediate => "immediate",
+            Parameter::Relative => "relative"
+        })
+        .collect();
+
+    if modes.is_empty() {
+        Some(name.to_string())
+    } else {
+        Some(format!("{} ({})", name, modes.join(", ")))
+    }
+}
+
+// Why a program stopped running
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum RunState {
+    // It output a value, and carries on from the next instruction when run again
+    Output(i64),
+    // It needs an input it hasn't been given, and carries on from the instruction that
+    // needed it once one is added
+    NeedsInput,
+    Halted
+}
+
+// One instruction run by `Program::step`, with where the machine was when it ran it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Step {
+    pub pointer: usize,
+    pub relative_base: i64,
+    pub instruction: Instruction,
+    // The parameters as they are in memory, before their modes are applied
+    pub operands: Vec<i64>,
+    // None if the instruction ran and the program carries on as normal. Halted or
+    // NeedsInput are also given for a machine which was already stopped, in which case
+    // nothing ran.
+    pub outcome: Option<RunState>
+}
+
+// Somewhere a running program takes its inputs from. None means there is nothing to give
+// it yet, and the program stops to wait as it would with no inputs queued.
+pub trait InputSource {
+    fn next_input(&mut self) -> Option<i64>;
+}
+
+pub type InputFn = dyn FnMut() -> i64 + Send;
+
+// A closure a machine calls for each input it needs once its queue is empty, shared by
+// every clone of the machine
+#[derive(Clone)]
+struct InputProvider(Arc<Mutex<Box<InputFn>>>);
+
+impl fmt::Debug for InputProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InputProvider")
+    }
+}
+
+// Somewhere a running program's outputs go
+pub trait OutputSink {
+    fn send_output(&mut self, value: i64) -> Result<()>;
+}
+
+impl InputSource for VecDeque<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.pop_front()
+    }
+}
+
+impl<F: FnMut() -> Option<i64>> InputSource for F {
+    fn next_input(&mut self) -> Option<i64> {
+        self()
+    }
+}
+
+// Waits for the next value, giving None once every sender has gone
+impl InputSource for Receiver<i64> {
+    fn next_input(&mut self) -> Option<i64> {
+        self.recv().ok()
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    fn send_output(&mut self, value: i64) -> Result<()> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    fn send_output(&mut self, value: i64) -> Result<()> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+impl<F: FnMut(i64) -> Result<()>> OutputSink for F {
+    fn send_output(&mut self, value: i64) -> Result<()> {
+        self(value)
+    }
+}
+
+impl OutputSink for Sender<i64> {
+    fn send_output(&mut self, value: i64) -> Result<()> {
+        match self.send(value) {
+            Ok(()) => Ok(()),
+            Err(_) => err!("Nothing is listening for output {}", value)
+        }
+    }
+}
+
+// Reads inputs from standard input, one number per line, until it's closed
+pub struct StdinSource;
+
+impl InputSource for StdinSource {
+    fn next_input(&mut self) -> Option<i64> {
+        let mut line = String::new();
+        while io::stdin().lock().read_line(&mut line).ok()? > 0 {
+            match line.trim().parse() {
+                Ok(input) => return Some(input),
+                Err(_) => {
+                    eprintln!("Cannot read input {:?}, expected a number", line.trim());
+                    line.clear();
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// Prints each output on its own line
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn send_output(&mut self, value: i64) -> Result<()> {
+        println!("{}", value);
+        Ok(())
+    }
+}
+
+// The name of each standard instruction in the disassembler and assembler, with its
+// opcode and how many parameters it takes
+pub const MNEMONICS: &[(&str, i64, usize)] = &[
+    ("add", 1, 3),
+    ("mul", 2, 3),
+    ("in", 3, 1),
+    ("out", 4, 1),
+    ("jnz", 5, 2),
+    ("jz", 6, 2),
+    ("lt", 7, 3),
+    ("eq", 8, 3),
+    ("arb", 9, 1),
+    ("halt", 99, 0),
+];
+
+// One instruction, or a cell which doesn't decode as one, from a program read in order
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Disassembled {
+    pub address: usize,
+    pub len: usize,
+    pub text: String
+}
+
+// Reads a program as instructions from the start, stepping over each instruction's
+// parameters. Data mixed in with the code throws this off until it finds its way back
+// to real instructions, but it's enough to see what a change does.
+//
+// Parameters are shown as `[n]` for a position, `n` for an immediate value and `[rb+n]`
+// relative to the relative base, e.g. "12: mul [4], 3, [rb+2]". A cell which isn't an
+// instruction is shown as `.data n`, the way the assembler reads it back.
+pub fn disassemble(memory: &[i64]) -> Vec<Disassembled> {
+    let mut lines = vec![];
+    let mut address = 0;
+    while address < memory.len() {
+        let decoded = Instruction::new(memory[address]).ok()
+            .filter(|instruction| address + instruction.parameters.len() < memory.len());
+
+        let (len, text) = match decoded {
+            Some(instruction) => {
+                let mnemonic = MNEMONICS.iter()
+                    .find(|&&(_, opcode, _)| opcode as usize == instruction.opcode)
+                    .map_or("halt", |&(mnemonic, _, _)| mnemonic);
+                let operands: Vec<String> = instruction.parameters.iter().enumerate()
+                    .map(|(n, parameter)| {
+                        let val = memory[address + n + 1];
+                        match parameter {
+                            Parameter::Position => format!("[{}]", val),
+                            Parameter::Immediate => val.to_string(),
+                            Parameter::Relative => format!("[rb{:+}]", val)
+                        }
+                    })
+                    .collect();
+
+                (1 + operands.len(), format!("{} {}", mnemonic, operands.join(", ")).trim_end().to_string())
+            },
+            None => (1, format!(".data {}", memory[address]))
+        };
+
+        lines.push(Disassembled {
+            address,
+            len,
+            text: format!("{}: {}", address, text)
+        });
+        address += len;
+    }
+
+    lines
+}
+
+// Compares two programs cell by cell, showing each change with the instructions around it
+// in both, as lines starting `-` for `a` and `+` for `b`. Changes within the same pair of
+// instructions are reported together.
+pub fn diff(a: &[i64], b: &[i64]) -> Vec<String> {
+    fn covering(lines: &[Disassembled], address: usize) -> Option<&Disassembled> {
+        let idx = lines.partition_point(|line| line.address <= address);
+        lines[..idx].last().filter(|line| address < line.address + line.len)
+    }
+
+    let a_lines = disassemble(a);
+    let b_lines = disassemble(b);
+
+    let mut hunks: Vec<(Option<&Disassembled>, Option<&Disassembled>, Vec<usize>)> = vec![];
+    for address in (0..a.len().max(b.len())).filter(|&address| a.get(address) != b.get(address)) {
+        let (a_line, b_line) = (covering(&a_lines, address), covering(&b_lines, address));
+        match hunks.last_mut() {
+            Some((last_a, last_b, addresses)) if *last_a == a_line && *last_b == b_line => addresses.push(address),
+            _ => hunks.push((a_line, b_line, vec![address]))
+        }
+    }
+
+    let mut report = vec![];
+    for (a_line, b_line, addresses) in hunks {
+        let addresses: Vec<String> = addresses.iter().map(|address| address.to_string()).collect();
+        report.push(format!("@ {}", addresses.join(", ")));
+        report.push(format!("- {}", a_line.map_or("(past the end)", |line| line.text.as_str())));
+        report.push(format!("+ {}", b_line.map_or("(past the end)", |line| line.text.as_str())));
+    }
+
+    report
+}
+
+pub type OpcodeHandler = dyn Fn(&[i64]) -> Result<Option<i64>> + Send + Sync;
+
+// An instruction added to the standard set. Its handler is given the values of its
+// parameters, read in the usual modes, and can return a value for the program to output.
+#[derive(Clone)]
+pub struct CustomOpcode {
+    params: usize,
+    handler: Arc<OpcodeHandler>
+}
+
+impl fmt::Debug for CustomOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CustomOpcode {{ params: {} }}", self.params)
+    }
+}
+
+// Something on the host which a program can talk to by reading and writing the addresses
+// it's mapped to, such as a console or a frame buffer. Offsets are from where it's mapped.
+pub trait Device {
+    // How many addresses the device takes up
+    fn size(&self) -> usize;
+    fn read(&mut self, offset: usize) -> i64;
+    fn write(&mut self, offset: usize, value: i64);
+}
+
+pub type SharedDevice = Arc<Mutex<dyn Device + Send>>;
+
+#[derive(Clone)]
+struct MappedDevice {
+    base: usize,
+    size: usize,
+    device: SharedDevice
+}
+
+impl fmt::Debug for MappedDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MappedDevice {{ base: {}, size: {} }}", self.base, self.size)
+    }
+}
+
+// How closely a machine sticks to the Intcode spec
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Compliance {
+    // Only the standard instructions, for checking a program will run on any Intcode
+    // computer. Writing to an immediate parameter, touching an address out of range or
+    // reaching an unknown opcode is an error.
+    Strict,
+    // As strict, but custom opcodes can be added. The default.
+    Standard,
+    // Runs what the first interpreters here ran: an immediate parameter is written as a
+    // position one, as days 2 and 5 did, addresses out of range read as zero and writes
+    // to them are dropped. Unknown opcodes are still an error, as nothing ever ran past one.
+    Lenient
+}
+
+// How a machine runs its instructions. An interpreted machine decodes each instruction
+// from memory as it reaches it. A compiled one decodes the whole program up front, so that
+// the instructions it runs over and over are only decoded once. Any instruction the
+// program writes over is decoded afresh when it's reached, as is everything while
+// debugging, tracing or profiling, so the two always run the same.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Execution {
+    Interpreted,
+    Compiled
+}
+
+impl Execution {
+    pub fn parse(s: &str) -> Result<Execution> {
+        match s {
+            "interpreted" => Ok(Execution::Interpreted),
+            "compiled" => Ok(Execution::Compiled),
+            _ => err!("Unknown execution {:?}, expected interpreted or compiled", s)
+        }
+    }
+}
+
+// What a machine does when a program writes over cells it has already run as
+// instructions, as some programs do to patch themselves
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum SelfModification {
+    // Let it, keeping a note for `code_writes`
+    Report,
+    // Stop with an error
+    Deny
+}
+
+impl SelfModification {
+    pub fn parse(s: &str) -> Result<SelfModification> {
+        match s {
+            "report" => Ok(SelfModification::Report),
+            "deny" => Ok(SelfModification::Deny),
+            _ => err!("Unknown handling of self-modifying code {:?}, expected report or deny", s)
+        }
+    }
+}
+
+// Writes a program made to one cell it had already run as part of an instruction
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CodeWrite {
+    pub address: usize,
+    // The instructions which wrote to it
+    pub writers: BTreeSet<usize>,
+    pub writes: u64,
+    pub original: i64,
+    pub latest: i64
+}
+
+impl fmt::Display for CodeWrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let writers: Vec<String> = self.writers.iter().map(|writer| writer.to_string()).collect();
+        write!(
+            f,
+            "{}: written {} times by the instructions at {}, from {} to {}",
+            self.address,
+            self.writes,
+            writers.join(", "),
+            self.original,
+            self.latest
+        )
+    }
+}
+
+// The cells a machine has run as instructions, and the writes made over them since
+#[derive(Clone, Debug)]
+struct CodeWatch {
+    handling: SelfModification,
+    executed: BTreeSet<usize>,
+    writes: BTreeMap<usize, CodeWrite>
+}
+
+// Builds a program which understands extra opcodes, such as a debug print from an
+// experimental compiler, without touching the standard ones, or which is more or less
+// forgiving of programs breaking the spec
+pub struct VmBuilder {
+    memory: Vec<i64>,
+    backend: MemoryBackend,
+    opcodes: BTreeMap<usize, CustomOpcode>,
+    devices: Vec<MappedDevice>,
+    compliance: Compliance,
+    execution: Execution,
+    self_modification: Option<SelfModification>,
+    input_fn: Option<InputProvider>
+}
+
+impl VmBuilder {
+    pub fn new(memory: Vec<i64>) -> VmBuilder {
+        VmBuilder {
+            memory,
+            backend: MemoryBackend::Paged,
+            opcodes: BTreeMap::new(),
+            devices: vec![],
+            compliance: Compliance::Standard,
+            execution: Execution::Interpreted,
+            self_modification: None,
+            input_fn: None
+        }
+    }
+
+    pub fn backend(mut self, backend: MemoryBackend) -> VmBuilder {
+        self.backend = backend;
+        self
+    }
+
+    pub fn opcode<F>(mut self, opcode: usize, params: usize, handler: F) -> VmBuilder
+        where F: Fn(&[i64]) -> Result<Option<i64>> + Send + Sync + 'static
+    {
+        self.opcodes.insert(opcode, CustomOpcode { params, handler: Arc::new(handler) });
+        self
+    }
+
+    // Routes parameter reads and writes of the addresses from `base` to `device` instead of
+    // memory. The caller keeps its own handle to look at the device afterwards. Programs
+    // can't run instructions from a device.
+    pub fn device(mut self, base: usize, device: SharedDevice) -> VmBuilder {
+        let size = device.lock().unwrap().size();
+        self.devices.push(MappedDevice { base, size, device });
+        self
+    }
+
+    pub fn compliance(mut self, compliance: Compliance) -> VmBuilder {
+        self.compliance = compliance;
+        self
+    }
+
+    pub fn execution(mut self, execution: Execution) -> VmBuilder {
+        self.execution = execution;
+        self
+    }
+
+    // Watches for the program writing over its own instructions once they've run. A
+    // watched machine is always interpreted, as it has to note every instruction it runs.
+    pub fn self_modification(mut self, handling: SelfModification) -> VmBuilder {
+        self.self_modification = Some(handling);
+        self
+    }
+
+    // Gives the machine a closure to call whenever it needs an input and has none queued,
+    // so that it never stops to wait. The closure isn't saved with the machine.
+    pub fn input_fn<F>(mut self, input_fn: F) -> VmBuilder
+        where F: FnMut() -> i64 + Send + 'static
+    {
+        self.input_fn = Some(InputProvider(Arc::new(Mutex::new(Box::new(input_fn)))));
+        self
+    }
+
+    pub fn build(mut self) -> Result<Program> {
+        if self.compliance == Compliance::Strict && !self.opcodes.is_empty() {
+            let opcodes: Vec<String> = self.opcodes.keys().map(|opcode| opcode.to_string()).collect();
+            return err!("Custom opcodes {} are not allowed in strict mode", opcodes.join(", "));
+        }
+
+        for &opcode in self.opcodes.keys() {
+            if opcode >= 100 || Instruction::new(opcode as i64).is_ok() {
+                return err!("Cannot add opcode {}, it must be below 100 and not a standard one", opcode);
+            }
+        }
+
+        self.devices.sort_by_key(|mapped| mapped.base);
+        for (a, b) in self.devices.iter().zip(self.devices.iter().skip(1)) {
+            if a.base + a.size > b.base {
+                return err!("Devices mapped at {} and {} overlap", a.base, b.base);
+            }
+        }
+
+        let mut program = Program::new(vec![]);
+        program.memory = Memory::with_backend(&self.memory, self.backend);
+        program.opcodes = Arc::new(self.opcodes);
+        program.lenient = self.compliance == Compliance::Lenient;
+        program.devices = Arc::new(self.devices);
+        if self.execution == Execution::Compiled {
+            program.compiled = Some(Compiled::new(&self.memory, self.backend));
+        }
+        program.input_fn = self.input_fn;
+        program.code_watch = self.self_modification.map(|handling| CodeWatch {
+            handling,
+            executed: BTreeSet::new(),
+            writes: BTreeMap::new()
+        });
+        Ok(program)
+    }
+}
+
+// A parameter with its mode applied, as far as it can be before running
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    Position(usize),
+    Immediate(i64),
+    // An offset from the relative base
+    Relative(i64)
+}
+
+// A standard instruction decoded along with its parameters
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Add(Operand, Operand, Operand),
+    Multiply(Operand, Operand, Operand),
+    Input(Operand),
+    Output(Operand),
+    JumpIfTrue(Operand, Operand),
+    JumpIfFalse(Operand, Operand),
+    LessThan(Operand, Operand, Operand),
+    Equals(Operand, Operand, Operand),
+    AdjustBase(Operand),
+    Halt
+}
+
+impl Op {
+    // The instruction starting at `idx`, or None for anything which can't be decoded
+    // ahead, which is left to the interpreter: data, custom opcodes, addresses out of range
+    // and writes to immediate parameters
+    fn decode(memory: &[i64], idx: usize, backend: MemoryBackend) -> Option<Op> {
+        let instruction = Instruction::new(memory[idx]).ok()?;
+        let cell = |n: usize| memory.get(idx + n + 1).cloned().unwrap_or(0);
+        let operand = |n: usize| match instruction.parameters[n] {
+            Parameter::Position if (0..=backend.max_address()).contains(&cell(n)) => Some(Operand::Position(cell(n) as usize)),
+            Parameter::Position => None,
+            Parameter::Immediate => Some(Operand::Immediate(cell(n))),
+            Parameter::Relative => Some(Operand::Relative(cell(n)))
+        };
+        let target = |n: usize| match operand(n)? {
+            Operand::Immediate(_) => None,
+            target => Some(target)
+        };
+
+        Some(match instruction.opcode {
+            1 => Op::Add(operand(0)?, operand(1)?, target(2)?),
+            2 => Op::Multiply(operand(0)?, operand(1)?, target(2)?),
+            3 => Op::Input(target(0)?),
+            4 => Op::Output(operand(0)?),
+            5 => Op::JumpIfTrue(operand(0)?, operand(1)?),
+            6 => Op::JumpIfFalse(operand(0)?, operand(1)?),
+            7 => Op::LessThan(operand(0)?, operand(1)?, target(2)?),
+            8 => Op::Equals(operand(0)?, operand(1)?, target(2)?),
+            9 => Op::AdjustBase(operand(0)?),
+            _ => Op::Halt
+        })
+    }
+}
+
+// A program decoded from every address, as a jump can land anywhere. The decoded
+// instructions are shared between copies of a machine, while each copy notes which of
+// them it has written over.
+#[derive(Clone, Debug)]
+struct Compiled {
+    ops: Arc<Vec<Option<Op>>>,
+    stale: Vec<bool>
+}
+
+impl Compiled {
+    fn new(memory: &[i64], backend: MemoryBackend) -> Compiled {
+        let ops: Vec<Option<Op>> = (0..memory.len()).map(|idx| Op::decode(memory, idx, backend)).collect();
+        Compiled { stale: vec![false; ops.len()], ops: Arc::new(ops) }
+    }
+
+    fn get(&self, idx: usize) -> Option<Op> {
+        match self.stale.get(idx) {
+            Some(false) => self.ops[idx],
+            _ => None
+        }
+    }
+
+    // Forgets every instruction which reads the cell written, up to three cells before it
+    fn written(&mut self, idx: usize) {
+        for stale in self.stale.iter_mut().take(idx + 1).skip(idx.saturating_sub(3)) {
+            *stale = true;
+        }
+    }
+}
+
+// A machine ran out of the instructions it was allowed, see `Program::limit_steps`, most
+// likely because it was stuck in a loop
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StepLimitExceeded {
+    pub limit: u64,
+    // The instruction it would have run next
+    pub pointer: usize
+}
+
+impl fmt::Display for StepLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Stopped after {} instructions at address {}, the machine may be stuck in a loop", self.limit, self.pointer)
+    }
+}
+
+impl Error for StepLimitExceeded {}
+
+// What running a machine with its inputs closed fails with when it asks for another
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InputExhausted {
+    // The input instruction
+    pub pointer: usize
+}
+
+impl fmt::Display for InputExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Needed another input at address {} after using every one it was given", self.pointer)
+    }
+}
+
+impl Error for InputExhausted {}
+
+// An Intcode computer which can be cloned cheaply part way through running, see `Memory`
+#[derive(Clone, Debug)]
+pub struct Program {
+    memory: Memory,
+    inputs: VecDeque<i64>,
+    pointer_idx: usize,
+    relative_base: i64,
+    halted: bool,
+    instructions: u64,
+    opcodes: Arc<BTreeMap<usize, CustomOpcode>>,
+    devices: Arc<Vec<MappedDevice>>,
+    lenient: bool,
+    step_limit: Option<u64>,
+    inputs_closed: bool,
+    input_fn: Option<InputProvider>,
+    compiled: Option<Compiled>,
+    code_watch: Option<CodeWatch>
+}
+
+impl Program {
+    pub fn new(memory: Vec<i64>) -> Program {
+        Program {
+            memory: Memory::new(&memory),
+            inputs: VecDeque::new(),
+            pointer_idx: 0,
+            relative_base: 0,
+            halted: false,
+            instructions: 0,
+            opcodes: Arc::new(BTreeMap::new()),
+            devices: Arc::new(vec![]),
+            lenient: false,
+            step_limit: None,
+            inputs_closed: false,
+            input_fn: None,
+            compiled: None,
+            code_watch: None
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Program> {
+        let memory: result::Result<Vec<i64>, _> = s.trim().split(',').map(|s| s.trim().parse()).collect();
+        Ok(Program::new(memory?))
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    // Whether the program has stopped to wait for more input
+    pub fn awaiting_input(&self) -> bool {
+        !self.halted && self.inputs.is_empty() && self.input_fn.is_none() && self.memory.get(self.pointer_idx) % 100 == 3
+    }
+
+    // Instructions executed by this machine, including before it was cloned
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    // Makes running the machine fail with `StepLimitExceeded` once it has executed `limit`
+    // instructions in all, counted as `instructions` counts them, rather than running for
+    // ever. The limit isn't saved with the machine.
+    pub fn limit_steps(&mut self, limit: u64) {
+        self.step_limit = Some(limit);
+    }
+
+    fn check_step_limit(&self) -> Result<()> {
+        match self.step_limit {
+            Some(limit) if self.instructions >= limit => {
+                Err(Box::new(StepLimitExceeded { limit, pointer: self.pointer_idx }))
+            },
+            _ => Ok(())
+        }
+    }
+
+    // Every cell written after it had been run as an instruction, in address order, if
+    // the machine was built to watch for them
+    pub fn code_writes(&self) -> Vec<&CodeWrite> {
+        self.code_watch.iter().flat_map(|watch| watch.writes.values()).collect()
+    }
+
+    pub fn add_input(&mut self, input: i64) {
+        self.inputs.push_back(input);
+    }
+
+    pub fn add_inputs<I: IntoIterator<Item = i64>>(&mut self, inputs: I) {
+        self.inputs.extend(inputs);
+    }
+
+    // Makes running the machine fail with `InputExhausted` when it needs an input and none
+    // is queued, rather than waiting for more, for machines given every input up front.
+    // Like the step limit, this isn't saved with the machine.
+    pub fn close_inputs(&mut self) {
+        self.inputs_closed = true;
+    }
+
+    // Whether there's an input to read, asking the input closure for one if the queue is
+    // empty and the machine has one
+    fn has_input(&mut self) -> bool {
+        if self.inputs.is_empty() {
+            if let Some(InputProvider(input_fn)) = &self.input_fn {
+                let input = (input_fn.lock().unwrap())();
+                self.inputs.push_back(input);
+            }
+        }
+
+        !self.inputs.is_empty()
+    }
+
+    // What running a machine which needs an input it hasn't got comes to
+    fn starved(&self) -> Result<RunState> {
+        if self.inputs_closed {
+            Err(Box::new(InputExhausted { pointer: self.pointer_idx }))
+        } else {
+            Ok(RunState::NeedsInput)
+        }
+    }
+
+    // Queues a line of ASCII text, as the springdroid and vacuum robot programs read
+    pub fn add_line(&mut self, line: &str) {
+        for c in line.chars() {
+            self.add_input(c as u8 as i64);
+        }
+        self.add_input(i64::from(b'\n'));
+    }
+
+    // Where the machine is and the instruction it's about to run
+    fn debug_state(&self) -> String {
+        let cells: Vec<i64> = (self.pointer_idx..self.pointer_idx + 4).map(|idx| self.memory.get(idx)).collect();
+        let instruction = disassemble(&cells).remove(0).text;
+        format!(
+            "{}: {} (relative base {}, {} inputs queued)",
+            self.pointer_idx,
+            instruction.split_once(": ").map_or(instruction.as_str(), |(_, text)| text),
+            self.relative_base,
+            self.inputs.len()
+        )
+    }
+
+    // Reads debugger commands from standard input until one resumes the machine, if it
+    // should stop here
+    fn debug_prompt(&mut self, opcode: usize) -> Result<()> {
+        let mut guard = DEBUGGER.lock().unwrap();
+        let debugger = match guard.as_mut() {
+            Some(debugger) => debugger,
+            None => return Ok(())
+        };
+        if !debugger.should_break(self.pointer_idx, opcode) {
+            return Ok(());
+        }
+
+        eprintln!("{}", self.debug_state());
+        let stdin = io::stdin();
+        loop {
+            eprint!("(debug) ");
+            let mut line = String::new();
+            // Running out of commands lets the machine carry on by itself
+            let action = if stdin.lock().read_line(&mut line)? == 0 {
+                DebugAction::Detach
+            } else {
+                debugger.execute(self, &line).unwrap_or_else(|e| DebugAction::Show(e.to_string()))
+            };
+
+            match action {
+                DebugAction::Show(shown) => if !shown.is_empty() { eprintln!("{}", shown) },
+                DebugAction::Resume => return Ok(()),
+                DebugAction::Detach => {
+                    DEBUGGING.store(false, Ordering::Relaxed);
+                    *guard = None;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn in_range(&self, idx: i64) -> Option<usize> {
+        if (0..=self.memory.backend().max_address()).contains(&idx) {
+            Some(idx as usize)
+        } else {
+            None
+        }
+    }
+
+    // An error about the instruction being run, saying where it is and what it is
+    fn fault(&self, message: String) -> Box<dyn Error> {
+        format!("{} at address {} (instruction {})", message, self.pointer_idx, self.memory.get(self.pointer_idx)).into()
+    }
+
+    // The result of some arithmetic, or an error if it didn't fit in a cell rather than
+    // carrying on with a wrapped value. See `intcode_wide` for running with wider cells.
+    fn checked(&self, result: Option<i64>, a: i64, op: &str, b: i64) -> Result<i64> {
+        result.ok_or_else(|| self.fault(format!("{} {} {} overflows 64 bits", a, op, b)))
+    }
+
+    // The address a parameter refers to, or None for an address out of range in lenient
+    // mode
+    fn address(&self, parameter_form: Parameter, val: i64) -> Result<Option<usize>> {
+        let idx = match parameter_form {
+            Parameter::Position => val,
+            Parameter::Relative => match self.relative_base.checked_add(val) {
+                Some(idx) => idx,
+                None if self.lenient => return Ok(None),
+                None => return Err(self.fault(format!("Relative address {} + {} overflows", self.relative_base, val)))
+            },
+            Parameter::Immediate if self.lenient => val,
+            Parameter::Immediate => return Err(self.fault("Cannot write to an immediate parameter".to_string()))
+        };
+
+        match self.in_range(idx) {
+            Some(idx) => Ok(Some(idx)),
+            None if self.lenient => Ok(None),
+            None if idx < 0 => Err(self.fault(format!("Cannot access negative address {}", idx))),
+            None => Err(self.fault(format!("Cannot access address {}, which is past the end of memory", idx)))
+        }
+    }
+
+    fn get_parameter(&self, instruction: &Instruction, n: usize) -> Result<i64> {
+        let val = self.memory.get(self.pointer_idx + n + 1);
+        match instruction.parameters[n] {
+            Parameter::Immediate => Ok(val),
+            form => Ok(self.address(form, val)?.map_or(0, |idx| self.load(idx)))
+        }
+    }
+
+    fn set_parameter(&mut self, instruction: &Instruction, n: usize, val: i64) -> Result<()> {
+        if let Some(idx) = self.address(instruction.parameters[n], self.memory.get(self.pointer_idx + n + 1))? {
+            if self.code_watch.is_some() {
+                self.watch_write(idx, val)?;
+            }
+            self.store(idx, val);
+        }
+
+        Ok(())
+    }
+
+    fn watch_write(&mut self, idx: usize, val: i64) -> Result<()> {
+        let (pointer, original) = (self.pointer_idx, self.memory.get(idx));
+        let handling = match self.code_watch.as_ref() {
+            Some(watch) if watch.executed.contains(&idx) => watch.handling,
+            _ => return Ok(())
+        };
+        if handling == SelfModification::Deny {
+            return Err(self.fault(format!("Cannot write {} over address {}, which has already run as an instruction", val, idx)));
+        }
+
+        if let Some(watch) = self.code_watch.as_mut() {
+            let write = watch.writes.entry(idx).or_insert_with(|| CodeWrite { address: idx, original, ..CodeWrite::default() });
+            write.writers.insert(pointer);
+            write.writes += 1;
+            write.latest = val;
+        }
+
+        Ok(())
+    }
+
+    fn mapped_device(&self, idx: usize) -> Option<&MappedDevice> {
+        self.devices.iter().find(|mapped| mapped.base <= idx && idx < mapped.base + mapped.size)
+    }
+
+    fn load(&self, idx: usize) -> i64 {
+        if !self.devices.is_empty() {
+            if let Some(mapped) = self.mapped_device(idx) {
+                return mapped.device.lock().unwrap().read(idx - mapped.base);
+            }
+        }
+
+        self.memory.get(idx)
+    }
+
+    fn store(&mut self, idx: usize, val: i64) {
+        if !self.devices.is_empty() {
+            if let Some(mapped) = self.mapped_device(idx) {
+                mapped.device.lock().unwrap().write(idx - mapped.base, val);
+                return;
+            }
+        }
+        if let Some(compiled) = self.compiled.as_mut() {
+            compiled.written(idx);
+        }
+
+        self.memory.set(idx, val);
+    }
+
+    // Runs until the program outputs a value, needs an input it hasn't been given or halts
+    pub fn run_program(&mut self) -> Result<RunState> {
+        let start = self.instructions;
+        let result = self.run_until_output();
+        count_executed(self.instructions - start);
+
+        result
+    }
+
+    fn run_until_output(&mut self) -> Result<RunState> {
+        let instrumented = (INSTRUMENTED && (DEBUGGING.load(Ordering::Relaxed) || TRACING.load(Ordering::Relaxed) || PROFILING.load(Ordering::Relaxed)))
+            || self.code_watch.is_some();
+
+        while !self.halted {
+            if let Some(op) = self.compiled.as_ref().filter(|_| !instrumented).and_then(|compiled| compiled.get(self.pointer_idx)) {
+                if let Op::Input(_) = op {
+                    if !self.has_input() {
+                        return self.starved();
+                    }
+                }
+                self.check_step_limit()?;
+                self.instructions += 1;
+
+                if let Some(output) = self.execute_op(op)? {
+                    return Ok(RunState::Output(output));
+                }
+                continue;
+            }
+
+            let instruction = self.decode()?;
+            if instruction.opcode == 3 && !self.has_input() {
+                return self.starved();
+            }
+            self.check_step_limit()?;
+            if INSTRUMENTED && DEBUGGING.load(Ordering::Relaxed) {
+                self.debug_prompt(instruction.opcode)?;
+            }
+            self.instructions += 1;
+
+            if let Some(output) = self.run_instruction(&instruction)? {
+                return Ok(RunState::Output(output));
+            }
+        }
+
+        Ok(RunState::Halted)
+    }
+
+    // Runs exactly one instruction, returning what it was and what came of it. Stepping
+    // a machine which is waiting for input or has halted runs nothing.
+    pub fn step(&mut self) -> Result<Step> {
+        let instruction = self.decode()?;
+        let pointer = self.pointer_idx;
+        let relative_base = self.relative_base;
+        let operands: Vec<i64> = (1..=instruction.parameters.len()).map(|n| self.memory.get(pointer + n)).collect();
+
+        let outcome = if self.halted {
+            Some(RunState::Halted)
+        } else if instruction.opcode == 3 && !self.has_input() {
+            Some(self.starved()?)
+        } else {
+            self.check_step_limit()?;
+            self.instructions += 1;
+            count_executed(1);
+            match self.run_instruction(&instruction)? {
+                Some(output) => Some(RunState::Output(output)),
+                None if self.halted => Some(RunState::Halted),
+                None => None
+            }
+        };
+
+        Ok(Step { pointer, relative_base, instruction, operands, outcome })
+    }
+
+    fn decode(&self) -> Result<Instruction> {
+        let code = self.memory.get(self.pointer_idx);
+        match Instruction::new(code) {
+            Ok(instruction) => Ok(instruction),
+            // Only look for custom opcodes once the standard ones don't match, so they
+            // cost nothing for standard programs
+            Err(e) => match self.opcodes.get(&((code % 100) as usize)).filter(|_| code >= 0) {
+                Some(custom) => Instruction::with_params(code, custom.params),
+                None => Err(e)
+            }
+        }
+    }
+
+    fn run_instruction(&mut self, instruction: &Instruction) -> Result<Option<i64>> {
+        if let Some(watch) = self.code_watch.as_mut() {
+            watch.executed.extend(self.pointer_idx..=self.pointer_idx + instruction.parameters.len());
+        }
+        if INSTRUMENTED && PROFILING.load(Ordering::Relaxed) {
+            if let Some(profile) = PROFILE.lock().unwrap().as_mut() {
+                self.count(instruction, profile);
+            }
+        }
+        if !(INSTRUMENTED && TRACING.load(Ordering::Relaxed)) {
+            return self.execute(instruction);
+        }
+
+        let (output, line) = self.execute_traced(instruction)?;
+        if let Some(tracer) = TRACER.lock().unwrap().as_mut() {
+            tracer.trace(&line)?;
+        }
+
+        Ok(output)
+    }
+
+    // Adds the instruction about to run to a profile
+    fn count(&self, instruction: &Instruction, profile: &mut VmProfile) {
+        *profile.opcodes.entry(instruction.opcode).or_insert(0) += 1;
+        *profile.instructions.entry(self.pointer_idx).or_insert(0) += 1;
+        for (n, &form) in instruction.parameters.iter().enumerate() {
+            if form == Parameter::Immediate {
+                continue;
+            }
+            if let Ok(Some(idx)) = self.address(form, self.memory.get(self.pointer_idx + n + 1)) {
+                *profile.accesses.entry(idx).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // Runs an instruction as `execute` does, also describing it for the trace with its
+    // parameters resolved and what it did
+    fn execute_traced(&mut self, instruction: &Instruction) -> Result<(Option<i64>, String)> {
+        let at = self.pointer_idx;
+        let params = instruction.parameters.len();
+        // These write to their last parameter
+        let writes = [1, 2, 3, 7, 8].contains(&instruction.opcode);
+        let operands: Vec<String> = (0..params)
+            .map(|n| self.traced_operand(instruction, n, writes && n + 1 == params))
+            .collect();
+
+        let output = self.execute(instruction)?;
+        let result = match instruction.opcode {
+            _ if writes => {
+                let written = self.address(instruction.parameters[params - 1], self.memory.get(at + params)).ok().flatten();
+                match written {
+                    Some(idx) if self.mapped_device(idx).is_some() => "written to a device".to_string(),
+                    Some(idx) => self.memory.get(idx).to_string(),
+                    None => "dropped".to_string()
+                }
+            },
+            5 | 6 => format!("pointer {}", self.pointer_idx),
+            9 => format!("relative base {}", self.relative_base),
+            99 => "halted".to_string(),
+            _ => match output {
+                Some(output) => format!("output {}", output),
+                None => "no output".to_string()
+            }
+        };
+
+        let mnemonic = MNEMONICS.iter()
+            .find(|&&(_, opcode, _)| opcode as usize == instruction.opcode)
+            .map_or(format!("op{}", instruction.opcode), |&(name, _, _)| name.to_string());
+        let line = if operands.is_empty() {
+            format!("{}: {} -> {}", at, mnemonic, result)
+        } else {
+            format!("{}: {} {} -> {}", at, mnemonic, operands.join(", "), result)
+        };
+
+        Ok((output, line))
+    }
+
+    // A parameter as the value it reads, or `[address]` for one it writes to. Device
+    // addresses aren't read for the trace, as reading a device can change it.
+    fn traced_operand(&self, instruction: &Instruction, n: usize, written: bool) -> String {
+        let val = self.memory.get(self.pointer_idx + n + 1);
+        if instruction.parameters[n] == Parameter::Immediate && !written {
+            return val.to_string();
+        }
+
+        match self.address(instruction.parameters[n], val) {
+            Ok(Some(idx)) if written || self.mapped_device(idx).is_some() => format!("[{}]", idx),
+            Ok(Some(idx)) => self.memory.get(idx).to_string(),
+            Ok(None) if written => "[out of range]".to_string(),
+            Ok(None) => "0".to_string(),
+            Err(_) => "?".to_string()
+        }
+    }
+
+    // Runs the instruction at the pointer, returning the value if it output one
+    fn execute(&mut self, instruction: &Instruction) -> Result<Option<i64>> {
+        match instruction.opcode {
+            1 | 2 | 7 | 8 => {
+                let input_1 = self.get_parameter(instruction, 0)?;
+                let input_2 = self.get_parameter(instruction, 1)?;
+                let result = match instruction.opcode {
+                    1 => self.checked(input_1.checked_add(input_2), input_1, "+", input_2)?,
+                    2 => self.checked(input_1.checked_mul(input_2), input_1, "*", input_2)?,
+                    7 => (input_1 < input_2) as i64,
+                    _ => (input_1 == input_2) as i64
+                };
+                self.set_parameter(instruction, 2, result)?;
+
+                self.pointer_idx += 4;
+            },
+            3 => {
+                let input = self.inputs.pop_front().ok_or("No inputs left!")?;
+                self.set_parameter(instruction, 0, input)?;
+
+                self.pointer_idx += 2;
+            },
+            4 => {
+                let output_val = self.get_parameter(instruction, 0)?;
+                self.pointer_idx += 2;
+
+                return Ok(Some(output_val));
+            },
+            5 | 6 => {
+                let input_1 = self.get_parameter(instruction, 0)?;
+                let input_2 = self.get_parameter(instruction, 1)?;
+                if (input_1 != 0) == (instruction.opcode == 5) {
+                    self.pointer_idx = match self.in_range(input_2) {
+                        Some(idx) => idx,
+                        None => return Err(self.fault(format!("Cannot jump to address {}", input_2)))
+                    };
+                } else {
+                    self.pointer_idx += 3;
+                }
+            },
+            9 => {
+                let offset = self.get_parameter(instruction, 0)?;
+                self.relative_base = self.relative_base.checked_add(offset)
+                    .ok_or_else(|| self.fault(format!("Relative base {} + {} overflows", self.relative_base, offset)))?;
+
+                self.pointer_idx += 2;
+            },
+            99 => self.halted = true,
+            x => {
+                let custom = self.opcodes.get(&x).cloned().ok_or(format!("Incorrect opcode: {}", x))?;
+                let values: Result<Vec<i64>> = (0..custom.params).map(|n| self.get_parameter(instruction, n)).collect();
+                let output = (custom.handler)(&values?)?;
+                self.pointer_idx += 1 + custom.params;
+
+                return Ok(output);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn read(&self, operand: Operand) -> Result<i64> {
+        match operand {
+            Operand::Position(idx) => Ok(self.load(idx)),
+            Operand::Immediate(val) => Ok(val),
+            Operand::Relative(offset) => Ok(self.address(Parameter::Relative, offset)?.map_or(0, |idx| self.load(idx)))
+        }
+    }
+
+    fn write(&mut self, operand: Operand, val: i64) -> Result<()> {
+        let idx = match operand {
+            Operand::Position(idx) => Some(idx),
+            Operand::Relative(offset) => self.address(Parameter::Relative, offset)?,
+            Operand::Immediate(_) => return Err(self.fault("Cannot write to an immediate parameter".to_string()))
+        };
+        if let Some(idx) = idx {
+            self.store(idx, val);
+        }
+
+        Ok(())
+    }
+
+    // Runs a decoded instruction as `execute` runs the one at the pointer
+    fn execute_op(&mut self, op: Op) -> Result<Option<i64>> {
+        match op {
+            Op::Add(a, b, c) | Op::Multiply(a, b, c) | Op::LessThan(a, b, c) | Op::Equals(a, b, c) => {
+                let input_1 = self.read(a)?;
+                let input_2 = self.read(b)?;
+                let result = match op {
+                    Op::Add(..) => self.checked(input_1.checked_add(input_2), input_1, "+", input_2)?,
+                    Op::Multiply(..) => self.checked(input_1.checked_mul(input_2), input_1, "*", input_2)?,
+                    Op::LessThan(..) => (input_1 < input_2) as i64,
+                    _ => (input_1 == input_2) as i64
+                };
+                self.write(c, result)?;
+
+                self.pointer_idx += 4;
+            },
+            Op::Input(a) => {
+                let input = self.inputs.pop_front().ok_or("No inputs left!")?;
+                self.write(a, input)?;
+
+                self.pointer_idx += 2;
+            },
+            Op::Output(a) => {
+                let output_val = self.read(a)?;
+                self.pointer_idx += 2;
+
+                return Ok(Some(output_val));
+            },
+            Op::JumpIfTrue(a, b) | Op::JumpIfFalse(a, b) => {
+                let input_1 = self.read(a)?;
+                let input_2 = self.read(b)?;
+                if (input_1 != 0) == matches!(op, Op::JumpIfTrue(..)) {
+                    self.pointer_idx = match self.in_range(input_2) {
+                        Some(idx) => idx,
+                        None => return Err(self.fault(format!("Cannot jump to address {}", input_2)))
+                    };
+                } else {
+                    self.pointer_idx += 3;
+                }
+            },
+            Op::AdjustBase(a) => {
+                let offset = self.read(a)?;
+                self.relative_base = self.relative_base.checked_add(offset)
+                    .ok_or_else(|| self.fault(format!("Relative base {} + {} overflows", self.relative_base, offset)))?;
+
+                self.pointer_idx += 2;
+            },
+            Op::Halt => self.halted = true
+        }
+
+        Ok(None)
+    }
+
+    // Runs until the program halts or needs an input which `source` can't give, passing
+    // every output to `sink`. Inputs already queued with `add_input` are used first.
+    pub fn run_with(&mut self, source: &mut dyn InputSource, sink: &mut dyn OutputSink) -> Result<()> {
+        loop {
+            match self.run_program()? {
+                RunState::Output(output) => sink.send_output(output)?,
+                RunState::Halted => return Ok(()),
+                RunState::NeedsInput => match source.next_input() {
+                    Some(input) => self.add_input(input),
+                    None => return Ok(())
+                }
+            }
+        }
+    }
+
+    // Runs until the program halts or waits for input, returning everything it output
+    pub fn run_to_end(&mut self) -> Result<Vec<i64>> {
+        self.outputs().collect()
+    }
+
+    // The same, for machines which have all the input they need: stopping to wait for
+    // more is an error rather than the end of the outputs
+    pub fn run_to_halt(&mut self) -> Result<Vec<i64>> {
+        let outputs = self.run_to_end()?;
+        if !self.halted {
+            return err!("Stopped waiting for more input after outputting {:?}", outputs);
+        }
+
+        Ok(outputs)
+    }
+
+    // Runs until the machine has output `n` values, or fewer if it halts or waits for
+    // input first, as for programs which answer each input with a fixed number of outputs
+    pub fn run_until_output_len(&mut self, n: usize) -> Result<Vec<i64>> {
+        self.outputs().take(n).collect()
+    }
+
+    // Runs the program as the outputs are asked for, ending when it halts or waits for
+    // input. An error ends the outputs too, after it has been returned.
+    pub fn outputs(&mut self) -> Outputs<'_> {
+        Outputs {
+            program: self,
+            failed: false
+        }
+    }
+
+    // Gives the machine every input it will get up front, to use its outputs as a plain
+    // iterator
+    pub fn scripted(mut self, inputs: &[i64]) -> Scripted {
+        self.inputs.extend(inputs);
+        Scripted { program: self, error: None }
+    }
+}
+
+// Saved machines start with this, followed by the backend, the flags (halted, lenient),
+// the pointer, relative base, instructions run, queued inputs and the cells which aren't
+// zero. Numbers are LEB128 varints, zigzag encoded where they can be negative, and each
+// cell's address is stored as the gap from the one before.
+const SAVE_MAGIC: &[u8] = b"ICSAVE1";
+
+fn write_varint(bytes: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        bytes.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+fn write_signed(bytes: &mut Vec<u8>, n: i64) {
+    write_varint(bytes, ((n << 1) ^ (n >> 63)) as u64);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or("Saved machine ends too soon")?;
+        *bytes = rest;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+
+    err!("Saved machine has a number which is too long")
+}
+
+fn read_signed(bytes: &mut &[u8]) -> Result<i64> {
+    let n = read_varint(bytes)?;
+    Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+}
+
+impl Program {
+    // Whether `bytes` look like a machine saved with `save`
+    pub fn is_saved(bytes: &[u8]) -> bool {
+        bytes.starts_with(SAVE_MAGIC)
+    }
+
+    // The whole state of the machine, to carry on later with `restore`. Custom opcodes and
+    // devices are code rather than state, so machines using them can't be saved.
+    pub fn save(&self) -> Result<Vec<u8>> {
+        if !self.opcodes.is_empty() || !self.devices.is_empty() {
+            return err!("Cannot save a machine with custom opcodes or devices");
+        }
+
+        let mut bytes = SAVE_MAGIC.to_vec();
+        bytes.push(match self.memory.backend() {
+            MemoryBackend::Paged => 0,
+            MemoryBackend::Sparse => 1
+        });
+        bytes.push(self.halted as u8 | (self.lenient as u8) << 1);
+        write_varint(&mut bytes, self.pointer_idx as u64);
+        write_signed(&mut bytes, self.relative_base);
+        write_varint(&mut bytes, self.instructions);
+
+        write_varint(&mut bytes, self.inputs.len() as u64);
+        for &input in &self.inputs {
+            write_signed(&mut bytes, input);
+        }
+
+        let cells = self.memory.cells();
+        write_varint(&mut bytes, cells.len() as u64);
+        let mut last = 0;
+        for (idx, val) in cells {
+            write_varint(&mut bytes, (idx - last) as u64);
+            write_signed(&mut bytes, val);
+            last = idx;
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn restore(bytes: &[u8]) -> Result<Program> {
+        let mut bytes = match bytes.strip_prefix(SAVE_MAGIC) {
+            Some(rest) => rest,
+            None => return err!("Not a saved Intcode machine")
+        };
+        let (backend, flags) = match bytes {
+            [0, flags, ..] => (MemoryBackend::Paged, *flags),
+            [1, flags, ..] => (MemoryBackend::Sparse, *flags),
+            _ => return err!("Saved machine has an unknown memory backend")
+        };
+        bytes = &bytes[2..];
+
+        let mut program = Program::new(vec![]);
+        program.memory = Memory::with_backend(&[], backend);
+        program.halted = flags & 1 != 0;
+        program.lenient = flags & 2 != 0;
+        program.pointer_idx = read_varint(&mut bytes)? as usize;
+        program.relative_base = read_signed(&mut bytes)?;
+        program.instructions = read_varint(&mut bytes)?;
+
+        for _ in 0..read_varint(&mut bytes)? {
+            program.inputs.push_back(read_signed(&mut bytes)?);
+        }
+
+        let mut idx = 0usize;
+        for _ in 0..read_varint(&mut bytes)? {
+            idx = idx.checked_add(read_varint(&mut bytes)? as usize).ok_or("Saved machine has a cell out of range")?;
+            if idx as i64 > backend.max_address() {
+                return err!("Saved machine has a cell out of range");
+            }
+            program.memory.set(idx, read_signed(&mut bytes)?);
+        }
+        if !bytes.is_empty() {
+            return err!("Saved machine has {} bytes left over", bytes.len());
+        }
+
+        Ok(program)
+    }
+}
+
+// A machine with a fixed input script, which iterates over its outputs until it halts or
+// runs out of script. An error ends the outputs too, and is kept for `finish`, so that
+// simple days can be pipelines such as `vm.outputs().last()`.
+#[derive(Debug)]
+pub struct Scripted {
+    program: Program,
+    error: Option<Box<dyn Error>>
+}
+
+impl Scripted {
+    // The outputs, leaving the machine to be finished afterwards
+    pub fn outputs(&mut self) -> &mut Scripted {
+        self
+    }
+
+    // Hands back the machine, or the error which stopped it
+    pub fn finish(self) -> Result<Program> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.program)
+        }
+    }
+}
+
+impl Iterator for Scripted {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        match self.program.run_program() {
+            Ok(RunState::Output(output)) => Some(output),
+            Ok(_) => None,
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+// Runs a machine on its own thread, reading inputs from `inputs` and sending outputs to
+// `outputs`, so that machines can be wired straight to each other. The thread ends when
+// the machine halts, or needs an input after every sender to `inputs` has gone, and hands
+// the machine back. Errors come back as strings, as boxed errors can't cross threads.
+pub fn spawn(mut program: Program, mut inputs: Receiver<i64>, mut outputs: Sender<i64>) -> JoinHandle<result::Result<Program, String>> {
+    thread::spawn(move || {
+        match program.run_with(&mut inputs, &mut outputs) {
+            Ok(()) => Ok(program),
+            Err(e) => Err(e.to_string())
+        }
+    })
+}
+
+// Machines each running on their own thread, for wiring together with channels
+#[derive(Default)]
+pub struct Executor {
+    machines: Vec<JoinHandle<result::Result<Program, String>>>
+}
+
+impl Executor {
+    pub fn new() -> Executor {
+        Executor::default()
+    }
+
+    // Starts `programs` in a line, each sending its outputs to the next. Returns the
+    // sender for the first machine's inputs and the receiver for the last one's outputs.
+    pub fn pipeline(programs: Vec<Program>) -> (Sender<i64>, Receiver<i64>, Executor) {
+        let mut executor = Executor::new();
+        let (first_input, mut inputs) = mpsc::channel();
+        for program in programs {
+            let (outputs, next_inputs) = mpsc::channel();
+            executor.start(program, inputs, outputs);
+            inputs = next_inputs;
+        }
+
+        (first_input, inputs, executor)
+    }
+
+    // Starts a machine on its own thread, see `spawn`, returning its index
+    pub fn start(&mut self, program: Program, inputs: Receiver<i64>, outputs: Sender<i64>) -> usize {
+        self.machines.push(spawn(program, inputs, outputs));
+        self.machines.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    // Waits for every machine to stop, returning them in the order they were started, or
+    // the first error. Machines only stop waiting for input once every sender to them
+    // has been dropped, so drop those first.
+    pub fn join(self) -> Result<Vec<Program>> {
+        let mut programs = vec![];
+        for (idx, machine) in self.machines.into_iter().enumerate() {
+            match machine.join() {
+                Ok(Ok(program)) => programs.push(program),
+                Ok(Err(e)) => return err!("Machine {} failed: {}", idx, e),
+                Err(_) => return err!("Machine {} panicked", idx)
+            }
+        }
+
+        Ok(programs)
+    }
+}
+
+pub struct Outputs<'a> {
+    program: &'a mut Program,
+    failed: bool
+}
+
+impl<'a> Iterator for Outputs<'a> {
+    type Item = Result<i64>;
+
+    fn next(&mut self) -> Option<Result<i64>> {
+        if self.failed {
+            return None;
+        }
+
+        match self.program.run_program() {
+            Ok(RunState::Output(output)) => Some(Ok(output)),
+            Ok(_) => None,
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    use aoc_problems::intcode_asm::assemble;
+
+    // The standard opcodes and how many parameters each takes
+    const OPCODES: &[(usize, usize)] = &[(1, 3), (2, 3), (3, 1), (4, 1), (5, 2), (6, 2), (7, 3), (8, 3), (9, 1), (99, 0)];
+
+    fn parameter() -> impl Strategy<Value = Parameter> {
+        prop_oneof![Just(Parameter::Position), Just(Parameter::Immediate), Just(Parameter::Relative)]
+    }
+
+    // Any valid instruction, with a mode for each of its parameters
+    fn instruction() -> impl Strategy<Value = Instruction> {
+        prop::sample::select(OPCODES).prop_flat_map(|(opcode, params)| {
+            prop::collection::vec(parameter(), params).prop_map(move |parameters| Instruction { opcode, parameters })
+        })
+    }
+
+    fn encode(instruction: &Instruction) -> i64 {
+        instruction.parameters.iter().rev().fold(0, |modes, parameter| {
+            modes * 10 + match parameter {
+                Parameter::Position => 0,
+                Parameter::Immediate => 1,
+                Parameter::Relative => 2
+            }
+        }) * 100 + instruction.opcode as i64
+    }
+
+    proptest! {
+        #[test]
+        fn intcode_decodes_every_valid_instruction(instruction in instruction()) {
+            prop_assert_eq!(Instruction::new(encode(&instruction)).unwrap(), instruction);
+        }
+
+        // Mode digits past the last parameter aren't looked at
+        #[test]
+        fn intcode_ignores_modes_past_the_parameters(instruction in instruction(), extra in 1..1000_i64) {
+            let code = encode(&instruction) + extra * 10_i64.pow(2 + instruction.parameters.len() as u32);
+            prop_assert_eq!(Instruction::new(code).unwrap(), instruction);
+        }
+
+        #[test]
+        fn intcode_rejects_bad_mode_digits(instruction in instruction(), digit in 3..10_i64, n in 0..3_usize) {
+            prop_assume!(n < instruction.parameters.len());
+            let mut code = encode(&instruction);
+            code += (digit - (code / 10_i64.pow(2 + n as u32) % 10)) * 10_i64.pow(2 + n as u32);
+            prop_assert_eq!(
+                Instruction::new(code).unwrap_err().to_string(),
+                format!("Cannot read parameter digit: {}", digit)
+            );
+        }
+
+        #[test]
+        fn intcode_rejects_bad_opcodes(opcode in 0..100_i64, modes in 0..1000_i64) {
+            prop_assume!(!OPCODES.iter().any(|&(valid, _)| valid as i64 == opcode));
+            prop_assert_eq!(
+                Instruction::new(modes * 100 + opcode).unwrap_err().to_string(),
+                format!("Cannot read opcode: {}", opcode)
+            );
+        }
+
+        #[test]
+        fn intcode_rejects_negative_instructions(code in i64::MIN..0) {
+            prop_assert!(Instruction::new(code).is_err());
+        }
+    }
+
+    #[test]
+    fn intcode_runs_programs() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let expected: Vec<i64> = quine.split(',').map(|s| s.parse().unwrap()).collect();
+        assert_eq!(Program::parse(quine).unwrap().run_to_end().unwrap(), expected);
+
+        let mut program = Program::parse("3,9,8,9,10,9,4,9,99,-1,8").unwrap();
+        program.add_input(8);
+        assert_eq!(program.run_to_end().unwrap(), vec![1]);
+        assert!(program.is_halted());
+        assert_eq!(program.instructions(), 4);
+
+        assert_eq!(Program::parse("104,1125899906842624,99").unwrap().run_program().unwrap(), RunState::Output(1125899906842624));
+
+        let mut program = Program::parse("3,0,4,0,99").unwrap();
+        assert_eq!(program.run_program().unwrap(), RunState::NeedsInput);
+        assert!(program.awaiting_input() && !program.is_halted());
+        program.add_input(5);
+        assert_eq!(program.run_program().unwrap(), RunState::Output(5));
+        assert_eq!(program.run_program().unwrap(), RunState::Halted);
+        assert_eq!(program.run_program().unwrap(), RunState::Halted);
+    }
+
+    #[test]
+    fn intcode_iterates_outputs() {
+        let mut program = Program::parse("104,1,104,2,3,0,104,3,99").unwrap();
+        assert_eq!(program.outputs().map(|n| n.unwrap() * 10).collect::<Vec<_>>(), vec![10, 20]);
+        assert!(program.awaiting_input());
+
+        program.add_input(0);
+        assert_eq!(program.outputs().next().transpose().unwrap(), Some(3));
+        assert_eq!(program.outputs().count(), 0);
+
+        let mut pairs = Program::parse("104,1,104,2,104,3,3,0,99").unwrap();
+        assert_eq!(pairs.run_until_output_len(2).unwrap(), vec![1, 2]);
+        assert_eq!(pairs.run_until_output_len(2).unwrap(), vec![3]);
+        assert_eq!(pairs.clone().run_to_end().unwrap(), vec![]);
+        assert_eq!(pairs.run_to_halt().unwrap_err().to_string(), "Stopped waiting for more input after outputting []");
+        pairs.add_input(5);
+        assert_eq!(pairs.run_to_halt().unwrap(), vec![]);
+
+        let mut outputs_then_fails = Program::parse("104,7,42").unwrap();
+        let mut outputs = outputs_then_fails.outputs();
+        assert_eq!(outputs.next().unwrap().unwrap(), 7);
+        assert!(outputs.next().unwrap().is_err());
+        assert!(outputs.next().is_none());
+    }
+
+    #[test]
+    fn intcode_scripted_outputs() {
+        // Adds up pairs of inputs
+        let adder = assemble("
+            loop:   in [a]
+                    in [b]
+                    add [a], [b], [a]
+                    out [a]
+                    jz 0, loop
+            a:      .data 0
+            b:      .data 0
+        ").unwrap();
+
+        let mut vm = Program::new(adder.clone()).scripted(&[1, 2, 3, 4, 5]);
+        assert_eq!(vm.outputs().collect::<Vec<i64>>(), vec![3, 7]);
+        assert!(vm.finish().unwrap().awaiting_input());
+
+        assert_eq!(Program::new(adder).scripted(&[10, 20, 30, 40]).last(), Some(70));
+
+        let mut broken = Program::new(vec![104, 1, 104, 2, 42]).scripted(&[]);
+        assert_eq!(broken.outputs().collect::<Vec<i64>>(), vec![1, 2]);
+        assert!(broken.finish().is_err());
+    }
+
+    #[test]
+    fn intcode_saves_and_restores() {
+        // Adds up pairs of inputs, after moving the relative base below zero
+        let adder = assemble("
+                    arb -5
+            loop:   in [a]
+                    in [b]
+                    add [a], [b], [a]
+                    out [a]
+                    jz 0, loop
+            a:      .data 0
+            b:      .data -1000000000000
+        ").unwrap();
+
+        for &backend in &[MemoryBackend::Paged, MemoryBackend::Sparse] {
+            let mut program = VmBuilder::new(adder.clone()).backend(backend).build().unwrap();
+            program.add_input(1);
+            program.add_input(2);
+            program.add_input(3);
+            assert_eq!(program.run_to_end().unwrap(), vec![3]);
+            program.memory.set(100_000, 7);
+
+            let saved = program.save().unwrap();
+            assert!(Program::is_saved(&saved));
+            let mut restored = Program::restore(&saved).unwrap();
+            assert_eq!(restored.memory().backend(), backend);
+            assert_eq!(restored.memory().cells(), program.memory().cells());
+            assert_eq!((restored.relative_base, restored.instructions()), (-5, program.instructions()));
+
+            // Carries on with the input it had queued
+            restored.add_input(4);
+            assert_eq!(restored.run_to_end().unwrap(), vec![7]);
+
+            assert!(Program::restore(&saved[..saved.len() - 1]).is_err());
+        }
+
+        assert!(Program::restore(b"1,2,3").is_err());
+        let custom = VmBuilder::new(vec![42, 99]).opcode(42, 0, |_| Ok(None)).build().unwrap();
+        assert!(custom.save().is_err());
+    }
+
+    #[test]
+    fn intcode_limits_steps() {
+        // Outputs 7 then jumps back to the start for ever
+        let mut program = Program::new(vec![104, 7, 1105, 1, 0]);
+        program.limit_steps(5);
+        let error = program.run_to_end().unwrap_err();
+        assert_eq!(error.downcast_ref::<StepLimitExceeded>(), Some(&StepLimitExceeded { limit: 5, pointer: 2 }));
+        assert_eq!(program.instructions(), 5);
+        assert!(program.step().unwrap_err().is::<StepLimitExceeded>());
+
+        // A program which finishes within the limit runs as usual
+        let mut program = Program::new(vec![104, 7, 99]);
+        program.limit_steps(2);
+        assert_eq!(program.run_to_end().unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn intcode_closes_inputs() {
+        // Outputs the sum of two inputs
+        let adder = vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0];
+        for &execution in &[Execution::Interpreted, Execution::Compiled] {
+            let mut program = VmBuilder::new(adder.clone()).execution(execution).build().unwrap();
+            program.add_inputs(vec![2, 3]);
+            program.close_inputs();
+            assert_eq!(program.run_to_end().unwrap(), vec![5]);
+
+            let mut program = VmBuilder::new(adder.clone()).execution(execution).build().unwrap();
+            program.add_inputs(Some(2));
+            let mut open = program.clone();
+            program.close_inputs();
+            let error = program.run_to_end().unwrap_err();
+            assert_eq!(error.downcast_ref::<InputExhausted>(), Some(&InputExhausted { pointer: 2 }));
+            assert!(program.step().unwrap_err().is::<InputExhausted>());
+
+            // Left open, the machine waits for the input instead
+            assert_eq!(open.run_to_end().unwrap(), vec![]);
+            assert!(open.awaiting_input());
+        }
+    }
+
+    #[test]
+    fn intcode_calls_input_closures() {
+        // Outputs the sum of three inputs
+        let adder = vec![3, 20, 3, 21, 3, 22, 1, 20, 21, 20, 1, 20, 22, 20, 4, 20, 99];
+        for &execution in &[Execution::Interpreted, Execution::Compiled] {
+            let mut counter = 0;
+            let mut program = VmBuilder::new(adder.clone())
+                .execution(execution)
+                .input_fn(move || { counter += 1; counter })
+                .build()
+                .unwrap();
+            assert!(!program.awaiting_input());
+            assert_eq!(program.run_to_end().unwrap(), vec![6]);
+        }
+
+        // Queued inputs come first, and the closure can follow state the caller changes
+        let joystick = Arc::new(AtomicU64::new(7));
+        let tilt = joystick.clone();
+        let mut program = VmBuilder::new(adder).input_fn(move || tilt.load(Ordering::Relaxed) as i64).build().unwrap();
+        program.add_input(100);
+        assert_eq!(program.step().unwrap().outcome, None);
+        assert_eq!(program.step().unwrap().outcome, None);
+        joystick.store(1, Ordering::Relaxed);
+        assert_eq!(program.run_to_end().unwrap(), vec![108]);
+    }
+
+    #[test]
+    fn intcode_counts_instructions_on_this_thread() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        for &execution in &[Execution::Interpreted, Execution::Compiled] {
+            let memory: Vec<i64> = quine.split(',').map(|s| s.parse().unwrap()).collect();
+            let (outputs, executed) = count_instructions(|| {
+                VmBuilder::new(memory).execution(execution).build().unwrap().run_to_end().unwrap()
+            });
+            assert_eq!(outputs.len(), 16);
+            assert_eq!(executed, 81);
+        }
+
+        // Machines on other threads count towards the process, but not this thread
+        let before = instructions_executed();
+        let ((), executed) = count_instructions(|| {
+            thread::spawn(move || Program::parse(quine).unwrap().run_to_end().unwrap()).join().unwrap();
+        });
+        assert_eq!(executed, 0);
+        assert!(instructions_executed() >= before + 81);
+    }
+
+    #[test]
+    fn intcode_watches_self_modifying_code() {
+        // Counts down from 3, decrementing the immediate operand of its own output
+        let countdown = vec![104, 3, 101, -1, 1, 1, 1005, 1, 0, 99];
+        let mut program = VmBuilder::new(countdown.clone()).self_modification(SelfModification::Report).build().unwrap();
+        assert_eq!(program.run_to_end().unwrap(), vec![3, 2, 1]);
+        let writes = program.code_writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].to_string(), "1: written 3 times by the instructions at 2, from 3 to 0");
+
+        let mut program = VmBuilder::new(countdown.clone()).self_modification(SelfModification::Deny).build().unwrap();
+        assert_eq!(
+            program.run_to_end().unwrap_err().to_string(),
+            "Cannot write 2 over address 1, which has already run as an instruction at address 2 (instruction 101)"
+        );
+
+        // Writes to data, or to code which hasn't run yet, are left alone
+        let mut program = VmBuilder::new(vec![1101, 2, 3, 5, 104, 0, 99]).self_modification(SelfModification::Deny).build().unwrap();
+        assert_eq!(program.run_to_end().unwrap(), vec![5]);
+        assert!(program.code_writes().is_empty());
+        assert!(Program::new(countdown).code_writes().is_empty());
+    }
+
+    #[test]
+    fn intcode_forks_share_memory() {
+        let mut program = Program::new((0..2000).map(|n| if n == 0 { 99 } else { n }).collect());
+        let mut fork = program.clone();
+        assert_eq!(fork.memory().shared_pages(program.memory()), 4);
+
+        fork.memory.set(1, -1);
+        fork.memory.set(100_000, 7);
+        assert_eq!(fork.memory().shared_pages(program.memory()), 3);
+        assert_eq!((fork.memory().get(1), program.memory().get(1)), (-1, 1));
+        assert_eq!((fork.memory().get(100_000), program.memory().get(100_000)), (7, 0));
+
+        program.memory.set(1, 1);
+        assert_eq!(program.run_to_end().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn intcode_plugs_in_sources_and_sinks() {
+        let doubler = Program::parse("3,11,1002,11,2,11,4,11,1105,1,0").unwrap();
+
+        let mut program = doubler.clone();
+        let mut outputs = vec![];
+        program.run_with(&mut VecDeque::from(vec![1, 2, 3]), &mut outputs).unwrap();
+        assert_eq!(outputs, vec![2, 4, 6]);
+        assert!(program.awaiting_input());
+
+        let mut program = doubler.clone();
+        let mut inputs = 10..13;
+        let mut total = 0;
+        program.run_with(&mut || inputs.next(), &mut |value| { total += value; Ok(()) }).unwrap();
+        assert_eq!(total, 66);
+
+    }
+
+    #[test]
+    fn intcode_wires_machines_with_channels() {
+        let doubler = Program::parse("3,11,1002,11,2,11,4,11,1105,1,0").unwrap();
+
+        let (input_sender, first_inputs) = mpsc::channel();
+        let (first_outputs, second_inputs) = mpsc::channel();
+        let (second_outputs, output_receiver) = mpsc::channel();
+        let first = spawn(doubler.clone(), first_inputs, first_outputs);
+        let second = spawn(doubler, second_inputs, second_outputs);
+
+        input_sender.send(3).unwrap();
+        input_sender.send(5).unwrap();
+        assert_eq!(output_receiver.recv(), Ok(12));
+        assert_eq!(output_receiver.recv(), Ok(20));
+
+        drop(input_sender);
+        assert!(first.join().unwrap().unwrap().awaiting_input());
+        assert!(second.join().unwrap().unwrap().awaiting_input());
+        assert!(output_receiver.recv().is_err());
+
+        let (_, inputs) = mpsc::channel();
+        let (outputs, output_receiver) = mpsc::channel();
+        drop(output_receiver);
+        assert!(spawn(Program::parse("104,1,99").unwrap(), inputs, outputs).join().unwrap().is_err());
+    }
+
+    #[test]
+    fn intcode_executor_runs_pipelines() {
+        let doubler = Program::parse("3,11,1002,11,2,11,4,11,1105,1,0").unwrap();
+        let (first_input, last_output, executor) = Executor::pipeline(vec![doubler.clone(), doubler.clone(), doubler]);
+        assert_eq!(executor.len(), 3);
+
+        first_input.send(1).unwrap();
+        first_input.send(5).unwrap();
+        assert_eq!(last_output.recv(), Ok(8));
+        assert_eq!(last_output.recv(), Ok(40));
+
+        drop(first_input);
+        let machines = executor.join().unwrap();
+        assert!(machines.iter().all(|machine| machine.awaiting_input() && machine.instructions() == 8));
+
+        let (_, last_output, executor) = Executor::pipeline(vec![Program::parse("104,1,42").unwrap()]);
+        assert_eq!(last_output.recv(), Ok(1));
+        assert!(executor.join().unwrap_err().to_string().starts_with("Machine 0 failed"));
+    }
+
+    #[test]
+    fn intcode_disassembles_and_diffs() {
+        let a: Vec<i64> = vec![1002, 4, 3, 4, 33, 204, -1, 99];
+        let lines: Vec<String> = disassemble(&a).into_iter().map(|line| line.text).collect();
+        assert_eq!(lines, vec!["0: mul [4], 3, [4]", "4: .data 33", "5: out [rb-1]", "7: halt"]);
+
+        let mut b = a.clone();
+        b[2] = 5;
+        b[3] = 6;
+        b.push(42);
+        assert_eq!(diff(&a, &b), vec![
+            "@ 2, 3", "- 0: mul [4], 3, [4]", "+ 0: mul [4], 5, [6]",
+            "@ 8", "- (past the end)", "+ 8: .data 42",
+        ]);
+        assert!(diff(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn intcode_dumps_and_diffs_memory() {
+        // Stores its input and the input plus five
+        let mut program = Program::parse("3,100,1001,100,5,101,99").unwrap();
+        let before = program.memory().dump();
+        assert_eq!(before, "0: 3\n1: 100\n2: 1001\n3: 100\n4: 5\n5: 101\n6: 99\n");
+        program.add_input(-2);
+        program.run_to_end().unwrap();
+
+        let before = Memory::parse_dump(&before).unwrap();
+        let after = Memory::parse_dump(&program.memory().dump()).unwrap();
+        assert_eq!(before.changes(&after), vec![(100, 0, -2), (101, 0, 3)]);
+        assert_eq!(after.changes(program.memory()), vec![]);
+        assert!(Memory::parse_dump("0: 3\n1 100").is_err());
+    }
+
+    #[test]
+    fn intcode_runs_custom_opcodes() {
+        let printed = Arc::new(std::sync::Mutex::new(vec![]));
+        let builder = || {
+            let log = printed.clone();
+            VmBuilder::new(vec![42, 8, 1143, 5, 10, 104, 1, 99, 7])
+                .opcode(42, 1, move |values| { log.lock().unwrap().push(values[0]); Ok(None) })
+                .opcode(43, 2, |values| Ok(Some(values[0] * values[1])))
+        };
+
+        let mut program = builder().build().unwrap();
+        assert_eq!(program.run_to_end().unwrap(), vec![50, 1]);
+        assert_eq!(*printed.lock().unwrap(), vec![7]);
+        assert_eq!(program.instructions(), 4);
+
+        assert!(builder().compliance(Compliance::Strict).build().is_err());
+        assert!(VmBuilder::new(vec![99]).opcode(4, 1, |_| Ok(None)).build().is_err());
+        assert!(Program::new(vec![42, 0, 99]).run_to_end().is_err());
+
+        // Standard programs run the same with extra opcodes around
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let expected: Vec<i64> = quine.split(',').map(|s| s.parse().unwrap()).collect();
+        let mut extended = VmBuilder::new(expected.clone()).opcode(42, 1, |_| Ok(None)).build().unwrap();
+        assert_eq!(extended.run_to_end().unwrap(), expected);
+        assert_eq!(Program::parse(quine).unwrap().run_to_end().unwrap(), expected);
+    }
+
+    #[test]
+    fn intcode_sparse_memory() {
+        // Writes 7 a trillion cells out through a relative address, then reads it back
+        let far_out = vec![109, 1_000_000_000_000, 21101, 3, 4, 0, 204, 0, 99];
+        let mut program = VmBuilder::new(far_out).backend(MemoryBackend::Sparse).build().unwrap();
+        assert_eq!(program.run_to_end().unwrap(), vec![7]);
+        assert_eq!(program.memory().backend(), MemoryBackend::Sparse);
+
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let expected: Vec<i64> = quine.split(',').map(|s| s.parse().unwrap()).collect();
+        let mut program = VmBuilder::new(expected.clone()).backend(MemoryBackend::Sparse).build().unwrap();
+        let fork = program.clone();
+        assert_eq!(program.run_to_end().unwrap(), expected);
+        assert_eq!(fork.memory().shared_pages(program.memory()), 0);
+        assert_eq!(fork.memory().get(100), 0);
+
+        assert!(MemoryBackend::parse("heap").is_err());
+    }
+
+    #[test]
+    fn intcode_compliance_modes() {
+        // Each program with what it outputs in strict and lenient mode, or None for an error
+        let conformance = [
+            // Adds 2 and 3 into an immediate parameter, then outputs cell 7
+            ("11101,2,3,7,4,7,99,0", None, Some(vec![5])),
+            // Outputs cell -1
+            ("4,-1,99", None, Some(vec![0])),
+            // Writes to cell -5, then outputs 9
+            ("1101,1,1,-5,104,9,99", None, Some(vec![9])),
+            // Outputs relative cell -3 from a relative base of 1
+            ("109,1,204,-3,99", None, Some(vec![0])),
+            ("42,99", None, None),
+            ("1002,6,3,6,104,1,33", Some(vec![1]), Some(vec![1])),
+        ];
+
+        for (source, strict, lenient) in conformance {
+            let memory: Vec<i64> = source.split(',').map(|s| s.parse().unwrap()).collect();
+            assert_eq!(Program::new(memory.clone()).run_to_end().ok(), strict);
+            for (compliance, expected) in [(Compliance::Strict, strict), (Compliance::Lenient, lenient)] {
+                let mut program = VmBuilder::new(memory.clone()).compliance(compliance).build().unwrap();
+                assert_eq!(program.run_to_end().ok(), expected, "{} in {:?} mode", source, compliance);
+            }
+        }
+    }
+
+    #[test]
+    fn intcode_compiles_programs() {
+        let compiled = |source: &str, compliance: Compliance| {
+            let memory: Vec<i64> = source.split(',').map(|s| s.parse().unwrap()).collect();
+            VmBuilder::new(memory).compliance(compliance).execution(Execution::Compiled).build().unwrap()
+        };
+
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut program = compiled(quine, Compliance::Standard);
+        let mut interpreted = Program::parse(quine).unwrap();
+        assert_eq!(program.run_to_end().unwrap(), interpreted.run_to_end().unwrap());
+        assert_eq!(program.instructions(), interpreted.instructions());
+
+        // Writes 7 over the parameter of the output after it, which must be decoded again
+        let mut program = compiled("1101,3,4,5,104,0,99", Compliance::Standard);
+        assert_eq!(program.clone().run_to_end().unwrap(), vec![7]);
+        assert_eq!(program.run_to_end().unwrap(), vec![7]);
+
+        // What can't be decoded ahead is left to the interpreter, so the modes still apply
+        assert!(compiled("11101,2,3,7,4,7,99,0", Compliance::Standard).run_to_end().is_err());
+        assert_eq!(compiled("11101,2,3,7,4,7,99,0", Compliance::Lenient).run_to_end().unwrap(), vec![5]);
+        assert_eq!(compiled("109,1,204,-3,99", Compliance::Lenient).run_to_end().unwrap(), vec![0]);
+
+        let mut program = compiled("3,0,4,0,99", Compliance::Standard);
+        assert_eq!(program.run_program().unwrap(), RunState::NeedsInput);
+        program.add_input(5);
+        assert_eq!(program.run_to_end().unwrap(), vec![5]);
+
+        assert!(Execution::parse("jit").is_err());
+    }
+
+    #[test]
+    fn intcode_reports_bad_addresses() {
+        let cases = [
+            ("4,-1,99", "Cannot access negative address -1 at address 0 (instruction 4)"),
+            ("1,0,0,9999999999999,99", "Cannot access address 9999999999999, which is past the end of memory at address 0 (instruction 1)"),
+            ("109,-5,204,1,99", "Cannot access negative address -4 at address 2 (instruction 204)"),
+            ("1105,1,-7", "Cannot jump to address -7 at address 0 (instruction 1105)"),
+            ("109,9223372036854775807,109,1,99", "Relative base 9223372036854775807 + 1 overflows at address 2 (instruction 109)"),
+        ];
+
+        for (source, message) in cases {
+            let err = Program::parse(source).unwrap().run_to_end().unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn intcode_debugger_commands() {
+        let mut program = Program::parse("1101,2,3,9,204,9,99").unwrap();
+        let mut debugger = Debugger::new();
+        let run = |debugger: &mut Debugger, program: &mut Program, command: &str| match debugger.execute(program, command) {
+            Ok(DebugAction::Show(shown)) => shown,
+            other => format!("{:?}", other)
+        };
+
+        assert!(debugger.should_break(0, 1));
+        assert_eq!(run(&mut debugger, &mut program, "i"), "0: add 2, 3, [9] (relative base 0, 0 inputs queued)");
+        assert_eq!(run(&mut debugger, &mut program, "b 4"), "Breaking at 4");
+        assert_eq!(run(&mut debugger, &mut program, "b op 99"), "Breaking on opcode 99");
+        assert!(!debugger.should_break(0, 1));
+        assert!(debugger.should_break(4, 4) && debugger.should_break(6, 99));
+        assert_eq!(run(&mut debugger, &mut program, "d op 99"), "No longer breaking on opcode 99");
+        assert!(!debugger.should_break(6, 99));
+
+        assert_eq!(run(&mut debugger, &mut program, "set 2 40"), "2 = 40");
+        assert_eq!(run(&mut debugger, &mut program, "x 0 4"), "0: 1101,2,40,9");
+        assert_eq!(run(&mut debugger, &mut program, "rb -9"), "Relative base -9");
+        assert_eq!(program.run_to_end().unwrap(), vec![1101]);
+
+        assert_eq!(run(&mut debugger, &mut program, "s"), "Ok(Resume)");
+        assert!(debugger.should_break(100, 1));
+        assert!(run(&mut debugger, &mut program, "x -1").contains("not an address"));
+        assert!(run(&mut debugger, &mut program, "b").contains("Unknown command"));
+        assert_eq!(run(&mut debugger, &mut program, "q"), "Ok(Detach)");
+    }
+
+    #[test]
+    fn intcode_steps_one_instruction_at_a_time() {
+        let mut program = Program::new(vec![109, 5, 21101, 2, 3, 16, 204, 16, 3, 20, 99]);
+
+        let step = program.step().unwrap();
+        assert_eq!((step.pointer, step.relative_base, step.operands, step.outcome), (0, 0, vec![5], None));
+        assert_eq!(step.instruction, Instruction { opcode: 9, parameters: vec![Parameter::Immediate] });
+
+        let step = program.step().unwrap();
+        assert_eq!((step.pointer, step.relative_base, step.instruction.opcode), (2, 5, 1));
+        assert_eq!(step.instruction.parameters, vec![Parameter::Immediate, Parameter::Immediate, Parameter::Relative]);
+        assert_eq!(program.memory().get(21), 5);
+
+        assert_eq!(program.step().unwrap().outcome, Some(RunState::Output(5)));
+        assert_eq!(program.step().unwrap().outcome, Some(RunState::NeedsInput));
+        assert_eq!(program.instructions(), 3);
+
+        program.add_input(7);
+        assert_eq!(program.step().unwrap().outcome, None);
+        assert_eq!(program.memory().get(20), 7);
+        assert_eq!(program.step().unwrap().outcome, Some(RunState::Halted));
+        assert_eq!(program.step().unwrap().outcome, Some(RunState::Halted));
+        assert_eq!(program.instructions(), 5);
+    }
+
+    #[test]
+    fn intcode_traces_instructions() {
+        let mut program = Program::new(assemble("
+                in [rb+20]
+                add [20], 4, [21]
+                out [21]
+                jz 0, end
+                arb -3
+            end: halt
+        ").unwrap());
+        program.add_input(3);
+
+        let mut lines = vec![];
+        while !program.is_halted() {
+            let instruction = program.decode().unwrap();
+            lines.push(program.execute_traced(&instruction).unwrap().1);
+        }
+        assert_eq!(lines, vec![
+            "0: in [20] -> 3",
+            "2: add 3, 4, [21] -> 7",
+            "6: out 7 -> output 7",
+            "8: jz 0, 13 -> pointer 13",
+            "13: halt -> halted",
+        ]);
+    }
+
+    #[test]
+    fn intcode_profiles_hot_spots() {
+        // Counts down from 3, so the loop runs three times
+        let mut program = Program::new(assemble("
+            loop:   add [n], -1, [n]
+                    jnz [n], loop
+                    halt
+            n:      .data 3
+        ").unwrap());
+
+        // Counted into a profile of its own, as other tests' machines would add to the
+        // global one
+        let mut profile = VmProfile::default();
+        while !program.is_halted() {
+            let instruction = program.decode().unwrap();
+            program.count(&instruction, &mut profile);
+            program.execute(&instruction).unwrap();
+        }
+
+        assert_eq!(profile, VmProfile {
+            opcodes: vec![(1, 3), (5, 3), (99, 1)].into_iter().collect(),
+            instructions: vec![(0, 3), (4, 3), (7, 1)].into_iter().collect(),
+            accesses: vec![(8, 9)].into_iter().collect()
+        });
+        let report = profile.report(1);
+        assert!(report.starts_with("Opcodes (7 instructions):\n  add               3   42.9%\n  jnz               3   42.9%\n  halt              1   14.3%"));
+        assert!(report.ends_with("Hottest instructions:\n         0            3\nHottest memory:\n         8            9"));
+    }
+
+    #[test]
+    fn intcode_describes_instructions() {
+        assert_eq!(describe_instruction(203), Some("input (relative)".to_string()));
+        assert_eq!(describe_instruction(1002), Some("multiply (position, immediate, position)".to_string()));
+        assert_eq!(describe_instruction(99), Some("halt".to_string()));
+        assert_eq!(describe_instruction(42), None);
+    }
+}
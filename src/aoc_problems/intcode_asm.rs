@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::result;
+
+use aoc_problems::intcode::MNEMONICS;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// Turns a small assembly language into an Intcode program, so that tests can say what a
+// program does rather than spelling it out as numbers. It reads what `disassemble`
+// writes, less the addresses:
+//
+//     ; Doubles every input
+//     loop:   in [x]
+//             mul [x], 2, [x]
+//             out [x]
+//             jnz 1, loop
+//     x:      .data 0
+//
+// Parameters are `[n]` for a position, `n` for an immediate value and `[rb+n]` relative
+// to the relative base. A label can stand in for a number anywhere, as the address it
+// labels, and `.data` puts values straight into the program. Comments start with `;`.
+pub fn assemble(source: &str) -> Result<Vec<i64>> {
+    // Labels can be used before they're defined, so each cell is kept as its text until
+    // every label has an address
+    let mut cells: Vec<(usize, Cell)> = vec![];
+    let mut labels: HashMap<String, i64> = HashMap::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        let line_number = idx + 1;
+        let mut line = line.split(';').next().unwrap().trim();
+
+        while let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            if !is_label(label) {
+                break;
+            }
+            if labels.insert(label.to_string(), cells.len() as i64).is_some() {
+                return err!("Line {}: label {} is defined twice", line_number, label);
+            }
+            line = line[colon + 1..].trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = match line.find(char::is_whitespace) {
+            Some(space) => (&line[..space], line[space..].trim()),
+            None => (line, "")
+        };
+        let operands: Vec<&str> = if operands.is_empty() {
+            vec![]
+        } else {
+            operands.split(',').map(|operand| operand.trim()).collect()
+        };
+
+        if mnemonic == ".data" {
+            for operand in operands {
+                cells.push((line_number, Cell::Value(operand.to_string())));
+            }
+            continue;
+        }
+
+        let &(_, opcode, params) = match MNEMONICS.iter().find(|&&(name, _, _)| name == mnemonic) {
+            Some(instruction) => instruction,
+            None => return err!("Line {}: unknown instruction {:?}", line_number, mnemonic)
+        };
+        if operands.len() != params {
+            return err!("Line {}: {} takes {} operands, not {}", line_number, mnemonic, params, operands.len());
+        }
+
+        let operands: Vec<(i64, String)> = operands.iter()
+            .map(|operand| parse_operand(operand).ok_or(format!("Line {}: cannot read operand {:?}", line_number, operand)))
+            .collect::<result::Result<_, _>>()?;
+        let modes: i64 = operands.iter().rev().fold(0, |modes, &(mode, _)| modes * 10 + mode);
+        cells.push((line_number, Cell::Opcode(modes * 100 + opcode)));
+        for (_, value) in operands {
+            cells.push((line_number, Cell::Value(value)));
+        }
+    }
+
+    cells.into_iter()
+        .map(|(line_number, cell)| match cell {
+            Cell::Opcode(code) => Ok(code),
+            Cell::Value(value) => match value.parse() {
+                Ok(n) => Ok(n),
+                Err(_) if is_label(&value) => match labels.get(&value) {
+                    Some(&address) => Ok(address),
+                    None => err!("Line {}: unknown label {}", line_number, value)
+                },
+                Err(_) => err!("Line {}: cannot read value {:?}", line_number, value)
+            }
+        })
+        .collect()
+}
+
+enum Cell {
+    Opcode(i64),
+    // A number or a label
+    Value(String)
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && s != "rb"
+}
+
+// An operand's parameter mode and the value to go with it, which may still be a label
+fn parse_operand(operand: &str) -> Option<(i64, String)> {
+    let value = |s: &str| {
+        let s = s.trim();
+        if s.parse::<i64>().is_ok() || is_label(s) { Some(s.to_string()) } else { None }
+    };
+
+    match operand.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        Some(inner) => match inner.trim().strip_prefix("rb") {
+            Some("") => Some((2, "0".to_string())),
+            Some(offset) => {
+                let offset = offset.trim();
+                match offset.strip_prefix('+') {
+                    Some(offset) => value(offset).map(|offset| (2, offset)),
+                    None if offset.starts_with('-') => value(&offset.replace(' ', "")).map(|offset| (2, offset)),
+                    None => None
+                }
+            },
+            None => value(inner).map(|address| (0, address))
+        },
+        None => value(operand).map(|n| (1, n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_problems::intcode::{disassemble, Program};
+
+    #[test]
+    fn intcode_asm_assembles_programs() {
+        let doubler = assemble("
+            ; Doubles every input
+            loop:   in [x]
+                    mul [x], 2, [x]
+                    out [x]
+                    jnz 1, loop
+            x:      .data 0
+        ").unwrap();
+        assert_eq!(doubler, vec![3, 11, 1002, 11, 2, 11, 4, 11, 1105, 1, 0, 0]);
+
+        let quine = assemble("
+            ; Outputs itself
+            start:  arb 1
+                    out [rb-1]
+                    add [100], 1, [100]
+                    eq [100], 16, [101]
+                    jz [101], start
+                    halt
+        ").unwrap();
+        assert_eq!(quine, vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]);
+        assert_eq!(Program::new(quine.clone()).run_to_end().unwrap(), quine);
+
+        // What the disassembler writes assembles back to the same program
+        let listing: Vec<String> = disassemble(&doubler).iter()
+            .map(|line| line.text.split_once(": ").unwrap().1.to_string())
+            .collect();
+        assert_eq!(assemble(&listing.join("\n")).unwrap(), doubler);
+
+        let mut program = Program::new(doubler);
+        program.add_input(21);
+        assert_eq!(program.run_to_end().unwrap(), vec![42]);
+    }
+
+    #[test]
+    fn intcode_asm_reports_mistakes() {
+        for (source, message) in &[
+            ("jmp 1, 2", "Line 1: unknown instruction \"jmp\""),
+            ("halt\nadd 1, 2", "Line 2: add takes 3 operands, not 2"),
+            ("out [x", "Line 1: cannot read operand \"[x\""),
+            ("out [nowhere]", "Line 1: unknown label nowhere"),
+            ("a: halt\na: halt", "Line 2: label a is defined twice"),
+        ] {
+            assert_eq!(assemble(source).unwrap_err().to_string(), *message);
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::result;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use aoc_problems::intcode::{Program, RunState};
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+type Task<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + 'a>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// The most a machine does in one poll, as outputs or inputs, before going to the back of
+// the queue, so that a busy machine can't keep the others from running. A machine which
+// loops without ever touching a port still runs until it does.
+const QUANTUM: usize = 64;
+
+// Intcode machines as futures, so that many can share one thread and a scheduler can tell
+// when every one of them is waiting for input. This crate is on the 2015 edition, which
+// has no `async fn`, so the futures are written out by hand.
+
+#[derive(Debug, Default)]
+struct PortState {
+    values: VecDeque<i64>,
+    waker: Option<Waker>,
+    closed: bool
+}
+
+// A queue of values between machines on the same thread. Clones share the queue.
+#[derive(Clone, Debug, Default)]
+pub struct Port {
+    state: Rc<RefCell<PortState>>
+}
+
+impl Port {
+    pub fn new() -> Port {
+        Port::default()
+    }
+
+    pub fn send(&self, value: i64) {
+        let mut state = self.state.borrow_mut();
+        state.values.push_back(value);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    // Once closed and empty, a machine waiting on the port stops instead
+    pub fn close(&self) {
+        let mut state = self.state.borrow_mut();
+        state.closed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    // Takes every value nobody has read yet
+    pub fn drain(&self) -> Vec<i64> {
+        self.state.borrow_mut().values.drain(..).collect()
+    }
+
+    pub fn recv(&self) -> Recv<'_> {
+        Recv { port: self }
+    }
+
+    fn poll_recv(&self, cx: &mut Context) -> Poll<Option<i64>> {
+        let mut state = self.state.borrow_mut();
+        match state.values.pop_front() {
+            Some(value) => Poll::Ready(Some(value)),
+            None if state.closed => Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// The next value sent to a port, or None once it's closed
+pub struct Recv<'a> {
+    port: &'a Port
+}
+
+impl<'a> Future for Recv<'a> {
+    type Output = Option<i64>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<i64>> {
+        self.port.poll_recv(cx)
+    }
+}
+
+// Runs until the program outputs a value or halts, waiting on `inputs` whenever it needs
+// input. Gives `RunState::NeedsInput` only if the port is closed.
+pub fn step<'a>(program: &'a mut Program, inputs: &'a Port) -> Step<'a> {
+    Step { program, inputs }
+}
+
+pub struct Step<'a> {
+    program: &'a mut Program,
+    inputs: &'a Port
+}
+
+impl<'a> Future for Step<'a> {
+    type Output = Result<RunState>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<RunState>> {
+        let this = &mut *self;
+        poll_step(this.program, this.inputs, cx)
+    }
+}
+
+fn poll_step(program: &mut Program, inputs: &Port, cx: &mut Context) -> Poll<Result<RunState>> {
+    loop {
+        match program.run_program() {
+            Ok(RunState::NeedsInput) => match inputs.poll_recv(cx) {
+                Poll::Ready(Some(input)) => program.add_input(input),
+                Poll::Ready(None) => return Poll::Ready(Ok(RunState::NeedsInput)),
+                Poll::Pending => return Poll::Pending
+            },
+            result => return Poll::Ready(result)
+        }
+    }
+}
+
+// Runs a machine between two ports until it halts or `inputs` is closed while it's
+// waiting, then hands it back
+pub fn run(program: Program, inputs: Port, outputs: Port) -> Run {
+    Run {
+        program: Some(program),
+        inputs,
+        outputs
+    }
+}
+
+pub struct Run {
+    program: Option<Program>,
+    inputs: Port,
+    outputs: Port
+}
+
+impl Future for Run {
+    type Output = Result<Program>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<Program>> {
+        let this = &mut *self;
+        let program = this.program.as_mut().expect("Machine polled after it stopped");
+        for _ in 0..QUANTUM {
+            match poll_step(program, &this.inputs, cx) {
+                Poll::Ready(Ok(RunState::Output(output))) => this.outputs.send(output),
+                Poll::Ready(Ok(_)) => return Poll::Ready(Ok(this.program.take().unwrap())),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+
+        // Used up its turn, so it waits behind every other machine which is ready
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+struct TaskWaker {
+    idx: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.idx);
+    }
+}
+
+// Polls tasks on the current thread whenever something they wait on changes, taking turns
+// in the order they became ready. Running stops once nothing is left to do, which is
+// either every task having finished or every one left being idle, waiting on a port
+// nobody has sent to.
+pub struct Scheduler<'a, T> {
+    tasks: Vec<Option<Task<'a, T>>>,
+    results: Vec<Option<T>>,
+    ready: Arc<Mutex<VecDeque<usize>>>
+}
+
+impl<'a, T> Default for Scheduler<'a, T> {
+    fn default() -> Scheduler<'a, T> {
+        Scheduler {
+            tasks: vec![],
+            results: vec![],
+            ready: Arc::new(Mutex::new(VecDeque::new()))
+        }
+    }
+}
+
+impl<'a, T> Scheduler<'a, T> {
+    pub fn new() -> Scheduler<'a, T> {
+        Scheduler::default()
+    }
+
+    // Adds a task, returning its index
+    pub fn spawn<F: Future<Output = Result<T>> + 'a>(&mut self, task: F) -> usize {
+        self.tasks.push(Some(Box::pin(task)));
+        self.results.push(None);
+        self.ready.lock().unwrap().push_back(self.tasks.len() - 1);
+        self.tasks.len() - 1
+    }
+
+    // Polls tasks until none can make progress, returning whether they have all finished.
+    // Sending to a port afterwards lets the tasks waiting on it carry on with another run.
+    pub fn run(&mut self) -> Result<bool> {
+        loop {
+            let next = self.ready.lock().unwrap().pop_front();
+            let idx = match next {
+                Some(idx) => idx,
+                None => return Ok(self.tasks.iter().all(|task| task.is_none()))
+            };
+
+            let waker = Waker::from(Arc::new(TaskWaker { idx, ready: self.ready.clone() }));
+            let poll = match self.tasks[idx].as_mut() {
+                Some(task) => task.as_mut().poll(&mut Context::from_waker(&waker)),
+                None => continue
+            };
+
+            match poll {
+                Poll::Ready(Ok(result)) => {
+                    self.tasks[idx] = None;
+                    self.results[idx] = Some(result);
+                },
+                Poll::Ready(Err(e)) => return Err(e),
+                Poll::Pending => {}
+            }
+        }
+    }
+
+    // What each task gave back, in the order they were spawned, or None if it hasn't
+    // finished
+    pub fn into_results(self) -> Vec<Option<T>> {
+        self.results
+    }
+}
+
+// A packet between machines on a network, as day 23's NICs send them
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Packet {
+    pub x: i64,
+    pub y: i64
+}
+
+// Decides where the packets on a network go, so that special addresses such as day 23's
+// NAT at 255 are handled outside the machines
+pub trait Router {
+    // The machine a packet sent to `address` is delivered to, or None if the router has
+    // kept it
+    fn route(&mut self, address: i64, packet: Packet) -> Result<Option<usize>>;
+
+    // Every machine is waiting on an empty queue. A packet to deliver to wake the
+    // network, or None to stop it.
+    fn idle(&mut self) -> Result<Option<(usize, Packet)>>;
+}
+
+// Machines talking in packets, each with an address and its own queue of input. A
+// machine sends a packet by outputting the address followed by X and Y, and reads -1 when
+// its queue is empty rather than waiting. Reading -1 again without sending anything in
+// between counts as idle.
+pub struct Network<R: Router> {
+    scheduler: Scheduler<'static, Program>,
+    inboxes: Rc<Vec<Port>>,
+    router: Rc<RefCell<R>>
+}
+
+impl<R: Router + 'static> Network<R> {
+    // Machine i is given address i as its first input
+    pub fn new(programs: Vec<Program>, router: R) -> Network<R> {
+        let inboxes: Rc<Vec<Port>> = Rc::new((0..programs.len()).map(|_| Port::new()).collect());
+        let router = Rc::new(RefCell::new(router));
+
+        let mut scheduler = Scheduler::new();
+        for (address, mut program) in programs.into_iter().enumerate() {
+            program.add_input(address as i64);
+            scheduler.spawn(Nic {
+                program: Some(program),
+                address,
+                inboxes: inboxes.clone(),
+                router: router.clone(),
+                sending: vec![],
+                idle: false
+            });
+        }
+
+        Network { scheduler, inboxes, router }
+    }
+
+    // Runs until the router stops the network while it's idle, or every machine halts,
+    // handing back the router
+    pub fn run(mut self) -> Result<R> {
+        while !self.scheduler.run()? {
+            let wake_with = self.router.borrow_mut().idle()?;
+            match wake_with {
+                Some((machine, packet)) => deliver(&self.inboxes, machine, packet)?,
+                None => break
+            }
+        }
+
+        drop(self.scheduler);
+        match Rc::try_unwrap(self.router) {
+            Ok(router) => Ok(router.into_inner()),
+            Err(_) => err!("Router is still in use")
+        }
+    }
+}
+
+fn deliver(inboxes: &[Port], machine: usize, packet: Packet) -> Result<()> {
+    match inboxes.get(machine) {
+        Some(inbox) => {
+            inbox.send(packet.x);
+            inbox.send(packet.y);
+            Ok(())
+        },
+        None => err!("No machine {} to deliver {:?} to", machine, packet)
+    }
+}
+
+// A machine on a `Network`
+struct Nic<R: Router> {
+    program: Option<Program>,
+    address: usize,
+    inboxes: Rc<Vec<Port>>,
+    router: Rc<RefCell<R>>,
+    // The part of a packet output so far
+    sending: Vec<i64>,
+    // Whether it has read -1 and sent nothing since
+    idle: bool
+}
+
+impl<R: Router> Future for Nic<R> {
+    type Output = Result<Program>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<Program>> {
+        let this = &mut *self;
+        let program = this.program.as_mut().expect("Machine polled after it stopped");
+        for _ in 0..QUANTUM {
+            match program.run_program() {
+                Ok(RunState::Output(value)) => {
+                    this.idle = false;
+                    this.sending.push(value);
+                    if let [address, x, y] = this.sending[..] {
+                        this.sending.clear();
+                        let packet = Packet { x, y };
+                        let routed = this.router.borrow_mut().route(address, packet);
+                        match routed {
+                            Ok(Some(machine)) => if let Err(e) = deliver(&this.inboxes, machine, packet) {
+                                return Poll::Ready(Err(e));
+                            },
+                            Ok(None) => {},
+                            Err(e) => return Poll::Ready(Err(e))
+                        }
+                    }
+                },
+                Ok(RunState::NeedsInput) => match this.inboxes[this.address].poll_recv(cx) {
+                    Poll::Ready(Some(input)) => {
+                        this.idle = false;
+                        program.add_input(input);
+                    },
+                    Poll::Ready(None) => return Poll::Ready(Ok(this.program.take().unwrap())),
+                    // Waits for a packet, which wakes it through the port
+                    Poll::Pending if this.idle => return Poll::Pending,
+                    Poll::Pending => {
+                        this.idle = true;
+                        program.add_input(-1);
+                    }
+                },
+                Ok(RunState::Halted) => return Poll::Ready(Ok(this.program.take().unwrap())),
+                Err(e) => return Poll::Ready(Err(e))
+            }
+        }
+
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+// Day 23's NAT: packets sent to address 255 are kept, and whenever the network is idle
+// the last of them is sent to machine 0. It stops the network once it has sent machine
+// 0 the same Y twice in a row.
+#[derive(Clone, Debug, Default)]
+pub struct Nat {
+    machines: usize,
+    // The first packet sent to it
+    pub first: Option<Packet>,
+    last: Option<Packet>,
+    // The Y of every packet it has sent to machine 0
+    pub sent: Vec<i64>
+}
+
+impl Nat {
+    pub fn new(machines: usize) -> Nat {
+        Nat { machines, ..Nat::default() }
+    }
+}
+
+impl Router for Nat {
+    fn route(&mut self, address: i64, packet: Packet) -> Result<Option<usize>> {
+        match address {
+            255 => {
+                self.first = self.first.or(Some(packet));
+                self.last = Some(packet);
+                Ok(None)
+            },
+            address if 0 <= address && (address as usize) < self.machines => Ok(Some(address as usize)),
+            address => err!("No machine at address {} for {:?}", address, packet)
+        }
+    }
+
+    fn idle(&mut self) -> Result<Option<(usize, Packet)>> {
+        let packet = match self.last {
+            Some(packet) => packet,
+            None => return err!("The network went idle before anything was sent to the NAT")
+        };
+        if self.sent.last() == Some(&packet.y) {
+            return Ok(None);
+        }
+
+        self.sent.push(packet.y);
+        Ok(Some((0, packet)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_problems::intcode_asm::assemble;
+
+    #[test]
+    fn intcode_async_detects_idle_machines() {
+        let doubler = Program::parse("3,11,1002,11,2,11,4,11,1105,1,0").unwrap();
+        let ports: Vec<Port> = (0..3).map(|_| Port::new()).collect();
+
+        let mut scheduler = Scheduler::new();
+        scheduler.spawn(run(doubler.clone(), ports[0].clone(), ports[1].clone()));
+        scheduler.spawn(run(doubler, ports[1].clone(), ports[2].clone()));
+
+        ports[0].send(3);
+        assert!(!scheduler.run().unwrap());
+        assert_eq!(ports[2].drain(), vec![12]);
+
+        ports[0].send(1);
+        ports[0].send(2);
+        assert!(!scheduler.run().unwrap());
+        assert_eq!(ports[2].drain(), vec![4, 8]);
+
+        ports[0].close();
+        ports[1].close();
+        assert!(scheduler.run().unwrap());
+        assert!(scheduler.into_results().iter().all(|machine| machine.as_ref().unwrap().awaiting_input()));
+    }
+
+    #[test]
+    fn intcode_async_takes_turns() {
+        // Each machine outputs its input a hundred times
+        let repeater = assemble("
+                    in [n]
+            loop:   out [n]
+                    add [count], 1, [count]
+                    lt [count], 100, [more]
+                    jnz [more], loop
+                    halt
+            n:      .data 0
+            count:  .data 0
+            more:   .data 0
+        ").unwrap();
+        let shared = Port::new();
+        let inputs: Vec<Port> = (0..2).map(|_| Port::new()).collect();
+
+        let mut scheduler = Scheduler::new();
+        for (idx, inputs) in inputs.iter().enumerate() {
+            scheduler.spawn(run(Program::new(repeater.clone()), inputs.clone(), shared.clone()));
+            inputs.send(idx as i64 + 1);
+        }
+        assert!(scheduler.run().unwrap());
+
+        // Neither machine waits for input, so only the quantum makes them take turns
+        let outputs = shared.drain();
+        let turns: Vec<(i64, usize)> = outputs.chunk_by(|a, b| a == b).map(|turn| (turn[0], turn.len())).collect();
+        assert_eq!(turns, vec![(1, 64), (2, 64), (1, 36), (2, 36)]);
+    }
+
+    #[test]
+    fn intcode_async_routes_packets() {
+        // Machine 1 starts things off by sending the NAT a packet, then sends every
+        // packet it gets there, taking one off Y until it's 8. Machine 0 passes packets
+        // on to machine 1.
+        let relay = assemble("
+                    in [x]
+            loop:   in [x]
+                    eq [x], -1, [t]
+                    jnz [t], loop
+                    in [y]
+                    out 1
+                    out [x]
+                    out [y]
+                    jz 0, loop
+            x:      .data 0
+            y:      .data 0
+            t:      .data 0
+        ").unwrap();
+        let countdown = assemble("
+                    in [x]
+                    out 255
+                    out 0
+                    out 10
+            loop:   in [x]
+                    eq [x], -1, [t]
+                    jnz [t], loop
+                    in [y]
+                    lt 8, [y], [t]
+                    jz [t], send
+                    add [y], -1, [y]
+            send:   out 255
+                    out [x]
+                    out [y]
+                    jz 0, loop
+            x:      .data 0
+            y:      .data 0
+            t:      .data 0
+        ").unwrap();
+
+        let network = Network::new(vec![Program::new(relay.clone()), Program::new(countdown)], Nat::new(2));
+        let nat = network.run().unwrap();
+        assert_eq!(nat.first, Some(Packet { x: 0, y: 10 }));
+        assert_eq!(nat.sent, vec![10, 9, 8]);
+
+        // Sending to a machine which isn't there is an error
+        let lost = assemble("out 7\nout 1\nout 2\nhalt").unwrap();
+        assert!(Network::new(vec![Program::new(lost)], Nat::new(1)).run().is_err());
+    }
+
+    #[test]
+    fn intcode_async_steps() {
+        let mut program = Program::parse("3,0,4,0,99").unwrap();
+        let inputs = Port::new();
+
+        let mut scheduler = Scheduler::new();
+        scheduler.spawn(step(&mut program, &inputs));
+        assert!(!scheduler.run().unwrap());
+        inputs.send(5);
+        assert!(scheduler.run().unwrap());
+        assert_eq!(scheduler.into_results(), vec![Some(RunState::Output(5))]);
+    }
+}
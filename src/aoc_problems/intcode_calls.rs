@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use aoc_problems::intcode::disassemble;
+
+// The routines of a program, found from the way the puzzle programs call each other:
+//
+//     add 11, 0, [rb+0]   ; the return address, just past the jump
+//     jz 0, 282           ; into the routine
+//
+// and return with a jump to whatever is at [rb+0], after moving the relative base back
+// down past the frame the routine took with `arb` on its way in.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CallGraph {
+    pub routines: Vec<Routine>
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Routine {
+    pub name: String,
+    // Where it starts, and where the next routine starts
+    pub entry: usize,
+    pub end: usize,
+    // How many cells it moves the relative base by on the way in, if it starts with `arb`
+    pub frame: Option<i64>,
+    // The entries of the routines it calls, and the jumps which call it
+    pub calls: BTreeSet<usize>,
+    pub callers: BTreeSet<usize>,
+    // Its jumps back to the caller
+    pub returns: Vec<usize>
+}
+
+// What the parameter mode digits of an instruction code say about one parameter
+fn mode(code: i64, n: u32) -> i64 {
+    code / 10_i64.pow(n + 2) % 10
+}
+
+// The address an unconditional jump at `address` goes to, if it always jumps to an
+// immediate address: `jnz` with a nonzero immediate test or `jz` with a zero one
+fn jump_target(memory: &[i64], address: usize, target_mode: i64) -> Option<i64> {
+    let code = *memory.get(address)?;
+    let (test, target) = (*memory.get(address + 1)?, *memory.get(address + 2)?);
+    let always = match code % 100 {
+        5 => test != 0,
+        6 => test == 0,
+        _ => false
+    };
+
+    if always && mode(code, 0) == 1 && mode(code, 1) == target_mode {
+        Some(target)
+    } else {
+        None
+    }
+}
+
+// The routine called by the jump following an add or multiply at `address`, if that
+// stores the address just past the jump in a relative cell
+fn call_target(memory: &[i64], address: usize) -> Option<usize> {
+    let code = *memory.get(address)?;
+    let stored = match code % 100 {
+        1 => memory.get(address + 1)?.checked_add(*memory.get(address + 2)?)?,
+        2 => memory.get(address + 1)?.checked_mul(*memory.get(address + 2)?)?,
+        _ => return None
+    };
+    if (mode(code, 0), mode(code, 1), mode(code, 2)) != (1, 1, 2) || stored != address as i64 + 7 {
+        return None;
+    }
+
+    match jump_target(memory, address + 4, 1) {
+        Some(target) if target >= 0 && (target as usize) < memory.len() => Some(target as usize),
+        _ => None
+    }
+}
+
+pub fn call_graph(memory: &[i64]) -> CallGraph {
+    let addresses: Vec<usize> = disassemble(memory).iter().map(|line| line.address).collect();
+
+    // Every call, from the jump to the routine's entry
+    let calls: BTreeMap<usize, usize> = addresses.iter()
+        .filter_map(|&address| call_target(memory, address).map(|target| (address + 4, target)))
+        .collect();
+    let returns: Vec<usize> = addresses.iter().cloned().filter(|&address| jump_target(memory, address, 2).is_some()).collect();
+
+    let mut entries: BTreeSet<usize> = calls.values().cloned().collect();
+    entries.insert(0);
+    let entries: Vec<usize> = entries.into_iter().collect();
+
+    let routines = entries.iter().enumerate().map(|(idx, &entry)| {
+        let end = entries.get(idx + 1).cloned().unwrap_or(memory.len());
+        let called = calls.values().any(|&target| target == entry);
+        let frame = match memory.get(entry..entry + 2) {
+            Some(&[109, frame]) => Some(frame),
+            _ => None
+        };
+
+        Routine {
+            name: if called { format!("fn_{}", entry) } else { "main".to_string() },
+            entry,
+            end,
+            frame,
+            calls: calls.range(entry..end).map(|(_, &target)| target).collect(),
+            callers: calls.iter().filter(|&(_, &target)| target == entry).map(|(&jump, _)| jump).collect(),
+            returns: returns.iter().cloned().filter(|&address| (entry..end).contains(&address)).collect()
+        }
+    }).collect();
+
+    CallGraph { routines }
+}
+
+impl CallGraph {
+    pub fn routine(&self, entry: usize) -> Option<&Routine> {
+        self.routines.iter().find(|routine| routine.entry == entry)
+    }
+
+    fn names(&self, entries: &BTreeSet<usize>) -> String {
+        let names: Vec<&str> = entries.iter().filter_map(|&entry| self.routine(entry)).map(|routine| routine.name.as_str()).collect();
+        names.join(", ")
+    }
+
+    // One line for each routine, saying what calls it and what it calls
+    pub fn report(&self) -> String {
+        let lines: Vec<String> = self.routines.iter().map(|routine| {
+            let mut details = vec![];
+            if let Some(frame) = routine.frame {
+                details.push(format!("frame {}", frame));
+            }
+            if !routine.callers.is_empty() {
+                let callers: Vec<String> = routine.callers.iter().map(|caller| caller.to_string()).collect();
+                details.push(format!("called from {}", callers.join(", ")));
+            }
+            if !routine.calls.is_empty() {
+                details.push(format!("calls {}", self.names(&routine.calls)));
+            }
+            if !routine.returns.is_empty() {
+                let returns: Vec<String> = routine.returns.iter().map(|address| address.to_string()).collect();
+                details.push(format!("returns at {}", returns.join(", ")));
+            }
+
+            let span = format!("{}..{}", routine.entry, routine.end);
+            format!("{:<10} {:<12} {}", routine.name, span, details.join("; ")).trim_end().to_string()
+        }).collect();
+
+        lines.join("\n")
+    }
+
+    // The disassembly of `memory` with a label line starting each routine, and each call
+    // and return marked
+    pub fn listing(&self, memory: &[i64]) -> Vec<String> {
+        let mut lines = vec![];
+        for line in disassemble(memory) {
+            if let Some(routine) = self.routine(line.address) {
+                lines.push(format!("{}:", routine.name));
+            }
+
+            let callee = self.routines.iter()
+                .find(|routine| routine.callers.contains(&line.address))
+                .map(|routine| routine.name.as_str());
+            let returns = self.routines.iter().any(|routine| routine.returns.contains(&line.address));
+            lines.push(match (callee, returns) {
+                (Some(name), _) => format!("{:<32} ; call {}", line.text, name),
+                (None, true) => format!("{:<32} ; return", line.text),
+                (None, false) => line.text
+            });
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_problems::intcode::Program;
+    use aoc_problems::intcode_asm::assemble;
+
+    #[test]
+    fn intcode_calls_finds_routines() {
+        // Outputs the square of its input plus one, through two routines
+        let program = assemble("
+                    arb 100
+                    in [rb+1]
+                    add back, 0, [rb+0]
+                    jz 0, square_plus_one
+            back:   out [rb+1]
+                    halt
+
+            square_plus_one:
+                    arb 3
+                    add [rb-2], 0, [rb+1]
+                    add again, 0, [rb+0]
+                    jnz 1, square
+            again:  add [rb+1], 1, [rb-2]
+                    arb -3
+                    jz 0, [rb+0]
+
+            square: mul [rb+1], [rb+1], [rb+1]
+                    jnz 1, [rb+0]
+        ").unwrap();
+        let mut run = Program::new(program.clone());
+        run.add_input(7);
+        assert_eq!(run.run_to_end().unwrap(), vec![50]);
+
+        let graph = call_graph(&program);
+        let names: Vec<&str> = graph.routines.iter().map(|routine| routine.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "fn_14", "fn_36"]);
+        assert_eq!(graph.routines[1].frame, Some(3));
+        assert_eq!(graph.routines[1].calls, vec![36].into_iter().collect());
+        assert_eq!(graph.routines[2].callers, vec![24].into_iter().collect());
+        assert_eq!(graph.report(), [
+            "main       0..14        frame 100; calls fn_14",
+            "fn_14      14..36       frame 3; called from 8; calls fn_36; returns at 33",
+            "fn_36      36..43       called from 24; returns at 40",
+        ].join("\n"));
+
+        let listing = graph.listing(&program);
+        assert!(listing.contains(&"fn_14:".to_string()));
+        assert!(listing.contains(&format!("{:<32} ; call fn_14", "8: jz 0, 14")));
+        assert!(listing.contains(&format!("{:<32} ; return", "40: jnz 1, [rb+0]")));
+    }
+}
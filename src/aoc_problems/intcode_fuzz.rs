@@ -0,0 +1,141 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use aoc_problems::intcode::{Compliance, Execution, MemoryBackend, RunState, VmBuilder};
+use util::rng::Rng;
+
+// How long each fuzzed program may run. Random programs loop for ever easily, and a
+// loop is not what the fuzzer is looking for.
+const MAX_STEPS: u64 = 10_000;
+
+// Opcodes to build instructions from, including ones that don't exist
+const OPCODES: &[i64] = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 99, 0, 10, 98];
+
+// Cell values which sit on the edges of what the VM has to cope with
+const EXTREMES: &[i64] = &[i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX - 1, i64::MAX, 1 << 32, 1 << 62];
+
+// A program which made the VM panic instead of returning an error, and how to find it again
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Crash {
+    pub seed: u64,
+    pub program: Vec<i64>,
+    pub execution: Execution,
+    pub backend: MemoryBackend,
+    pub compliance: Compliance,
+    pub message: String
+}
+
+impl fmt::Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let program: Vec<String> = self.program.iter().map(|n| n.to_string()).collect();
+        write!(
+            f,
+            "seed {} ({:?}, {:?} memory, {:?}) panicked: {}\n  {}",
+            self.seed,
+            self.execution,
+            self.backend,
+            self.compliance,
+            self.message,
+            program.join(",")
+        )
+    }
+}
+
+// A program of up to `max_len` cells, mostly instructions with random parameter modes
+// and operands pointing in and around the program, so that random runs get past their
+// first few instructions
+pub fn random_program(rng: &mut Rng, max_len: usize) -> Vec<i64> {
+    let len = 1 + rng.below(max_len);
+    (0..len).map(|_| match rng.below(8) {
+        0..=2 => {
+            let modes = (0..3).fold(0, |modes, _| modes * 10 + rng.below(4) as i64);
+            modes * 100 + OPCODES[rng.below(OPCODES.len())]
+        },
+        3..=5 => rng.below(3 * len) as i64 - len as i64,
+        6 => EXTREMES[rng.below(EXTREMES.len())],
+        _ => rng.next_u64() as i64
+    }).collect()
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string()
+        }
+    }
+}
+
+// Runs `memory` to the end, feeding it random inputs whenever it asks, returning the
+// panic message if the VM panicked. Errors are what a broken program should get, so
+// they count as a pass.
+fn run_once(memory: &[i64], execution: Execution, backend: MemoryBackend, compliance: Compliance, rng: &mut Rng) -> Option<String> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut program = match VmBuilder::new(memory.to_vec()).execution(execution).backend(backend).compliance(compliance).build() {
+            Ok(program) => program,
+            Err(_) => return
+        };
+        program.limit_steps(MAX_STEPS);
+        let mut inputs = 0;
+        loop {
+            match program.run_program() {
+                Ok(RunState::Output(_)) => {},
+                Ok(RunState::NeedsInput) if inputs < 100 => {
+                    inputs += 1;
+                    program.add_input(EXTREMES[rng.below(EXTREMES.len())]);
+                },
+                _ => return
+            }
+        }
+    }));
+
+    result.err().map(panic_message)
+}
+
+// Runs `program` on every combination of execution, memory and compliance mode, each
+// given the same inputs from `seed`, collecting the ones which panicked. The default
+// panic hook still prints each panic, which callers wanting quiet output can swap out.
+pub fn check(program: &[i64], seed: u64) -> Vec<Crash> {
+    let mut crashes = vec![];
+    for &execution in &[Execution::Interpreted, Execution::Compiled] {
+        for &backend in &[MemoryBackend::Paged, MemoryBackend::Sparse] {
+            for &compliance in &[Compliance::Strict, Compliance::Standard, Compliance::Lenient] {
+                let mut inputs = Rng::new(seed);
+                if let Some(message) = run_once(program, execution, backend, compliance, &mut inputs) {
+                    crashes.push(Crash { seed, program: program.to_vec(), execution, backend, compliance, message });
+                }
+            }
+        }
+    }
+
+    crashes
+}
+
+// Checks `runs` random programs, the first made from `seed` and each after from the next
+pub fn fuzz(runs: usize, seed: u64) -> Vec<Crash> {
+    let mut crashes = vec![];
+    for run in 0..runs as u64 {
+        let seed = seed.wrapping_add(run);
+        let program = random_program(&mut Rng::new(seed), 32);
+        crashes.extend(check(&program, seed));
+    }
+
+    crashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intcode_fuzz_finds_no_panics() {
+        let crashes = fuzz(500, 2019);
+        let report: Vec<String> = crashes.iter().take(5).map(|crash| crash.to_string()).collect();
+        assert!(crashes.is_empty(), "{} crashes, including\n{}", crashes.len(), report.join("\n"));
+
+        // The same seed always gives the same program
+        assert_eq!(random_program(&mut Rng::new(7), 32), random_program(&mut Rng::new(7), 32));
+    }
+}
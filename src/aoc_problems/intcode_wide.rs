@@ -0,0 +1,258 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::result;
+use std::str::FromStr;
+
+use aoc_problems::intcode::MemoryBackend;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// What an Intcode cell holds. The shared VM in `intcode` only runs 64 bit cells, so a
+// program whose numbers outgrow them stops there with an overflow error, and can be run
+// again here with wider ones to see what it meant to do.
+pub trait Word: Copy + Default + Ord + fmt::Display + fmt::Debug + FromStr {
+    const BITS: u32;
+
+    fn from_i64(n: i64) -> Self;
+    // None if it doesn't fit, as when it's used as an address or an opcode
+    fn to_i64(self) -> Option<i64>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! word {
+    ($t:ty) => {
+        impl Word for $t {
+            const BITS: u32 = <$t>::BITS;
+
+            fn from_i64(n: i64) -> $t {
+                n as $t
+            }
+
+            fn to_i64(self) -> Option<i64> {
+                i64::try_from(self).ok()
+            }
+
+            fn checked_add(self, other: $t) -> Option<$t> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_mul(self, other: $t) -> Option<$t> {
+                <$t>::checked_mul(self, other)
+            }
+        }
+    };
+}
+
+word!(i64);
+word!(i128);
+
+// A plain Intcode interpreter over any word size, without the devices, custom opcodes,
+// compliance modes and tooling of the shared VM. Addresses and the relative base still
+// have to fit in 64 bits.
+#[derive(Clone, Debug)]
+pub struct WideProgram<W: Word> {
+    program: Vec<W>,
+    // Cells past the end of the program which have been written
+    extra: HashMap<usize, W>,
+    inputs: VecDeque<W>,
+    pointer: usize,
+    relative_base: i64,
+    halted: bool,
+    instructions: u64
+}
+
+impl<W: Word> WideProgram<W> {
+    pub fn new(memory: Vec<W>) -> WideProgram<W> {
+        WideProgram {
+            program: memory,
+            extra: HashMap::new(),
+            inputs: VecDeque::new(),
+            pointer: 0,
+            relative_base: 0,
+            halted: false,
+            instructions: 0
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<WideProgram<W>> {
+        let memory: Vec<W> = s.trim().split(',')
+            .map(|n| n.trim().parse().or(err!("Cannot read {:?} as a {} bit cell", n.trim(), W::BITS)))
+            .collect::<Result<_>>()?;
+        Ok(WideProgram::new(memory))
+    }
+
+    pub fn add_input(&mut self, input: W) {
+        self.inputs.push_back(input);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    fn get(&self, idx: usize) -> W {
+        match self.program.get(idx) {
+            Some(&val) => val,
+            None => self.extra.get(&idx).cloned().unwrap_or_default()
+        }
+    }
+
+    fn set(&mut self, idx: usize, val: W) {
+        match self.program.get_mut(idx) {
+            Some(cell) => *cell = val,
+            None => {
+                self.extra.insert(idx, val);
+            }
+        }
+    }
+
+    fn fault<T>(&self, message: String) -> Result<T> {
+        err!("{} at address {} (instruction {})", message, self.pointer, self.get(self.pointer))
+    }
+
+    fn address(&self, mode: i64, n: usize) -> Result<usize> {
+        let val = self.get(self.pointer + n + 1);
+        let idx = match (mode, val.to_i64()) {
+            (0, Some(idx)) => Some(idx),
+            (2, Some(offset)) => self.relative_base.checked_add(offset),
+            (1, _) => return self.fault("Cannot write to an immediate parameter".to_string()),
+            _ => None
+        };
+
+        match idx {
+            Some(idx) if (0..=MemoryBackend::Sparse.max_address()).contains(&idx) => Ok(idx as usize),
+            _ => self.fault(format!("Cannot access address {}", val))
+        }
+    }
+
+    fn read(&self, modes: i64, n: usize) -> Result<W> {
+        match modes / 10_i64.pow(n as u32) % 10 {
+            1 => Ok(self.get(self.pointer + n + 1)),
+            mode => Ok(self.get(self.address(mode, n)?))
+        }
+    }
+
+    fn write(&mut self, modes: i64, n: usize, val: W) -> Result<()> {
+        let idx = self.address(modes / 10_i64.pow(n as u32) % 10, n)?;
+        self.set(idx, val);
+        Ok(())
+    }
+
+    // Runs until the program halts or needs an input it hasn't been given, returning
+    // everything it output
+    pub fn run_to_end(&mut self) -> Result<Vec<W>> {
+        let mut outputs = vec![];
+        while !self.halted {
+            let code = match self.get(self.pointer).to_i64() {
+                Some(code) if code >= 0 => code,
+                _ => return self.fault("Cannot read instruction".to_string())
+            };
+            let (opcode, modes) = (code % 100, code / 100);
+            if opcode == 3 && self.inputs.is_empty() {
+                break;
+            }
+            self.instructions += 1;
+
+            match opcode {
+                1 | 2 | 7 | 8 => {
+                    let (a, b) = (self.read(modes, 0)?, self.read(modes, 1)?);
+                    let result = match opcode {
+                        1 => a.checked_add(b),
+                        2 => a.checked_mul(b),
+                        7 => Some(W::from_i64((a < b) as i64)),
+                        _ => Some(W::from_i64((a == b) as i64))
+                    };
+                    let result = match result {
+                        Some(result) => result,
+                        None => return self.fault(format!("{} {} {} overflows {} bits", a, if opcode == 1 { "+" } else { "*" }, b, W::BITS))
+                    };
+                    self.write(modes, 2, result)?;
+                    self.pointer += 4;
+                },
+                3 => {
+                    let input = self.inputs.pop_front().unwrap();
+                    self.write(modes, 0, input)?;
+                    self.pointer += 2;
+                },
+                4 => {
+                    outputs.push(self.read(modes, 0)?);
+                    self.pointer += 2;
+                },
+                5 | 6 => {
+                    let (a, b) = (self.read(modes, 0)?, self.read(modes, 1)?);
+                    if (a != W::default()) == (opcode == 5) {
+                        self.pointer = match b.to_i64() {
+                            Some(target) if target >= 0 => target as usize,
+                            _ => return self.fault(format!("Cannot jump to address {}", b))
+                        };
+                    } else {
+                        self.pointer += 3;
+                    }
+                },
+                9 => {
+                    let offset = self.read(modes, 0)?;
+                    self.relative_base = match offset.to_i64().and_then(|offset| self.relative_base.checked_add(offset)) {
+                        Some(base) => base,
+                        None => return self.fault(format!("Relative base {} + {} overflows", self.relative_base, offset))
+                    };
+                    self.pointer += 2;
+                },
+                99 => self.halted = true,
+                _ => return self.fault("Cannot read opcode".to_string())
+            }
+        }
+
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_problems::intcode::Program;
+
+    #[test]
+    fn intcode_wide_runs_past_64_bits() {
+        // Squares its input, then squares that
+        let squares = "3,100,2,100,100,100,2,100,100,100,4,100,99";
+        let mut narrow = Program::parse(squares).unwrap();
+        narrow.add_input(1000);
+        assert_eq!(narrow.run_to_end().unwrap(), vec![1_000_000_000_000]);
+        let mut narrow = Program::parse(squares).unwrap();
+        narrow.add_input(1 << 20);
+        assert_eq!(
+            narrow.run_to_end().unwrap_err().to_string(),
+            "1099511627776 * 1099511627776 overflows 64 bits at address 6 (instruction 2)"
+        );
+
+        let mut wide: WideProgram<i128> = WideProgram::parse(squares).unwrap();
+        wide.add_input(1 << 20);
+        assert_eq!(wide.run_to_end().unwrap(), vec![1 << 80]);
+        assert!(wide.is_halted());
+        assert_eq!(wide.instructions(), 5);
+
+        // Both widths run the same as the shared VM otherwise
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let outputs: Vec<i64> = WideProgram::parse(quine).unwrap().run_to_end().unwrap();
+        assert_eq!(outputs, Program::parse(quine).unwrap().run_to_end().unwrap());
+
+        let mut wide: WideProgram<i128> = WideProgram::parse("3,0,4,0,99").unwrap();
+        assert_eq!(wide.run_to_end().unwrap(), vec![]);
+        assert!(!wide.is_halted());
+        wide.add_input(i128::MAX);
+        assert_eq!(wide.run_to_end().unwrap(), vec![i128::MAX]);
+
+        assert!(WideProgram::<i64>::parse("1,2,170141183460469231731687303715884105727").is_err());
+    }
+}
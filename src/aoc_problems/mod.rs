@@ -60,3 +60,286 @@ pub mod day_20;
 
 #[allow(dead_code)]
 pub mod day_21;
+
+#[allow(dead_code)]
+pub mod ascii;
+
+#[allow(dead_code)]
+pub mod devices;
+
+#[allow(dead_code)]
+pub mod facts;
+
+#[allow(dead_code)]
+pub mod intcode;
+
+#[allow(dead_code)]
+pub mod intcode_asm;
+
+#[allow(dead_code)]
+pub mod intcode_async;
+
+#[allow(dead_code)]
+pub mod intcode_calls;
+
+#[allow(dead_code)]
+pub mod intcode_fuzz;
+
+#[allow(dead_code)]
+pub mod intcode_wide;
+
+use std::error::Error;
+use std::fmt;
+
+use features::{self, Feature};
+use options::{DayOption, OptionKind, Options};
+use util::input;
+
+pub type Validator = fn(&str) -> Result<(), Box<dyn Error>>;
+
+pub type BothSolver = fn(String, &Options) -> (String, String);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tag {
+    Intcode,
+    Grid,
+    Graph,
+    Math
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Tag::Intcode => "intcode",
+            Tag::Grid => "grid",
+            Tag::Graph => "graph",
+            Tag::Math => "math",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct Day {
+    pub number: usize,
+    pub title: &'static str,
+    pub tags: &'static [Tag],
+    // Whether the solver draws pictures or frames of its state as it runs, and whether it
+    // has alternative strategies
+    pub visualization: bool,
+    pub strategies: bool,
+    pub options: &'static [DayOption],
+    // Options which only work when a feature is built in, such as drawing a heatmap
+    pub needs: &'static [(&'static str, Feature)],
+    // Checks the puzzle input before solving, so a bad input gets a useful error
+    pub validate: Option<Validator>,
+    pub q1: fn(String, &Options) -> String,
+    pub q2: fn(String, &Options) -> String,
+    // Solves both parts at once, for days where part 2 can reuse the work of part 1
+    pub both: Option<BothSolver>,
+}
+
+impl Day {
+    // Inputs for other accounts live in a subdirectory of inputs/ named after the profile,
+    // and may have been compressed, in which case the `.gz` or `.zst` file is used
+    pub fn input_file(&self, profile: Option<&str>) -> String {
+        let path = match profile {
+            Some(profile) => format!("./inputs/{}/day{:02}.txt", profile, self.number),
+            None => format!("./inputs/day{:02}.txt", self.number)
+        };
+        input::find_file(&path).unwrap_or(path)
+    }
+
+    // Fails if any of `options` needs a feature this build left out
+    pub fn check_features(&self, options: &Options) -> Result<(), Box<dyn Error>> {
+        for &(name, feature) in self.needs {
+            if options.is_set(name) {
+                features::require(feature, &format!("--{}", name))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // The features this day's options need which this build left out
+    pub fn missing_features(&self) -> Vec<Feature> {
+        let mut missing: Vec<Feature> = self.needs.iter().map(|&(_, feature)| feature).filter(|feature| !feature.enabled()).collect();
+        missing.dedup();
+        missing
+    }
+}
+
+// Registers a day whose solvers take just the input file, with any of the other fields
+// overridden by `name: value` pairs after the tags
+macro_rules! day {
+    ($number:expr, $module:ident, $title:expr, [$($tag:ident),*] $(, $field:ident: $value:expr)*) => {
+        Day {
+            $($field: $value,)*
+            ..Day {
+                number: $number,
+                title: $title,
+                tags: &[$(Tag::$tag),*],
+                visualization: false,
+                strategies: false,
+                options: &[],
+                needs: &[],
+                validate: None,
+                q1: |fname, _| $module::q1(fname).to_string(),
+                q2: |fname, _| $module::q2(fname).to_string(),
+                both: None,
+            }
+        }
+    };
+}
+
+// Every day which reads its puzzle input from a file. Day 4's input is a number range
+// rather than a file, so it isn't registered here.
+pub fn registry() -> Vec<Day> {
+    let intcode = Some(input::validate_intcode as Validator);
+
+    vec![
+        day!(1, day_01, "The Tyranny of the Rocket Equation", [Math]),
+        day!(2, day_02, "1202 Program Alarm", [Intcode], validate: intcode),
+        day!(3, day_03, "Crossed Wires", [Grid]),
+        day!(5, day_05, "Sunny with a Chance of Asteroids", [Intcode], validate: intcode),
+        day!(6, day_06, "Universal Orbit Map", [Graph]),
+        day!(7, day_07, "Amplification Circuit", [Intcode],
+            validate: intcode,
+            options: &[
+                DayOption { name: "csv", kind: OptionKind::Text, help: "also write the signal for every ordering of the phase settings to this file" },
+            ],
+            q1: |fname, options| match options.text("csv") {
+                Some(out) => day_07::report(fname, 1, out).to_string(),
+                None => day_07::q1(fname).to_string()
+            },
+            q2: |fname, options| match options.text("csv") {
+                Some(out) => day_07::report(fname, 2, out).to_string(),
+                None => day_07::q2(fname).to_string()
+            }),
+        day!(8, day_08, "Space Image Format", [Grid], visualization: true),
+        day!(9, day_09, "Sensor Boost", [Intcode],
+            validate: intcode,
+            both: Some(|fname, _| {
+                let (keycode, coordinates) = day_09::both(fname);
+                (keycode.to_string(), coordinates)
+            })),
+        day!(10, day_10, "Monitoring Station", [Grid, Math],
+            validate: Some(|s| input::validate_grid(s, "#.")),
+            options: &[
+                DayOption { name: "add", kind: OptionKind::Text, help: "in part 1, add asteroids at these x,y positions, separated by semicolons, before choosing the station" },
+                DayOption { name: "remove", kind: OptionKind::Text, help: "in part 1, take away the asteroids at these x,y positions first" },
+                DayOption { name: "heatmap", kind: OptionKind::Flag, help: "in part 1, draw how many asteroids can be seen from every position" },
+            ],
+            needs: &[("heatmap", Feature::Viz)],
+            q1: |fname, options| day_10::q1_with(fname, options.text("add"), options.text("remove"), options.flag("heatmap")).to_string()),
+        day!(11, day_11, "Space Police", [Intcode, Grid],
+            visualization: true,
+            validate: intcode,
+            options: &[
+                DayOption { name: "batch", kind: OptionKind::Flag, help: "in part 1, paint from every starting panel colour and direction and compare them" },
+            ],
+            q1: |fname, options| if options.flag("batch") { day_11::batch(fname).to_string() } else { day_11::q1(fname).to_string() }),
+        day!(12, day_12, "The N-Body Problem", [Math],
+            options: &[
+                DayOption { name: "steps", kind: OptionKind::Number, help: "time steps to simulate in part 1 (default 1000)" },
+            ],
+            q1: |fname, options| day_12::q1_for_steps(fname, options.number("steps").unwrap_or(1000)).to_string()),
+        day!(13, day_13, "Care Package", [Intcode, Grid],
+            visualization: true,
+            strategies: true,
+            validate: intcode,
+            options: &[
+                DayOption { name: "follow", kind: OptionKind::Flag, help: "move the paddle towards the ball instead of where it will land" },
+                DayOption { name: "animate", kind: OptionKind::Flag, help: "draw the game in the terminal as it plays" },
+                DayOption { name: "frame-skip", kind: OptionKind::Number, help: "when animating, only draw every nth frame (default 1)" },
+                DayOption { name: "record", kind: OptionKind::Text, help: "save every frame of the game to this file, for `aoc_2019 playback`" },
+            ],
+            needs: &[("animate", Feature::Viz), ("frame-skip", Feature::Viz), ("record", Feature::Viz)],
+            q2: |fname, options| {
+                let controller = if options.flag("follow") { day_13::Controller::Follow } else { day_13::Controller::Predict };
+                let frame_skip = if options.flag("animate") { Some(options.number("frame-skip").unwrap_or(1)) } else { None };
+                day_13::q2_with(fname, controller, frame_skip, options.text("record")).to_string()
+            }),
+        day!(14, day_14, "Space Stoichiometry", [Graph, Math]),
+        day!(15, day_15, "Oxygen System", [Intcode, Grid, Graph],
+            visualization: true,
+            validate: intcode,
+            options: &[
+                DayOption { name: "from", kind: OptionKind::Text, help: "in part 1, map the area and find the steps to oxygen from x,y instead" },
+                DayOption { name: "record", kind: OptionKind::Text, help: "in part 2, save the map after every move to this file, for `aoc_2019 playback`" },
+                DayOption { name: "heatmap", kind: OptionKind::Flag, help: "in part 2, draw how far each square is from the oxygen system" },
+            ],
+            needs: &[("record", Feature::Viz), ("heatmap", Feature::Viz)],
+            q1: |fname, options| match options.text("from") {
+                Some(start) => day_15::q1_from(fname, start).to_string(),
+                None => day_15::q1(fname).to_string()
+            },
+            q2: |fname, options| day_15::q2_with(fname, options.text("record"), options.flag("heatmap")).to_string(),
+            both: Some(|fname, options| match (options.text("from"), options.text("record")) {
+                (None, None) if !options.flag("heatmap") => {
+                    let (steps, minutes) = day_15::both(fname);
+                    (steps.to_string(), minutes.to_string())
+                },
+                (from, record) => {
+                    let steps = match from {
+                        Some(start) => day_15::q1_from(fname.clone(), start),
+                        None => day_15::q1(fname.clone())
+                    };
+                    (steps.to_string(), day_15::q2_with(fname, record, options.flag("heatmap")).to_string())
+                }
+            })),
+        day!(16, day_16, "Flawed Frequency Transmission", [Math],
+            options: &[
+                DayOption { name: "pattern", kind: OptionKind::Text, help: "in part 1, use this base pattern instead of 0,1,0,-1" },
+                DayOption { name: "repeat", kind: OptionKind::Number, help: "in part 1, repeat each pattern value this many times as often (default 1)" },
+            ],
+            q1: |fname, options| match (options.text("pattern"), options.number("repeat")) {
+                (None, None) => day_16::q1(fname),
+                (pattern, repeat) => day_16::q1_with(fname, pattern.unwrap_or("0,1,0,-1"), repeat.unwrap_or(1))
+            }),
+        day!(17, day_17, "Set and Forget", [Intcode, Grid], validate: intcode),
+        day!(18, day_18, "Many-Worlds Interpretation", [Grid, Graph],
+            options: &[
+                DayOption { name: "verify", kind: OptionKind::Flag, help: "in part 1, walk the vault in the key order found to check the answer" },
+                DayOption { name: "heatmap", kind: OptionKind::Flag, help: "in part 1, draw the steps between every pair of keys" },
+            ],
+            needs: &[("heatmap", Feature::Viz)],
+            q1: |fname, options| day_18::q1_with(fname, options.flag("verify"), options.flag("heatmap")).to_string()),
+        day!(19, day_19, "Tractor Beam", [Intcode, Grid], validate: intcode),
+        day!(20, day_20, "Donut Maze", [Grid, Graph],
+            options: &[
+                DayOption { name: "dot", kind: OptionKind::Text, help: "in part 1, also write the graph of portals to this file in Graphviz DOT format" },
+                DayOption { name: "heatmap", kind: OptionKind::Flag, help: "in part 2, draw the steps from AA to every tile of the outermost level" },
+            ],
+            needs: &[("heatmap", Feature::Viz)],
+            q1: |fname, options| day_20::q1_with(fname, options.text("dot")).to_string(),
+            q2: |fname, options| day_20::q2_with(fname, options.flag("heatmap")).to_string()),
+        day!(21, day_21, "Springdroid Adventure", [Intcode],
+            validate: intcode,
+            options: &[
+                DayOption { name: "traces", kind: OptionKind::Text, help: "write where short springscripts fail to this file instead of solving" },
+            ],
+            q1: |fname, options| match options.text("traces") {
+                Some(out) => day_21::traces(fname, 1, out).to_string(),
+                None => day_21::q1(fname).to_string()
+            },
+            q2: |fname, options| match options.text("traces") {
+                Some(out) => day_21::traces(fname, 2, out).to_string(),
+                None => day_21::q2(fname).to_string()
+            }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_is_ordered_and_described() {
+        let days = registry();
+
+        assert!(days.windows(2).all(|pair| pair[0].number < pair[1].number));
+        assert!(days.iter().all(|day| !day.title.is_empty() && !day.tags.is_empty()));
+        assert!(days.iter().filter(|day| day.tags.contains(&Tag::Intcode)).all(|day| day.validate.is_some()));
+        assert!(days.iter().all(|day| day.needs.iter().all(|&(name, _)| day.options.iter().any(|option| option.name == name))));
+    }
+}
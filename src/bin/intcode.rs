@@ -0,0 +1,231 @@
+extern crate aoc_2019;
+
+use std::env;
+use std::fs;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::result;
+
+use aoc_2019::aoc_problems::intcode::{self, Memory, Program, RunState, Tracer};
+use aoc_2019::options::{DayOption, OptionKind, Options};
+use aoc_2019::util::input;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+const USAGE: &str = "Usage: intcode <file> [--ascii]
+
+Runs an Intcode program, reading its inputs from standard input one per line and
+printing its outputs as they come. Lines starting with : are commands:
+    :dump [addr] [count]  show memory, the whole program by default
+    :write <file>         save every cell of memory which isn't zero to a file
+    :diff <file>          show the addresses which have changed since that file was written
+    :reset                start the program again from the file
+    :trace                log each instruction to standard error, or stop logging
+    :help                 show this
+    :quit                 stop";
+
+const OPTIONS: &[DayOption] = &[
+    DayOption { name: "ascii", kind: OptionKind::Flag, help: "read inputs as lines of text and print outputs below 128 as characters" },
+];
+
+// Cells shown on each line of a dump
+const DUMP_WIDTH: usize = 10;
+
+// One program being run from the terminal
+struct Session {
+    fresh: Program,
+    program: Program,
+    // How many cells the file held, which is what a plain :dump shows
+    length: usize,
+    ascii: bool,
+    tracing: bool
+}
+
+impl Session {
+    fn new(source: &str, ascii: bool) -> Result<Session> {
+        input::validate_intcode(source)?;
+        let program = Program::parse(source)?;
+        Ok(Session {
+            fresh: program.clone(),
+            program,
+            length: source.trim().split(',').count(),
+            ascii,
+            tracing: false
+        })
+    }
+
+    // Runs until the program wants input it hasn't got or halts, showing what it output
+    fn run(&mut self) -> Result<String> {
+        let mut shown = String::new();
+        loop {
+            match self.program.run_program()? {
+                RunState::Output(value) if self.ascii && (0..128).contains(&value) => shown.push(value as u8 as char),
+                RunState::Output(value) => {
+                    if !shown.is_empty() && !shown.ends_with('\n') {
+                        shown.push('\n');
+                    }
+                    shown.push_str(&format!("{}\n", value));
+                },
+                RunState::NeedsInput => break,
+                RunState::Halted => {
+                    shown.push_str(&format!("Halted after {} instructions\n", self.program.instructions()));
+                    break;
+                }
+            }
+        }
+
+        Ok(shown)
+    }
+
+    // Handles one line typed at the terminal, returning what to show, or None to stop
+    fn handle(&mut self, line: &str) -> Result<Option<String>> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |n: usize| -> Result<usize> {
+            words[n].parse().or(err!("Cannot read {:?} as an address or count", words[n]))
+        };
+
+        let shown = match words.as_slice() {
+            [":quit"] => return Ok(None),
+            [":help"] => format!("{}\n", USAGE),
+            [":reset"] => {
+                self.program = self.fresh.clone();
+                format!("Reset\n{}", self.run()?)
+            },
+            [":trace"] => {
+                self.tracing = !self.tracing;
+                if self.tracing {
+                    intcode::attach_tracer(Tracer::Writer(Box::new(io::stderr())));
+                    "Tracing to standard error\n".to_string()
+                } else {
+                    intcode::detach_tracer()?;
+                    "Stopped tracing\n".to_string()
+                }
+            },
+            [":dump"] => self.dump(0, self.length)?,
+            [":dump", _] => self.dump(number(1)?, DUMP_WIDTH)?,
+            [":dump", _, _] => self.dump(number(1)?, number(2)?)?,
+            [":write", file] => {
+                fs::write(file, self.program.memory().dump())?;
+                format!("Wrote memory to {}\n", file)
+            },
+            [":diff", file] => self.diff(&Memory::parse_dump(&input::read_file(file)?)?),
+            [command, ..] if command.starts_with(':') => return err!("Unknown command {}, try :help", command),
+            _ if self.program.is_halted() => "The program has halted, :reset to run it again\n".to_string(),
+            _ if self.ascii => {
+                self.program.add_line(line);
+                self.run()?
+            },
+            _ => {
+                for word in line.split(',').map(|word| word.trim()).filter(|word| !word.is_empty()) {
+                    let value = word.parse().or(err!("Cannot read {:?} as an input, expected a number", word))?;
+                    self.program.add_input(value);
+                }
+                self.run()?
+            }
+        };
+
+        Ok(Some(shown))
+    }
+
+    fn dump(&self, start: usize, count: usize) -> Result<String> {
+        let end = match start.checked_add(count) {
+            Some(end) => end,
+            None => return err!("Cannot dump {} values from address {}, that runs past the last address", count, start),
+        };
+
+        let mut lines = String::new();
+        for row in (start..end).step_by(DUMP_WIDTH) {
+            let cells: Vec<String> = (row..row.saturating_add(DUMP_WIDTH).min(end))
+                .map(|idx| self.program.memory().get(idx).to_string())
+                .collect();
+            lines.push_str(&format!("{:>6}: {}\n", row, cells.join(",")));
+        }
+
+        Ok(lines)
+    }
+
+    // Each address whose value has changed since `dumped`
+    fn diff(&self, dumped: &Memory) -> String {
+        let changes = dumped.changes(self.program.memory());
+        if changes.is_empty() {
+            return "No changes\n".to_string();
+        }
+
+        changes.iter().map(|(address, before, after)| format!("{:>6}: {} -> {}\n", address, before, after)).collect()
+    }
+}
+
+fn repl(file: &str, ascii: bool) -> Result<()> {
+    let mut session = Session::new(&input::read_file(file)?, ascii)?;
+    print!("{}", session.run()?);
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        match session.handle(line?.trim_end()) {
+            Ok(Some(shown)) => print!("{}", shown),
+            Ok(None) => break,
+            Err(e) => println!("{}", e)
+        }
+        io::stdout().flush()?;
+    }
+
+    if session.tracing {
+        intcode::detach_tracer()?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((file, rest)) if !file.starts_with("--") => {
+            Options::parse(OPTIONS, rest).and_then(|options| repl(file, options.flag("ascii")))
+        },
+        _ => err!("{}\n{}", USAGE, Options::help(OPTIONS))
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intcode_repl_runs_commands() {
+        // Adds up pairs of inputs for ever
+        let mut session = Session::new("3,100,3,101,1,100,101,102,4,102,1105,1,0", false).unwrap();
+        assert_eq!(session.run().unwrap(), "");
+        assert_eq!(session.handle("2, 3").unwrap().unwrap(), "5\n");
+        assert_eq!(session.handle("10").unwrap().unwrap(), "");
+        assert_eq!(session.handle("-4").unwrap().unwrap(), "6\n");
+        assert_eq!(session.handle(":dump 100 3").unwrap().unwrap(), "   100: 10,-4,6\n");
+        assert_eq!(session.handle(":dump").unwrap().unwrap(), "     0: 3,100,3,101,1,100,101,102,4,102\n    10: 1105,1,0\n");
+        let dumped = Memory::parse_dump(&session.program.memory().dump()).unwrap();
+        assert_eq!(session.diff(&dumped), "No changes\n");
+        assert_eq!(session.handle("1, 1").unwrap().unwrap(), "2\n");
+        assert_eq!(session.diff(&dumped), "   100: 10 -> 1\n   101: -4 -> 1\n   102: 6 -> 2\n");
+        assert!(session.handle("x").is_err());
+        assert!(session.handle(":frobnicate").is_err());
+        assert!(session.handle(&format!(":dump {} 2", usize::MAX)).is_err());
+        assert!(session.handle(&format!(":dump {}", usize::MAX - 3)).is_err());
+
+        assert_eq!(session.handle(":reset").unwrap().unwrap(), "Reset\n");
+        assert_eq!(session.handle(":dump 100").unwrap().unwrap(), "   100: 0,0,0,0,0,0,0,0,0,0\n");
+        assert_eq!(session.handle(":quit").unwrap(), None);
+
+        // Echoes a line of text back, then halts
+        let mut session = Session::new("3,20,4,20,1008,20,10,21,1006,21,0,99", true).unwrap();
+        assert_eq!(session.handle("hi").unwrap().unwrap(), "hi\nHalted after 13 instructions\n");
+        assert_eq!(session.handle("more").unwrap().unwrap(), "The program has halted, :reset to run it again\n");
+    }
+}
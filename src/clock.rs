@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::result;
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// How often the timeout guard looks to see whether the solver has finished
+const POLL: Duration = Duration::from_millis(10);
+
+// Where time comes from for anything that waits, so that tests can drive it with a
+// `MockClock` instead of sleeping
+pub trait Clock {
+    // Time since some fixed point, which only has meaning compared with other readings
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+}
+
+// The real time
+pub struct SystemClock {
+    start: Instant
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// A clock for tests, which only moves when something sleeps on it or it's advanced by
+// hand. Clones share the same time.
+#[cfg(test)]
+#[derive(Clone, Debug, Default)]
+pub struct MockClock {
+    now: Arc<Mutex<Duration>>
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new() -> MockClock {
+        MockClock::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+// Runs `solve` on its own thread, giving up with an error if it hasn't finished within
+// `limit`. A solver which has been given up on carries on in the background until the
+// process exits, as threads can't be stopped from outside.
+pub fn run_with_timeout<T, F>(clock: &dyn Clock, limit: Duration, solve: F) -> Result<T>
+    where T: Send + 'static, F: FnOnce() -> T + Send + 'static
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // Nobody is listening any more if the guard has already given up
+        let _ = sender.send(solve());
+    });
+
+    let start = clock.now();
+    loop {
+        match receiver.try_recv() {
+            Ok(result) => return Ok(result),
+            Err(TryRecvError::Disconnected) => return err!("Solver panicked"),
+            Err(TryRecvError::Empty) if clock.now() - start >= limit => {
+                return err!("Solver didn't finish within {:?}", limit);
+            },
+            Err(TryRecvError::Empty) => clock.sleep(POLL)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_times_out_solvers() {
+        // Each check the guard makes costs no real time on a mock clock, so a solver which
+        // does finish is given long enough for its thread to start
+        let hour = Duration::from_secs(3600);
+        assert_eq!(run_with_timeout(&MockClock::new(), hour, || 42).unwrap(), 42);
+        assert!(run_with_timeout(&MockClock::new(), hour, || -> usize { panic!("no answer") }).is_err());
+
+        // The mock clock runs out the limit without waiting for it, while the solver
+        // never finishes
+        let clock = MockClock::new();
+        let error = run_with_timeout(&clock, Duration::from_secs(5), || loop { thread::park(); }).err().unwrap();
+        assert_eq!(error.to_string(), "Solver didn't finish within 5s");
+        assert!(clock.now() >= Duration::from_secs(5));
+    }
+}
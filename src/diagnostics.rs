@@ -0,0 +1,175 @@
+use std::error::Error;
+use std::result;
+
+use aoc_problems::intcode::{Compliance, VmBuilder};
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// One of the puzzle programs which test an Intcode computer, run with one input
+pub struct Check {
+    pub day: usize,
+    pub name: &'static str,
+    // What the program shows the computer can do
+    pub features: &'static str,
+    // Cells set before running, as day 2 sets its noun and verb
+    patch: &'static [(usize, i64)],
+    inputs: &'static [i64],
+    judge: fn(i64, &[i64]) -> Result<String>
+}
+
+pub const CHECKS: &[Check] = &[
+    Check {
+        day: 2,
+        name: "gravity assist",
+        features: "add, multiply, halt, position parameters",
+        patch: &[(1, 12), (2, 2)],
+        inputs: &[],
+        judge: |first_cell, _| Ok(format!("position 0 = {}", first_cell))
+    },
+    Check {
+        day: 5,
+        name: "TEST air conditioner",
+        features: "input, output, immediate parameters",
+        patch: &[],
+        inputs: &[1],
+        judge: diagnostic_code
+    },
+    Check {
+        day: 5,
+        name: "TEST thermal radiator",
+        features: "jumps and comparisons",
+        patch: &[],
+        inputs: &[5],
+        judge: single_output
+    },
+    Check {
+        day: 9,
+        name: "BOOST test mode",
+        features: "relative parameters, large numbers, memory past the program",
+        patch: &[],
+        inputs: &[1],
+        // BOOST outputs the opcodes it found broken before its keycode
+        judge: single_output
+    },
+    Check {
+        day: 9,
+        name: "BOOST sensor mode",
+        features: "running for a while",
+        patch: &[],
+        inputs: &[2],
+        judge: single_output
+    },
+];
+
+// The modes every check is run in, see `Compliance`
+pub const MODES: &[Compliance] = &[Compliance::Strict, Compliance::Lenient];
+
+// TEST outputs 0 for each check it passes, then a diagnostic code
+fn diagnostic_code(_: i64, outputs: &[i64]) -> Result<String> {
+    match outputs.split_last() {
+        Some((&code, checks)) if checks.iter().all(|&check| check == 0) => {
+            Ok(format!("{} checks passed, diagnostic code {}", checks.len(), code))
+        },
+        Some(_) => err!("Failed checks, outputs {:?}", outputs),
+        None => err!("No diagnostic code")
+    }
+}
+
+fn single_output(_: i64, outputs: &[i64]) -> Result<String> {
+    match outputs {
+        &[output] => Ok(format!("output {}", output)),
+        _ => err!("Expected one output, got {:?}", outputs)
+    }
+}
+
+impl Check {
+    // Runs the check on `memory` on the shared VM, saying what it found if it passed
+    pub fn run(&self, memory: &[i64], compliance: Compliance) -> Result<String> {
+        let mut memory = memory.to_vec();
+        for &(idx, value) in self.patch {
+            if idx >= memory.len() {
+                return err!("Program is too short to set position {}", idx);
+            }
+            memory[idx] = value;
+        }
+
+        let mut program = VmBuilder::new(memory).compliance(compliance).build()?;
+        program.add_inputs(self.inputs.iter().cloned());
+        let outputs = program.run_to_halt()?;
+
+        (self.judge)(program.memory().get(0), &outputs)
+    }
+}
+
+// The result of one check in one mode
+pub struct Outcome {
+    pub day: usize,
+    pub name: &'static str,
+    pub features: &'static str,
+    pub compliance: Compliance,
+    pub result: Result<String>
+}
+
+pub fn report(outcomes: &[Outcome]) -> String {
+    let mut lines = vec![format!("{:>3}  {:<22}  {:<8}  {:<6}  Details", "Day", "Program", "Mode", "Result")];
+    for outcome in outcomes {
+        let (result, details) = match &outcome.result {
+            Ok(found) => ("pass", format!("{} ({})", found, outcome.features)),
+            Err(e) => ("FAIL", e.to_string())
+        };
+        lines.push(format!(
+            "{:>3}  {:<22}  {:<8}  {:<6}  {}",
+            outcome.day,
+            outcome.name,
+            format!("{:?}", outcome.compliance).to_lowercase(),
+            result,
+            details
+        ));
+    }
+
+    let failed = outcomes.iter().filter(|outcome| outcome.result.is_err()).count();
+    lines.push(match failed {
+        0 => format!("All {} checks passed", outcomes.len()),
+        n => format!("{} of {} checks failed", n, outcomes.len())
+    });
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_problems::intcode_asm::assemble;
+
+    #[test]
+    fn diagnostics_judge_programs() {
+        // Passes two checks and reports a code for any input
+        let test = assemble("
+                in [100]
+                out 0
+                out 0
+                out [100]
+                halt
+        ").unwrap();
+        assert_eq!(CHECKS[1].run(&test, Compliance::Strict).unwrap(), "2 checks passed, diagnostic code 1");
+        assert_eq!(CHECKS[2].run(&test, Compliance::Lenient).unwrap_err().to_string(), "Expected one output, got [0, 0, 5]");
+
+        // Writing to an immediate parameter only runs in lenient mode
+        let immediate_write = vec![11101, 0, 0, 0, 99];
+        assert!(CHECKS[0].run(&immediate_write, Compliance::Strict).is_err());
+        assert_eq!(CHECKS[0].run(&immediate_write, Compliance::Lenient).unwrap(), "position 0 = 14");
+
+        let outcomes = vec![
+            Outcome { day: 5, name: "TEST", features: "input", compliance: Compliance::Strict, result: Ok("output 1".to_string()) },
+            Outcome { day: 9, name: "BOOST", features: "relative", compliance: Compliance::Lenient, result: err!("broken") },
+        ];
+        let report = report(&outcomes);
+        assert!(report.contains("  9  BOOST                   lenient   FAIL    broken"));
+        assert!(report.ends_with("1 of 2 checks failed"));
+    }
+}
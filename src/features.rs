@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::fmt;
+use std::result;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// Capabilities which can be left out of a build with `--no-default-features`. Whatever
+// needs one that's missing stops with an error saying how to get it back, rather than the
+// build failing or the solver panicking part way through.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Feature {
+    // Animations, recordings, playback and heatmaps in the terminal
+    Viz,
+    // Serving recordings to the browser
+    Http
+}
+
+pub const FEATURES: &[Feature] = &[Feature::Viz, Feature::Http];
+
+impl Feature {
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Viz => "viz",
+            Feature::Http => "http"
+        }
+    }
+
+    pub fn enabled(self) -> bool {
+        match self {
+            Feature::Viz => cfg!(feature = "viz"),
+            Feature::Http => cfg!(feature = "http")
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Fails unless `feature` was built in, saying that `what` needs it
+pub fn require(feature: Feature, what: &str) -> Result<()> {
+    if feature.enabled() {
+        Ok(())
+    } else {
+        err!("{} needs the {} feature, rebuild with --features {}", what, feature, feature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features_explain_what_is_missing() {
+        for &feature in FEATURES {
+            match require(feature, "--heatmap") {
+                Ok(()) => assert!(feature.enabled()),
+                Err(e) => assert_eq!(e.to_string(), format!("--heatmap needs the {} feature, rebuild with --features {}", feature, feature))
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::result;
+
+use prelude::*;
+use util::rng::Rng;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// Days with an input generator, with the meaning and default of `--size` for each
+pub const GENERATORS: &[(usize, &str, usize)] = &[
+    (10, "width and height of the asteroid field", 100),
+    (12, "number of moons", 16),
+    (14, "number of chemicals in the chain of reactions", 40),
+    (16, "number of digits in the signal", 6500),
+    (18, "width and height of the vault", 161),
+];
+
+// Builds a synthetic puzzle input, larger than the official ones when `size` allows,
+// for profiling the solvers. The same seed always gives the same input.
+pub fn generate(day: usize, size: Option<usize>, seed: u64) -> Result<String> {
+    let default_size = match GENERATORS.iter().find(|&&(number, _, _)| number == day) {
+        Some(&(_, _, default_size)) => default_size,
+        None => return err!("No input generator for day {}", day)
+    };
+
+    let size = size.unwrap_or(default_size);
+    if size == 0 {
+        return err!("Size must be at least 1");
+    }
+
+    let mut rng = Rng::new(seed);
+    Ok(match day {
+        10 => asteroid_field(&mut rng, size),
+        12 => moons(&mut rng, size),
+        14 => reactions(&mut rng, size),
+        16 => signal(&mut rng, size),
+        18 => vault(&mut rng, size)?,
+        _ => unreachable!()
+    })
+}
+
+// xorshift64*, which is plenty for making up puzzle inputs
+// Days whose inputs can be disguised, with what's changed
+pub const ANONYMIZERS: &[(usize, &str)] = &[
+    (6, "renames every object but COM, YOU and SAN and shuffles the orbits"),
+    (10, "mirrors the asteroid field left to right, which keeps part 1's answer but not part 2's"),
+    (14, "renames every chemical but ORE and FUEL and shuffles the reactions and their inputs"),
+];
+
+// Disguises a puzzle input so that it can be shared, say to show a solver failing,
+// without posting the input itself. The answers stay the same, except where
+// `ANONYMIZERS` says otherwise. The same seed always disguises an input the same way.
+pub fn anonymize(day: usize, input: &str, seed: u64) -> Result<String> {
+    let mut rng = Rng::new(seed);
+    match day {
+        6 => orbits(&mut rng, input),
+        10 => Ok(Grid2D::from_lines(input.trim())?.flip_horizontal().to_string()),
+        14 => chemicals(&mut rng, input),
+        _ => err!("Cannot anonymize inputs for day {}", day)
+    }
+}
+
+// A name no other object or chemical has, from `alphabet`
+fn fresh_name(rng: &mut Rng, alphabet: &[u8], length: usize, taken: &mut HashSet<String>) -> String {
+    loop {
+        let name: String = (0..length).map(|_| alphabet[rng.below(alphabet.len())] as char).collect();
+        if taken.insert(name.clone()) {
+            return name;
+        }
+    }
+}
+
+fn orbits(rng: &mut Rng, input: &str) -> Result<String> {
+    const KEPT: &[&str] = &["COM", "YOU", "SAN"];
+    let mut taken: HashSet<String> = KEPT.iter().map(|name| name.to_string()).collect();
+    let mut names: HashMap<String, String> = KEPT.iter().map(|name| (name.to_string(), name.to_string())).collect();
+
+    let mut lines = vec![];
+    for line in input.trim().lines() {
+        let (centre, orbiter) = line.trim().split_once(')').ok_or(format!("Cannot read orbit {:?}", line))?;
+        let mut rename = |name: &str| names.entry(name.to_string())
+            .or_insert_with(|| fresh_name(rng, b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", 3, &mut taken))
+            .clone();
+        let orbit = format!("{}){}", rename(centre), rename(orbiter));
+        lines.push(orbit);
+    }
+    rng.shuffle(&mut lines);
+
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+fn chemicals(rng: &mut Rng, input: &str) -> Result<String> {
+    const KEPT: &[&str] = &["ORE", "FUEL"];
+    let mut taken: HashSet<String> = KEPT.iter().map(|name| name.to_string()).collect();
+    let mut names: HashMap<String, String> = KEPT.iter().map(|name| (name.to_string(), name.to_string())).collect();
+
+    let mut lines = vec![];
+    for line in input.trim().lines() {
+        let (inputs, output) = line.trim().split_once(" => ").ok_or(format!("Cannot read reaction {:?}", line))?;
+        let mut rename = |material: &str| -> Result<String> {
+            let (amount, chemical) = material.trim().split_once(' ').ok_or(format!("Cannot read {:?} in {:?}", material, line))?;
+            let name = names.entry(chemical.to_string())
+                .or_insert_with(|| {
+                    let length = 1 + rng.below(5);
+                    fresh_name(rng, b"ABCDEFGHIJKLMNOPQRSTUVWXYZ", length, &mut taken)
+                })
+                .clone();
+            Ok(format!("{} {}", amount, name))
+        };
+
+        let mut inputs: Vec<String> = inputs.split(", ").map(&mut rename).collect::<Result<_>>()?;
+        let output = rename(output)?;
+        rng.shuffle(&mut inputs);
+        lines.push(format!("{} => {}", inputs.join(", "), output));
+    }
+    rng.shuffle(&mut lines);
+
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+fn asteroid_field(rng: &mut Rng, size: usize) -> String {
+    let mut field = Grid2D::new(size, size, '.');
+    for pt in field.points().collect::<Vec<_>>() {
+        if rng.below(4) == 0 {
+            field[pt] = '#';
+        }
+    }
+
+    field.to_string()
+}
+
+fn moons(rng: &mut Rng, count: usize) -> String {
+    let mut coordinate = || rng.below(41) as i32 - 20;
+
+    (0..count)
+        .map(|_| format!("<x={}, y={}, z={}>\n", coordinate(), coordinate(), coordinate()))
+        .collect()
+}
+
+// A chain of reactions where each chemical is made from the one before, and sometimes
+// another earlier chemical or ore, so the ore needed grows about exponentially with the
+// length of the chain. Long chains need more than 64 bits to count.
+fn reactions(rng: &mut Rng, length: usize) -> String {
+    // Four letters starting with X can't be ORE or FUEL
+    let name = |idx: usize| -> String {
+        let letters: String = (0..3).rev().map(|place| (b'A' + (idx / 26usize.pow(place) % 26) as u8) as char).collect();
+        format!("X{}", letters)
+    };
+
+    let mut lines = vec![format!("{} ORE => {} {}", 1 + rng.below(9), 1 + rng.below(3), name(0))];
+    for idx in 1..length {
+        let mut inputs = vec![format!("{} {}", 2 + rng.below(4), name(idx - 1))];
+        match rng.below(3) {
+            0 if idx > 1 => inputs.push(format!("{} {}", 1 + rng.below(3), name(rng.below(idx - 1)))),
+            1 => inputs.push(format!("{} ORE", 1 + rng.below(9))),
+            _ => {}
+        }
+        lines.push(format!("{} => {} {}", inputs.join(", "), 1 + rng.below(3), name(idx)));
+    }
+    lines.push(format!("1 {} => 1 FUEL", name(length - 1)));
+    rng.shuffle(&mut lines);
+
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+fn signal(rng: &mut Rng, length: usize) -> String {
+    let digits: String = (0..length).map(|_| (b'0' + rng.below(10) as u8) as char).collect();
+    format!("{}\n", digits)
+}
+
+// A perfect maze with the entrance in the middle, keys in dead ends and doors placed so
+// that every key can be reached using only the keys collected before it
+fn vault(rng: &mut Rng, size: usize) -> Result<String> {
+    Ok(vault_with_doors(rng, size)?.0.to_string())
+}
+
+// A door's cell and letter
+type Door = (Point2, char);
+
+// The vault, and where each door went
+fn vault_with_doors(rng: &mut Rng, size: usize) -> Result<(Grid2D<char>, Vec<Door>)> {
+    let size = size | 1;
+    if size < 5 {
+        return err!("A vault needs to be at least 5 wide");
+    }
+
+    let mut vault = Grid2D::new(size, size, '#');
+    let half = (size / 2) as i64;
+    let entrance = Point2::new(half | 1, half | 1);
+
+    // Carve the maze with a depth first search over the odd coordinates, recording the
+    // route back towards the entrance as we go
+    let mut parents: HashMap<Point2, Point2> = HashMap::new();
+    let mut stack = vec![entrance];
+    vault[entrance] = '.';
+    while let Some(&current) = stack.last() {
+        let options: Vec<Point2> = [(0, -2), (-2, 0), (2, 0), (0, 2)].iter()
+            .map(|&(dx, dy)| current + Point2::new(dx, dy))
+            .filter(|&pt| pt.x > 0 && pt.y > 0 && pt.x < size as i64 - 1 && pt.y < size as i64 - 1)
+            .filter(|&pt| vault[pt] == '#')
+            .collect();
+
+        if options.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let next = options[rng.below(options.len())];
+        let between = Point2::new((current.x + next.x) / 2, (current.y + next.y) / 2);
+        vault[between] = '.';
+        vault[next] = '.';
+        parents.insert(between, current);
+        parents.insert(next, between);
+        stack.push(next);
+    }
+
+    let route = |mut pt: Point2| {
+        let mut route = vec![];
+        while let Some(&parent) = parents.get(&pt) {
+            route.push(pt);
+            pt = parent;
+        }
+        route
+    };
+
+    let mut dead_ends: Vec<Point2> = vault.points()
+        .filter(|&pt| pt != entrance && vault[pt] == '.')
+        .filter(|&pt| vault.neighbours(pt).into_iter().filter(|&n| vault[n] == '.').count() == 1)
+        .collect();
+    rng.shuffle(&mut dead_ends);
+    dead_ends.truncate(26);
+
+    let mut names: Vec<char> = (b'a'..=b'z').map(|c| c as char).collect();
+    rng.shuffle(&mut names);
+
+    // Keys are collected in the order of `dead_ends`, so the door for each key goes on
+    // the way to the next key, away from anything needed to reach the earlier ones
+    let mut needed: HashSet<Point2> = HashSet::new();
+    let mut doors = vec![];
+    for (idx, &key) in dead_ends.iter().enumerate() {
+        vault[key] = names[idx];
+        needed.extend(route(key));
+
+        // The route starts at the next key's own cell, which its key would overwrite
+        if let Some(&next_key) = dead_ends.get(idx + 1) {
+            let spots: Vec<Point2> = route(next_key).into_iter()
+                .skip(1)
+                .filter(|pt| !needed.contains(pt) && vault[*pt] == '.')
+                .collect();
+            if !spots.is_empty() {
+                let door = (spots[rng.below(spots.len())], names[idx].to_ascii_uppercase());
+                vault[door.0] = door.1;
+                doors.push(door);
+            }
+        }
+    }
+
+    vault[entrance] = '@';
+
+    Ok((vault, doors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs, process};
+
+    use aoc_problems::{day_06, day_14};
+
+    #[test]
+    fn generate_is_repeatable() {
+        assert_eq!(generate(16, Some(20), 7).unwrap(), generate(16, Some(20), 7).unwrap());
+        assert_ne!(generate(16, Some(20), 7).unwrap(), generate(16, Some(20), 8).unwrap());
+        assert_eq!(generate(12, Some(5), 1).unwrap().lines().count(), 5);
+        assert!(validate_grid(&generate(10, Some(30), 1).unwrap(), "#.").is_ok());
+        assert!(generate(20, None, 1).is_err());
+    }
+
+    #[test]
+    fn generate_anonymizes_with_the_same_answers() {
+        let dir = env::temp_dir().join(format!("aoc_2019_anonymize_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let solve = |name: &str, input: &str, solver: fn(String) -> String| {
+            let path = dir.join(name).to_str().unwrap().to_string();
+            fs::write(&path, input).unwrap();
+            solver(path)
+        };
+
+        let orbits = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN\n";
+        let disguised = anonymize(6, orbits, 1).unwrap();
+        assert!(!disguised.contains("B)C") && disguised.contains("COM)"));
+        for solver in [|path| day_06::q1(path).to_string(), |path| day_06::q2(path).to_string()] {
+            assert_eq!(solve("orbits", &disguised, solver), solve("orbits", orbits, solver));
+        }
+
+        let reactions = generate(14, Some(12), 5).unwrap();
+        let disguised = anonymize(14, &reactions, 1).unwrap();
+        assert_eq!(disguised.lines().count(), reactions.lines().count());
+        assert!(!disguised.contains("XAAA") && disguised.contains(" => 1 FUEL"));
+        let solver = |path| day_14::q1(path).to_string();
+        assert_eq!(solve("reactions", &disguised, solver), solve("reactions", &reactions, solver));
+
+        assert_eq!(anonymize(10, ".#.\n##.\n", 1).unwrap(), ".#.\n.##\n");
+        assert!(anonymize(1, "12\n", 1).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn generate_vault_keeps_its_doors() {
+        for seed in 0..50 {
+            let (vault, doors) = vault_with_doors(&mut Rng::new(seed), 21).unwrap();
+            assert!(!doors.is_empty());
+            for (pt, door) in doors {
+                assert_eq!(vault[pt], door, "seed {}: door {} at {} was overwritten", seed, door, pt);
+            }
+        }
+    }
+
+    #[test]
+    fn generate_solvable_vault() {
+        let vault = Grid2D::from_lines(generate(18, Some(41), 3).unwrap().trim()).unwrap();
+        let keys: HashSet<char> = vault.iter().map(|(_, &c)| c).filter(|c| c.is_ascii_lowercase()).collect();
+        assert!(!keys.is_empty());
+
+        // Keep flooding from the entrance, opening doors for every key reached so far
+        let entrance = vault.points().find(|&pt| vault[pt] == '@').unwrap();
+        let mut collected: HashSet<char> = HashSet::new();
+        loop {
+            let mut seen = HashSet::new();
+            let mut stack = vec![entrance];
+            while let Some(pt) = stack.pop() {
+                let c = vault[pt];
+                if c == '#' || (c.is_ascii_uppercase() && !collected.contains(&c.to_ascii_lowercase())) {
+                    continue;
+                }
+                if seen.insert(pt) {
+                    stack.extend(vault.neighbours(pt));
+                }
+            }
+
+            let reached: HashSet<char> = seen.iter().map(|&pt| vault[pt]).filter(|c| c.is_ascii_lowercase()).collect();
+            if reached.len() == collected.len() {
+                break;
+            }
+            collected = reached;
+        }
+
+        assert_eq!(collected, keys);
+    }
+}
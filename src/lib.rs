@@ -0,0 +1,27 @@
+#[macro_use]
+extern crate lazy_static;
+
+extern crate flate2;
+extern crate itertools;
+extern crate log;
+#[cfg(test)]
+extern crate proptest;
+extern crate regex;
+extern crate ruzstd;
+extern crate sha2;
+
+// Everything the command line tools share: the solvers and the runner behind
+// `aoc_2019`, and the Intcode VM behind `intcode` as well
+pub mod aoc_problems;
+pub mod clock;
+pub mod diagnostics;
+pub mod features;
+pub mod generate;
+pub mod manifest;
+pub mod options;
+pub mod prelude;
+pub mod runner;
+pub mod season;
+pub mod stats;
+pub mod util;
+pub mod viz;
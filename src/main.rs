@@ -1,17 +1,13 @@
-#[macro_use]
-extern crate lazy_static;
+extern crate aoc_2019;
 
-extern crate itertools;
-extern crate regex;
+use std::env;
+use std::process;
 
-use std::time::Instant;
-
-mod aoc_problems;
+use aoc_2019::runner;
 
 fn main() {
-    let now = Instant::now();
-    let result = aoc_problems::day_21::q2("./inputs/day21.txt".to_string());
-    let elapsed = now.elapsed();
-    println!("Answer: {:?}", result);
-    println!("Elapsed time: {:?}", elapsed);
+    if let Err(e) = runner::run(env::args().skip(1).collect()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 }
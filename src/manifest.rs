@@ -0,0 +1,301 @@
+use std::error::Error;
+use std::iter::Peekable;
+use std::result;
+use std::str::Chars;
+use std::thread;
+
+use util::input;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// Everything needed to run a day again the same way, saved as JSON with `--manifest` and
+// replayed with `aoc_2019 rerun`. The input is hashed after decompressing, so compressing
+// it doesn't count as a change.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Manifest {
+    pub day: usize,
+    // "1", "2" or "both"
+    pub part: String,
+    pub profile: Option<String>,
+    pub input: String,
+    pub input_hash: String,
+    // The day's own options, such as strategies, as they were given
+    pub options: Vec<String>,
+    // No solver is random yet, so this is only filled in by hand for now
+    pub seed: Option<usize>,
+    pub threads: usize,
+    pub version: String,
+    pub backend: String
+}
+
+impl Manifest {
+    pub fn new(day: usize, part: &str, profile: Option<&str>, input: &str, options: &[String]) -> Result<Manifest> {
+        Ok(Manifest {
+            day,
+            part: part.to_string(),
+            profile: profile.map(|profile| profile.to_string()),
+            input: input.to_string(),
+            input_hash: hash_input(input)?,
+            options: options.to_vec(),
+            seed: None,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            // Solvers always run on paged memory, only `intcode run` can choose
+            backend: "paged".to_string()
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let options: Vec<String> = self.options.iter().map(|option| quote(option)).collect();
+        let fields = [
+            ("day", self.day.to_string()),
+            ("part", quote(&self.part)),
+            ("profile", self.profile.as_ref().map_or("null".to_string(), |profile| quote(profile))),
+            ("input", quote(&self.input)),
+            ("input_hash", quote(&self.input_hash)),
+            ("options", format!("[{}]", options.join(", "))),
+            ("seed", self.seed.map_or("null".to_string(), |seed| seed.to_string())),
+            ("threads", self.threads.to_string()),
+            ("version", quote(&self.version)),
+            ("backend", quote(&self.backend)),
+        ];
+
+        let fields: Vec<String> = fields.iter().map(|(name, value)| format!("  \"{}\": {}", name, value)).collect();
+        format!("{{\n{}\n}}\n", fields.join(",\n"))
+    }
+
+    pub fn parse(json: &str) -> Result<Manifest> {
+        let fields = read_object(json)?;
+        let field = |name: &str| match fields.iter().find(|(key, _)| key == name) {
+            Some((_, value)) => Ok(value),
+            None => err!("Manifest is missing {:?}", name)
+        };
+        let text = |name: &str| match field(name)? {
+            Value::Text(s) => Ok(s.clone()),
+            _ => err!("Manifest {:?} should be a string", name)
+        };
+        let number = |name: &str| match field(name)? {
+            Value::Number(n) => Ok(Some(*n)),
+            Value::Null => Ok(None),
+            _ => err!("Manifest {:?} should be a number", name)
+        };
+
+        Ok(Manifest {
+            day: number("day")?.ok_or("Manifest \"day\" should be a number")?,
+            part: text("part")?,
+            profile: match field("profile")? {
+                Value::Null => None,
+                _ => Some(text("profile")?)
+            },
+            input: text("input")?,
+            input_hash: text("input_hash")?,
+            options: match field("options")? {
+                Value::List(options) => options.clone(),
+                _ => return err!("Manifest \"options\" should be a list of strings")
+            },
+            seed: number("seed")?,
+            threads: number("threads")?.unwrap_or(1),
+            version: text("version")?,
+            backend: text("backend")?
+        })
+    }
+
+    // Ways in which running now differs from the manifest, which may explain a different
+    // answer or time
+    pub fn differences(&self) -> Result<Vec<String>> {
+        let now = Manifest::new(self.day, &self.part, self.profile.as_deref(), &self.input, &self.options)?;
+
+        let mut differences = vec![];
+        if now.input_hash != self.input_hash {
+            differences.push(format!("{} has changed (hash {}, was {})", self.input, now.input_hash, self.input_hash));
+        }
+        if now.version != self.version {
+            differences.push(format!("Running version {}, was {}", now.version, self.version));
+        }
+        if now.threads != self.threads {
+            differences.push(format!("Threads available: {}, was {}", now.threads, self.threads));
+        }
+
+        Ok(differences)
+    }
+}
+
+fn hash_input(path: &str) -> Result<String> {
+    let contents = input::read_file(path)?;
+    Ok(format!("fnv1a:{:016x}", input::fnv1a(contents.as_bytes())))
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Value {
+    Null,
+    Number(usize),
+    Text(String),
+    List(Vec<String>)
+}
+
+// Reads the JSON a manifest is saved as: one object whose values are null, whole numbers,
+// strings or lists of strings
+fn read_object(json: &str) -> Result<Vec<(String, Value)>> {
+    let mut chars = json.chars().peekable();
+    expect(&mut chars, '{')?;
+
+    let mut fields = vec![];
+    if next_token(&mut chars) == Some('}') {
+        chars.next();
+    } else {
+        loop {
+            let key = read_string(&mut chars)?;
+            expect(&mut chars, ':')?;
+            fields.push((key, read_value(&mut chars)?));
+
+            match next_token(&mut chars) {
+                Some(',') => { chars.next(); },
+                Some('}') => { chars.next(); break; },
+                x => return err!("Expected , or }} in manifest, not {:?}", x)
+            }
+        }
+    }
+
+    match next_token(&mut chars) {
+        None => Ok(fields),
+        Some(x) => err!("Unexpected {:?} after the end of the manifest", x)
+    }
+}
+
+fn read_value(chars: &mut Peekable<Chars>) -> Result<Value> {
+    match next_token(chars) {
+        Some('"') => Ok(Value::Text(read_string(chars)?)),
+        Some('[') => {
+            chars.next();
+            let mut items = vec![];
+            if next_token(chars) == Some(']') {
+                chars.next();
+                return Ok(Value::List(items));
+            }
+            loop {
+                items.push(read_string(chars)?);
+                match next_token(chars) {
+                    Some(',') => { chars.next(); },
+                    Some(']') => { chars.next(); return Ok(Value::List(items)); },
+                    x => return err!("Expected , or ] in manifest, not {:?}", x)
+                }
+            }
+        },
+        Some('n') => {
+            let word: String = chars.by_ref().take(4).collect();
+            if word == "null" {
+                Ok(Value::Null)
+            } else {
+                err!("Cannot read {:?} in manifest", word)
+            }
+        },
+        Some(c) if c.is_ascii_digit() => {
+            let mut digits = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                digits.push(c);
+                chars.next();
+            }
+            Ok(Value::Number(digits.parse()?))
+        },
+        x => err!("Cannot read a value in manifest from {:?}", x)
+    }
+}
+
+fn read_string(chars: &mut Peekable<Chars>) -> Result<String> {
+    expect(chars, '"')?;
+
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('u') => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&code, 16).ok().and_then(std::char::from_u32);
+                    s.push(c.ok_or(format!("Cannot read escape \\u{} in manifest", code))?);
+                },
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some(c) => s.push(c),
+                None => return err!("Manifest ends in the middle of a string")
+            },
+            Some(c) => s.push(c),
+            None => return err!("Manifest ends in the middle of a string")
+        }
+    }
+}
+
+// The next character which isn't whitespace, without taking it
+fn next_token(chars: &mut Peekable<Chars>) -> Option<char> {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+    chars.peek().cloned()
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<()> {
+    match next_token(chars) {
+        Some(c) if c == expected => {
+            chars.next();
+            Ok(())
+        },
+        x => err!("Expected {:?} in manifest, not {:?}", expected, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_round_trips() {
+        let manifest = Manifest {
+            day: 12,
+            part: "1".to_string(),
+            profile: Some("alt \"b\"".to_string()),
+            input: "./inputs/alt/day12.txt".to_string(),
+            input_hash: "fnv1a:0123456789abcdef".to_string(),
+            options: vec!["--steps".to_string(), "100".to_string()],
+            seed: None,
+            threads: 8,
+            version: "0.1.0".to_string(),
+            backend: "paged".to_string()
+        };
+
+        let json = manifest.to_json();
+        assert!(json.contains("\"options\": [\"--steps\", \"100\"]"));
+        assert_eq!(Manifest::parse(&json).unwrap(), manifest);
+
+        let no_options = json.replace("[\"--steps\", \"100\"]", "[ ]").replace("null", "42");
+        let parsed = Manifest::parse(&no_options).unwrap();
+        assert_eq!((parsed.options.len(), parsed.seed), (0, Some(42)));
+    }
+
+    #[test]
+    fn manifest_rejects_bad_json() {
+        assert!(Manifest::parse("").is_err());
+        assert!(Manifest::parse("{\"day\": 12}").is_err());
+        assert!(Manifest::parse("{\"day\": \"12\"").is_err());
+        assert!(read_object("{\"a\": 1} x").is_err());
+        assert_eq!(read_object("{ }").unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::result;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OptionKind {
+    Flag,
+    Number,
+    Text
+}
+
+// An extra command line option accepted by a single day, e.g. `--steps 10`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DayOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+    pub help: &'static str
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OptionValue {
+    Flag,
+    Number(usize),
+    Text(String)
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Options {
+    values: HashMap<String, OptionValue>
+}
+
+impl Options {
+    // Parses `--name [value]` pairs, only accepting the options in `specs`
+    pub fn parse(specs: &[DayOption], args: &[String]) -> Result<Options> {
+        let mut values = HashMap::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => return err!("Unexpected argument: {}", arg)
+            };
+
+            let spec = match specs.iter().find(|spec| spec.name == name) {
+                Some(spec) => spec,
+                None => return err!("Unknown option --{}\n{}", name, Options::help(specs))
+            };
+
+            let value = match spec.kind {
+                OptionKind::Flag => OptionValue::Flag,
+                kind => {
+                    let value = args.next().ok_or(format!("Option --{} needs a value", name))?;
+                    if kind == OptionKind::Number {
+                        let n = value.parse().map_err(|e| format!("Cannot read --{} {}: {}", name, value, e))?;
+                        OptionValue::Number(n)
+                    } else {
+                        OptionValue::Text(value.clone())
+                    }
+                }
+            };
+
+            values.insert(name.to_string(), value);
+        }
+
+        Ok(Options { values })
+    }
+
+    pub fn help(specs: &[DayOption]) -> String {
+        if specs.is_empty() {
+            return "This day takes no extra options".to_string();
+        }
+
+        let lines: Vec<String> = specs.iter().map(|spec| {
+            let usage = match spec.kind {
+                OptionKind::Flag => format!("--{}", spec.name),
+                OptionKind::Number => format!("--{} <n>", spec.name),
+                OptionKind::Text => format!("--{} <value>", spec.name),
+            };
+            format!("    {:<24} {}", usage, spec.help)
+        }).collect();
+
+        format!("Options:\n{}", lines.join("\n"))
+    }
+
+    // Whether the option was given at all, whatever its kind
+    pub fn is_set(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.values.get(name) == Some(&OptionValue::Flag)
+    }
+
+    pub fn number(&self, name: &str) -> Option<usize> {
+        match self.values.get(name) {
+            Some(OptionValue::Number(n)) => Some(*n),
+            _ => None
+        }
+    }
+
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.values.get(name) {
+            Some(OptionValue::Text(s)) => Some(s.as_str()),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECS: &[DayOption] = &[
+        DayOption { name: "steps", kind: OptionKind::Number, help: "" },
+        DayOption { name: "ai", kind: OptionKind::Flag, help: "" },
+        DayOption { name: "strategy", kind: OptionKind::Text, help: "" },
+    ];
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn options_parse_typed_values() {
+        let options = Options::parse(SPECS, &args("--steps 10 --ai --strategy bfs")).unwrap();
+
+        assert_eq!(options.number("steps"), Some(10));
+        assert!(options.flag("ai"));
+        assert_eq!(options.text("strategy"), Some("bfs"));
+        assert_eq!(options.number("strategy"), None);
+    }
+
+    #[test]
+    fn options_reject_bad_input() {
+        assert!(Options::parse(SPECS, &args("--minutes 10")).is_err());
+        assert!(Options::parse(SPECS, &args("--steps")).is_err());
+        assert!(Options::parse(SPECS, &args("--steps -1")).is_err());
+        assert!(Options::parse(SPECS, &args("steps")).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+// The types most solvers and tools need, so they can `use prelude::*;` rather than
+// reaching into each module
+pub use aoc_problems::intcode::{Compliance, Execution, MemoryBackend, Program, RunState, VmBuilder};
+pub use aoc_problems::{Day, Tag, Validator};
+pub use options::{DayOption, OptionKind, Options};
+pub use util::cache::Memo;
+pub use util::combinatorics::{combinations, permutations, product};
+pub use util::digits::{digits, from_digits, parse_digits};
+pub use util::geom::{clockwise_cmp, Segment};
+pub use util::grid::{astar, bounds, flood_fill, flood_fill_from, label_regions, reconstruct_path, Grid2D, Path, Point2, Rect, Region, SparseGrid, SubGrid};
+pub use util::input::{read_file, validate_grid, validate_intcode};
+pub use util::iter::{chunks_exact_mapped, take_until, windows2, windows3};
+pub use util::maze::{compress, compress_by, WeightedGraph};
@@ -0,0 +1,1533 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::panic;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::result;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use aoc_problems::{self, facts, intcode, intcode_calls, intcode_fuzz, Day, Tag};
+use aoc_problems::intcode::{Program, Tracer};
+use aoc_problems::intcode_wide::WideProgram;
+use clock::{self, SystemClock};
+use diagnostics::{self, Outcome};
+use features::FEATURES;
+use generate::{self, ANONYMIZERS, GENERATORS};
+use manifest::Manifest;
+use options::{DayOption, OptionKind, Options};
+use season::{self, Answers, Row, Verdict};
+use stats::{self, Record};
+use util::input;
+use viz;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+const USAGE: &str = "Usage:
+    aoc_2019 <day> [part] [options]   run part 1, 2 or both (the default) of a day, with any options it accepts
+    aoc_2019 --day <day> [part] ...   the same, where <day> is a number (9, 09, day09) or part of a title
+    aoc_2019 all [--show-output]      run every day, logging solver output to logs/dayNN.log
+    aoc_2019 all --wall-time          run every day with no logs or output, and show only the total time
+    aoc_2019 season [options]         download any missing inputs, run every day and check the answers
+                                      against inputs/answers.tsv, printing a Markdown report
+    aoc_2019 generate <day> [options] print a large synthetic input for profiling a day
+    aoc_2019 anonymize <day> ...      print a day's input disguised for sharing, with --seed and --file
+    aoc_2019 intcode run <file> ...   run any Intcode program, with --inputs 1,2,3 and --bench <n>
+    aoc_2019 intcode diff <a> <b>     show the cells which differ between two Intcode programs
+    aoc_2019 intcode dump-diff <a> <b> show the addresses which differ between two memory dumps
+    aoc_2019 intcode fuzz [options]   run random programs on the Intcode VM, listing any that panic
+    aoc_2019 intcode disasm <file>    disassemble an Intcode program with its routines labelled, or
+                                      --graph to show only which routines call which
+    aoc_2019 playback <file> [--fps n] replay frames saved by a day's --record option
+    aoc_2019 serve-viz <file> ...     replay them in the browser instead, with --port and --fps
+    aoc_2019 compress [--remove]      gzip the plain text inputs to save space, removing the
+                                      originals only with --remove
+    aoc_2019 list-days                list the registered days with their titles and tags
+    aoc_2019 rerun <manifest>         run a day again as recorded by --manifest
+    aoc_2019 stats [--json <file>]    median times, instructions and search states of every run of all
+    aoc_2019 diagnostics              check the shared Intcode VM still runs days 2, 5 and 9's test programs
+
+Either command also accepts:
+    --redact            replace answers, wherever they're printed, with a salted hash
+    --verbose           also show facts the solver found on the way, like day 10's station
+    --numbers <style>   show numeric answers and times as plain (default), separated or scientific
+    --profile <name>    read inputs from inputs/<name>/ instead of inputs/
+    --manifest <file>   when running a day, save what's needed to rerun it to this JSON file
+    --timeout <secs>    give up on a day which hasn't finished in this many seconds
+    --debug             step through an Intcode day or `intcode run` in the debugger, h for help
+    --hotspots          after an Intcode day or `intcode run`, show how often each opcode ran and the
+                        busiest instruction and memory addresses
+    --trace <file>      log every instruction an Intcode day or `intcode run` executes to a file, or
+                        `--trace log` to log them at trace level through the log crate to standard error";
+
+// Commands other than running a day, none of which can be recorded in a manifest
+const COMMANDS: &[&str] = &["all", "season", "compress", "list-days", "generate", "anonymize", "intcode", "playback", "serve-viz", "rerun", "stats", "diagnostics"];
+
+const LOG_DIR: &str = "logs";
+
+// Where the salt for --redact is kept, in the log directory, when it isn't given in
+// AOC_REDACT_SALT
+const REDACT_SALT_FILE: &str = "redact-salt";
+
+// Set for the child which runs a day for --redact, so that it shows its answers
+const REDACTED_CHILD: &str = "AOC_REDACTED_CHILD";
+
+const INTCODE_OPTIONS: &[DayOption] = &[
+    DayOption { name: "inputs", kind: OptionKind::Text, help: "comma separated values to give the program, in order" },
+    DayOption { name: "bench", kind: OptionKind::Number, help: "run the program this many times and report how fast it ran" },
+    DayOption { name: "stdin", kind: OptionKind::Flag, help: "read more inputs from standard input, one per line, printing outputs as they come" },
+    DayOption { name: "memory", kind: OptionKind::Text, help: "keep memory paged (default) or sparse, which suits programs writing to far out addresses" },
+    DayOption { name: "execution", kind: OptionKind::Text, help: "run the program interpreted (default) or compiled, decoding every instruction up front" },
+    DayOption { name: "save", kind: OptionKind::Text, help: "save the machine to this file when it stops, to carry on later by running the file" },
+    DayOption { name: "dump", kind: OptionKind::Text, help: "write every cell of memory which isn't zero to this file when the program stops" },
+    DayOption { name: "max-steps", kind: OptionKind::Number, help: "stop with an error after this many instructions, for programs that may loop for ever" },
+    DayOption { name: "self-modifying", kind: OptionKind::Text, help: "report or deny writes over instructions the program has already run, running it interpreted" },
+    DayOption { name: "cells", kind: OptionKind::Text, help: "run with i64 cells (default), or i128 on a plain interpreter, for programs overflowing 64 bits" },
+];
+
+const FUZZ_OPTIONS: &[DayOption] = &[
+    DayOption { name: "runs", kind: OptionKind::Number, help: "random programs to try (default 10000)" },
+    DayOption { name: "seed", kind: OptionKind::Number, help: "seed for the first program, with each run after it using the next (default 2019)" },
+];
+
+const COMPRESS_OPTIONS: &[DayOption] = &[
+    DayOption { name: "remove", kind: OptionKind::Flag, help: "delete each plain input once its copy is written" },
+];
+
+const DISASM_OPTIONS: &[DayOption] = &[
+    DayOption { name: "graph", kind: OptionKind::Flag, help: "show the routines and their calls instead of the whole listing" },
+];
+
+const SEASON_OPTIONS: &[DayOption] = &[
+    DayOption { name: "session", kind: OptionKind::Text, help: "adventofcode.com session cookie for downloading inputs, or set AOC_SESSION" },
+    DayOption { name: "report", kind: OptionKind::Text, help: "also write the Markdown report to this file" },
+];
+
+// Sent with every download, as adventofcode.com asks automated tools to say who they are
+const USER_AGENT: &str = "github.com/Quantum-Desmond/Advent-of-Code-2019 season";
+
+const PLAYBACK_OPTIONS: &[DayOption] = &[
+    DayOption { name: "fps", kind: OptionKind::Number, help: "frames to show per second (default 30)" },
+];
+
+const SERVE_VIZ_OPTIONS: &[DayOption] = &[
+    DayOption { name: "port", kind: OptionKind::Number, help: "port to serve the page on (default 8019)" },
+    DayOption { name: "fps", kind: OptionKind::Number, help: "frames to show per second (default 30)" },
+];
+
+const STATS_OPTIONS: &[DayOption] = &[
+    DayOption { name: "json", kind: OptionKind::Text, help: "also write the statistics to this file as JSON" },
+];
+
+const GENERATE_OPTIONS: &[DayOption] = &[
+    DayOption { name: "size", kind: OptionKind::Number, help: "how big an input to make, depending on the day" },
+    DayOption { name: "seed", kind: OptionKind::Number, help: "seed for the random input (default 2019)" },
+];
+
+const ANONYMIZE_OPTIONS: &[DayOption] = &[
+    DayOption { name: "seed", kind: OptionKind::Number, help: "seed for the new names and order (default 2019)" },
+    DayOption { name: "file", kind: OptionKind::Text, help: "disguise this file instead of the day's input" },
+];
+
+pub fn run(mut args: Vec<String>) -> Result<()> {
+    let given = args.clone();
+    let redact = take_flag(&mut args, "--redact");
+    let verbose = take_flag(&mut args, "--verbose");
+    let debug = take_flag(&mut args, "--debug");
+    let trace = take_value(&mut args, "--trace")?;
+    let hotspots = take_flag(&mut args, "--hotspots");
+    let profile = take_value(&mut args, "--profile")?;
+    let style = match take_value(&mut args, "--numbers")? {
+        Some(style) => NumberStyle::parse(&style)?,
+        None => NumberStyle::Plain
+    };
+    let manifest = take_value(&mut args, "--manifest")?;
+    let timeout = match take_value(&mut args, "--timeout")? {
+        Some(secs) => match secs.parse::<f64>() {
+            Ok(secs) if secs > 0.0 && secs.is_finite() => Some(Duration::from_secs_f64(secs)),
+            _ => return err!("--timeout must be a positive number of seconds, not {}", secs)
+        },
+        None => None
+    };
+    if let Some(day) = take_value(&mut args, "--day")? {
+        args.insert(0, day);
+    }
+    if manifest.is_some() && args.first().is_some_and(|arg| COMMANDS.contains(&arg.as_str())) {
+        return err!("--manifest can only record running a day");
+    }
+    if timeout.is_some() && args.first().is_some_and(|arg| arg != "rerun" && COMMANDS.contains(&arg.as_str())) {
+        return err!("--timeout can only limit running a day or `rerun`");
+    }
+    for &(used, flag) in &[(debug, "--debug"), (trace.is_some(), "--trace"), (hotspots, "--hotspots")] {
+        if used && !intcode::INSTRUMENTED {
+            return err!("{} needs a build without the lean feature", flag);
+        }
+        if used && args.first().is_some_and(|arg| arg != "intcode" && COMMANDS.contains(&arg.as_str())) {
+            return err!("{} only works on a day or `intcode run`", flag);
+        }
+    }
+    // A solver's own output can give its answer away, so a day is run in a child which
+    // shows its answers, and everything the child prints is passed on with them hidden
+    let runs_solver = args.first().is_some_and(|arg| arg == "rerun" || !COMMANDS.contains(&arg.as_str()));
+    if redact && runs_solver && env::var_os(REDACTED_CHILD).is_none() {
+        if debug {
+            return err!("--redact can't hide answers from the debugger");
+        }
+        return run_redacted(&given, style);
+    }
+    let salt = if redact { Some(redact_salt()?) } else { None };
+    let presentation = Presentation { redact: salt, verbose, style };
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("all") => match &args[1..] {
+            [] => run_all(false, &presentation, profile),
+            [flag] if flag == "--show-output" => run_all(true, &presentation, profile),
+            [flag] if flag == "--wall-time" => time_all(profile),
+            _ => err!("{}", USAGE)
+        },
+        Some("season") => {
+            let options = Options::parse(SEASON_OPTIONS, &args[1..])?;
+            let session = options.text("session").map(|session| session.to_string()).or_else(|| env::var("AOC_SESSION").ok());
+            run_season(session.as_deref(), options.text("report"), &presentation, profile)
+        },
+        Some("compress") => {
+            let options = Options::parse(COMPRESS_OPTIONS, &args[1..])?;
+            compress_inputs(profile, options.flag("remove"))
+        },
+        Some("list-days") if args.len() == 1 => {
+            list_days();
+            Ok(())
+        },
+        Some("generate") if args.len() >= 2 => {
+            let options = Options::parse(GENERATE_OPTIONS, &args[2..])?;
+            let day = select_day(&args[1])?.number;
+            let seed = options.number("seed").unwrap_or(2019) as u64;
+            print!("{}", generate::generate(day, options.number("size"), seed)?);
+            Ok(())
+        },
+        Some("generate") => {
+            let days: Vec<String> = GENERATORS.iter()
+                .map(|&(day, size, default)| format!("    {:<4} --size is the {} (default {})", day, size, default))
+                .collect();
+            err!("Usage: aoc_2019 generate <day> [--size <n>] [--seed <n>]\nDays:\n{}", days.join("\n"))
+        },
+        Some("anonymize") if args.len() >= 2 => {
+            let options = Options::parse(ANONYMIZE_OPTIONS, &args[2..])?;
+            let day = select_day(&args[1])?;
+            let file = match options.text("file") {
+                Some(file) => file.to_string(),
+                None => day.input_file(profile.as_deref())
+            };
+            let seed = options.number("seed").unwrap_or(2019) as u64;
+            print!("{}", generate::anonymize(day.number, &input::read_file(file)?, seed)?);
+            Ok(())
+        },
+        Some("anonymize") => {
+            let days: Vec<String> = ANONYMIZERS.iter().map(|&(day, what)| format!("    {:<4} {}", day, what)).collect();
+            err!("Usage: aoc_2019 anonymize <day> [--seed <n>] [--file <file>]\nDays:\n{}", days.join("\n"))
+        },
+        Some("intcode") if args.len() >= 3 && args[1] == "run" => {
+            let options = Options::parse(INTCODE_OPTIONS, &args[3..])?;
+            if debug {
+                intcode::attach_debugger(intcode::Debugger::new());
+            }
+            traced(trace.as_deref(), || profiled(hotspots, || run_intcode(&args[2], &options, style)))
+        },
+        Some("playback") if args.len() >= 2 => {
+            let options = Options::parse(PLAYBACK_OPTIONS, &args[2..])?;
+            viz::playback(&args[1], options.number("fps").unwrap_or(30))
+        },
+        Some("serve-viz") if args.len() >= 2 => {
+            let options = Options::parse(SERVE_VIZ_OPTIONS, &args[2..])?;
+            viz::serve(&args[1], options.number("port").unwrap_or(8019), options.number("fps").unwrap_or(30))
+        },
+        Some("intcode") if args.len() == 4 && args[1] == "diff" => diff_intcode(&args[2], &args[3]),
+        Some("intcode") if args.len() == 4 && args[1] == "dump-diff" => diff_dumps(&args[2], &args[3]),
+        Some("intcode") if args.len() >= 2 && args[1] == "fuzz" => {
+            let options = Options::parse(FUZZ_OPTIONS, &args[2..])?;
+            fuzz_intcode(options.number("runs").unwrap_or(10_000), options.number("seed").unwrap_or(2019) as u64)
+        },
+        Some("intcode") if args.len() >= 3 && args[1] == "disasm" => {
+            let options = Options::parse(DISASM_OPTIONS, &args[3..])?;
+            disasm_intcode(&args[2], options.flag("graph"))
+        },
+        Some("rerun") if args.len() == 2 => rerun(&args[1], &presentation, timeout),
+        Some("stats") => {
+            let options = Options::parse(STATS_OPTIONS, &args[1..])?;
+            show_stats(profile, options.text("json"))
+        },
+        Some("diagnostics") if args.len() == 1 => run_diagnostics(profile),
+        Some("intcode") => err!(
+            "Usage: aoc_2019 intcode run <file> [options]\n       aoc_2019 intcode diff <a> <b>\n       aoc_2019 intcode dump-diff <a> <b>\n       aoc_2019 intcode fuzz [options]\n       aoc_2019 intcode disasm <file> [--graph]\n{}\nFuzzing:\n{}\nDisassembling:\n{}",
+            Options::help(INTCODE_OPTIONS),
+            Options::help(FUZZ_OPTIONS),
+            Options::help(DISASM_OPTIONS)
+        ),
+        Some(day) if !day.starts_with("--") => {
+            let day = select_day(day)?;
+            // The part can be left out, in which case both are run
+            let (part, rest) = match args.get(1) {
+                Some(part) if !part.starts_with("--") => (part.as_str(), &args[2..]),
+                _ => ("both", &args[1..])
+            };
+            let parsed_part = parse_part(part)?;
+            let options = Options::parse(day.options, rest)?;
+            let input_file = day.input_file(profile.as_deref());
+            if (debug || trace.is_some() || hotspots) && !day.tags.contains(&Tag::Intcode) {
+                let what = if debug { "debug" } else if hotspots { "profile" } else { "trace" };
+                return err!("Day {} doesn't run Intcode, so there's nothing to {}", day.number, what);
+            }
+            if debug {
+                intcode::attach_debugger(intcode::Debugger::new());
+            }
+            // Recorded before running, so a solver which fails can still be rerun
+            if let Some(path) = manifest {
+                let manifest = Manifest::new(day.number, part, profile.as_deref(), &input_file, rest)?;
+                fs::write(path, manifest.to_json())?;
+            }
+            traced(trace.as_deref(), || profiled(hotspots, || run_part(day, parsed_part, input_file, &options, &presentation, timeout)))
+        },
+        _ => err!("{}", USAGE)
+    }
+}
+
+// Runs with the Intcode VM counting where it spends its time, then shows the busiest
+// opcodes and addresses
+fn profiled(enabled: bool, run: impl FnOnce() -> Result<()>) -> Result<()> {
+    if !enabled {
+        return run();
+    }
+
+    intcode::start_profiling();
+    let result = run();
+    let profile = intcode::stop_profiling();
+    result?;
+    println!("{}", profile.report(10));
+
+    Ok(())
+}
+
+// Prints log records to standard error, for `--trace log`
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        eprintln!("{} {}: {}", record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+static STDERR_LOGGER: StderrLogger = StderrLogger;
+
+// Runs with every Intcode instruction logged to `target`, which is a file or `log` to go
+// through the `log` crate to standard error
+fn traced(target: Option<&str>, run: impl FnOnce() -> Result<()>) -> Result<()> {
+    let tracer = match target {
+        None => return run(),
+        Some("log") => {
+            log::set_logger(&STDERR_LOGGER).map_err(|e| e.to_string())?;
+            log::set_max_level(log::LevelFilter::Trace);
+            Tracer::Log
+        },
+        Some(path) => Tracer::Writer(Box::new(BufWriter::new(File::create(path)?)))
+    };
+
+    intcode::attach_tracer(tracer);
+    let result = run();
+    intcode::detach_tracer()?;
+    result
+}
+
+// Removes a global flag from anywhere in the arguments, returning whether it was present
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let present = args.iter().any(|arg| arg == name);
+    args.retain(|arg| arg != name);
+    present
+}
+
+// Removes a global `--name value` pair from anywhere in the arguments
+fn take_value(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    match args.iter().position(|arg| arg == name) {
+        Some(idx) if idx + 1 < args.len() => {
+            let value = args.remove(idx + 1);
+            args.remove(idx);
+            Ok(Some(value))
+        },
+        Some(_) => err!("Option {} needs a value", name),
+        None => Ok(None)
+    }
+}
+
+fn find_day(number: usize) -> Result<Day> {
+    match aoc_problems::registry().into_iter().find(|day| day.number == number) {
+        Some(day) => Ok(day),
+        None => err!("No solution registered for day {}", number)
+    }
+}
+
+// Finds a day from a number such as `9`, `09` or `day09`, or otherwise from part of its
+// title. Titles are matched first as a substring, then as a subsequence, so `n-body` and
+// `nbody` both pick out day 12, ignoring case either way.
+fn select_day(selector: &str) -> Result<Day> {
+    let selector = selector.trim().to_lowercase();
+    let number = selector.strip_prefix("day").unwrap_or(&selector).trim_start_matches(&[' ', '-', '_'][..]);
+    if let Ok(number) = number.parse() {
+        return find_day(number);
+    }
+
+    let mut candidates: Vec<Day> = aoc_problems::registry().into_iter()
+        .filter(|day| day.title.to_lowercase().contains(selector.as_str()))
+        .collect();
+    if candidates.is_empty() {
+        candidates = aoc_problems::registry().into_iter()
+            .filter(|day| is_subsequence(&selector, &day.title.to_lowercase()))
+            .collect();
+    }
+
+    match candidates.len() {
+        0 => err!("No day matches {:?}, see `aoc_2019 list-days`", selector),
+        1 => Ok(candidates.remove(0)),
+        _ => {
+            let names: Vec<String> = candidates.iter()
+                .map(|day| format!("    {:>3}  {}", day.number, day.title))
+                .collect();
+            err!("{:?} matches more than one day:\n{}", selector, names.join("\n"))
+        }
+    }
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().filter(|c| !c.is_whitespace()).all(|c| haystack.any(|h| h == c))
+}
+
+fn list_days() {
+    println!("{:>3}  {:<36}  {:<22}  Extras", "Day", "Title", "Tags");
+    for day in aoc_problems::registry() {
+        let tags: Vec<String> = day.tags.iter().map(|tag| tag.to_string()).collect();
+
+        let mut extras = vec![];
+        if day.visualization {
+            extras.push("visualization");
+        }
+        if day.strategies {
+            extras.push("strategies");
+        }
+        if !day.options.is_empty() {
+            extras.push("options");
+        }
+        let missing: Vec<String> = day.missing_features().iter().map(|feature| format!("without {}", feature)).collect();
+        let missing = missing.join(", ");
+        if !missing.is_empty() {
+            extras.push(&missing);
+        }
+
+        let line = format!("{:>3}  {:<36}  {:<22}  {}", day.number, day.title, tags.join(", "), extras.join(", "));
+        println!("{}", line.trim_end());
+    }
+
+    let missing: Vec<&str> = FEATURES.iter().filter(|feature| !feature.enabled()).map(|feature| feature.name()).collect();
+    if !missing.is_empty() {
+        println!("Built without: {}", missing.join(", "));
+    }
+}
+
+fn compress_inputs(profile: Option<String>, remove: bool) -> Result<()> {
+    for day in aoc_problems::registry() {
+        let input_file = day.input_file(profile.as_deref());
+        if input_file.ends_with(".txt") && Path::new(&input_file).exists() {
+            println!("Compressed {}", input::compress_file(&input_file, remove)?);
+        }
+    }
+
+    Ok(())
+}
+
+// Reads `1`, `2` or `both`, with both parts given as None
+fn parse_part(part: &str) -> Result<Option<usize>> {
+    match part {
+        "1" => Ok(Some(1)),
+        "2" => Ok(Some(2)),
+        "both" => Ok(None),
+        x => err!("Part must be 1, 2 or both, not {}", x)
+    }
+}
+
+// How `run_part` shows its results
+#[derive(Clone, Debug)]
+struct Presentation {
+    // The salt to hash answers with, when they're redacted
+    redact: Option<String>,
+    verbose: bool,
+    style: NumberStyle
+}
+
+impl Presentation {
+    // Redacted answers are hashed from the plain answer, so that they're the same whatever
+    // the style
+    fn answer(&self, answer: &str) -> String {
+        match &self.redact {
+            Some(salt) => redact_answer(salt, answer),
+            None => self.style.format_answer(answer)
+        }
+    }
+}
+
+// With a timeout the solver runs on its own thread, and is given up on if it takes longer
+fn run_part(day: Day, part: Option<usize>, input_file: String, options: &Options, presentation: &Presentation, timeout: Option<Duration>) -> Result<()> {
+    day.check_features(options)?;
+    if !Path::new(&input_file).exists() {
+        return err!("Cannot find input file {}", input_file);
+    }
+
+    if let Some(validate) = day.validate {
+        if let Err(e) = validate(&input::read_file(&input_file)?) {
+            return err!("{}: {}", input_file, e);
+        }
+    }
+
+    let (q1, q2, both) = (day.q1, day.q2, day.both);
+    let solve = move |input_file: String, options: Options| match part {
+        Some(1) => vec![q1(input_file, &options)],
+        Some(_) => vec![q2(input_file, &options)],
+        // Days which can share work between the parts do so, the rest run one by one
+        None => match both {
+            Some(both) => {
+                let (answer_1, answer_2) = both(input_file, &options);
+                vec![answer_1, answer_2]
+            },
+            None => vec![q1(input_file.clone(), &options), q2(input_file, &options)]
+        }
+    };
+
+    let now = Instant::now();
+    let answers = match timeout {
+        Some(limit) => {
+            let options = options.clone();
+            // Facts are kept per thread, so the solver's are brought back with its answers
+            let (answers, found) = clock::run_with_timeout(&SystemClock::default(), limit, move || {
+                (solve(input_file, options), facts::take())
+            })?;
+            for (name, value) in found {
+                facts::record(&name, value);
+            }
+            answers
+        },
+        None => solve(input_file, options.clone())
+    };
+    match answers.as_slice() {
+        [answer] => println!("Answer: {}", presentation.answer(answer)),
+        answers => {
+            for (idx, answer) in answers.iter().enumerate() {
+                println!("Part {}: {}", idx + 1, presentation.answer(answer));
+            }
+        }
+    }
+    println!("Elapsed time: {}", presentation.style.format_elapsed(now.elapsed()));
+    // Only days running on the shared Intcode VM count their instructions
+    if day.tags.contains(&Tag::Intcode) && intcode::instructions_executed() > 0 {
+        println!("Instructions: {}", intcode::instructions_executed());
+    }
+
+    // Running both parts can find the same fact twice, which is only worth showing once
+    let facts = facts::take();
+    if presentation.verbose {
+        let mut shown = vec![];
+        for fact in facts {
+            if !shown.contains(&fact) {
+                println!("Fact: {} = {}", fact.0, fact.1);
+                shown.push(fact);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Runs a day as a manifest recorded it, first warning about anything which has changed
+// since, such as the input
+fn rerun(path: &str, presentation: &Presentation, timeout: Option<Duration>) -> Result<()> {
+    let manifest = Manifest::parse(&fs::read_to_string(path)?)?;
+    for difference in manifest.differences()? {
+        eprintln!("Warning: {}", difference);
+    }
+
+    let day = find_day(manifest.day)?;
+    let options = Options::parse(day.options, &manifest.options)?;
+    run_part(day, parse_part(&manifest.part)?, manifest.input, &options, presentation, timeout)
+}
+
+// Runs the puzzle programs which test an Intcode computer through the shared VM in each
+// compliance mode, as a smoke test that it still does everything the days needed
+fn run_diagnostics(profile: Option<String>) -> Result<()> {
+    let mut outcomes = vec![];
+    for check in diagnostics::CHECKS {
+        let input_file = find_day(check.day)?.input_file(profile.as_deref());
+        let source = input::read_file(&input_file)?;
+        if let Err(e) = input::validate_intcode(&source) {
+            return err!("{}: {}", input_file, e);
+        }
+        let memory: Vec<i64> = source.trim().split(',').map(|n| n.trim().parse()).collect::<result::Result<_, _>>()?;
+
+        for &compliance in diagnostics::MODES {
+            outcomes.push(Outcome {
+                day: check.day,
+                name: check.name,
+                features: check.features,
+                compliance,
+                result: check.run(&memory, compliance)
+            });
+        }
+    }
+
+    println!("{}", diagnostics::report(&outcomes));
+    if outcomes.iter().any(|outcome| outcome.result.is_err()) {
+        return err!("The Intcode VM failed diagnostics");
+    }
+
+    Ok(())
+}
+
+// Shows where two Intcode programs differ, such as two people's puzzle inputs
+fn diff_intcode(a: &str, b: &str) -> Result<()> {
+    let mut programs = vec![];
+    for file in &[a, b] {
+        let source = input::read_file(file)?;
+        if let Err(e) = input::validate_intcode(&source) {
+            return err!("{}: {}", file, e);
+        }
+        let memory: Vec<i64> = source.trim().split(',').map(|n| n.trim().parse()).collect::<result::Result<_, _>>()?;
+        programs.push(memory);
+    }
+
+    let report = intcode::diff(&programs[0], &programs[1]);
+    if report.is_empty() {
+        println!("{} and {} are the same program", a, b);
+    } else {
+        println!("--- {}\n+++ {}", a, b);
+        for line in report {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
+
+// Lists the addresses whose values differ between two dumps saved by `intcode run --dump`,
+// such as one taken a move apart from the other to find where a game keeps its state
+fn diff_dumps(a: &str, b: &str) -> Result<()> {
+    let mut dumps = vec![];
+    for file in &[a, b] {
+        match intcode::Memory::parse_dump(&input::read_file(file)?) {
+            Ok(memory) => dumps.push(memory),
+            Err(e) => return err!("{}: {}", file, e)
+        }
+    }
+
+    let changes = dumps[0].changes(&dumps[1]);
+    if changes.is_empty() {
+        println!("{} and {} hold the same memory", a, b);
+    } else {
+        println!("--- {}\n+++ {}", a, b);
+        for (address, before, after) in changes {
+            println!("{:>8}: {} -> {}", address, before, after);
+        }
+    }
+
+    Ok(())
+}
+
+// Prints the disassembly of an Intcode program split into the routines it calls, or just
+// the call graph between them
+fn disasm_intcode(file: &str, graph_only: bool) -> Result<()> {
+    let source = input::read_file(file)?;
+    if let Err(e) = input::validate_intcode(&source) {
+        return err!("{}: {}", file, e);
+    }
+    let memory: Vec<i64> = source.trim().split(',').map(|n| n.trim().parse()).collect::<result::Result<_, _>>()?;
+
+    let graph = intcode_calls::call_graph(&memory);
+    if graph_only {
+        println!("{}", graph.report());
+    } else {
+        for line in graph.listing(&memory) {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
+
+// Runs random programs on the VM, listing any which made it panic rather than return an
+// error. The panics themselves are kept quiet, as there could be thousands.
+fn fuzz_intcode(runs: usize, seed: u64) -> Result<()> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let crashes = intcode_fuzz::fuzz(runs, seed);
+    panic::set_hook(hook);
+
+    for crash in &crashes {
+        println!("{}", crash);
+    }
+    match crashes.len() {
+        0 => {
+            println!("No panics in {} programs", runs);
+            Ok(())
+        },
+        n => err!("{} runs panicked", n)
+    }
+}
+
+// Runs an Intcode program with 128 bit cells on the plain interpreter in `intcode_wide`
+fn run_wide_intcode(file: &str, options: &Options, style: NumberStyle) -> Result<()> {
+    if let Some(option) = ["bench", "stdin", "memory", "execution", "save", "dump", "max-steps", "self-modifying"].iter().find(|&&name| options.is_set(name)) {
+        return err!("--{} only works with i64 cells", option);
+    }
+
+    let mut program: WideProgram<i128> = WideProgram::parse(&input::read_file(file)?)?;
+    if let Some(inputs) = options.text("inputs") {
+        for input in inputs.split(',') {
+            program.add_input(input.trim().parse().or(err!("Cannot read inputs {:?}, expected numbers separated by commas", inputs))?);
+        }
+    }
+
+    let now = Instant::now();
+    let outputs: Vec<String> = program.run_to_end()?.iter().map(|n| n.to_string()).collect();
+    let elapsed = now.elapsed();
+    println!("Outputs: {}", outputs.join(","));
+    if program.is_halted() {
+        println!("Halted after {} instructions", program.instructions());
+    } else {
+        println!("Waiting for more input after {} instructions", program.instructions());
+    }
+    println!("Elapsed time: {}", style.format_elapsed(elapsed));
+
+    Ok(())
+}
+
+// Runs an Intcode program from any file, printing what it outputs. With `--bench` the
+// program is run that many more times from a fresh copy, and the timings summarised.
+fn run_intcode(file: &str, options: &Options, style: NumberStyle) -> Result<()> {
+    match options.text("cells") {
+        None | Some("i64") => {},
+        Some("i128") => return run_wide_intcode(file, options, style),
+        Some(cells) => return err!("Unknown cells {:?}, expected i64 or i128", cells)
+    }
+
+    // A machine saved with --save carries on from where it was
+    let saved = fs::read(file).ok().filter(|bytes| Program::is_saved(bytes));
+
+    let inputs: Vec<i64> = match options.text("inputs") {
+        Some(inputs) => match inputs.split(',').map(|n| n.trim().parse()).collect() {
+            Ok(inputs) => inputs,
+            Err(_) => return err!("Cannot read inputs {:?}, expected numbers separated by commas", inputs)
+        },
+        None => vec![]
+    };
+
+    if options.flag("stdin") && options.number("bench").is_some() {
+        return err!("--bench cannot time a program reading from standard input");
+    }
+
+    let mut program = match saved {
+        Some(saved) => {
+            if options.text("memory").is_some() || options.text("execution").is_some() || options.text("self-modifying").is_some() {
+                return err!("--memory, --execution and --self-modifying cannot change a saved machine");
+            }
+            Program::restore(&saved)?
+        },
+        None => {
+            let source = input::read_file(file)?;
+            if let Err(e) = input::validate_intcode(&source) {
+                return err!("{}: {}", file, e);
+            }
+
+            let backend = intcode::MemoryBackend::parse(options.text("memory").unwrap_or("paged"))?;
+            let execution = intcode::Execution::parse(options.text("execution").unwrap_or("interpreted"))?;
+            let memory: Vec<i64> = source.trim().split(',').map(|n| n.trim().parse()).collect::<result::Result<_, _>>()?;
+            let mut builder = intcode::VmBuilder::new(memory).backend(backend).execution(execution);
+            if let Some(handling) = options.text("self-modifying") {
+                builder = builder.self_modification(intcode::SelfModification::parse(handling)?);
+            }
+            builder.build()?
+        }
+    };
+    program.add_inputs(inputs);
+    if let Some(limit) = options.number("max-steps") {
+        program.limit_steps(limit as u64);
+    }
+    let fresh = program.clone();
+
+    let now = Instant::now();
+    if options.flag("stdin") {
+        program.run_with(&mut intcode::StdinSource, &mut intcode::StdoutSink)?;
+    } else {
+        let outputs = program.run_to_end()?;
+        let outputs: Vec<String> = outputs.iter().map(|n| n.to_string()).collect();
+        println!("Outputs: {}", outputs.join(","));
+    }
+    let elapsed = now.elapsed();
+
+    if program.is_halted() {
+        println!("Halted after {} instructions", program.instructions());
+    } else {
+        println!("Waiting for more input after {} instructions", program.instructions());
+    }
+    println!("Elapsed time: {}", style.format_elapsed(elapsed));
+    if options.text("self-modifying").is_some() {
+        let writes = program.code_writes();
+        println!("Writes over code it had run: {}", writes.len());
+        for write in writes {
+            println!("  address {}", write);
+        }
+    }
+    if let Some(path) = options.text("save") {
+        fs::write(path, program.save()?)?;
+        println!("Saved to {}", path);
+    }
+    if let Some(path) = options.text("dump") {
+        fs::write(path, program.memory().dump())?;
+        println!("Dumped memory to {}", path);
+    }
+
+    if let Some(runs) = options.number("bench") {
+        if runs == 0 {
+            return err!("--bench needs at least one run");
+        }
+
+        let mut timings = vec![];
+        for _ in 0..runs {
+            let mut program = fresh.clone();
+            let now = Instant::now();
+            program.run_to_end()?;
+            timings.push(now.elapsed());
+        }
+
+        let total: Duration = timings.iter().sum();
+        let mean = total / runs as u32;
+        let fastest = *timings.iter().min().unwrap();
+        let rate = program.instructions() as f64 / mean.as_secs_f64() / 1e6;
+        println!(
+            "Benchmark: {} runs, mean {}, fastest {}, {:.1} million instructions per second",
+            runs,
+            style.format_elapsed(mean),
+            style.format_elapsed(fastest),
+            rate
+        );
+    }
+
+    Ok(())
+}
+
+// A line a child printed with plain numbers, with its answer and time shown in `style`
+fn restyle_line(line: &str, style: NumberStyle) -> String {
+    if let Some(idx) = line.find("Answer: ") {
+        let (start, answer) = line.split_at(idx + "Answer: ".len());
+        format!("{}{}", start, style.format_answer(answer))
+    } else if let Some(elapsed) = line.strip_prefix("Elapsed time: ").and_then(stats::parse_elapsed) {
+        format!("Elapsed time: {}", style.format_elapsed(elapsed))
+    } else {
+        line.to_string()
+    }
+}
+
+// Replaces an answer with the start of its salted SHA-256, so redacted output can still be
+// compared between runs with the same salt without giving the answer away
+fn redact_answer(salt: &str, answer: &str) -> String {
+    let digest = Sha256::new().chain_update(salt).chain_update([0]).chain_update(answer).finalize();
+    let hex: String = digest.iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+    format!("<redacted {}>", hex)
+}
+
+// Answers are small numbers, which anyone could find again from an unsalted hash by trying
+// them all. So each machine makes up its own salt the first time it redacts anything, and
+// machines whose hashes should match can be given the same one in AOC_REDACT_SALT.
+fn redact_salt() -> Result<String> {
+    if let Ok(salt) = env::var("AOC_REDACT_SALT") {
+        return Ok(salt);
+    }
+
+    let path = format!("{}/{}", LOG_DIR, REDACT_SALT_FILE);
+    if Path::new(&path).exists() {
+        return Ok(input::read_file(&path)?.trim().to_string());
+    }
+    // Each RandomState has its own keys from the operating system's random numbers
+    let salt: String = (0..2).map(|_| format!("{:016x}", RandomState::new().build_hasher().finish())).collect();
+    fs::create_dir_all(LOG_DIR)?;
+    fs::write(&path, &salt)?;
+
+    Ok(salt)
+}
+
+// Runs the day the arguments ask for in a child, as `run` would without --redact, then
+// prints what it printed with every answer hashed and times in `style`
+fn run_redacted(args: &[String], style: NumberStyle) -> Result<()> {
+    let salt = redact_salt()?;
+    let mut args = args.to_vec();
+    take_flag(&mut args, "--redact");
+    take_value(&mut args, "--numbers")?;
+
+    let output = Command::new(env::current_exe()?)
+        .args(&args)
+        .arg("--numbers")
+        .arg(NumberStyle::Plain.name())
+        .env(REDACTED_CHILD, "1")
+        .stdin(Stdio::null())
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let answers = answers_in(&stdout);
+    for line in stdout.lines() {
+        println!("{}", restyle_line(&redact_line(line, &answers, &salt), style));
+    }
+    for line in stderr.lines() {
+        eprintln!("{}", redact_line(line, &answers, &salt));
+    }
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        err!("The day failed ({})", output.status)
+    }
+}
+
+// Where the answer starts in a line printed by `run_part`, if the line is one
+fn answer_start(line: &str) -> Option<usize> {
+    match line.find("Answer: ") {
+        Some(idx) => Some(idx + "Answer: ".len()),
+        None => line.strip_prefix("Part ")
+            .and_then(|rest| rest.split_once(": "))
+            .filter(|(part, _)| part.parse::<usize>().is_ok())
+            .map(|(part, _)| "Part ".len() + part.len() + ": ".len())
+    }
+}
+
+// The answers in the lines printed by `run_part`, longest first so that one answer which
+// holds another is hidden whole
+fn answers_in(output: &str) -> Vec<String> {
+    let mut answers: Vec<String> = output.lines()
+        .filter_map(|line| answer_start(line).map(|idx| &line[idx..]))
+        .filter(|answer| !answer.is_empty())
+        .map(|answer| answer.to_string())
+        .collect();
+    answers.sort_by_key(|answer| Reverse(answer.len()));
+    answers.dedup();
+    answers
+}
+
+// Whether `c` carries on the value next to it: letters and digits do, as does a point or
+// comma with a digit `beyond` it, or a minus sign in front
+fn joins(c: Option<char>, beyond: Option<char>, in_front: bool) -> bool {
+    match c {
+        Some(c) if c.is_alphanumeric() || c == '_' => true,
+        Some('.') | Some(',') => beyond.is_some_and(|c| c.is_ascii_digit()),
+        Some('-') => in_front,
+        _ => false
+    }
+}
+
+// Whether the `len` bytes of `line` from `start` are a value of their own, rather than
+// part of a longer one such as a time or an instruction count
+fn stands_alone(line: &str, start: usize, len: usize) -> bool {
+    let mut before = line[..start].chars().rev();
+    let (c, beyond) = (before.next(), before.next());
+    let in_front = joins(c, beyond, true);
+
+    let mut after = line[start + len..].chars();
+    let (c, beyond) = (after.next(), after.next());
+    !in_front && !joins(c, beyond, false)
+}
+
+// Hides the answers in a line of output. The answer on a line printed by `run_part` is
+// hidden whole. Elsewhere an answer is only hidden where it stands alone, and the line is
+// read once from the start, so the hex of one hash is never taken for another answer.
+fn redact_line(line: &str, answers: &[String], salt: &str) -> String {
+    if let Some(idx) = answer_start(line).filter(|&idx| idx < line.len()) {
+        let (start, answer) = line.split_at(idx);
+        return format!("{}{}", start, redact_answer(salt, answer));
+    }
+
+    let mut redacted = String::new();
+    let mut idx = 0;
+    while let Some(c) = line[idx..].chars().next() {
+        match answers.iter().find(|answer| line[idx..].starts_with(answer.as_str()) && stands_alone(line, idx, answer.len())) {
+            Some(answer) => {
+                redacted.push_str(&redact_answer(salt, answer));
+                idx += answer.len();
+            },
+            None => {
+                redacted.push(c);
+                idx += c.len_utf8();
+            }
+        }
+    }
+
+    redacted
+}
+
+// How numeric answers and elapsed times are shown. Redacted answers are hashed from the
+// plain answer, so they don't depend on the style.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum NumberStyle {
+    Plain,
+    // Thousands separated by commas, with times in nanoseconds
+    Separated,
+    // Answers of seven digits or more as e.g. 3.264e14, with times in seconds
+    Scientific
+}
+
+impl NumberStyle {
+    fn parse(style: &str) -> Result<NumberStyle> {
+        match style {
+            "plain" => Ok(NumberStyle::Plain),
+            "separated" => Ok(NumberStyle::Separated),
+            "scientific" => Ok(NumberStyle::Scientific),
+            x => err!("Number style must be plain, separated or scientific, not {}", x)
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            NumberStyle::Plain => "plain",
+            NumberStyle::Separated => "separated",
+            NumberStyle::Scientific => "scientific"
+        }
+    }
+
+    // Answers which aren't a single integer, such as day 16's digits, are left alone
+    fn format_answer(self, answer: &str) -> String {
+        if self == NumberStyle::Plain || (answer.starts_with('0') && answer != "0") {
+            return answer.to_string();
+        }
+
+        let n: i128 = match answer.parse() {
+            Ok(n) => n,
+            Err(_) => return answer.to_string()
+        };
+
+        match self {
+            NumberStyle::Separated => separate_thousands(n),
+            NumberStyle::Scientific if n.abs() >= 1_000_000 => format!("{:.3e}", n as f64),
+            _ => answer.to_string()
+        }
+    }
+
+    fn format_elapsed(self, elapsed: Duration) -> String {
+        match self {
+            NumberStyle::Plain => format!("{:?}", elapsed),
+            NumberStyle::Separated => format!("{} ns", separate_thousands(elapsed.as_nanos() as i128)),
+            NumberStyle::Scientific => format!("{:.3e} s", elapsed.as_secs_f64())
+        }
+    }
+}
+
+fn separate_thousands(n: i128) -> String {
+    let digits = n.abs().to_string();
+    let mut separated = String::new();
+    for (idx, c) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            separated.push(',');
+        }
+        separated.push(c);
+    }
+
+    if n < 0 {
+        format!("-{}", separated)
+    } else {
+        separated
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct PartSummary {
+    day: usize,
+    part: usize,
+    answer: Option<String>,
+    elapsed: Option<String>,
+    instructions: Option<String>,
+    states: Option<String>,
+    succeeded: bool
+}
+
+impl PartSummary {
+    fn new(day: usize, part: usize) -> PartSummary {
+        PartSummary {
+            day,
+            part,
+            ..Default::default()
+        }
+    }
+
+    // The part's run for the history, if its time could be read
+    fn record(&self) -> Option<Record> {
+        let elapsed = stats::parse_elapsed(self.elapsed.as_ref()?)?;
+        let number = |n: &Option<String>| n.as_ref().and_then(|n| n.parse().ok());
+        Some(Record::new(self.day, self.part, elapsed, number(&self.instructions), number(&self.states)))
+    }
+
+    // The part's time as `style` shows it. Children always print plain numbers, which is
+    // kept as it is if it somehow can't be read.
+    fn elapsed_in(&self, style: NumberStyle) -> Option<String> {
+        let elapsed = self.elapsed.as_ref()?;
+        Some(stats::parse_elapsed(elapsed).map_or(elapsed.clone(), |elapsed| style.format_elapsed(elapsed)))
+    }
+
+    // Picks the answer and timing out of the lines printed by `run_part`. Some solvers
+    // leave a partial line on stdout, so the answer may not start at the beginning.
+    fn read_line(&mut self, line: &str) {
+        if let Some(idx) = line.find("Answer: ") {
+            self.answer = Some(line[idx + "Answer: ".len()..].to_string());
+        } else if let Some(elapsed) = line.strip_prefix("Elapsed time: ") {
+            self.elapsed = Some(elapsed.to_string());
+        } else if let Some(instructions) = line.strip_prefix("Instructions: ") {
+            self.instructions = Some(instructions.to_string());
+        } else if let Some(states) = line.strip_prefix("Fact: states searched = ") {
+            self.states = Some(states.to_string());
+        }
+    }
+}
+
+// Runs every registered day in a child process so that each solver's debugging output
+// goes to its own log file rather than the terminal, then prints a summary table.
+fn run_all(show_output: bool, presentation: &Presentation, profile: Option<String>) -> Result<()> {
+    let log_dir = log_dir(profile.as_deref());
+    let summaries = run_logged(show_output, presentation, profile)?;
+    print_summary(&summaries, &log_dir, presentation);
+
+    Ok(())
+}
+
+fn log_dir(profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{}/{}", LOG_DIR, profile),
+        None => LOG_DIR.to_string()
+    }
+}
+
+// Runs every part of every day as `all` does, adding them to the history
+fn run_logged(show_output: bool, presentation: &Presentation, profile: Option<String>) -> Result<Vec<PartSummary>> {
+    let exe = env::current_exe()?;
+    let log_dir = log_dir(profile.as_deref());
+    fs::create_dir_all(&log_dir)?;
+
+    let mut summaries: Vec<PartSummary> = vec![];
+    for day in aoc_problems::registry() {
+        let mut log = File::create(format!("{}/day{:02}.log", log_dir, day.number))?;
+
+        for part in 1..=2 {
+            writeln!(log, "=== Day {} part {} ===", day.number, part)?;
+
+            // Shown as well as logged, it's copied from a pipe
+            let stderr = if show_output {
+                Stdio::piped()
+            } else {
+                Stdio::from(log.try_clone()?)
+            };
+
+            let mut command = Command::new(&exe);
+            command.arg(day.number.to_string()).arg(part.to_string());
+            if let Some(profile) = &profile {
+                command.arg("--profile").arg(profile);
+            }
+            // Plain, so that the answers are compared and stored as they are and only shown in
+            // the style asked for, and verbose, so that the facts the history keeps are printed
+            command.arg("--numbers").arg(NumberStyle::Plain.name()).arg("--verbose");
+
+            let mut child = command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(stderr)
+                .spawn()?;
+
+            // Redacted output is held back until the answer it has to hide is known, as the
+            // solver may print it on the way
+            let hold = presentation.redact.is_some();
+
+            // Read on its own thread, so that neither pipe fills up waiting for the other
+            let errors = match child.stderr.take() {
+                Some(stderr) => {
+                    let mut log = log.try_clone()?;
+                    Some(thread::spawn(move || -> io::Result<Vec<String>> {
+                        let mut held = vec![];
+                        for line in BufReader::new(stderr).lines() {
+                            let line = line?;
+                            writeln!(log, "{}", line)?;
+                            if hold {
+                                held.push(line);
+                            } else {
+                                eprintln!("{}", line);
+                            }
+                        }
+                        Ok(held)
+                    }))
+                },
+                None => None
+            };
+
+            let mut summary = PartSummary::new(day.number, part);
+            let mut held = vec![];
+            let stdout = child.stdout.take().ok_or("Cannot capture solver output")?;
+            for line in BufReader::new(stdout).lines() {
+                let line = line?;
+                writeln!(log, "{}", line)?;
+                summary.read_line(&line);
+                if show_output && hold {
+                    held.push(line);
+                } else if show_output {
+                    println!("{}", restyle_line(&line, presentation.style));
+                }
+            }
+
+            let held_errors = match errors {
+                Some(errors) => errors.join().map_err(|_| "Cannot copy solver errors to the log")??,
+                None => vec![]
+            };
+            if let Some(salt) = &presentation.redact {
+                let answers: Vec<String> = summary.answer.iter().filter(|answer| !answer.is_empty()).cloned().collect();
+                for line in held {
+                    println!("{}", restyle_line(&redact_line(&line, &answers, salt), presentation.style));
+                }
+                for line in held_errors {
+                    eprintln!("{}", redact_line(&line, &answers, salt));
+                }
+            }
+            summary.succeeded = child.wait()?.success();
+            summaries.push(summary);
+        }
+    }
+
+    let records: Vec<Record> = summaries.iter()
+        .filter(|summary| summary.succeeded)
+        .filter_map(|summary| summary.record())
+        .collect();
+    stats::append(&format!("{}/{}", log_dir, stats::HISTORY_FILE), &records)?;
+
+    Ok(summaries)
+}
+
+// The whole year in one go: downloads the inputs which aren't there yet, runs every day as
+// `all` does, and checks each answer against the ones stored with the inputs, storing any
+// part's answer the first time it's seen. The report is Markdown, to paste anywhere.
+fn run_season(session: Option<&str>, report: Option<&str>, presentation: &Presentation, profile: Option<String>) -> Result<()> {
+    let days = aoc_problems::registry();
+    for day in &days {
+        let input_file = day.input_file(profile.as_deref());
+        if Path::new(&input_file).exists() {
+            continue;
+        }
+        match session {
+            Some(session) => {
+                fetch_input(day.number, session, &input_file)?;
+                println!("Downloaded {}", input_file);
+            },
+            None => return err!("There is no {}, give --session or set AOC_SESSION to download it", input_file)
+        }
+    }
+
+    let summaries = run_logged(false, presentation, profile.clone())?;
+
+    let answers_file = match &profile {
+        Some(profile) => format!("./inputs/{}/{}", profile, season::ANSWERS_FILE),
+        None => format!("./inputs/{}", season::ANSWERS_FILE)
+    };
+    let mut answers = if Path::new(&answers_file).exists() {
+        Answers::parse(&input::read_file(&answers_file)?)?
+    } else {
+        Answers::default()
+    };
+
+    let rows = season_rows(&days, &summaries, &mut answers, presentation);
+    fs::write(&answers_file, answers.to_tsv())?;
+
+    let markdown = season::markdown(&rows);
+    print!("{}", markdown);
+    if let Some(report) = report {
+        fs::write(report, &markdown)?;
+    }
+
+    match rows.iter().filter(|row| matches!(row.verdict, Verdict::Wrong { .. } | Verdict::Failed)).count() {
+        0 => Ok(()),
+        n => err!("{} parts failed or gave a different answer, see {}", n, log_dir(profile.as_deref()))
+    }
+}
+
+// The report's row for each part, checking its answer against the stored one and storing
+// it if there wasn't one. Answers are shown as `presentation` shows them, including the
+// stored answer a wrong one is told apart from, so a redacted report gives none away.
+fn season_rows(days: &[Day], summaries: &[PartSummary], answers: &mut Answers, presentation: &Presentation) -> Vec<Row> {
+    let mut rows = vec![];
+    for summary in summaries {
+        let answer = summary.answer.as_deref().filter(|_| summary.succeeded);
+        let verdict = match season::verify(answers, summary.day, summary.part, answer) {
+            Verdict::Wrong { expected } => Verdict::Wrong { expected: presentation.answer(&expected) },
+            verdict => verdict
+        };
+        if let (Verdict::New, Some(answer)) = (&verdict, answer) {
+            answers.insert(summary.day, summary.part, answer);
+        }
+        rows.push(Row {
+            day: summary.day,
+            part: summary.part,
+            title: days.iter().find(|day| day.number == summary.day).map_or("", |day| day.title).to_string(),
+            answer: answer.map(|answer| presentation.answer(answer)),
+            elapsed: summary.elapsed_in(presentation.style),
+            verdict
+        });
+    }
+
+    rows
+}
+
+// Downloads a day's input with curl, passing the session cookie through its standard input
+// rather than the command line, where other users could see it
+fn fetch_input(day: usize, session: &str, path: &str) -> Result<()> {
+    let mut child = match Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-", "--user-agent", USER_AGENT])
+        .arg(season::input_url(day))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return err!("Downloading inputs needs curl: {}", e)
+    };
+    let mut stdin = child.stdin.take().ok_or("Cannot pass the session cookie to curl")?;
+    writeln!(stdin, "cookie = \"session={}\"", session)?;
+    drop(stdin);
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return err!("Cannot download day {}'s input: {}", day, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, output.stdout)?;
+
+    Ok(())
+}
+
+// Runs every part of every day one after another, as `all` does but with nothing else
+// going on: no logs, no verbose facts, no history and no solver output. The total is
+// shown to the millisecond, as finer digits would only be noise from one run to the next.
+fn time_all(profile: Option<String>) -> Result<()> {
+    let exe = env::current_exe()?;
+    let mut failed = vec![];
+
+    let now = Instant::now();
+    for day in aoc_problems::registry() {
+        for part in 1..=2 {
+            let mut command = Command::new(&exe);
+            command.arg(day.number.to_string()).arg(part.to_string());
+            if let Some(profile) = &profile {
+                command.arg("--profile").arg(profile);
+            }
+            let status = command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+            if !status.success() {
+                failed.push(format!("{}.{}", day.number, part));
+            }
+        }
+    }
+    let elapsed = now.elapsed();
+
+    let build = if intcode::INSTRUMENTED { "" } else { " (lean build)" };
+    println!("Whole year: {:.3}s{}", elapsed.as_secs_f64(), build);
+    if !failed.is_empty() {
+        println!("Failed, but still counted: {}", failed.join(", "));
+    }
+
+    Ok(())
+}
+
+// Sums up every `all` run so far, by day and part
+fn show_stats(profile: Option<String>, json: Option<&str>) -> Result<()> {
+    let log_dir = match &profile {
+        Some(profile) => format!("{}/{}", LOG_DIR, profile),
+        None => LOG_DIR.to_string()
+    };
+    let summary = stats::summarise(&stats::read(&format!("{}/{}", log_dir, stats::HISTORY_FILE))?);
+
+    println!("{}", stats::table(&summary));
+    if let Some(json) = json {
+        fs::write(json, stats::to_json(&summary))?;
+    }
+
+    Ok(())
+}
+
+fn print_summary(summaries: &[PartSummary], log_dir: &str, presentation: &Presentation) {
+    println!("{:>3}  {:>4}  {:<28}  {:<14}  Instructions", "Day", "Part", "Answer", "Time");
+    for summary in summaries {
+        let answer = if !summary.succeeded {
+            format!("FAILED (see {}/day{:02}.log)", log_dir, summary.day)
+        } else {
+            summary.answer.as_ref().map(|answer| presentation.answer(answer)).unwrap_or_default()
+        };
+
+        let line = format!(
+            "{:>3}  {:>4}  {:<28}  {:<14}  {}",
+            summary.day,
+            summary.part,
+            answer,
+            summary.elapsed_in(presentation.style).unwrap_or_default(),
+            summary.instructions.clone().unwrap_or_default()
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runner_reads_part_output() {
+        let mut summary = PartSummary::new(9, 2);
+        for line in "Result outputted = 1\n...#..Answer: 1,2,3\nElapsed time: 1.5ms\nInstructions: 1234\nFact: states searched = 56".lines() {
+            summary.read_line(line);
+        }
+
+        assert_eq!(summary.answer, Some("1,2,3".to_string()));
+        assert_eq!(summary.elapsed, Some("1.5ms".to_string()));
+        assert_eq!(summary.instructions, Some("1234".to_string()));
+        assert_eq!(summary.states, Some("56".to_string()));
+        assert_eq!(summary.record().map(|record| record.elapsed), Some(Duration::from_micros(1500)));
+        assert_eq!(summary.elapsed_in(NumberStyle::Separated), Some("1,500,000 ns".to_string()));
+
+        assert_eq!(restyle_line("..#Answer: 326365108375488", NumberStyle::Separated), "..#Answer: 326,365,108,375,488");
+        assert_eq!(restyle_line("Elapsed time: 1.5ms", NumberStyle::Scientific), "Elapsed time: 1.500e-3 s");
+        assert_eq!(restyle_line("Result outputted = 1", NumberStyle::Scientific), "Result outputted = 1");
+    }
+
+    #[test]
+    fn runner_redacts_season_reports() {
+        let mut answers = Answers::parse("9\t1\t3460311188\n9\t2\t42202\n").unwrap();
+        let summaries: Vec<PartSummary> = [(1, "3460311188"), (2, "42203")].iter().map(|&(part, answer)| {
+            let mut summary = PartSummary::new(9, part);
+            summary.read_line(&format!("Answer: {}", answer));
+            summary.succeeded = true;
+            summary
+        }).collect();
+
+        let presentation = Presentation { redact: Some("salt".to_string()), verbose: false, style: NumberStyle::Plain };
+        let rows = season_rows(&aoc_problems::registry(), &summaries, &mut answers, &presentation);
+        assert_eq!(rows[0].verdict, Verdict::Correct);
+        assert_eq!(rows[1].verdict, Verdict::Wrong { expected: redact_answer("salt", "42202") });
+
+        let report = season::markdown(&rows);
+        for answer in ["3460311188", "42202", "42203"] {
+            assert!(!report.contains(answer), "{} is in the report", answer);
+        }
+    }
+
+    #[test]
+    fn runner_takes_global_options() {
+        let mut args: Vec<String> = "12 1 --profile alt --steps 10 --redact".split(' ').map(|s| s.to_string()).collect();
+
+        assert!(take_flag(&mut args, "--redact"));
+        assert_eq!(take_value(&mut args, "--profile").unwrap(), Some("alt".to_string()));
+        assert_eq!(args, vec!["12", "1", "--steps", "10"]);
+
+        let mut args = vec!["all".to_string(), "--profile".to_string()];
+        assert!(take_value(&mut args, "--profile").is_err());
+    }
+
+    #[test]
+    fn runner_selects_days() {
+        for selector in &["9", "09", "day09", "Day 9", "sensor", "SENSOR BOOST"] {
+            assert_eq!(select_day(selector).unwrap().number, 9);
+        }
+        assert_eq!(select_day("nbody").unwrap().number, 12);
+
+        let error = select_day("space").err().unwrap().to_string();
+        assert!(error.contains("Space Image Format") && error.contains("Space Police"));
+        assert!(select_day("day 4").is_err());
+        assert!(select_day("xyzzy").is_err());
+    }
+
+    #[test]
+    fn runner_parses_parts() {
+        assert_eq!(parse_part("1").unwrap(), Some(1));
+        assert_eq!(parse_part("2").unwrap(), Some(2));
+        assert_eq!(parse_part("both").unwrap(), None);
+        assert!(parse_part("3").is_err());
+    }
+
+    #[test]
+    fn runner_formats_numbers() {
+        assert_eq!(NumberStyle::Separated.format_answer("326365108375488"), "326,365,108,375,488");
+        assert_eq!(NumberStyle::Separated.format_answer("-1234"), "-1,234");
+        assert_eq!(NumberStyle::Separated.format_answer("412"), "412");
+        assert_eq!(NumberStyle::Scientific.format_answer("326365108375488"), "3.264e14");
+        assert_eq!(NumberStyle::Scientific.format_answer("20940"), "20940");
+        assert_eq!(NumberStyle::Plain.format_answer("326365108375488"), "326365108375488");
+
+        // Digit strings where leading zeros matter, and anything else, are left alone
+        assert_eq!(NumberStyle::Separated.format_answer("01234567"), "01234567");
+        assert_eq!(NumberStyle::Separated.format_answer("3460311188,42202"), "3460311188,42202");
+
+        assert_eq!(NumberStyle::Separated.format_elapsed(Duration::from_micros(151_162)), "151,162,000 ns");
+        assert_eq!(NumberStyle::Scientific.format_elapsed(Duration::from_millis(1500)), "1.500e0 s");
+        assert!(NumberStyle::parse("fancy").is_err());
+    }
+
+    #[test]
+    fn runner_redacts_answers() {
+        let redacted = redact_answer("salt", "3471229");
+
+        assert!(!redacted.contains("3471229"));
+        assert_eq!(redacted.len(), "<redacted >".len() + 16);
+        assert_eq!(redacted, redact_answer("salt", "3471229"));
+        assert_ne!(redacted, redact_answer("salt", "5203967"));
+        assert_ne!(redacted, redact_answer("pepper", "3471229"));
+
+        // However the answer would have been shown, the hash is the same
+        for style in [NumberStyle::Plain, NumberStyle::Separated, NumberStyle::Scientific] {
+            let presentation = Presentation { redact: Some("salt".to_string()), verbose: false, style };
+            assert_eq!(presentation.answer("326365108375488"), redact_answer("salt", "326365108375488"));
+        }
+
+        // Including wherever the solver printed them on the way
+        let output = "Result outputted = 3460311188\nPart 1: 3460311188\nPart 2: 42202\nElapsed time: 1.5ms";
+        let answers = answers_in(output);
+        assert_eq!(answers, vec!["3460311188".to_string(), "42202".to_string()]);
+        let redacted: Vec<String> = output.lines().map(|line| redact_line(line, &answers, "salt")).collect();
+        assert_eq!(redacted[0], format!("Result outputted = {}", redact_answer("salt", "3460311188")));
+        assert_eq!(redacted[2], format!("Part 2: {}", redact_answer("salt", "42202")));
+        assert_eq!(redacted[3], "Elapsed time: 1.5ms");
+
+        // Short answers are only hidden where they stand alone, and never inside the hash
+        // of another answer
+        let output = "Result outputted = 12\nCounted 2, 12, -2 and 212.\nPart 1: 12\nPart 2: 2\nElapsed time: 1.212ms\nInstructions: 1212";
+        let answers = answers_in(output);
+        assert_eq!(answers, vec!["12".to_string(), "2".to_string()]);
+        let (twelve, two) = (redact_answer("salt", "12"), redact_answer("salt", "2"));
+        let redacted: Vec<String> = output.lines().map(|line| redact_line(line, &answers, "salt")).collect();
+        assert_eq!(redacted, vec![
+            format!("Result outputted = {}", twelve),
+            format!("Counted {}, {}, -2 and 212.", two, twelve),
+            format!("Part 1: {}", twelve),
+            format!("Part 2: {}", two),
+            "Elapsed time: 1.212ms".to_string(),
+            "Instructions: 1212".to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::result;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// The answers a `season` run checks against, kept next to the inputs they came from. Any
+// part without one has its answer added after the run, so the first run of a season
+// records the answers and every later one checks them. It's tab separated: day, part and
+// answer on each line.
+pub const ANSWERS_FILE: &str = "answers.tsv";
+
+pub fn input_url(day: usize) -> String {
+    format!("https://adventofcode.com/2019/day/{}/input", day)
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Answers {
+    answers: BTreeMap<(usize, usize), String>
+}
+
+impl Answers {
+    pub fn parse(s: &str) -> Result<Answers> {
+        let mut answers = BTreeMap::new();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                [day, part, answer] => match (day.parse(), part.parse()) {
+                    (Ok(day), Ok(part)) => {
+                        answers.insert((day, part), answer.to_string());
+                    },
+                    _ => return err!("Cannot read {:?} as a day and part", line)
+                },
+                _ => return err!("Cannot read {:?} as an answer, expected day, part and answer separated by tabs", line)
+            }
+        }
+
+        Ok(Answers { answers })
+    }
+
+    pub fn get(&self, day: usize, part: usize) -> Option<&str> {
+        self.answers.get(&(day, part)).map(|answer| answer.as_str())
+    }
+
+    pub fn insert(&mut self, day: usize, part: usize, answer: &str) {
+        self.answers.insert((day, part), answer.to_string());
+    }
+
+    pub fn to_tsv(&self) -> String {
+        self.answers.iter().map(|(&(day, part), answer)| format!("{}\t{}\t{}\n", day, part, answer)).collect()
+    }
+}
+
+// How one part's answer compares with the stored one
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    Correct,
+    Wrong { expected: String },
+    // Nothing stored for the part yet, so this answer will be
+    New,
+    Failed
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::Wrong { expected } => write!(f, "wrong, expected {}", expected),
+            Verdict::New => write!(f, "new"),
+            Verdict::Failed => write!(f, "failed")
+        }
+    }
+}
+
+pub fn verify(answers: &Answers, day: usize, part: usize, answer: Option<&str>) -> Verdict {
+    match (answer, answers.get(day, part)) {
+        (None, _) => Verdict::Failed,
+        (Some(_), None) => Verdict::New,
+        (Some(answer), Some(expected)) if answer == expected => Verdict::Correct,
+        (Some(_), Some(expected)) => Verdict::Wrong { expected: expected.to_string() }
+    }
+}
+
+// One part's line in the report
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Row {
+    pub day: usize,
+    pub part: usize,
+    pub title: String,
+    pub answer: Option<String>,
+    pub elapsed: Option<String>,
+    pub verdict: Verdict
+}
+
+// The season as a Markdown table, with a count of the parts in each state after it
+pub fn markdown(rows: &[Row]) -> String {
+    let mut lines = vec![
+        "| Day | Part | Title | Answer | Time | Verdict |".to_string(),
+        "| --: | ---: | ----- | ------ | ---- | ------- |".to_string(),
+    ];
+    for row in rows {
+        lines.push(format!(
+            "| {} | {} | {} | {} | {} | {} |",
+            row.day,
+            row.part,
+            row.title,
+            row.answer.as_deref().unwrap_or("").replace('|', "\\|"),
+            row.elapsed.as_deref().unwrap_or(""),
+            row.verdict
+        ));
+    }
+
+    let count = |matches: fn(&Verdict) -> bool| rows.iter().filter(|row| matches(&row.verdict)).count();
+    lines.push(String::new());
+    lines.push(format!(
+        "{} correct, {} wrong, {} new, {} failed",
+        count(|verdict| *verdict == Verdict::Correct),
+        count(|verdict| matches!(verdict, Verdict::Wrong { .. })),
+        count(|verdict| *verdict == Verdict::New),
+        count(|verdict| *verdict == Verdict::Failed)
+    ));
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn season_verifies_and_reports() {
+        let mut answers = Answers::parse("1\t1\t3318604\n1\t2\t4975039\n\n9\t2\t1,2\n").unwrap();
+        assert_eq!(Answers::parse(&answers.to_tsv()).unwrap(), answers);
+        assert!(Answers::parse("1 1 3318604").is_err());
+        assert!(Answers::parse("one\t1\t3318604").is_err());
+
+        assert_eq!(verify(&answers, 1, 1, Some("3318604")), Verdict::Correct);
+        assert_eq!(verify(&answers, 1, 2, Some("4975040")), Verdict::Wrong { expected: "4975039".to_string() });
+        assert_eq!(verify(&answers, 2, 1, Some("5")), Verdict::New);
+        assert_eq!(verify(&answers, 1, 1, None), Verdict::Failed);
+        answers.insert(2, 1, "5");
+        assert_eq!(verify(&answers, 2, 1, Some("5")), Verdict::Correct);
+
+        let row = |part, answer: Option<&str>, verdict| Row {
+            day: 9,
+            part,
+            title: "Sensor Boost".to_string(),
+            answer: answer.map(|answer| answer.to_string()),
+            elapsed: answer.map(|_| "1.5ms".to_string()),
+            verdict
+        };
+        let report = markdown(&[row(1, Some("a|b"), Verdict::New), row(2, None, Verdict::Failed)]);
+        assert_eq!(report.lines().nth(2), Some("| 9 | 1 | Sensor Boost | a\\|b | 1.5ms | new |"));
+        assert_eq!(report.lines().nth(3), Some("| 9 | 2 | Sensor Boost |  |  | failed |"));
+        assert!(report.ends_with("\n0 correct, 0 wrong, 1 new, 1 failed\n"));
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use util::input;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// Every `aoc_2019 all` adds a line per part to this file in the log directory, which
+// `aoc_2019 stats` sums up. It's tab separated, with a header line.
+pub const HISTORY_FILE: &str = "history.tsv";
+
+const HEADER: &str = "timestamp\tday\tpart\telapsed_ns\tinstructions\tstates";
+
+// One part of one day from one run
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Record {
+    // Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub day: usize,
+    pub part: usize,
+    pub elapsed: Duration,
+    // Instructions executed, for Intcode days
+    pub instructions: Option<u64>,
+    // States a search visited, for graph days which count them
+    pub states: Option<u64>
+}
+
+impl Record {
+    pub fn new(day: usize, part: usize, elapsed: Duration, instructions: Option<u64>, states: Option<u64>) -> Record {
+        Record {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            day,
+            part,
+            elapsed,
+            instructions,
+            states
+        }
+    }
+
+    fn to_line(self) -> String {
+        let optional = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or_default();
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.timestamp,
+            self.day,
+            self.part,
+            self.elapsed.as_nanos(),
+            optional(self.instructions),
+            optional(self.states)
+        )
+    }
+
+    fn parse(line: &str) -> Result<Record> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 6 {
+            return err!("Expected 6 fields in history line {:?}", line);
+        }
+        let optional = |field: &str| -> Result<Option<u64>> {
+            if field.is_empty() { Ok(None) } else { Ok(Some(field.parse()?)) }
+        };
+
+        Ok(Record {
+            timestamp: fields[0].parse()?,
+            day: fields[1].parse()?,
+            part: fields[2].parse()?,
+            elapsed: Duration::from_nanos(fields[3].parse()?),
+            instructions: optional(fields[4])?,
+            states: optional(fields[5])?
+        })
+    }
+}
+
+pub fn append(path: &str, records: &[Record]) -> Result<()> {
+    let new = !Path::new(path).exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if new {
+        writeln!(file, "{}", HEADER)?;
+    }
+    for record in records {
+        writeln!(file, "{}", record.to_line())?;
+    }
+
+    Ok(())
+}
+
+pub fn read(path: &str) -> Result<Vec<Record>> {
+    if !Path::new(path).exists() {
+        return err!("No run history at {} yet, `aoc_2019 all` starts one", path);
+    }
+
+    let history = input::read_file(path)?;
+    let mut lines = history.lines();
+    if lines.next() != Some(HEADER) {
+        return err!("{} is not a run history", path);
+    }
+
+    lines.filter(|line| !line.is_empty()).map(Record::parse).collect()
+}
+
+// Reads an elapsed time as `run_part` prints it in any number style, e.g. `151.162ms`,
+// `151,162,000 ns` or `1.512e-1 s`
+pub fn parse_elapsed(elapsed: &str) -> Option<Duration> {
+    if let Some(nanos) = elapsed.strip_suffix(" ns") {
+        return nanos.replace(',', "").parse().ok().map(Duration::from_nanos);
+    }
+    // Rounded to the nanosecond, as e.g. 151.162ms isn't exact as a float
+    let nanos = |value: f64, scale: f64| Duration::from_nanos((value * scale).round() as u64);
+    if let Some(secs) = elapsed.strip_suffix(" s") {
+        return secs.parse().ok().map(|secs| nanos(secs, 1e9));
+    }
+
+    let units = [("ns", 1.0), ("µs", 1e3), ("ms", 1e6), ("s", 1e9)];
+    let &(unit, scale) = units.iter().find(|(unit, _)| elapsed.ends_with(unit))?;
+    let value: f64 = elapsed[..elapsed.len() - unit.len()].parse().ok()?;
+    Some(nanos(value, scale))
+}
+
+// The runs of one part of one day, summed up
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PartStats {
+    pub day: usize,
+    pub part: usize,
+    pub runs: usize,
+    pub median_elapsed: Duration,
+    pub instructions: Option<u64>,
+    pub states: Option<u64>
+}
+
+// The middle value, or the mean of the middle two when there's an even number of them
+fn median(mut values: Vec<u128>) -> Option<u128> {
+    values.sort_unstable();
+    match values.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(values[n / 2]),
+        n => Some((values[n / 2 - 1] + values[n / 2]) / 2)
+    }
+}
+
+// Each part's median time, instructions and states over every run of it, by day and part
+pub fn summarise(records: &[Record]) -> Vec<PartStats> {
+    let mut by_part: BTreeMap<(usize, usize), Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        by_part.entry((record.day, record.part)).or_default().push(record);
+    }
+
+    by_part.into_iter()
+        .map(|((day, part), runs)| {
+            let median_of = |field: fn(&Record) -> Option<u64>| {
+                median(runs.iter().filter_map(|&run| field(run)).map(u128::from).collect()).map(|n| n as u64)
+            };
+
+            PartStats {
+                day,
+                part,
+                runs: runs.len(),
+                median_elapsed: Duration::from_nanos(median(runs.iter().map(|run| run.elapsed.as_nanos()).collect()).unwrap_or(0) as u64),
+                instructions: median_of(|run| run.instructions),
+                states: median_of(|run| run.states)
+            }
+        })
+        .collect()
+}
+
+pub fn table(stats: &[PartStats]) -> String {
+    let optional = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or_default();
+
+    let mut lines = vec![format!("{:>3}  {:>4}  {:>4}  {:<14}  {:<12}  States", "Day", "Part", "Runs", "Median time", "Instructions")];
+    for part in stats {
+        let line = format!(
+            "{:>3}  {:>4}  {:>4}  {:<14}  {:<12}  {}",
+            part.day,
+            part.part,
+            part.runs,
+            format!("{:?}", part.median_elapsed),
+            optional(part.instructions),
+            optional(part.states)
+        );
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines.join("\n")
+}
+
+pub fn to_json(stats: &[PartStats]) -> String {
+    let optional = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or_else(|| "null".to_string());
+
+    let parts: Vec<String> = stats.iter()
+        .map(|part| format!(
+            "  {{\"day\": {}, \"part\": {}, \"runs\": {}, \"median_ns\": {}, \"instructions\": {}, \"states\": {}}}",
+            part.day,
+            part.part,
+            part.runs,
+            part.median_elapsed.as_nanos(),
+            optional(part.instructions),
+            optional(part.states)
+        ))
+        .collect();
+
+    format!("[\n{}\n]\n", parts.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_parse_elapsed_times() {
+        assert_eq!(parse_elapsed("151.162ms"), Some(Duration::from_micros(151_162)));
+        assert_eq!(parse_elapsed("439.58µs"), Some(Duration::from_nanos(439_580)));
+        assert_eq!(parse_elapsed("151,162,000 ns"), Some(Duration::from_micros(151_162)));
+        assert_eq!(parse_elapsed("1.500e0 s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_elapsed("2.5s"), Some(Duration::from_millis(2500)));
+        assert_eq!(parse_elapsed("soon"), None);
+    }
+
+    #[test]
+    fn stats_summarise_history() {
+        let records = [
+            Record::new(9, 2, Duration::from_millis(30), Some(371206), None),
+            Record::new(20, 2, Duration::from_millis(5), None, Some(900)),
+            Record::new(9, 2, Duration::from_millis(40), Some(371206), None),
+            Record::new(9, 2, Duration::from_millis(90), Some(371206), None),
+            Record::new(20, 2, Duration::from_millis(7), None, Some(900)),
+        ];
+        for record in &records {
+            assert_eq!(Record::parse(&record.to_line()).unwrap(), *record);
+        }
+
+        let stats = summarise(&records);
+        assert_eq!(stats, vec![
+            PartStats { day: 9, part: 2, runs: 3, median_elapsed: Duration::from_millis(40), instructions: Some(371206), states: None },
+            PartStats { day: 20, part: 2, runs: 2, median_elapsed: Duration::from_millis(6), instructions: None, states: Some(900) },
+        ]);
+        assert!(table(&stats).lines().nth(1).unwrap().starts_with("  9     2     3  40ms"));
+        assert!(to_json(&stats).contains("\"median_ns\": 6000000, \"instructions\": null, \"states\": 900}"));
+    }
+}
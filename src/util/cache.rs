@@ -0,0 +1,234 @@
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::hash::Hash;
+use std::result;
+use std::sync::Mutex;
+
+use util::input;
+
+// Remembers the results of an expensive function by its argument. A bounded memo
+// forgets the least recently used result once it is full; finding that one is a scan
+// over every entry, which is fine for the few thousand entries the puzzles need.
+#[derive(Clone, Debug)]
+pub struct Memo<K, V> {
+    values: HashMap<K, (V, u64)>,
+    limit: Option<usize>,
+    clock: u64,
+    hits: usize,
+    misses: usize
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Memo<K, V> {
+    pub fn new() -> Memo<K, V> {
+        Memo {
+            values: HashMap::new(),
+            limit: None,
+            clock: 0,
+            hits: 0,
+            misses: 0
+        }
+    }
+
+    // Panics if `limit` is 0, since such a memo could never remember anything
+    pub fn bounded(limit: usize) -> Memo<K, V> {
+        assert!(limit > 0, "A bounded memo needs room for at least one value");
+
+        Memo {
+            limit: Some(limit),
+            ..Memo::new()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // How many lookups were answered from the memo, and how many had to be computed
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.clock += 1;
+        let clock = self.clock;
+
+        self.values.get_mut(key).map(|entry| {
+            entry.1 = clock;
+            entry.0.clone()
+        })
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(limit) = self.limit {
+            if self.values.len() >= limit && !self.values.contains_key(&key) {
+                self.evict();
+            }
+        }
+
+        self.clock += 1;
+        self.values.insert(key, (value, self.clock));
+    }
+
+    pub fn get_or_compute<F: FnOnce(&K) -> V>(&mut self, key: K, compute: F) -> V {
+        if let Some(value) = self.get(&key) {
+            self.hits += 1;
+            return value;
+        }
+
+        self.misses += 1;
+        let value = compute(&key);
+        self.insert(key, value.clone());
+
+        value
+    }
+
+    // As `get_or_compute`, but for functions that can fail. Failures aren't remembered.
+    pub fn try_get_or_compute<E, F: FnOnce(&K) -> Result<V, E>>(&mut self, key: K, compute: F) -> Result<V, E> {
+        if let Some(value) = self.get(&key) {
+            self.hits += 1;
+            return Ok(value);
+        }
+
+        self.misses += 1;
+        let value = compute(&key)?;
+        self.insert(key, value.clone());
+
+        Ok(value)
+    }
+
+    // Forgets every result and starts counting hits and misses again
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.clock = 0;
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    fn evict(&mut self) {
+        let oldest = self.values.iter()
+            .min_by_key(|(_, &(_, used))| used)
+            .map(|(key, _)| key.clone());
+
+        if let Some(key) = oldest {
+            self.values.remove(&key);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for Memo<K, V> {
+    fn default() -> Memo<K, V> {
+        Memo::new()
+    }
+}
+
+// Inputs already parsed by this process, by file and by what they were parsed into, with
+// the hash of the contents they were parsed from
+type WarmInputs = BTreeMap<(String, TypeId), (u64, Box<dyn Any + Send>)>;
+
+static WARM: Mutex<WarmInputs> = Mutex::new(BTreeMap::new());
+
+// Reads the input at `path` and parses it, or hands back a copy of what it was parsed
+// into last time if the contents haven't changed since. Only the file is read again, so
+// a solver run over and over in one process spends its time solving, not parsing.
+pub fn warm<T, F>(path: &str, parse: F) -> result::Result<T, Box<dyn Error>>
+    where T: Clone + Send + 'static, F: FnOnce(&str) -> result::Result<T, Box<dyn Error>>
+{
+    let contents = input::read_file(path)?;
+    let hash = input::fnv1a(contents.as_bytes());
+    let key = (path.to_string(), TypeId::of::<T>());
+
+    if let Some((warm_hash, value)) = WARM.lock().unwrap().get(&key) {
+        if let Some(value) = value.downcast_ref::<T>().filter(|_| *warm_hash == hash) {
+            return Ok(value.clone());
+        }
+    }
+
+    let value = parse(&contents)?;
+    WARM.lock().unwrap().insert(key, (hash, Box::new(value.clone())));
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn cache_memo_computes_once() {
+        let mut memo = Memo::new();
+        let mut calls = 0;
+        for n in [3, 4, 3, 3, 4].iter() {
+            let square = memo.get_or_compute(*n, |&n| {
+                calls += 1;
+                n * n
+            });
+            assert_eq!(square, n * n);
+        }
+
+        assert_eq!(calls, 2);
+        assert_eq!((memo.hits(), memo.misses()), (3, 2));
+        assert_eq!(memo.try_get_or_compute(5, |_| Err("failed")), Err("failed"));
+        assert_eq!(memo.len(), 2);
+
+        memo.clear();
+        assert!(memo.is_empty());
+        assert_eq!((memo.hits(), memo.misses()), (0, 0));
+        assert_eq!(memo.get_or_compute(3, |&n| n * n), 9);
+        assert_eq!((memo.hits(), memo.misses()), (0, 1));
+    }
+
+    #[test]
+    fn cache_warm_parses_again_when_the_input_changes() {
+        let path = env::temp_dir().join(format!("aoc_2019_warm_{}.txt", process::id()));
+        let path = path.to_str().unwrap();
+        let parses = Cell::new(0);
+        let parse = |s: &str| -> result::Result<Vec<i64>, Box<dyn Error>> {
+            parses.set(parses.get() + 1);
+            Ok(s.trim().split(',').map(|n| n.parse()).collect::<result::Result<_, _>>()?)
+        };
+
+        fs::write(path, "1,2,3").unwrap();
+        assert_eq!(warm(path, parse).unwrap(), vec![1, 2, 3]);
+        assert_eq!(warm(path, parse).unwrap(), vec![1, 2, 3]);
+        assert_eq!(parses.get(), 1);
+        // The same file parsed into something else is kept apart
+        assert_eq!(warm(path, |s| Ok(s.len())).unwrap(), 5);
+
+        fs::write(path, "4,5").unwrap();
+        assert_eq!(warm(path, parse).unwrap(), vec![4, 5]);
+        assert_eq!(parses.get(), 2);
+        // A failed parse isn't remembered
+        fs::write(path, "4,x").unwrap();
+        assert!(warm(path, parse).is_err());
+        fs::remove_file(path).unwrap();
+        assert!(warm(path, parse).is_err());
+    }
+
+    #[test]
+    fn cache_memo_evicts_least_recently_used() {
+        let mut memo = Memo::bounded(2);
+        memo.insert('a', 1);
+        memo.insert('b', 2);
+        assert_eq!(memo.get(&'a'), Some(1));
+
+        memo.insert('c', 3);
+        assert_eq!(memo.len(), 2);
+        assert_eq!(memo.get(&'b'), None);
+        assert_eq!(memo.get(&'a'), Some(1));
+        assert_eq!(memo.get(&'c'), Some(3));
+    }
+}
@@ -0,0 +1,166 @@
+// Every ordering of 0..size, generated with Heap's algorithm
+pub fn permutations(size: usize) -> Permutations {
+    Permutations { idxs: (0..size).collect(), swaps: vec![0; size], i: 0 }
+}
+
+pub struct Permutations {
+    idxs: Vec<usize>,
+    swaps: Vec<usize>,
+    i: usize,
+}
+
+impl Iterator for Permutations {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.i > 0 {
+            loop {
+                if self.i >= self.swaps.len() { return None; }
+                if self.swaps[self.i] < self.i { break; }
+                self.swaps[self.i] = 0;
+                self.i += 1;
+            }
+            self.idxs.swap(self.i, (self.i & 1) * self.swaps[self.i]);
+            self.swaps[self.i] += 1;
+        }
+        self.i = 1;
+        Some(self.idxs.clone())
+    }
+}
+
+// Every way of choosing `k` of 0..n, each in increasing order, in lexicographic order
+pub fn combinations(n: usize, k: usize) -> Combinations {
+    Combinations {
+        n,
+        idxs: (0..k).collect(),
+        done: k > n
+    }
+}
+
+pub struct Combinations {
+    n: usize,
+    idxs: Vec<usize>,
+    done: bool
+}
+
+impl Iterator for Combinations {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let current = self.idxs.clone();
+
+        // Move the rightmost index which still has room along by one, and pack the
+        // indexes after it straight in behind it
+        let k = self.idxs.len();
+        match (0..k).rev().find(|&i| self.idxs[i] < self.n - k + i) {
+            Some(i) => {
+                self.idxs[i] += 1;
+                for j in i + 1..k {
+                    self.idxs[j] = self.idxs[j - 1] + 1;
+                }
+            },
+            None => self.done = true
+        }
+
+        Some(current)
+    }
+}
+
+// Every choice of one index below each of `sizes`, in lexicographic order, like nested
+// loops over 0..sizes[0], 0..sizes[1], ...
+pub fn product(sizes: &[usize]) -> Product {
+    Product {
+        sizes: sizes.to_vec(),
+        idxs: vec![0; sizes.len()],
+        done: sizes.contains(&0)
+    }
+}
+
+pub struct Product {
+    sizes: Vec<usize>,
+    idxs: Vec<usize>,
+    done: bool
+}
+
+impl Iterator for Product {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let current = self.idxs.clone();
+
+        // Count upwards, with each position wrapping round at its own size
+        self.done = true;
+        for i in (0..self.sizes.len()).rev() {
+            self.idxs[i] += 1;
+            if self.idxs[i] < self.sizes[i] {
+                self.done = false;
+                break;
+            }
+            self.idxs[i] = 0;
+        }
+
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn factorial(n: usize) -> usize {
+        (1..=n).product()
+    }
+
+    #[test]
+    fn combinatorics_permutations() {
+        for n in 0..=6 {
+            let perms: Vec<Vec<usize>> = permutations(n).collect();
+            let distinct: HashSet<&Vec<usize>> = perms.iter().collect();
+
+            assert_eq!(perms.len(), factorial(n));
+            assert_eq!(distinct.len(), perms.len());
+            assert!(perms.iter().all(|perm| {
+                let mut sorted = perm.clone();
+                sorted.sort();
+                sorted == (0..n).collect::<Vec<_>>()
+            }));
+        }
+    }
+
+    #[test]
+    fn combinatorics_combinations() {
+        assert_eq!(
+            combinations(4, 2).collect::<Vec<_>>(),
+            vec![vec![0, 1], vec![0, 2], vec![0, 3], vec![1, 2], vec![1, 3], vec![2, 3]]
+        );
+
+        for n in 0..=7 {
+            for k in 0..=n + 1 {
+                let combs: Vec<Vec<usize>> = combinations(n, k).collect();
+                let expected = if k > n { 0 } else { factorial(n) / (factorial(k) * factorial(n - k)) };
+
+                assert_eq!(combs.len(), expected, "{} choose {}", n, k);
+                assert!(combs.windows(2).all(|pair| pair[0] < pair[1]));
+                assert!(combs.iter().all(|comb| comb.windows(2).all(|pair| pair[0] < pair[1])));
+            }
+        }
+    }
+
+    #[test]
+    fn combinatorics_product() {
+        assert_eq!(
+            product(&[2, 3]).collect::<Vec<_>>(),
+            vec![vec![0, 0], vec![0, 1], vec![0, 2], vec![1, 0], vec![1, 1], vec![1, 2]]
+        );
+        assert_eq!(product(&[]).collect::<Vec<_>>(), vec![Vec::<usize>::new()]);
+        assert_eq!(product(&[3, 0, 2]).count(), 0);
+        assert_eq!(product(&[2, 3, 4, 1]).count(), 24);
+    }
+}
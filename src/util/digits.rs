@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::result;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// The decimal digits of `n`, most significant first
+pub fn digits(mut n: u64) -> Vec<u8> {
+    let mut digits = vec![];
+    loop {
+        digits.push((n % 10) as u8);
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+
+    digits.reverse();
+    digits
+}
+
+// Exactly `width` decimal digits of `n`, least significant first, so higher digits are
+// dropped and missing ones are zero. This is the order Intcode parameter modes are in.
+pub fn low_digits(n: u64, width: usize) -> Vec<u8> {
+    (0..width).scan(n, |n, _| {
+        let digit = (*n % 10) as u8;
+        *n /= 10;
+        Some(digit)
+    }).collect()
+}
+
+// The last `N` decimal digits of `n`, most significant first and padded with zeros
+pub fn fixed_digits<const N: usize>(n: u64) -> [u8; N] {
+    let mut digits = [0; N];
+    for (digit, low) in digits.iter_mut().rev().zip(low_digits(n, N)) {
+        *digit = low;
+    }
+    digits
+}
+
+pub fn from_digits(digits: &[u8]) -> u64 {
+    digits.iter().fold(0, |n, &d| n * 10 + u64::from(d))
+}
+
+// Reads a string of decimal digits, such as a day 8 image or a day 16 signal
+pub fn parse_digits(s: &str) -> Result<Vec<u8>> {
+    s.trim().chars().enumerate().map(|(idx, c)| match c.to_digit(10) {
+        Some(d) => Ok(d as u8),
+        None => err!("Expected a digit at position {}, found {:?}", idx + 1, c)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_round_trip() {
+        assert_eq!(digits(0), vec![0]);
+        assert_eq!(digits(1002), vec![1, 0, 0, 2]);
+        assert_eq!(from_digits(&digits(123_456_789)), 123_456_789);
+        assert_eq!(fixed_digits::<6>(1234), [0, 0, 1, 2, 3, 4]);
+        assert_eq!(fixed_digits::<2>(1234), [3, 4]);
+    }
+
+    #[test]
+    fn digits_for_parameter_modes() {
+        assert_eq!(low_digits(1002 / 100, 3), vec![0, 1, 0]);
+        assert_eq!(low_digits(0, 2), vec![0, 0]);
+        assert_eq!(low_digits(7, 0), vec![]);
+        assert_eq!(low_digits(12345, 2), vec![5, 4]);
+    }
+
+    #[test]
+    fn digits_parse() {
+        assert_eq!(parse_digits("0123\n").unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(parse_digits("12x4").unwrap_err().to_string(), "Expected a digit at position 3, found 'x'");
+    }
+}
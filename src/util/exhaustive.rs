@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+
+use util::grid::{Grid2D, Point2};
+
+// Every small case of an input, for tests to check invariants against all of them rather
+// than a few examples. Nothing is random, so a failing case fails every run.
+
+// Every grid of true and false cells from 1x1 up to `max_width` by `max_height`
+pub fn bool_grids(max_width: usize, max_height: usize) -> impl Iterator<Item = Grid2D<bool>> {
+    (1..=max_height).flat_map(move |height| {
+        (1..=max_width).flat_map(move |width| {
+            (0..1u64 << (width * height)).map(move |bits| {
+                Grid2D::from_fn(width, height, |pt| bits >> (pt.y as usize * width + pt.x as usize) & 1 == 1)
+            })
+        })
+    })
+}
+
+// Steps from `start` to every true cell it can reach, found by shortening distances until
+// none change. Too slow for real grids, but simple enough to check the searches against.
+pub fn brute_force_distances(grid: &Grid2D<bool>, start: Point2) -> BTreeMap<Point2, usize> {
+    let mut distances = BTreeMap::new();
+    if grid.get(start) != Some(&true) {
+        return distances;
+    }
+    distances.insert(start, 0);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for pt in grid.points() {
+            let closest = [(0, -1), (-1, 0), (1, 0), (0, 1)].iter()
+                .filter_map(|&(dx, dy)| distances.get(&Point2::new(pt.x + dx, pt.y + dy)))
+                .min()
+                .map(|&dist| dist + 1);
+            if let Some(dist) = closest.filter(|_| grid[pt]) {
+                if distances.get(&pt).is_none_or(|&known| dist < known) {
+                    distances.insert(pt, dist);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    distances
+}
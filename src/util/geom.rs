@@ -0,0 +1,171 @@
+use std::cmp::{self, Ordering};
+
+use util::grid::Point2;
+
+// Orders `a` and `b` by the angle they make around `origin`, sweeping clockwise from
+// straight up with y increasing downwards, as on the puzzle maps. Points in the same
+// direction compare equal, whatever their distance. Only integer arithmetic is used, so
+// there are no rounding surprises for nearly parallel directions.
+pub fn clockwise_cmp(origin: Point2, a: Point2, b: Point2) -> Ordering {
+    let (a, b) = (a - origin, b - origin);
+
+    half_turn(a).cmp(&half_turn(b)).then_with(|| 0.cmp(&cross(a, b)))
+}
+
+// 0 for directions from straight up round to just before straight down, 1 for the rest
+fn half_turn(v: Point2) -> u8 {
+    if v.x > 0 || (v.x == 0 && v.y <= 0) { 0 } else { 1 }
+}
+
+// Positive when `b` is clockwise of `a`, less than half a turn away
+fn cross(a: Point2, b: Point2) -> i64 {
+    a.x * b.y - a.y * b.x
+}
+
+// A straight run of grid cells from `start` to `end` inclusive, which must be horizontal,
+// vertical or diagonal
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Segment {
+    pub start: Point2,
+    pub end: Point2
+}
+
+impl Segment {
+    pub fn new(start: Point2, end: Point2) -> Segment {
+        let (dx, dy) = ((end.x - start.x).abs(), (end.y - start.y).abs());
+        assert!(dx == 0 || dy == 0 || dx == dy, "{} to {} is not a straight line of cells", start, end);
+
+        Segment { start, end }
+    }
+
+    // The single step taken between neighbouring cells
+    pub fn step(&self) -> Point2 {
+        Point2::new((self.end.x - self.start.x).signum(), (self.end.y - self.start.y).signum())
+    }
+
+    // Number of steps from one end to the other
+    pub fn length(&self) -> usize {
+        cmp::max((self.end.x - self.start.x).abs(), (self.end.y - self.start.y).abs()) as usize
+    }
+
+    pub fn is_axis_aligned(&self) -> bool {
+        self.start.x == self.end.x || self.start.y == self.end.y
+    }
+
+    // Every cell covered, from `start` to `end`
+    pub fn points(&self) -> impl Iterator<Item = Point2> {
+        let (start, step) = (self.start, self.step());
+        (0..=self.length() as i64).map(move |n| Point2::new(start.x + n * step.x, start.y + n * step.y))
+    }
+
+    pub fn contains(&self, pt: Point2) -> bool {
+        self.steps_to(pt).is_some()
+    }
+
+    // How far along the segment `pt` is, if it's on it at all
+    pub fn steps_to(&self, pt: Point2) -> Option<usize> {
+        let offset = pt - self.start;
+        let steps = cmp::max(offset.x.abs(), offset.y.abs());
+        let step = self.step();
+
+        if offset == Point2::new(steps * step.x, steps * step.y) && steps as usize <= self.length() {
+            Some(steps as usize)
+        } else {
+            None
+        }
+    }
+
+    // The cells two axis-aligned segments share, which is itself a segment if there are any
+    pub fn overlap(&self, other: &Segment) -> Option<Segment> {
+        assert!(self.is_axis_aligned() && other.is_axis_aligned(), "Can only overlap axis-aligned segments");
+
+        // An axis-aligned segment is its own bounding box, so the overlap is where the boxes meet
+        let min_x = cmp::max(cmp::min(self.start.x, self.end.x), cmp::min(other.start.x, other.end.x));
+        let max_x = cmp::min(cmp::max(self.start.x, self.end.x), cmp::max(other.start.x, other.end.x));
+        let min_y = cmp::max(cmp::min(self.start.y, self.end.y), cmp::min(other.start.y, other.end.y));
+        let max_y = cmp::min(cmp::max(self.start.y, self.end.y), cmp::max(other.start.y, other.end.y));
+
+        if min_x > max_x || min_y > max_y {
+            None
+        } else {
+            Some(Segment::new(Point2::new(min_x, min_y), Point2::new(max_x, max_y)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn angle(origin: Point2, pt: Point2) -> f64 {
+        let angle = ((pt.x - origin.x) as f64).atan2((origin.y - pt.y) as f64);
+        if angle < 0.0 { angle + 2.0 * PI } else { angle }
+    }
+
+    #[test]
+    fn geom_clockwise_order() {
+        let origin = Point2::new(0, 0);
+        let mut points = vec![
+            Point2::new(-1, 0), Point2::new(0, 1), Point2::new(1, -1),
+            Point2::new(0, -1), Point2::new(-1, -1), Point2::new(1, 0),
+        ];
+        points.sort_by(|&a, &b| clockwise_cmp(origin, a, b));
+
+        assert_eq!(points, vec![
+            Point2::new(0, -1), Point2::new(1, -1), Point2::new(1, 0),
+            Point2::new(0, 1), Point2::new(-1, 0), Point2::new(-1, -1),
+        ]);
+        assert_eq!(clockwise_cmp(origin, Point2::new(2, 4), Point2::new(1, 2)), Ordering::Equal);
+    }
+
+    #[test]
+    fn geom_clockwise_matches_atan2() {
+        let origin = Point2::new(3, -2);
+        let points: Vec<Point2> = (-6..=12).flat_map(|x| (-11..=7).map(move |y| Point2::new(x, y)))
+            .filter(|&pt| pt != origin)
+            .collect();
+
+        for &a in &points {
+            for &b in &points {
+                let (angle_a, angle_b) = (angle(origin, a), angle(origin, b));
+                let expected = if (angle_a - angle_b).abs() < 1e-12 {
+                    Ordering::Equal
+                } else {
+                    angle_a.partial_cmp(&angle_b).unwrap()
+                };
+                assert_eq!(clockwise_cmp(origin, a, b), expected, "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn geom_segment_points() {
+        let segment = Segment::new(Point2::new(3, 1), Point2::new(0, 1));
+
+        assert_eq!(segment.length(), 3);
+        assert_eq!(
+            segment.points().collect::<Vec<_>>(),
+            vec![Point2::new(3, 1), Point2::new(2, 1), Point2::new(1, 1), Point2::new(0, 1)]
+        );
+        assert_eq!(segment.steps_to(Point2::new(1, 1)), Some(2));
+        assert_eq!(segment.steps_to(Point2::new(4, 1)), None);
+        assert_eq!(segment.steps_to(Point2::new(1, 2)), None);
+
+        let diagonal = Segment::new(Point2::new(0, 0), Point2::new(-2, 2));
+        assert_eq!(diagonal.points().last(), Some(Point2::new(-2, 2)));
+        assert!(diagonal.contains(Point2::new(-1, 1)));
+        assert!(!diagonal.contains(Point2::new(-1, 0)));
+    }
+
+    #[test]
+    fn geom_segment_overlap() {
+        let horizontal = Segment::new(Point2::new(0, 5), Point2::new(8, 5));
+        let vertical = Segment::new(Point2::new(3, 9), Point2::new(3, 2));
+        let along = Segment::new(Point2::new(10, 5), Point2::new(6, 5));
+
+        assert_eq!(horizontal.overlap(&vertical), Some(Segment::new(Point2::new(3, 5), Point2::new(3, 5))));
+        assert_eq!(horizontal.overlap(&along), Some(Segment::new(Point2::new(6, 5), Point2::new(8, 5))));
+        assert_eq!(vertical.overlap(&along), None);
+    }
+}
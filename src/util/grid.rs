@@ -0,0 +1,851 @@
+use std::cmp::{self, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::iter::{self, FromIterator};
+use std::ops::{Add, Sub, AddAssign, Index, IndexMut};
+use std::result;
+
+use util::iter::windows2;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Point2 {
+    pub x: i64,
+    pub y: i64
+}
+
+impl Point2 {
+    pub fn new(x: i64, y: i64) -> Point2 {
+        Point2 { x, y }
+    }
+
+    pub fn neighbours(self) -> Vec<Point2> {
+        vec![
+            Point2::new(self.x, self.y - 1),
+            Point2::new(self.x - 1, self.y),
+            Point2::new(self.x + 1, self.y),
+            Point2::new(self.x, self.y + 1),
+        ]
+    }
+
+    pub fn manhattan_distance(self, other: Point2) -> i64 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    // Turns the point clockwise about `origin` as seen on screen, with y increasing downwards.
+    // Negative turns go anticlockwise.
+    pub fn rotate_about(self, origin: Point2, quarter_turns: i64) -> Point2 {
+        let offset = self - origin;
+        let turned = match quarter_turns.rem_euclid(4) {
+            0 => offset,
+            1 => Point2::new(-offset.y, offset.x),
+            2 => Point2::new(-offset.x, -offset.y),
+            _ => Point2::new(offset.y, -offset.x),
+        };
+
+        origin + turned
+    }
+}
+
+impl Ord for Point2 {
+    fn cmp(&self, other: &Point2) -> cmp::Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+impl PartialOrd for Point2 {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for Point2 {
+    type Output = Point2;
+
+    fn add(self, other: Point2) -> Point2 {
+        Point2 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl Sub for Point2 {
+    type Output = Point2;
+
+    fn sub(self, other: Point2) -> Point2 {
+        Point2 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl AddAssign for Point2 {
+    fn add_assign(&mut self, other: Self) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl fmt::Debug for Point2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl fmt::Display for Point2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+// Dense rectangular grid stored row by row, with (0, 0) in the top left.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Grid2D<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>
+}
+
+impl<T> Grid2D<T> {
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Grid2D<T>> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+
+        let mut cells = Vec::with_capacity(width * height);
+        for (y, row) in rows.into_iter().enumerate() {
+            if row.len() != width {
+                return err!("Row {} has length {}, expected {}", y, row.len(), width);
+            }
+            cells.extend(row);
+        }
+
+        Ok(
+            Grid2D {
+                width,
+                height,
+                cells
+            }
+        )
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, pt: Point2) -> bool {
+        pt.x >= 0 && pt.y >= 0 && (pt.x as usize) < self.width && (pt.y as usize) < self.height
+    }
+
+    fn idx(&self, pt: Point2) -> Option<usize> {
+        if self.contains(pt) {
+            Some(pt.y as usize * self.width + pt.x as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, pt: Point2) -> Option<&T> {
+        self.idx(pt).map(|idx| &self.cells[idx])
+    }
+
+    pub fn get_mut(&mut self, pt: Point2) -> Option<&mut T> {
+        match self.idx(pt) {
+            Some(idx) => Some(&mut self.cells[idx]),
+            None => None
+        }
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point2> {
+        let width = self.width;
+        (0..self.width * self.height).map(move |idx| Point2::new((idx % width) as i64, (idx / width) as i64))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point2, &T)> {
+        self.points().zip(self.cells.iter())
+    }
+
+    // Orthogonal neighbours of `pt` which lie inside the grid
+    pub fn neighbours(&self, pt: Point2) -> Vec<Point2> {
+        pt.neighbours().into_iter().filter(|&n| self.contains(n)).collect()
+    }
+
+    // A view of the cells in `rect`, or None if it doesn't lie wholly inside the grid
+    pub fn sub_grid(&self, rect: Rect) -> Option<SubGrid<'_, T>> {
+        let inside = rect.origin.x >= 0 && rect.origin.y >= 0
+            && rect.origin.x as usize + rect.width <= self.width
+            && rect.origin.y as usize + rect.height <= self.height;
+        if inside {
+            Some(SubGrid { grid: self, rect })
+        } else {
+            None
+        }
+    }
+
+    // Views of every `width` by `height` block of the grid, overlapping, in reading order
+    // of their top left corners
+    pub fn windows(&self, width: usize, height: usize) -> impl Iterator<Item = SubGrid<'_, T>> {
+        let across = (self.width + 1).saturating_sub(width);
+        let down = if width == 0 { 0 } else { (self.height + 1).saturating_sub(height) };
+        (0..across * down).map(move |idx| SubGrid {
+            grid: self,
+            rect: Rect::new(Point2::new((idx % across) as i64, (idx / across) as i64), width, height)
+        })
+    }
+}
+
+impl<T: Clone> Grid2D<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Grid2D<T> {
+        Grid2D {
+            width,
+            height,
+            cells: vec![fill; width * height]
+        }
+    }
+
+    // Builds a grid by looking up each of its cells
+    pub fn from_fn<F: Fn(Point2) -> T>(width: usize, height: usize, cell: F) -> Grid2D<T> {
+        let cells = (0..width * height)
+            .map(|idx| cell(Point2::new((idx % width) as i64, (idx / width) as i64)))
+            .collect();
+
+        Grid2D { width, height, cells }
+    }
+
+    // A quarter turn clockwise, so the left column becomes the top row
+    pub fn rotate90(&self) -> Grid2D<T> {
+        let height = self.height as i64;
+        Grid2D::from_fn(self.height, self.width, |pt| self[Point2::new(pt.y, height - 1 - pt.x)].clone())
+    }
+
+    // Mirrored left to right
+    pub fn flip_horizontal(&self) -> Grid2D<T> {
+        let width = self.width as i64;
+        Grid2D::from_fn(self.width, self.height, |pt| self[Point2::new(width - 1 - pt.x, pt.y)].clone())
+    }
+
+    // Mirrored top to bottom
+    pub fn flip_vertical(&self) -> Grid2D<T> {
+        let height = self.height as i64;
+        Grid2D::from_fn(self.width, self.height, |pt| self[Point2::new(pt.x, height - 1 - pt.y)].clone())
+    }
+}
+
+impl Grid2D<char> {
+    pub fn from_lines(s: &str) -> Result<Grid2D<char>> {
+        Grid2D::from_rows(s.lines().map(|line| line.chars().collect()).collect())
+    }
+}
+
+impl<T> Index<Point2> for Grid2D<T> {
+    type Output = T;
+
+    fn index(&self, pt: Point2) -> &T {
+        self.get(pt).unwrap_or_else(|| panic!("{} is outside the grid", pt))
+    }
+}
+
+impl<T> IndexMut<Point2> for Grid2D<T> {
+    fn index_mut(&mut self, pt: Point2) -> &mut T {
+        self.get_mut(pt).unwrap_or_else(|| panic!("{} is outside the grid", pt))
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid2D<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.cells.chunks(cmp::max(self.width, 1)) {
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// The smallest box holding all the points, as its top left and bottom right corners
+pub fn bounds<I: IntoIterator<Item = Point2>>(points: I) -> Option<(Point2, Point2)> {
+    points.into_iter().fold(None, |bounds, pt| match bounds {
+        None => Some((pt, pt)),
+        Some((min, max)) => Some((
+            Point2::new(cmp::min(min.x, pt.x), cmp::min(min.y, pt.y)),
+            Point2::new(cmp::max(max.x, pt.x), cmp::max(max.y, pt.y))
+        ))
+    })
+}
+
+// A block of cells `width` across and `height` down, with `origin` in its top left
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Rect {
+    pub origin: Point2,
+    pub width: usize,
+    pub height: usize
+}
+
+impl Rect {
+    pub fn new(origin: Point2, width: usize, height: usize) -> Rect {
+        Rect { origin, width, height }
+    }
+
+    pub fn contains(&self, pt: Point2) -> bool {
+        let offset = pt - self.origin;
+        offset.x >= 0 && offset.y >= 0 && (offset.x as usize) < self.width && (offset.y as usize) < self.height
+    }
+}
+
+// Borrows the cells of a `Rect` inside a grid, indexed from its own top left corner, so
+// a block can be looked at without copying it out
+#[derive(Debug)]
+pub struct SubGrid<'a, T: 'a> {
+    grid: &'a Grid2D<T>,
+    rect: Rect
+}
+
+// Derived Clone and Copy would need T to be Clone
+impl<'a, T> Clone for SubGrid<'a, T> {
+    fn clone(&self) -> SubGrid<'a, T> {
+        *self
+    }
+}
+
+impl<'a, T> Copy for SubGrid<'a, T> {}
+
+impl<'a, T> SubGrid<'a, T> {
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn width(&self) -> usize {
+        self.rect.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.rect.height
+    }
+
+    pub fn get(&self, pt: Point2) -> Option<&'a T> {
+        if Rect::new(Point2::default(), self.rect.width, self.rect.height).contains(pt) {
+            self.grid.get(self.rect.origin + pt)
+        } else {
+            None
+        }
+    }
+
+    // The cells of one row of the view, as a slice of the grid
+    pub fn row(&self, y: usize) -> Option<&'a [T]> {
+        if y >= self.rect.height {
+            return None;
+        }
+        let start = (self.rect.origin.y as usize + y) * self.grid.width + self.rect.origin.x as usize;
+        Some(&self.grid.cells[start..start + self.rect.width])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> {
+        let view = *self;
+        (0..self.rect.height).filter_map(move |y| view.row(y))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point2, &'a T)> {
+        let width = self.rect.width;
+        self.rows().flat_map(|row| row.iter()).enumerate()
+            .map(move |(idx, cell)| (Point2::new((idx % width) as i64, (idx / width) as i64), cell))
+    }
+}
+
+impl<'a, T> Index<Point2> for SubGrid<'a, T> {
+    type Output = T;
+
+    fn index(&self, pt: Point2) -> &T {
+        self.get(pt).unwrap_or_else(|| panic!("{} is outside the view", pt))
+    }
+}
+
+impl<'a, T: Clone> SubGrid<'a, T> {
+    pub fn to_grid(self) -> Grid2D<T> {
+        Grid2D::from_fn(self.rect.width, self.rect.height, |pt| self[pt].clone())
+    }
+}
+
+// Cells keyed by position, for maps which are discovered bit by bit and can stretch off
+// in any direction, including to negative coordinates
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Point2, T>
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new() -> SparseGrid<T> {
+        SparseGrid {
+            cells: HashMap::new()
+        }
+    }
+
+    pub fn insert(&mut self, pt: Point2, value: T) -> Option<T> {
+        self.cells.insert(pt, value)
+    }
+
+    pub fn get(&self, pt: Point2) -> Option<&T> {
+        self.cells.get(&pt)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point2, &T)> {
+        self.cells.iter().map(|(&pt, value)| (pt, value))
+    }
+
+    pub fn bounds(&self) -> Option<(Point2, Point2)> {
+        bounds(self.cells.keys().cloned())
+    }
+}
+
+impl<T: Clone> SparseGrid<T> {
+    // Copies the cells into a grid just big enough to hold them, filling any gaps. Also
+    // returns where the grid's top left corner was, which is subtracted from every point.
+    pub fn to_dense(&self, fill: T) -> (Grid2D<T>, Point2) {
+        let (min, max) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return (Grid2D::new(0, 0, fill), Point2::new(0, 0))
+        };
+
+        let width = (max.x - min.x + 1) as usize;
+        let height = (max.y - min.y + 1) as usize;
+        let grid = Grid2D::from_fn(width, height, |pt| self.get(pt + min).unwrap_or(&fill).clone());
+
+        (grid, min)
+    }
+}
+
+impl<T> FromIterator<(Point2, T)> for SparseGrid<T> {
+    fn from_iter<I: IntoIterator<Item = (Point2, T)>>(iter: I) -> SparseGrid<T> {
+        SparseGrid {
+            cells: iter.into_iter().collect()
+        }
+    }
+}
+
+// The cells reachable from a starting cell, with the number of steps to each
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Region {
+    distances: BTreeMap<Point2, usize>
+}
+
+impl Region {
+    pub fn len(&self) -> usize {
+        self.distances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    pub fn contains(&self, pt: Point2) -> bool {
+        self.distances.contains_key(&pt)
+    }
+
+    pub fn distance(&self, pt: Point2) -> Option<usize> {
+        self.distances.get(&pt).cloned()
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point2> + '_ {
+        self.distances.keys().cloned()
+    }
+
+    // Steps to the furthest cell, e.g. how long something takes to spread over the region
+    pub fn max_distance(&self) -> usize {
+        self.distances.values().cloned().max().unwrap_or(0)
+    }
+
+    // The distances laid out over a grid of the given size, None outside the region
+    pub fn distance_grid(&self, width: usize, height: usize) -> Grid2D<Option<usize>> {
+        let mut grid = Grid2D::new(width, height, None);
+        for (&pt, &dist) in &self.distances {
+            if grid.get(pt).is_some() {
+                grid[pt] = Some(dist);
+            }
+        }
+        grid
+    }
+}
+
+// Breadth first search out from `start` over the cells where `passable` holds. The
+// region is empty if `start` itself isn't passable.
+pub fn flood_fill<T, F: Fn(&T) -> bool>(grid: &Grid2D<T>, start: Point2, passable: F) -> Region {
+    flood_fill_from(grid, iter::once(start), passable)
+}
+
+// As `flood_fill`, but searching out from all of `starts` at once, so each distance is
+// to the nearest of them. Starts which aren't passable are left out.
+pub fn flood_fill_from<T, F, I>(grid: &Grid2D<T>, starts: I, passable: F) -> Region
+    where F: Fn(&T) -> bool, I: IntoIterator<Item = Point2>
+{
+    let mut distances = BTreeMap::new();
+    let mut queue = VecDeque::new();
+    for start in starts {
+        if grid.get(start).is_some_and(&passable) && !distances.contains_key(&start) {
+            distances.insert(start, 0);
+            queue.push_back(start);
+        }
+    }
+
+    while let Some(pt) = queue.pop_front() {
+        let dist = distances[&pt];
+        for next in grid.neighbours(pt) {
+            if passable(&grid[next]) && !distances.contains_key(&next) {
+                distances.insert(next, dist + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    Region { distances }
+}
+
+// Splits the passable cells into connected regions. Each cell is labelled with the index
+// of its region in the returned list, or None if it isn't passable.
+pub fn label_regions<T, F: Fn(&T) -> bool>(grid: &Grid2D<T>, passable: F) -> (Grid2D<Option<usize>>, Vec<Region>) {
+    let mut labels = Grid2D::new(grid.width(), grid.height(), None);
+    let mut regions: Vec<Region> = vec![];
+
+    for pt in grid.points() {
+        if labels[pt].is_some() || !passable(&grid[pt]) {
+            continue;
+        }
+
+        let region = flood_fill(grid, pt, &passable);
+        for member in region.points() {
+            labels[member] = Some(regions.len());
+        }
+        regions.push(region);
+    }
+
+    (labels, regions)
+}
+
+// Heuristics for `astar`, which never overestimate the steps between two points
+pub fn manhattan(a: Point2, b: Point2) -> usize {
+    a.manhattan_distance(b) as usize
+}
+
+pub fn chebyshev(a: Point2, b: Point2) -> usize {
+    cmp::max((a.x - b.x).abs(), (a.y - b.y).abs()) as usize
+}
+
+// Makes `astar` a plain breadth first search
+pub fn zero(_: Point2, _: Point2) -> usize {
+    0
+}
+
+// A route through a grid: every point on it, starting point included, and the step
+// taken between each, e.g. (0, -1) for a move up
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Path {
+    pub points: Vec<Point2>,
+    pub moves: Vec<Point2>
+}
+
+impl Path {
+    // The number of steps taken
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+// Follows a search's map of where each point was first reached from back from `end`
+// to the point it started at
+pub fn reconstruct_path(came_from: &HashMap<Point2, Point2>, end: Point2) -> Path {
+    let mut points = vec![end];
+    while let Some(&previous) = came_from.get(points.last().unwrap()) {
+        points.push(previous);
+    }
+    points.reverse();
+
+    let moves = windows2(&points).map(|(&from, &to)| to - from).collect();
+    Path { points, moves }
+}
+
+// A* search for a shortest path of single orthogonal steps from `start` to `goal`,
+// only stepping onto points for which `passable` holds. Ties are broken by point
+// order, so the same path is found every time.
+pub fn astar<P, H>(start: Point2, goal: Point2, passable: P, heuristic: H) -> Option<Path>
+    where P: Fn(Point2) -> bool, H: Fn(Point2, Point2) -> usize
+{
+    let mut came_from: HashMap<Point2, Point2> = HashMap::new();
+    let mut steps: HashMap<Point2, usize> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+
+    steps.insert(start, 0);
+    queue.push(Reverse((heuristic(start, goal), 0, start)));
+
+    while let Some(Reverse((_, dist, pt))) = queue.pop() {
+        if pt == goal {
+            return Some(reconstruct_path(&came_from, goal));
+        }
+
+        if dist > steps[&pt] {
+            continue;
+        }
+
+        for next in pt.neighbours() {
+            if !passable(next) || steps.get(&next).is_some_and(|&known| known <= dist + 1) {
+                continue;
+            }
+
+            steps.insert(next, dist + 1);
+            came_from.insert(next, pt);
+            queue.push(Reverse((dist + 1 + heuristic(next, goal), dist + 1, next)));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use util::exhaustive::{bool_grids, brute_force_distances};
+
+    #[test]
+    fn grid_from_lines() {
+        let grid = Grid2D::from_lines("#.#\n.@.").unwrap();
+
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert_eq!(grid[Point2::new(1, 1)], '@');
+        assert_eq!(grid.get(Point2::new(3, 0)), None);
+        assert_eq!(grid.get(Point2::new(-1, 0)), None);
+        assert_eq!(grid.to_string(), "#.#\n.@.\n");
+    }
+
+    #[test]
+    fn grid_rejects_ragged_rows() {
+        assert!(Grid2D::from_lines("###\n##").is_err());
+    }
+
+    #[test]
+    fn grid_neighbours_stay_in_bounds() {
+        let grid = Grid2D::new(2, 2, 0);
+
+        assert_eq!(
+            grid.neighbours(Point2::new(0, 0)),
+            vec![Point2::new(1, 0), Point2::new(0, 1)]
+        );
+    }
+
+    #[test]
+    fn grid_transforms_compose_on_every_small_grid() {
+        // Every size from 1x1 to 3x3, with every pattern of cells
+        assert_eq!(bool_grids(3, 3).count(), 2 + 4 + 8 + 4 + 16 + 64 + 8 + 64 + 512);
+        for grid in bool_grids(3, 3) {
+            let turned = grid.rotate90();
+            assert_eq!((turned.width(), turned.height()), (grid.height(), grid.width()));
+            assert_eq!(turned.rotate90().rotate90().rotate90(), grid);
+            assert_eq!(turned.rotate90(), grid.flip_horizontal().flip_vertical());
+            assert_eq!(grid.flip_horizontal().flip_horizontal(), grid);
+            assert_eq!(grid.flip_vertical().flip_vertical(), grid);
+            // A quarter turn is a flip about the diagonal followed by a mirror
+            let transposed = Grid2D::from_fn(grid.height(), grid.width(), |pt| grid[Point2::new(pt.y, pt.x)]);
+            assert_eq!(turned, transposed.flip_horizontal());
+        }
+    }
+
+    #[test]
+    fn grid_neighbours_are_symmetric_on_every_small_grid() {
+        // Neighbours only depend on the size of the grid, so one grid of each size will do
+        for grid in bool_grids(3, 3).filter(|grid| grid.points().all(|pt| !grid[pt])) {
+            for pt in grid.points() {
+                let neighbours = grid.neighbours(pt);
+                assert!(neighbours.iter().all(|&n| grid.contains(n) && pt.manhattan_distance(n) == 1));
+                assert!(neighbours.iter().all(|&n| grid.neighbours(n).contains(&pt)));
+                assert_eq!(neighbours.len(), pt.neighbours().iter().filter(|&&n| grid.contains(n)).count());
+            }
+        }
+    }
+
+    #[test]
+    fn grid_searches_match_brute_force_on_every_small_grid() {
+        for grid in bool_grids(3, 3) {
+            let (labels, regions) = label_regions(&grid, |&open| open);
+            for start in grid.points() {
+                let expected = brute_force_distances(&grid, start);
+                let region = flood_fill(&grid, start, |&open| open);
+                assert_eq!(region.points().map(|pt| (pt, region.distance(pt).unwrap())).collect::<BTreeMap<_, _>>(), expected, "from {} in\n{}", start, grid);
+
+                // Every cell reached is in the same labelled region as the start
+                match labels[start] {
+                    Some(label) => {
+                        assert!(expected.keys().all(|&pt| labels[pt] == Some(label)));
+                        assert_eq!(regions[label].len(), expected.len());
+                    },
+                    None => assert!(expected.is_empty())
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_views_borrow_blocks() {
+        let grid = Grid2D::from_lines("abcd\nefgh\nijkl").unwrap();
+        let view = grid.sub_grid(Rect::new(Point2::new(1, 1), 2, 2)).unwrap();
+        assert_eq!((view.width(), view.height()), (2, 2));
+        assert_eq!((view[Point2::new(0, 0)], view[Point2::new(1, 1)]), ('f', 'k'));
+        assert_eq!(view.get(Point2::new(2, 0)), None);
+        assert_eq!(view.row(1), Some(&['j', 'k'][..]));
+        assert_eq!(view.to_grid(), Grid2D::from_lines("fg\njk").unwrap());
+        assert_eq!(view.iter().map(|(_, &c)| c).collect::<String>(), "fgjk");
+        assert!(grid.sub_grid(Rect::new(Point2::new(3, 0), 2, 1)).is_none());
+        assert!(grid.sub_grid(Rect::new(Point2::new(-1, 0), 1, 1)).is_none());
+
+        let windows: Vec<Rect> = grid.windows(3, 2).map(|view| view.rect()).collect();
+        assert_eq!(windows, vec![
+            Rect::new(Point2::new(0, 0), 3, 2),
+            Rect::new(Point2::new(1, 0), 3, 2),
+            Rect::new(Point2::new(0, 1), 3, 2),
+            Rect::new(Point2::new(1, 1), 3, 2),
+        ]);
+        assert_eq!(grid.windows(5, 1).count(), 0);
+        assert_eq!(grid.windows(4, 3).count(), 1);
+
+        // Every window matches the block copied out cell by cell
+        for window in grid.windows(2, 2) {
+            let origin = window.rect().origin;
+            assert_eq!(window.to_grid(), Grid2D::from_fn(2, 2, |pt| grid[origin + pt]));
+        }
+    }
+
+    #[test]
+    fn grid_flood_fill() {
+        let grid = Grid2D::from_lines("
+#######
+#.....#
+#.###.#
+#..#..#
+#######".trim()).unwrap();
+        let region = flood_fill(&grid, Point2::new(1, 1), |&c| c == '.');
+
+        assert_eq!(region.len(), 11);
+        assert_eq!(region.distance(Point2::new(5, 3)), Some(6));
+        assert_eq!(region.max_distance(), 7);
+        assert!(!region.contains(Point2::new(3, 3)));
+        assert!(flood_fill(&grid, Point2::new(0, 0), |&c| c == '.').is_empty());
+    }
+
+    #[test]
+    fn grid_flood_fill_from_several_starts() {
+        let grid = Grid2D::from_lines("
+.....
+.#.#.
+.....".trim()).unwrap();
+        let region = flood_fill_from(&grid, vec![Point2::new(0, 0), Point2::new(4, 2), Point2::new(1, 1)], |&c| c == '.');
+        let distances = region.distance_grid(grid.width(), grid.height());
+
+        assert_eq!(region.len(), 13);
+        assert_eq!(distances[Point2::new(2, 1)], Some(3));
+        assert_eq!(distances[Point2::new(4, 0)], Some(2));
+        assert_eq!(distances[Point2::new(1, 1)], None);
+        assert_eq!(region.max_distance(), 3);
+    }
+
+    #[test]
+    fn grid_astar() {
+        let grid = Grid2D::from_lines("
+#######
+#.....#
+#.###.#
+#..#..#
+#######".trim()).unwrap();
+        let open = |pt: Point2| grid.get(pt) == Some(&'.');
+        let (start, goal) = (Point2::new(1, 3), Point2::new(5, 3));
+
+        let path = astar(start, goal, open, manhattan).unwrap();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path.points.first(), Some(&start));
+        assert_eq!(path.points.last(), Some(&goal));
+        assert_eq!(path.moves[0], Point2::new(0, -1));
+        assert!(path.points.windows(2).all(|pair| pair[0].manhattan_distance(pair[1]) == 1));
+
+        for &heuristic in &[chebyshev as fn(Point2, Point2) -> usize, zero] {
+            assert_eq!(astar(start, goal, open, heuristic).map(|path| path.len()), Some(8));
+        }
+        assert_eq!(astar(start, Point2::new(3, 3), open, manhattan), None);
+        assert!(astar(start, start, open, manhattan).unwrap().is_empty());
+    }
+
+    #[test]
+    fn grid_label_regions() {
+        let grid = Grid2D::from_lines("
+..#..
+..#.#
+###..
+.#...".trim()).unwrap();
+        let (labels, regions) = label_regions(&grid, |&c| c == '.');
+
+        assert_eq!(regions.iter().map(|region| region.len()).collect::<Vec<_>>(), vec![4, 8, 1]);
+        assert_eq!(labels[Point2::new(1, 1)], Some(0));
+        assert_eq!(labels[Point2::new(4, 3)], Some(1));
+        assert_eq!(labels[Point2::new(0, 3)], Some(2));
+        assert_eq!(labels[Point2::new(2, 0)], None);
+    }
+
+    #[test]
+    fn grid_rotate_point() {
+        let origin = Point2::new(1, 1);
+        let up = Point2::new(1, 0);
+
+        assert_eq!(up.rotate_about(origin, 1), Point2::new(2, 1));
+        assert_eq!(up.rotate_about(origin, 2), Point2::new(1, 2));
+        assert_eq!(up.rotate_about(origin, -1), Point2::new(0, 1));
+        assert_eq!(up.rotate_about(origin, 7), up.rotate_about(origin, -1));
+        assert_eq!(Point2::new(3, -2).rotate_about(Point2::new(0, 0), 4), Point2::new(3, -2));
+    }
+
+    #[test]
+    fn grid_rotate_and_flip() {
+        let grid = Grid2D::from_lines("ab\ncd\nef").unwrap();
+
+        assert_eq!(grid.rotate90().to_string(), "eca\nfdb\n");
+        assert_eq!(grid.rotate90().rotate90().rotate90().rotate90(), grid);
+        assert_eq!(grid.flip_horizontal().to_string(), "ba\ndc\nfe\n");
+        assert_eq!(grid.flip_vertical().to_string(), "ef\ncd\nab\n");
+    }
+
+    #[test]
+    fn grid_sparse_to_dense() {
+        let mut sparse = SparseGrid::new();
+        sparse.insert(Point2::new(-2, 3), '#');
+        sparse.insert(Point2::new(1, 2), '@');
+        sparse.insert(Point2::new(0, 4), '#');
+
+        assert_eq!(sparse.bounds(), Some((Point2::new(-2, 2), Point2::new(1, 4))));
+
+        let (dense, origin) = sparse.to_dense('.');
+        assert_eq!(origin, Point2::new(-2, 2));
+        assert_eq!(dense.to_string(), "...@\n#...\n..#.\n");
+        assert_eq!(dense[Point2::new(1, 2) - origin], '@');
+
+        assert_eq!(bounds(vec![]), None);
+        assert_eq!(SparseGrid::<char>::new().to_dense('.').0.width(), 0);
+    }
+}
@@ -0,0 +1,237 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::result;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use ruzstd::StreamingDecoder;
+
+use util::cache;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// Reads a puzzle input, decompressing it first if it ends in `.gz` or `.zst`
+pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let path = path.as_ref();
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => GzDecoder::new(file).read_to_string(&mut contents)?,
+        Some("zst") => {
+            let mut decoder = StreamingDecoder::new(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            decoder.read_to_string(&mut contents)?
+        },
+        _ => file.read_to_string(&mut contents)?
+    };
+
+    Ok(contents)
+}
+
+// Reads an Intcode program, keeping it parsed for the next time it's asked for
+pub fn read_intcode(path: &str) -> Result<Vec<i64>> {
+    cache::warm(path, |s| {
+        validate_intcode(s)?;
+        Ok(s.trim().split(',').map(|n| n.trim().parse()).collect::<result::Result<_, _>>()?)
+    })
+}
+
+// Finds the input at `path`, or a compressed copy of it next to it
+pub fn find_file(path: &str) -> Option<String> {
+    [path.to_string(), format!("{}.gz", path), format!("{}.zst", path)].iter()
+        .find(|candidate| Path::new(candidate).exists())
+        .cloned()
+}
+
+// A stable FNV-1a hash, which comes out the same on every machine and build, unlike the
+// standard library's hasher
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Writes a gzipped copy of a plain text input next to it, returning the new file's name.
+// The original is only removed when asked, as it may well be checked in.
+pub fn compress_file(path: &str, remove: bool) -> io::Result<String> {
+    let compressed = format!("{}.gz", path);
+    let mut encoder = GzEncoder::new(File::create(&compressed)?, Compression::best());
+    encoder.write_all(fs::read(path)?.as_slice())?;
+    encoder.finish()?;
+    if remove {
+        fs::remove_file(path)?;
+    }
+
+    Ok(compressed)
+}
+
+// Checks that an Intcode program is a comma-separated list of integers, reporting the
+// line and column (both counted from 1) of the first value which isn't. The days read a
+// program by trimming the whole file and parsing each value between the commas as it is,
+// so the program has to be on one line, with no space around its values.
+pub fn validate_intcode(s: &str) -> Result<()> {
+    // Blank lines are skipped rather than trimmed off, so that the line numbers match the file
+    let mut lines = s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let (line_idx, line) = match lines.next() {
+        Some(line) => line,
+        None => return err!("Intcode program is empty")
+    };
+
+    let mut column = line.chars().take_while(|c| c.is_whitespace()).count() + 1;
+    for value in line.trim().split(',') {
+        if value.parse::<i64>().is_err() {
+            return err!(
+                "line {}, column {}: expected an integer but found {:?}",
+                line_idx + 1,
+                column,
+                truncate(value)
+            );
+        }
+        column += value.chars().count() + 1;
+    }
+
+    match lines.next() {
+        Some((line_idx, _)) => err!("line {}: expected the whole program on one line", line_idx + 1),
+        None => Ok(())
+    }
+}
+
+// Checks that a grid is rectangular and only uses the characters in `allowed`
+pub fn validate_grid(s: &str, allowed: &str) -> Result<()> {
+    let lines: Vec<(usize, &str)> = s.lines()
+        .map(|line| line.trim_end())
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    let width = match lines.first() {
+        Some((_, line)) => line.chars().count(),
+        None => return err!("Grid is empty")
+    };
+
+    for &(line_idx, line) in &lines {
+        if let Some((column, c)) = line.chars().enumerate().find(|&(_, c)| !allowed.contains(c)) {
+            return err!(
+                "line {}, column {}: unexpected character {:?}, expected one of {:?}",
+                line_idx + 1,
+                column + 1,
+                c,
+                allowed
+            );
+        }
+
+        let line_width = line.chars().count();
+        if line_width != width {
+            return err!(
+                "line {}: grid is not rectangular, line has width {} but the first line has width {}",
+                line_idx + 1,
+                line_width,
+                width
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn truncate(s: &str) -> String {
+    if s.chars().count() > 20 {
+        format!("{}...", s.chars().take(20).collect::<String>())
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn input_accepts_intcode() {
+        assert!(validate_intcode("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99\n").is_ok());
+    }
+
+    #[test]
+    fn input_reports_bad_intcode() {
+        let error = validate_intcode("1,0,0,3,<html>,99").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 9: expected an integer but found \"<html>\""
+        );
+
+        let error = validate_intcode("1,0,0,3,\n99,,1").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 9: expected an integer but found \"\""
+        );
+
+        let error = validate_intcode("\n  1,0,x,3\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 7: expected an integer but found \"x\""
+        );
+
+        // What the days' parsers would fail on
+        let error = validate_intcode("1, 0,0,99").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1, column 3: expected an integer but found \" 0\""
+        );
+
+        let error = validate_intcode("1,0,0,3\n99\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: expected the whole program on one line");
+    }
+
+    #[test]
+    fn input_reads_compressed_files() {
+        let dir = env::temp_dir().join(format!("aoc_2019_input_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("day01.txt");
+        let path = path.to_str().unwrap();
+        fs::write(path, "12\n14\n").unwrap();
+
+        let compressed = compress_file(path, false).unwrap();
+        assert!(Path::new(path).exists());
+        assert_eq!(read_file(&compressed).unwrap(), "12\n14\n");
+        compress_file(path, true).unwrap();
+        assert!(!Path::new(path).exists());
+        assert_eq!(find_file(path), Some(compressed.clone()));
+        assert_eq!(read_file(&compressed).unwrap(), "12\n14\n");
+
+        // There's no zstd encoder to hand, but a frame holding one raw block is simple
+        // enough to write out: the magic number, a header giving the size, then the block
+        let contents = b"1969\n100756\n";
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x20, contents.len() as u8];
+        let block = 1 | (contents.len() as u32) << 3;
+        frame.extend_from_slice(&block.to_le_bytes()[..3]);
+        frame.extend_from_slice(contents);
+        fs::remove_file(&compressed).unwrap();
+        fs::write(format!("{}.zst", path), frame).unwrap();
+        assert_eq!(find_file(path), Some(format!("{}.zst", path)));
+        assert_eq!(read_file(format!("{}.zst", path)).unwrap(), "1969\n100756\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn input_reports_bad_grid() {
+        assert!(validate_grid(".#..#\n.....\n#####\n", "#.").is_ok());
+
+        let error = validate_grid(".#..#\n..x..\n", "#.").unwrap_err();
+        assert!(error.to_string().starts_with("line 2, column 3: unexpected character 'x'"));
+
+        let error = validate_grid(".#..#\n...\n", "#.").unwrap_err();
+        assert!(error.to_string().starts_with("line 2: grid is not rectangular"));
+
+        let error = validate_grid("\n.#..#\n..x..\n", "#.").unwrap_err();
+        assert!(error.to_string().starts_with("line 3, column 3: unexpected character 'x'"));
+    }
+}
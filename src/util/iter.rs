@@ -0,0 +1,75 @@
+// Small iterator helpers for loops that would otherwise collect into a Vec or pull in
+// itertools just to look at neighbouring items
+
+// Maps each full chunk of `size` items, ignoring any left over at the end
+pub fn chunks_exact_mapped<'a, T, R, F>(items: &'a [T], size: usize, f: F) -> impl Iterator<Item = R> + 'a
+    where F: FnMut(&'a [T]) -> R + 'a
+{
+    items.chunks_exact(size).map(f)
+}
+
+// Each pair of neighbouring items, e.g. (1, 2) and (2, 3) from [1, 2, 3]
+pub fn windows2<T>(items: &[T]) -> impl Iterator<Item = (&T, &T)> {
+    items.iter().zip(items.iter().skip(1))
+}
+
+// Each run of three neighbouring items
+pub fn windows3<T>(items: &[T]) -> impl Iterator<Item = (&T, &T, &T)> {
+    items.iter().zip(items.iter().skip(1)).zip(items.iter().skip(2))
+        .map(|((a, b), c)| (a, b, c))
+}
+
+// Like `take_while`, but stops after the first item for which `done` holds rather than
+// before it, so the item that ended a search is kept
+pub fn take_until<I, F>(iter: I, done: F) -> TakeUntil<I, F>
+    where I: Iterator, F: FnMut(&I::Item) -> bool
+{
+    TakeUntil {
+        iter,
+        done,
+        finished: false
+    }
+}
+
+pub struct TakeUntil<I, F> {
+    iter: I,
+    done: F,
+    finished: bool
+}
+
+impl<I, F> Iterator for TakeUntil<I, F>
+    where I: Iterator, F: FnMut(&I::Item) -> bool
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let item = self.iter.next()?;
+        self.finished = (self.done)(&item);
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iter_chunks_and_windows() {
+        let items = [1, 2, 3, 4, 5, 6, 7];
+
+        assert_eq!(chunks_exact_mapped(&items, 3, |chunk| chunk.iter().sum::<i32>()).collect::<Vec<_>>(), vec![6, 15]);
+        assert_eq!(windows2(&items[..3]).collect::<Vec<_>>(), vec![(&1, &2), (&2, &3)]);
+        assert_eq!(windows3(&items[..4]).map(|(a, b, c)| a * b * c).collect::<Vec<_>>(), vec![6, 24]);
+        assert_eq!(windows2(&items[..1]).count(), 0);
+    }
+
+    #[test]
+    fn iter_take_until() {
+        assert_eq!(take_until(1.., |&n| n * n > 10).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(take_until(vec![5, 6].into_iter(), |_| false).collect::<Vec<_>>(), vec![5, 6]);
+    }
+}
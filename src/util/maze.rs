@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, HashMap};
+
+use util::grid::{Grid2D, Point2};
+use util::search::BucketQueue;
+
+// Undirected graph between the interesting cells of a maze, weighted by the number of
+// steps along the corridor joining them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WeightedGraph {
+    edges: BTreeMap<Point2, Vec<(Point2, usize)>>,
+    max_weight: usize
+}
+
+impl WeightedGraph {
+    pub fn new() -> WeightedGraph {
+        WeightedGraph {
+            edges: BTreeMap::new(),
+            max_weight: 0
+        }
+    }
+
+    pub fn add_node(&mut self, node: Point2) {
+        self.edges.entry(node).or_default();
+    }
+
+    // Adds an edge in both directions, keeping only the shortest if the nodes are already joined
+    pub fn add_edge(&mut self, a: Point2, b: Point2, weight: usize) {
+        if a == b {
+            return;
+        }
+        self.max_weight = self.max_weight.max(weight);
+
+        for &(from, to) in [(a, b), (b, a)].iter() {
+            let adjacent = self.edges.entry(from).or_default();
+            match adjacent.iter_mut().find(|(node, _)| *node == to) {
+                Some(edge) => edge.1 = edge.1.min(weight),
+                None => adjacent.push((to, weight))
+            }
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &Point2> {
+        self.edges.keys()
+    }
+
+    pub fn contains(&self, node: Point2) -> bool {
+        self.edges.contains_key(&node)
+    }
+
+    pub fn neighbours(&self, node: Point2) -> &[(Point2, usize)] {
+        self.edges.get(&node).map_or(&[], |adjacent| adjacent.as_slice())
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().map(|adjacent| adjacent.len()).sum::<usize>() / 2
+    }
+
+    // Dijkstra from `from` to `to`, returning None if they are not connected
+    pub fn shortest_path(&self, from: Point2, to: Point2) -> Option<usize> {
+        let mut dists: HashMap<Point2, usize> = HashMap::new();
+        let mut queue = BucketQueue::new(self.max_weight);
+
+        dists.insert(from, 0);
+        queue.push(0, from);
+
+        while let Some((dist, node)) = queue.pop() {
+            if node == to {
+                return Some(dist);
+            }
+
+            if dist > dists[&node] {
+                continue;
+            }
+
+            for &(next, weight) in self.neighbours(node) {
+                let new_dist = dist + weight;
+                if !dists.contains_key(&next) || new_dist < dists[&next] {
+                    dists.insert(next, new_dist);
+                    queue.push(new_dist, next);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Dijkstra from `from` to every node it's connected to
+    pub fn distances(&self, from: Point2) -> HashMap<Point2, usize> {
+        let mut dists: HashMap<Point2, usize> = HashMap::new();
+        let mut queue = BucketQueue::new(self.max_weight);
+
+        dists.insert(from, 0);
+        queue.push(0, from);
+
+        while let Some((dist, node)) = queue.pop() {
+            if dist > dists[&node] {
+                continue;
+            }
+
+            for &(next, weight) in self.neighbours(node) {
+                let new_dist = dist + weight;
+                if !dists.contains_key(&next) || new_dist < dists[&next] {
+                    dists.insert(next, new_dist);
+                    queue.push(new_dist, next);
+                }
+            }
+        }
+
+        dists
+    }
+}
+
+// Collapses the corridors of a character maze into weighted edges. Walls are `#` (and blank
+// space, for mazes with labels around the edge), `.` is open floor, and every other character
+// (keys, doors, portals, start positions) is kept as a node alongside corridor junctions.
+// Dead-end corridors containing nothing of interest are dropped entirely.
+pub fn compress(grid: &Grid2D<char>) -> WeightedGraph {
+    compress_by(grid, |&c| c != '#' && c != ' ', |&c| c != '.')
+}
+
+pub fn compress_by<T, P, M>(grid: &Grid2D<T>, passable: P, marked: M) -> WeightedGraph
+    where P: Fn(&T) -> bool,
+          M: Fn(&T) -> bool
+{
+    let open_neighbours = |pt: Point2| -> Vec<Point2> {
+        grid.neighbours(pt).into_iter().filter(|&n| passable(&grid[n])).collect()
+    };
+
+    let is_node = |pt: Point2| -> bool {
+        marked(&grid[pt]) || open_neighbours(pt).len() > 2
+    };
+
+    let mut graph = WeightedGraph::new();
+    let nodes: Vec<Point2> = grid.points()
+        .filter(|&pt| passable(&grid[pt]) && is_node(pt))
+        .collect();
+
+    for &node in &nodes {
+        graph.add_node(node);
+
+        for start in open_neighbours(node) {
+            // walk the corridor until we reach another node or a dead end
+            let mut previous = node;
+            let mut current = start;
+            let mut steps = 1;
+            loop {
+                if is_node(current) {
+                    graph.add_edge(node, current, steps);
+                    break;
+                }
+
+                match open_neighbours(current).into_iter().find(|&n| n != previous) {
+                    Some(next) => {
+                        previous = current;
+                        current = next;
+                        steps += 1;
+                    },
+                    None => break
+                }
+            }
+        }
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cmp::Reverse;
+    use std::collections::{BinaryHeap, VecDeque};
+    use std::time::Instant;
+
+    use generate;
+
+    fn grid_distance(grid: &Grid2D<char>, from: Point2, to: Point2) -> Option<usize> {
+        let mut dists: HashMap<Point2, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+        dists.insert(from, 0);
+        queue.push_back(from);
+
+        while let Some(pt) = queue.pop_front() {
+            if pt == to {
+                return Some(dists[&pt]);
+            }
+            for n in grid.neighbours(pt) {
+                if grid[n] != '#' && grid[n] != ' ' && !dists.contains_key(&n) {
+                    dists.insert(n, dists[&pt] + 1);
+                    queue.push_back(n);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn assert_paths_preserved(maze: &str) {
+        let grid = Grid2D::from_lines(maze).unwrap();
+        let graph = compress(&grid);
+
+        let marked: Vec<Point2> = grid.iter()
+            .filter(|&(_, &c)| c != '#' && c != '.' && c != ' ')
+            .map(|(pt, _)| pt)
+            .collect();
+
+        for &a in &marked {
+            for &b in &marked {
+                assert_eq!(
+                    graph.shortest_path(a, b),
+                    grid_distance(&grid, a, b),
+                    "Distance from {} to {}", a, b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn maze_compress_corridor() {
+        let grid = Grid2D::from_lines("#######\n#a...b#\n#######").unwrap();
+        let graph = compress(&grid);
+
+        assert_eq!(graph.nodes().count(), 2);
+        assert_eq!(graph.neighbours(Point2::new(1, 1)), &[(Point2::new(5, 1), 4)]);
+    }
+
+    #[test]
+    fn maze_compress_removes_dead_ends() {
+        let grid = Grid2D::from_lines("
+#########
+#a.....b#
+####.####
+####.####
+#########".trim()).unwrap();
+        let graph = compress(&grid);
+
+        // the junction is kept, but the empty corridor below it leads nowhere
+        assert_eq!(graph.nodes().count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.shortest_path(Point2::new(1, 1), Point2::new(7, 1)), Some(6));
+
+        let distances = graph.distances(Point2::new(1, 1));
+        assert_eq!(distances.len(), 3);
+        assert_eq!(distances[&Point2::new(4, 1)], 3);
+        assert_eq!(distances[&Point2::new(7, 1)], 6);
+    }
+
+    #[test]
+    fn maze_compress_preserves_paths_through_junctions() {
+        assert_paths_preserved("
+#################
+#i.G..c...e..H.p#
+########.########
+#j.A..b...f..D.o#
+########@########
+#k.E..a...g..B.n#
+########.########
+#l.F..d...h..C.m#
+#################".trim());
+    }
+
+    #[test]
+    fn maze_compress_preserves_paths() {
+        assert_paths_preserved("
+########################
+#@..............ac.GI.b#
+###d#e#f################
+###A#B#C################
+###g#h#i################
+########################".trim());
+
+        assert_paths_preserved("
+#############
+#g#f.D#..h#l#
+#F###e#E###.#
+#dCba...BcIJ#
+#####.@.#####
+#nK.L...G...#
+#M###N#H###.#
+#o#m..#i#jk.#
+#############".trim());
+    }
+
+    // The BinaryHeap version of `shortest_path`, kept to compare against
+    fn heap_shortest_path(graph: &WeightedGraph, from: Point2, to: Point2) -> Option<usize> {
+        let mut dists: HashMap<Point2, usize> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        dists.insert(from, 0);
+        queue.push(Reverse((0, from)));
+
+        while let Some(Reverse((dist, node))) = queue.pop() {
+            if node == to {
+                return Some(dist);
+            }
+
+            if dist > dists[&node] {
+                continue;
+            }
+
+            for &(next, weight) in graph.neighbours(node) {
+                let new_dist = dist + weight;
+                if !dists.contains_key(&next) || new_dist < dists[&next] {
+                    dists.insert(next, new_dist);
+                    queue.push(Reverse((new_dist, next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    // A benchmark rather than a test: run it with
+    // `cargo test --release -- --ignored --nocapture maze_bench`
+    #[test]
+    #[ignore]
+    fn maze_bench_bucket_queue() {
+        let vault = Grid2D::from_lines(generate::generate(18, Some(201), 2019).unwrap().trim()).unwrap();
+
+        // The compressed graph has long corridors as edges, while marking every open cell
+        // gives a graph where all the weights are 1
+        let graphs = vec![
+            ("compressed", compress(&vault)),
+            ("unit weight", compress_by(&vault, |&c| c != '#', |_| true)),
+        ];
+
+        for (name, graph) in graphs {
+            let nodes: Vec<Point2> = graph.nodes().cloned().step_by(graph.nodes().count() / 30).collect();
+            let pairs: Vec<(Point2, Point2)> = nodes.iter().flat_map(|&a| nodes.iter().map(move |&b| (a, b))).collect();
+            println!("{}: {} nodes, {} edges, {} searches", name, graph.nodes().count(), graph.edge_count(), pairs.len());
+
+            let now = Instant::now();
+            let heap_total: usize = pairs.iter().map(|&(a, b)| heap_shortest_path(&graph, a, b).unwrap()).sum();
+            println!("    BinaryHeap:  {:?}", now.elapsed());
+
+            let now = Instant::now();
+            let bucket_total: usize = pairs.iter().map(|&(a, b)| graph.shortest_path(a, b).unwrap()).sum();
+            println!("    BucketQueue: {:?}", now.elapsed());
+
+            assert_eq!(heap_total, bucket_total);
+        }
+    }
+}
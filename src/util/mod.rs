@@ -0,0 +1,32 @@
+#[allow(dead_code)]
+pub mod cache;
+
+#[allow(dead_code)]
+pub mod combinatorics;
+
+#[allow(dead_code)]
+pub mod digits;
+
+#[cfg(test)]
+pub mod exhaustive;
+
+#[allow(dead_code)]
+pub mod geom;
+
+#[allow(dead_code)]
+pub mod grid;
+
+#[allow(dead_code)]
+pub mod input;
+
+#[allow(dead_code)]
+pub mod iter;
+
+#[allow(dead_code)]
+pub mod maze;
+
+#[allow(dead_code)]
+pub mod rng;
+
+#[allow(dead_code)]
+pub mod search;
@@ -0,0 +1,95 @@
+// A priority queue for searches where priorities are integers that never go below the
+// last one popped, and never get more than `max_step` above it, as in Dijkstra with
+// edge weights of at most `max_step`. Items are kept in a ring of one bucket per
+// priority, so pushing and popping are O(1) rather than the O(log n) of a BinaryHeap,
+// as long as `max_step` is small.
+#[derive(Clone, Debug)]
+pub struct BucketQueue<T> {
+    buckets: Vec<Vec<T>>,
+    current: usize,
+    len: usize
+}
+
+impl<T> BucketQueue<T> {
+    pub fn new(max_step: usize) -> BucketQueue<T> {
+        BucketQueue {
+            buckets: (0..=max_step).map(|_| vec![]).collect(),
+            current: 0,
+            len: 0
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Panics if `priority` is outside the range the queue can hold
+    pub fn push(&mut self, priority: usize, item: T) {
+        assert!(
+            priority >= self.current && priority - self.current < self.buckets.len(),
+            "Cannot push priority {} after popping priority {}", priority, self.current
+        );
+
+        let idx = priority % self.buckets.len();
+        self.buckets[idx].push(item);
+        self.len += 1;
+    }
+
+    // Removes an item with the lowest priority. Items with equal priority come out
+    // last in, first out.
+    pub fn pop(&mut self) -> Option<(usize, T)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        while self.buckets[self.current % self.buckets.len()].is_empty() {
+            self.current += 1;
+        }
+
+        self.len -= 1;
+        let idx = self.current % self.buckets.len();
+        self.buckets[idx].pop().map(|item| (self.current, item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_bucket_queue_order() {
+        let mut queue = BucketQueue::new(3);
+        queue.push(3, 'c');
+        queue.push(1, 'a');
+        queue.push(3, 'd');
+
+        assert_eq!(queue.pop(), Some((1, 'a')));
+        queue.push(2, 'b');
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop(), Some((2, 'b')));
+        assert_eq!(queue.pop(), Some((3, 'd')));
+        assert_eq!(queue.pop(), Some((3, 'c')));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn search_bucket_queue_is_monotone() {
+        let mut queue = BucketQueue::new(5);
+        queue.push(5, ());
+        queue.pop();
+        queue.push(4, ());
+    }
+
+    #[test]
+    #[should_panic]
+    fn search_bucket_queue_is_bounded() {
+        let mut queue = BucketQueue::new(2);
+        queue.push(3, ());
+    }
+}
@@ -0,0 +1,316 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::result;
+use std::time::Duration;
+
+use clock::{Clock, SystemClock};
+use util::grid::{Grid2D, Point2};
+use util::input;
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+const HEADER: &str = "aoc-frames 1";
+
+// ANSI 256-colour backgrounds for a heatmap, from blue for the smallest values through
+// green and yellow to red for the largest
+const HEAT: &[u8] = &[21, 27, 33, 39, 45, 51, 50, 49, 48, 47, 46, 82, 118, 154, 190, 226, 220, 214, 208, 202, 196];
+
+// Somewhere to show the frames of an animation: the terminal, a recording, or a page in
+// the browser
+pub trait Visualizer {
+    fn show(&mut self, frame: &str) -> Result<()>;
+}
+
+// Draws each frame over the last in the terminal, at no more than `fps` frames a second.
+// Time spent working out a frame counts towards its wait, so a slow solver isn't slowed
+// down further, and one which falls behind doesn't rush to catch up.
+pub struct Terminal<C: Clock = SystemClock> {
+    clock: C,
+    delay: Duration,
+    // When the next frame can be shown
+    next: Option<Duration>,
+    frames: usize
+}
+
+impl Terminal {
+    pub fn new(fps: usize) -> Terminal {
+        Terminal::with_clock(fps, SystemClock::default())
+    }
+}
+
+impl<C: Clock> Terminal<C> {
+    pub fn with_clock(fps: usize, clock: C) -> Terminal<C> {
+        Terminal {
+            clock,
+            delay: Duration::from_secs(1) / fps.max(1) as u32,
+            next: None,
+            frames: 0
+        }
+    }
+}
+
+impl<C: Clock> Visualizer for Terminal<C> {
+    fn show(&mut self, frame: &str) -> Result<()> {
+        let now = self.clock.now();
+        let shown = match self.next {
+            Some(due) if due > now => {
+                self.clock.sleep(due - now);
+                due
+            },
+            _ => now
+        };
+        self.next = Some(shown + self.delay);
+
+        self.frames += 1;
+        println!("\x1b[H\x1b[2J{}\nFrame {}", frame, self.frames);
+
+        Ok(())
+    }
+}
+
+// Saves the frames a solver draws to a file, so a run can go at full speed and be
+// watched afterwards with `aoc_2019 playback`. Each frame only stores the rows that
+// changed since the one before, run-length encoded, as most of a frame stays the same.
+//
+// The file starts with a header line, then each frame is an `F <rows>` line followed by
+// `R <row> <runs>` lines for the changed rows, where the runs are `count:char`
+// separated by commas.
+pub struct Recorder {
+    out: BufWriter<File>,
+    previous: Vec<String>,
+    frames: usize
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> Result<Recorder> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", HEADER)?;
+
+        Ok(Recorder {
+            out,
+            previous: vec![],
+            frames: 0
+        })
+    }
+
+    pub fn record(&mut self, frame: &str) -> Result<()> {
+        let rows: Vec<String> = frame.lines().map(|row| row.to_string()).collect();
+        self.out.write_all(encode_frame(&self.previous, &rows).as_bytes())?;
+        self.previous = rows;
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<usize> {
+        self.out.flush()?;
+        Ok(self.frames)
+    }
+}
+
+impl Visualizer for Recorder {
+    fn show(&mut self, frame: &str) -> Result<()> {
+        self.record(frame)
+    }
+}
+
+fn heat_cell(value: i64, min: i64, max: i64) -> String {
+    let idx = if max > min {
+        ((value - min) as i128 * (HEAT.len() - 1) as i128 / (max - min) as i128) as usize
+    } else {
+        0
+    };
+    format!("\x1b[48;5;{}m  \x1b[0m", HEAT[idx])
+}
+
+// Draws a grid of values for a terminal, two characters per cell so that cells come out
+// roughly square, coloured by how large each value is, with a legend below. Cells
+// without a value, such as walls, are left blank.
+pub fn heatmap(grid: &Grid2D<Option<i64>>) -> Result<String> {
+    let values: Vec<i64> = grid.iter().filter_map(|(_, &value)| value).collect();
+    let (min, max) = match (values.iter().min(), values.iter().max()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return Ok("Nothing to draw".to_string())
+    };
+
+    let mut drawn = String::new();
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            match grid[Point2::new(x as i64, y as i64)] {
+                Some(value) => drawn.push_str(&heat_cell(value, min, max)),
+                None => drawn.push_str("  ")
+            }
+        }
+        drawn.push('\n');
+    }
+
+    // Five values spread evenly from the smallest to the largest
+    let mut legend: Vec<i64> = (0..5).map(|step| min + (max - min) * step / 4).collect();
+    legend.dedup();
+    let legend: Vec<String> = legend.iter().map(|&value| format!("{} {}", heat_cell(value, min, max), value)).collect();
+    drawn.push_str(&legend.join("  "));
+
+    Ok(drawn)
+}
+
+fn encode_frame(previous: &[String], rows: &[String]) -> String {
+    let mut encoded = format!("F {}\n", rows.len());
+    for (idx, row) in rows.iter().enumerate() {
+        if previous.get(idx) != Some(row) {
+            encoded.push_str(&format!("R {} {}\n", idx, encode_row(row)));
+        }
+    }
+
+    encoded
+}
+
+fn encode_row(row: &str) -> String {
+    let mut runs: Vec<(usize, char)> = vec![];
+    for c in row.chars() {
+        match runs.last_mut() {
+            Some((count, last)) if *last == c => *count += 1,
+            _ => runs.push((1, c))
+        }
+    }
+
+    let runs: Vec<String> = runs.iter().map(|(count, c)| format!("{}:{}", count, c)).collect();
+    runs.join(",")
+}
+
+fn decode_row(runs: &str) -> Result<String> {
+    let mut row = String::new();
+    let mut chars = runs.chars().peekable();
+    while chars.peek().is_some() {
+        let count: String = chars.by_ref().take_while(|&c| c != ':').collect();
+        let count: usize = match count.parse() {
+            Ok(count) => count,
+            Err(_) => return err!("Cannot read run length {:?}", count)
+        };
+        let c = chars.next().ok_or("Run is missing its character")?;
+        row.extend((0..count).map(|_| c));
+
+        match chars.next() {
+            None | Some(',') => {},
+            Some(x) => return err!("Expected a comma between runs, not {:?}", x)
+        }
+    }
+
+    Ok(row)
+}
+
+// Every frame in a recording, in full
+pub fn read_frames(recording: &str) -> Result<Vec<String>> {
+    let mut lines = recording.lines();
+    if lines.next() != Some(HEADER) {
+        return err!("Not a frame recording");
+    }
+
+    // Rows carry over from one frame to the next unless they're changed
+    let mut frames = vec![];
+    let mut rows: Vec<String> = vec![];
+    let mut in_frame = false;
+    for line in lines {
+        if let Some(count) = line.strip_prefix("F ") {
+            if in_frame {
+                frames.push(rows.join("\n"));
+            }
+            rows.resize(count.parse()?, String::new());
+            in_frame = true;
+        } else if let Some(row) = line.strip_prefix("R ") {
+            let (idx, runs) = row.split_at(row.find(' ').ok_or("Row is missing its runs")?);
+            let idx: usize = idx.parse()?;
+            if idx >= rows.len() {
+                return err!("Row {} is outside the frame", idx);
+            }
+            rows[idx] = decode_row(&runs[1..])?;
+        } else {
+            return err!("Cannot read recording line {:?}", line);
+        }
+    }
+    if in_frame {
+        frames.push(rows.join("\n"));
+    }
+
+    Ok(frames)
+}
+
+// Shows every frame of a recording
+pub fn replay(path: &str, visualizer: &mut dyn Visualizer) -> Result<usize> {
+    let frames = read_frames(&input::read_file(path)?)?;
+    for frame in &frames {
+        visualizer.show(frame)?;
+    }
+
+    Ok(frames.len())
+}
+
+// Redraws a recording in the terminal at `fps` frames per second
+pub fn playback(path: &str, fps: usize) -> Result<()> {
+    replay(path, &mut Terminal::new(fps))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viz_encodes_changed_rows() {
+        let first: Vec<String> = vec!["#####".to_string(), "#.o.#".to_string(), "#####".to_string()];
+        let second: Vec<String> = vec!["#####".to_string(), "#..o#".to_string(), "#####".to_string()];
+
+        assert_eq!(encode_frame(&[], &first), "F 3\nR 0 5:#\nR 1 1:#,1:.,1:o,1:.,1:#\nR 2 5:#\n");
+        assert_eq!(encode_frame(&first, &second), "F 3\nR 1 1:#,2:.,1:o,1:#\n");
+
+        let recording = format!("{}\n{}{}F 0\n", HEADER, encode_frame(&[], &first), encode_frame(&first, &second));
+        assert_eq!(read_frames(&recording).unwrap(), vec![first.join("\n"), second.join("\n"), String::new()]);
+    }
+
+    #[test]
+    fn viz_draws_heatmaps() {
+        let grid = Grid2D::from_rows(vec![vec![Some(0), None], vec![Some(10), Some(20)]]).unwrap();
+        let drawn = heatmap(&grid).unwrap();
+        let lines: Vec<&str> = drawn.lines().collect();
+
+        assert_eq!(lines[0], "\x1b[48;5;21m  \x1b[0m  ");
+        assert_eq!(lines[1], "\x1b[48;5;46m  \x1b[0m\x1b[48;5;196m  \x1b[0m");
+        assert!(lines[2].starts_with("\x1b[48;5;21m  \x1b[0m 0  ") && lines[2].ends_with(" 20"));
+        assert_eq!(heatmap(&Grid2D::from_rows(vec![vec![None]]).unwrap()).unwrap(), "Nothing to draw");
+    }
+
+    #[test]
+    fn viz_terminal_paces_frames() {
+        use clock::MockClock;
+
+        let clock = MockClock::new();
+        let mut terminal = Terminal::with_clock(10, clock.clone());
+        for _ in 0..3 {
+            terminal.show("#").unwrap();
+        }
+        // The first frame goes straight out, the rest wait their turn
+        assert_eq!(clock.now(), Duration::from_millis(200));
+
+        // A frame which took longer than the delay to work out isn't held back
+        clock.advance(Duration::from_millis(250));
+        terminal.show("#").unwrap();
+        assert_eq!(clock.now(), Duration::from_millis(450));
+        clock.advance(Duration::from_millis(40));
+        terminal.show("#").unwrap();
+        assert_eq!(clock.now(), Duration::from_millis(550));
+    }
+
+    #[test]
+    fn viz_decodes_awkward_characters() {
+        for row in &["", "Score: 1234", "a,b:c", "██.:,"] {
+            assert_eq!(decode_row(&encode_row(row)).unwrap(), *row);
+        }
+
+        assert!(read_frames("not a recording").is_err());
+        assert!(decode_row("3#").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+// Drawing in the terminal, recording frames and playing them back, which the `viz` feature
+// builds in, and serving recordings to the browser, which needs `http` as well. A build
+// without them has stand-ins for what the solvers and the runner call, failing with the
+// error from `features::require`.
+#[cfg(feature = "viz")]
+mod draw;
+#[cfg(feature = "http")]
+mod serve;
+
+#[cfg(feature = "viz")]
+pub use self::draw::{heatmap, playback, read_frames, replay, Recorder, Terminal, Visualizer};
+#[cfg(not(feature = "viz"))]
+pub use self::without_viz::{heatmap, playback, Recorder};
+#[cfg(feature = "http")]
+pub use self::serve::{serve, Canvas};
+#[cfg(not(feature = "http"))]
+pub use self::without_http::serve;
+
+// The runner turns down the options which need these before a solver starts, so the
+// recorder is never made and the heatmap never drawn
+#[cfg(not(feature = "viz"))]
+mod without_viz {
+    use std::error::Error;
+    use std::result;
+
+    use features::{self, Feature};
+    use util::grid::Grid2D;
+
+    type Result<T> = result::Result<T, Box<dyn Error>>;
+
+    pub struct Recorder;
+
+    impl Recorder {
+        pub fn create(_path: &str) -> Result<Recorder> {
+            features::require(Feature::Viz, "--record").map(|_| Recorder)
+        }
+
+        pub fn record(&mut self, _frame: &str) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn finish(self) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    pub fn heatmap(_grid: &Grid2D<Option<i64>>) -> Result<String> {
+        features::require(Feature::Viz, "--heatmap").map(|_| String::new())
+    }
+
+    pub fn playback(_path: &str, _fps: usize) -> Result<()> {
+        features::require(Feature::Viz, "playback")
+    }
+}
+
+#[cfg(not(feature = "http"))]
+mod without_http {
+    use std::error::Error;
+    use std::result;
+
+    use features::{self, Feature};
+
+    pub fn serve(_path: &str, _port: usize, _fps: usize) -> result::Result<(), Box<dyn Error>> {
+        features::require(Feature::Http, "serve-viz")
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::result;
+use std::time::Duration;
+
+use viz::draw::{replay, Visualizer};
+
+type Result<T> = result::Result<T, Box<dyn Error>>;
+
+macro_rules! err {
+    ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
+}
+
+// Collects frames into a web page which plays them back on a canvas
+#[derive(Default)]
+pub struct Canvas {
+    frames: Vec<String>
+}
+
+impl Visualizer for Canvas {
+    fn show(&mut self, frame: &str) -> Result<()> {
+        self.frames.push(frame.to_string());
+        Ok(())
+    }
+}
+
+impl Canvas {
+    pub fn page(&self, fps: usize) -> String {
+        let frames: Vec<String> = self.frames.iter().map(|frame| json_string(frame)).collect();
+
+        format!(r##"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>aoc_2019</title></head>
+<body style="background: #0f0f23; color: #cccccc; font-family: monospace">
+<canvas id="screen"></canvas>
+<p id="status"></p>
+<script>
+const frames = [{frames}];
+const canvas = document.getElementById("screen");
+const ctx = canvas.getContext("2d");
+const size = 14;
+const rows = Math.max(1, ...frames.map(f => f.split("\n").length));
+const cols = Math.max(1, ...frames.map(f => Math.max(...f.split("\n").map(r => [...r].length))));
+ctx.font = size + "px monospace";
+canvas.width = cols * ctx.measureText("M").width;
+canvas.height = rows * size;
+let idx = 0;
+function draw() {{
+    ctx.fillStyle = "#0f0f23";
+    ctx.fillRect(0, 0, canvas.width, canvas.height);
+    ctx.font = size + "px monospace";
+    ctx.fillStyle = "#cccccc";
+    frames[idx].split("\n").forEach((row, y) => ctx.fillText(row, 0, (y + 1) * size - 3));
+    document.getElementById("status").textContent = "Frame " + (idx + 1) + " of " + frames.length;
+    if (idx + 1 < frames.length) {{
+        idx += 1;
+        setTimeout(draw, {delay});
+    }}
+}}
+if (frames.length > 0) {{
+    draw();
+}}
+</script>
+</body>
+</html>
+"##, frames = frames.join(","), delay = 1000 / fps.max(1))
+    }
+}
+
+// A string quoted and escaped for JavaScript
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '<' => quoted.push_str("\\u003c"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+// Serves a page playing a recording back on a canvas at http://127.0.0.1:<port>/ until
+// the process is stopped. Only the page itself is served; every other path is a 404.
+pub fn serve(path: &str, port: usize, fps: usize) -> Result<()> {
+    if port > usize::from(u16::MAX) {
+        return err!("Port must be at most {}, not {}", u16::MAX, port);
+    }
+
+    let mut canvas = Canvas::default();
+    let frames = replay(path, &mut canvas)?;
+    let page = canvas.page(fps);
+
+    let listener = TcpListener::bind(("127.0.0.1", port as u16))?;
+    println!("Serving {} frames from {} at http://127.0.0.1:{}/", frames, path, port);
+
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        // A client which connects and sends nothing would otherwise hold up every other
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut request = String::new();
+        if BufReader::new(&stream).read_line(&mut request).is_err() {
+            continue;
+        }
+
+        let response = match request.split_whitespace().nth(1) {
+            Some("/") => format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                page.len(),
+                page
+            ),
+            _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        };
+        // A browser giving up on a request shouldn't stop the server
+        let _ = stream.write_all(response.as_bytes());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viz_canvas_page_holds_frames() {
+        let mut canvas = Canvas::default();
+        canvas.show("#.\n.@").unwrap();
+        canvas.show("say \"hi\" </script>").unwrap();
+
+        let page = canvas.page(20);
+        assert!(page.contains(r##"const frames = ["#.\n.@","say \"hi\" \u003c/script>"];"##));
+        assert!(page.contains("setTimeout(draw, 50)"));
+
+        // JavaScript strings can't run over a line, so every line of the script has to
+        // close each string it opens
+        let script = &page[page.find("<script>").unwrap()..page.find("</script>").unwrap()];
+        for line in script.lines() {
+            let mut quotes = 0;
+            let mut escaped = false;
+            for c in line.chars() {
+                match c {
+                    '\\' if !escaped => escaped = true,
+                    '"' if !escaped => quotes += 1,
+                    _ => escaped = false
+                }
+            }
+            assert_eq!(quotes % 2, 0, "unterminated string in {:?}", line);
+        }
+    }
+}